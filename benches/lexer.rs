@@ -0,0 +1,53 @@
+//! Замер лексера на сгенерированном исходнике около 1 МБ.
+//!
+//! Запуск: `cargo bench --bench lexer`.
+
+use project_sofia_lib::lexer::Lexer;
+use project_sofia_lib::token::TokenType;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const RUNS: usize = 31;
+
+/// Исходник из повторяющегося фрагмента с идентификаторами, числами,
+/// строками (в том числе не-ASCII), операторами и комментариями.
+fn generated_source(min_len: usize) -> String {
+    let chunk = r#"
+// вычисление суммы
+let total_0 = fn(a, b) { if (a >= b) { a * 2 + b } else { b - a / 3 } };
+let name = "строка с юникодом ✓";
+let items = [1, 22, 333, 4444, "x", true, null];
+let h = {"key": items[0], "другой": name};
+"#;
+    let mut source = String::with_capacity(min_len + chunk.len());
+    while source.len() < min_len {
+        source.push_str(chunk);
+    }
+    source
+}
+
+/// Лучшее из `RUNS` времён разбора исходника на токены; создание строки
+/// входа в замер не входит.
+fn measure(source: &str) -> (Duration, usize) {
+    let mut tokens = 0;
+    let best = (0..RUNS)
+        .map(|_| {
+            let input = source.to_string();
+            let start = Instant::now();
+            let mut lexer = Lexer::new(input);
+            tokens = 0;
+            while black_box(lexer.next_token()).token_type != TokenType::Eof {
+                tokens += 1;
+            }
+            start.elapsed()
+        })
+        .min()
+        .expect("RUNS > 0");
+    (best, tokens)
+}
+
+fn main() {
+    let source = generated_source(1 << 20);
+    let (best, tokens) = measure(&source);
+    println!("{} bytes, {} tokens: {:.2?}", source.len(), tokens, best);
+}
@@ -0,0 +1,52 @@
+//! Замер цикла диспетчеризации VM: без отладочного хука каждая инструкция
+//! должна стоить столько же, сколько до его появления.
+//!
+//! Запуск: `cargo bench --bench vm_dispatch`.
+
+use project_sofia_lib::compiler::Compiler;
+use project_sofia_lib::lexer::Lexer;
+use project_sofia_lib::parser::Parser;
+use project_sofia_lib::vm::VM;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const RUNS: usize = 31;
+
+/// Программа из одних вызовов: `calls` вызовов функции, в теле которой
+/// `depth` вложенных вызовов арифметической функции.
+fn nested_calls(depth: usize, calls: usize) -> String {
+    let nested = (0..depth).fold("1".to_string(), |inner, _| format!("f({})", inner));
+    format!(
+        "let f = fn(x) {{ (x * 3 + 7) % 1000003 }}; let g = fn() {{ {} }}; {}",
+        nested,
+        "g(); ".repeat(calls)
+    )
+}
+
+/// Лучшее из `RUNS` времён выполнения программы на VM: минимум меньше
+/// медианы зависит от шума соседних процессов. Компиляция в замер не входит.
+fn measure(source: &str) -> Duration {
+    let program = Parser::new(Lexer::new(source.to_string()))
+        .parse_program()
+        .expect("benchmark program must parse");
+    let instructions = Compiler::new()
+        .compile(&program)
+        .expect("benchmark program must compile");
+
+    (0..RUNS)
+        .map(|_| {
+            let mut vm = VM::new(instructions.clone());
+            let start = Instant::now();
+            black_box(vm.run().expect("benchmark program must run"));
+            start.elapsed()
+        })
+        .min()
+        .expect("RUNS > 0")
+}
+
+fn main() {
+    for (depth, calls) in [(100, 1000), (200, 2000)] {
+        let source = nested_calls(depth, calls);
+        println!("{:>3} x {:<5} {:>10.2?}", depth, calls, measure(&source));
+    }
+}
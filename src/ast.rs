@@ -1,4 +1,4 @@
-use crate::token::Token;
+use crate::token::{Token, TokenType};
 use std::fmt;
 
 #[derive(Debug, PartialEq, Clone)]
@@ -18,6 +18,25 @@ impl fmt::Display for Node {
     }
 }
 
+/// Идентификатор узла AST. Парсер назначает его операторам по порядку
+/// разбора, начиная с единицы; `NodeId(0)` — у узлов, созданных не парсером.
+/// Компилятор передаёт идентификатор в байткод опкодом `MapToAst`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Hash, Default)]
+pub struct NodeId(pub u32);
+
+impl NodeId {
+    /// Назначен ли идентификатор парсером.
+    pub fn is_assigned(self) -> bool {
+        self.0 != 0
+    }
+}
+
+impl fmt::Display for NodeId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct Program {
     pub statements: Vec<Statement>,
@@ -36,6 +55,118 @@ impl Program {
         }
         s
     }
+
+    /// Ищет оператор с заданным идентификатором, в том числе вложенный в
+    /// блоки, функции, методы и ветви `match`.
+    pub fn find_node(&self, id: NodeId) -> Option<&Statement> {
+        if !id.is_assigned() {
+            return None;
+        }
+        find_in_statements(&self.statements, id)
+    }
+}
+
+fn find_in_statements(statements: &[Statement], id: NodeId) -> Option<&Statement> {
+    statements
+        .iter()
+        .find_map(|stmt| find_in_statement(stmt, id))
+}
+
+fn find_in_statement(statement: &Statement, id: NodeId) -> Option<&Statement> {
+    if statement.id() == id {
+        return Some(statement);
+    }
+    match statement {
+        Statement::Let(s) => find_in_expression(&s.value, id),
+        Statement::LetDestructure(s) => find_in_expression(&s.value, id),
+        Statement::Return(s) => find_in_expression(&s.return_value, id),
+        Statement::Expression(s) => find_in_expression(&s.expression, id),
+        Statement::Block(b) => find_in_statements(&b.statements, id),
+        Statement::ClassDeclaration(c) => find_in_properties(&c.properties, id).or_else(|| {
+            c.methods
+                .iter()
+                .find_map(|m| find_in_statements(&m.body.statements, id))
+        }),
+        Statement::StructDeclaration(s) => find_in_properties(&s.properties, id),
+        Statement::InterfaceDeclaration(_) | Statement::Break(_) | Statement::Continue(_) => None,
+    }
+}
+
+fn find_in_properties(properties: &[PropertyDeclaration], id: NodeId) -> Option<&Statement> {
+    properties
+        .iter()
+        .filter_map(|p| p.value.as_ref())
+        .find_map(|value| find_in_expression(value, id))
+}
+
+fn find_in_expressions(expressions: &[Expression], id: NodeId) -> Option<&Statement> {
+    expressions.iter().find_map(|e| find_in_expression(e, id))
+}
+
+fn find_in_block(block: Option<&BlockStatement>, id: NodeId) -> Option<&Statement> {
+    block.and_then(|b| find_in_statements(&b.statements, id))
+}
+
+fn find_in_expression(expression: &Expression, id: NodeId) -> Option<&Statement> {
+    match expression {
+        Expression::Prefix(p) => find_in_expression(&p.right, id),
+        Expression::Typeof(t) => find_in_expression(&t.operand, id),
+        Expression::Infix(i) => {
+            find_in_expression(&i.left, id).or_else(|| find_in_expression(&i.right, id))
+        }
+        Expression::If(i) => find_in_expression(&i.condition, id)
+            .or_else(|| find_in_block(Some(&i.consequence), id))
+            .or_else(|| find_in_block(i.alternative.as_ref(), id)),
+        Expression::IfLet(i) => find_in_expression(&i.value, id)
+            .or_else(|| find_in_block(Some(&i.consequence), id))
+            .or_else(|| find_in_block(i.alternative.as_ref(), id)),
+        Expression::For(f) => {
+            find_in_expression(&f.iterable, id).or_else(|| find_in_block(Some(&f.body), id))
+        }
+        Expression::Assign(a) => {
+            find_in_expression(&a.target, id).or_else(|| find_in_expression(&a.value, id))
+        }
+        Expression::FunctionLiteral(f) => find_in_block(Some(&f.body), id),
+        Expression::Call(c) => {
+            find_in_expression(&c.function, id).or_else(|| find_in_expressions(&c.arguments, id))
+        }
+        Expression::ArrayLiteral(a) => find_in_expressions(&a.elements, id),
+        Expression::HashLiteral(h) => h.pairs.iter().find_map(|(key, value)| {
+            find_in_expression(key, id).or_else(|| find_in_expression(value, id))
+        }),
+        Expression::Index(i) => {
+            find_in_expression(&i.left, id).or_else(|| find_in_expression(&i.index, id))
+        }
+        Expression::Range(r) => {
+            find_in_expression(&r.start, id).or_else(|| find_in_expression(&r.end, id))
+        }
+        Expression::New(n) => find_in_expressions(&n.arguments, id),
+        Expression::PropertyAccess(p) => find_in_expression(&p.left, id),
+        Expression::MethodCall(m) => {
+            find_in_expression(&m.object, id).or_else(|| find_in_expressions(&m.arguments, id))
+        }
+        Expression::Match(m) => find_in_expression(&m.value, id).or_else(|| {
+            m.arms.iter().find_map(|arm| {
+                arm.guard
+                    .as_ref()
+                    .and_then(|g| find_in_expression(g, id))
+                    .or_else(|| find_in_block(Some(&arm.consequence), id))
+            })
+        }),
+        Expression::Identifier(_)
+        | Expression::IntegerLiteral(_)
+        | Expression::Boolean(_)
+        | Expression::Null(_)
+        | Expression::StringLiteral(_)
+        | Expression::This(_)
+        | Expression::Super(_) => None,
+    }
+}
+
+impl Default for Program {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl fmt::Display for Program {
@@ -50,7 +181,10 @@ impl fmt::Display for Program {
 #[derive(Debug, PartialEq, Clone)]
 pub enum Statement {
     Let(LetStatement),
+    LetDestructure(LetDestructureStatement),
     Return(ReturnStatement),
+    Break(BreakStatement),
+    Continue(ContinueStatement),
     Expression(ExpressionStatement),
     Block(BlockStatement),
     ClassDeclaration(ClassDeclaration),
@@ -58,11 +192,48 @@ pub enum Statement {
     StructDeclaration(StructDeclaration),
 }
 
+impl Statement {
+    /// Идентификатор оператора; у блоков его нет — есть у операторов внутри.
+    pub fn id(&self) -> NodeId {
+        match self {
+            Statement::Let(s) => s.id,
+            Statement::LetDestructure(s) => s.id,
+            Statement::Return(s) => s.id,
+            Statement::Break(s) => s.id,
+            Statement::Continue(s) => s.id,
+            Statement::Expression(s) => s.id,
+            Statement::Block(_) => NodeId::default(),
+            Statement::ClassDeclaration(s) => s.id,
+            Statement::InterfaceDeclaration(s) => s.id,
+            Statement::StructDeclaration(s) => s.id,
+        }
+    }
+
+    /// Первый токен оператора; его позиция — позиция оператора.
+    pub fn token(&self) -> &Token {
+        match self {
+            Statement::Let(s) => &s.token,
+            Statement::LetDestructure(s) => &s.token,
+            Statement::Return(s) => &s.token,
+            Statement::Break(s) => &s.token,
+            Statement::Continue(s) => &s.token,
+            Statement::Expression(s) => &s.token,
+            Statement::Block(s) => &s.token,
+            Statement::ClassDeclaration(s) => &s.token,
+            Statement::InterfaceDeclaration(s) => &s.token,
+            Statement::StructDeclaration(s) => &s.token,
+        }
+    }
+}
+
 impl fmt::Display for Statement {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Statement::Let(s) => write!(f, "{}", s),
+            Statement::LetDestructure(s) => write!(f, "{}", s),
             Statement::Return(s) => write!(f, "{}", s),
+            Statement::Break(s) => write!(f, "{}", s),
+            Statement::Continue(s) => write!(f, "{}", s),
             Statement::Expression(s) => write!(f, "{}", s),
             Statement::Block(s) => write!(f, "{}", s),
             Statement::ClassDeclaration(s) => write!(f, "{}", s),
@@ -77,19 +248,27 @@ pub enum Expression {
     Identifier(Identifier),
     IntegerLiteral(IntegerLiteral),
     Boolean(BooleanLiteral),
+    Null(NullLiteral),
     Prefix(PrefixExpression),
     Infix(InfixExpression),
     If(IfExpression),
+    IfLet(IfLetExpression),
     FunctionLiteral(FunctionLiteral),
     Call(CallExpression),
     StringLiteral(StringLiteral),
     ArrayLiteral(ArrayLiteral),
+    HashLiteral(HashLiteral),
+    Index(IndexExpression),
+    Range(RangeExpression),
     New(NewExpression),
     This(ThisExpression),
     Super(SuperExpression),
     PropertyAccess(PropertyAccessExpression),
     MethodCall(MethodCallExpression),
     Match(MatchExpression),
+    For(ForExpression),
+    Assign(AssignExpression),
+    Typeof(TypeofExpression),
 }
 
 impl fmt::Display for Expression {
@@ -98,9 +277,11 @@ impl fmt::Display for Expression {
             Expression::Identifier(i) => write!(f, "{}", i.value),
             Expression::IntegerLiteral(i) => write!(f, "{}", i.value),
             Expression::Boolean(b) => write!(f, "{}", b.value),
+            Expression::Null(_) => write!(f, "null"),
             Expression::Prefix(p) => write!(f, "({}{})", p.operator, p.right),
             Expression::Infix(i) => write!(f, "({} {} {})", i.left, i.operator, i.right),
             Expression::If(i) => write!(f, "{}", i),
+            Expression::IfLet(i) => write!(f, "{}", i),
             Expression::FunctionLiteral(fl) => write!(f, "{}", fl),
             Expression::Call(c) => write!(f, "{}", c),
             Expression::Match(m) => write!(f, "{}", m),
@@ -109,32 +290,130 @@ impl fmt::Display for Expression {
                 let elements: Vec<String> = a.elements.iter().map(|e| e.to_string()).collect();
                 write!(f, "[{}]", elements.join(", "))
             }
+            Expression::HashLiteral(h) => {
+                let pairs: Vec<String> = h
+                    .pairs
+                    .iter()
+                    .map(|(key, value)| format!("{}: {}", key, value))
+                    .collect();
+                write!(f, "{{{}}}", pairs.join(", "))
+            }
+            Expression::Index(i) => write!(f, "({}[{}])", i.left, i.index),
+            Expression::Range(r) => write!(f, "({}..{})", r.start, r.end),
             Expression::New(n) => write!(f, "{}", n),
             Expression::This(t) => write!(f, "{}", t),
             Expression::Super(s) => write!(f, "{}", s),
             Expression::PropertyAccess(p) => write!(f, "{}", p),
             Expression::MethodCall(m) => write!(f, "{}", m),
+            Expression::For(fe) => write!(f, "{}", fe),
+            Expression::Assign(a) => write!(f, "({} = {})", a.target, a.value),
+            Expression::Typeof(t) => write!(f, "(typeof {})", t.operand),
         }
     }
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct LetStatement {
+    pub id: NodeId,
     pub token: Token,
     pub name: Identifier,
+    /// `let x: Int = 5;` — аннотация сохраняется, но пока не проверяется.
+    pub type_annotation: Option<Box<TypeAnnotation>>,
     pub value: Expression,
 }
 
+impl LetStatement {
+    /// `const x = ...;` — привязку нельзя перезаписать присваиванием.
+    pub fn is_constant(&self) -> bool {
+        self.token.token_type == TokenType::Const
+    }
+}
+
 impl fmt::Display for LetStatement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {}{} = {};",
+            self.token.literal,
+            self.name.value,
+            format_annotation(self.type_annotation.as_deref()),
+            self.value
+        )
+    }
+}
+
+/// Аннотация типа: имя и необязательные аргументы в квадратных скобках
+/// (`Int`, `Array[Int]`, `Map[String, Array[Int]]`). Это только
+/// структурированный текст: интерпретатор и компилятор её пропускают.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TypeAnnotation {
+    pub token: Token,
+    pub name: String,
+    pub arguments: Vec<TypeAnnotation>,
+}
+
+impl TypeAnnotation {
+    /// Сама аннотация и все вложенные в неё аргументы, в порядке записи.
+    pub fn walk(&self) -> Vec<&TypeAnnotation> {
+        let mut annotations = vec![self];
+        for argument in &self.arguments {
+            annotations.extend(argument.walk());
+        }
+        annotations
+    }
+}
+
+impl fmt::Display for TypeAnnotation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name)?;
+        if !self.arguments.is_empty() {
+            let arguments: Vec<String> = self.arguments.iter().map(|a| a.to_string()).collect();
+            write!(f, "[{}]", arguments.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// `: Тип` для необязательной аннотации или пустая строка.
+fn format_annotation(annotation: Option<&TypeAnnotation>) -> String {
+    annotation.map_or_else(String::new, |a| format!(": {}", a))
+}
+
+/// Деструктурирующее объявление `let [a, [b, c], ..rest] = значение;`.
+///
+/// Парсер допускает в `pattern` только кортежи, идентификаторы, `_` и
+/// остаток `..имя` последним элементом кортежа.
+#[derive(Debug, PartialEq, Clone)]
+pub struct LetDestructureStatement {
+    pub id: NodeId,
+    pub token: Token,
+    pub pattern: Box<Pattern>,
+    pub value: Expression,
+}
+
+impl fmt::Display for LetDestructureStatement {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(
             f,
             "{} {} = {};",
-            self.token.literal, self.name.value, self.value
+            self.token.literal,
+            binding_pattern_string(&self.pattern),
+            self.value
         )
     }
 }
 
+/// Записывает паттерн привязки в синтаксисе `let`: кортежи — в квадратных скобках.
+fn binding_pattern_string(pattern: &Pattern) -> String {
+    match pattern {
+        Pattern::Tuple(patterns) => {
+            let elements: Vec<String> = patterns.iter().map(binding_pattern_string).collect();
+            format!("[{}]", elements.join(", "))
+        }
+        other => other.to_string(),
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct Identifier {
     pub token: Token,
@@ -143,6 +422,7 @@ pub struct Identifier {
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct ReturnStatement {
+    pub id: NodeId,
     pub token: Token,
     pub return_value: Expression,
 }
@@ -153,8 +433,35 @@ impl fmt::Display for ReturnStatement {
     }
 }
 
+/// `break;` — досрочный выход из ближайшего цикла `for`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct BreakStatement {
+    pub id: NodeId,
+    pub token: Token,
+}
+
+impl fmt::Display for BreakStatement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{};", self.token.literal)
+    }
+}
+
+/// `continue;` — переход к следующей итерации ближайшего цикла `for`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ContinueStatement {
+    pub id: NodeId,
+    pub token: Token,
+}
+
+impl fmt::Display for ContinueStatement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{};", self.token.literal)
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct ExpressionStatement {
+    pub id: NodeId,
     pub token: Token,
     pub expression: Expression,
 }
@@ -192,6 +499,11 @@ pub struct BooleanLiteral {
     pub value: bool,
 }
 
+#[derive(Debug, PartialEq, Clone)]
+pub struct NullLiteral {
+    pub token: Token,
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct PrefixExpression {
     pub token: Token,
@@ -199,6 +511,14 @@ pub struct PrefixExpression {
     pub right: Box<Expression>,
 }
 
+/// Оператор `typeof значение`: имя типа значения строкой, как его
+/// возвращает `Object::type_str`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct TypeofExpression {
+    pub token: Token,
+    pub operand: Box<Expression>,
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct InfixExpression {
     pub token: Token,
@@ -225,26 +545,138 @@ impl fmt::Display for IfExpression {
     }
 }
 
+/// Условное сопоставление `if let паттерн = значение { ... } else { ... }`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct IfLetExpression {
+    pub token: Token,
+    pub pattern: Box<Pattern>,
+    pub value: Box<Expression>,
+    pub consequence: BlockStatement,
+    pub alternative: Option<BlockStatement>,
+}
+
+impl fmt::Display for IfLetExpression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "if let {} = {} {}",
+            self.pattern, self.value, self.consequence
+        )?;
+        if let Some(alt) = &self.alternative {
+            write!(f, " else {}", alt)?;
+        }
+        Ok(())
+    }
+}
+
+/// Цикл `for (переменная in итерируемое) { тело }`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ForExpression {
+    pub token: Token,
+    pub variable: Identifier,
+    pub iterable: Box<Expression>,
+    pub body: BlockStatement,
+}
+
+impl fmt::Display for ForExpression {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "for ({} in {}) {}",
+            self.variable.value, self.iterable, self.body
+        )
+    }
+}
+
+/// Присваивание `цель = значение`; цель — переменная или свойство.
+#[derive(Debug, PartialEq, Clone)]
+pub struct AssignExpression {
+    pub token: Token,
+    pub target: Box<Expression>,
+    pub value: Box<Expression>,
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct FunctionLiteral {
     pub token: Token,
     pub parameters: Vec<Identifier>,
+    /// Rest-параметр `...имя`: массив аргументов сверх `parameters`.
+    pub rest: Option<Identifier>,
+    /// Аннотации типов параметров и результата (`fn(a: Int): Int`).
+    pub types: SignatureTypes,
     pub body: BlockStatement,
 }
 
 impl fmt::Display for FunctionLiteral {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let params: Vec<String> = self.parameters.iter().map(|p| p.value.clone()).collect();
         write!(
             f,
-            "{}({}) {}",
+            "{}{} {}",
             self.token.literal,
-            params.join(", "),
+            self.types
+                .format_signature(&self.parameters, self.rest.as_ref()),
             self.body
         )
     }
 }
 
+/// Аннотации типов в сигнатуре функции или метода. `parameters` идёт
+/// параллельно списку параметров: `None` — параметр без аннотации.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct SignatureTypes {
+    pub parameters: Vec<Option<TypeAnnotation>>,
+    pub rest: Option<Box<TypeAnnotation>>,
+    pub return_type: Option<Box<TypeAnnotation>>,
+}
+
+impl SignatureTypes {
+    /// Аннотации сигнатуры: параметры, rest-параметр, тип результата.
+    pub fn annotations(&self) -> impl Iterator<Item = &TypeAnnotation> {
+        self.parameters
+            .iter()
+            .flatten()
+            .chain(self.rest.as_deref())
+            .chain(self.return_type.as_deref())
+    }
+
+    /// Список параметров в скобках и тип результата: `(a: Int, ...rest): Int`.
+    pub fn format_signature(&self, parameters: &[Identifier], rest: Option<&Identifier>) -> String {
+        format!(
+            "({}){}",
+            format_parameters(parameters, rest, Some(self)),
+            format_annotation(self.return_type.as_deref())
+        )
+    }
+}
+
+/// Список параметров через запятую; rest-параметр — последним, с `...`.
+/// С `types` к параметрам добавляются их аннотации.
+pub fn format_parameters(
+    parameters: &[Identifier],
+    rest: Option<&Identifier>,
+    types: Option<&SignatureTypes>,
+) -> String {
+    let annotation = |i: usize| {
+        types
+            .and_then(|types| types.parameters.get(i))
+            .and_then(Option::as_ref)
+    };
+    let mut names: Vec<String> = parameters
+        .iter()
+        .enumerate()
+        .map(|(i, p)| format!("{}{}", p.value, format_annotation(annotation(i))))
+        .collect();
+    if let Some(rest) = rest {
+        let annotation = types.and_then(|types| types.rest.as_deref());
+        names.push(format!(
+            "...{}{}",
+            rest.value,
+            format_annotation(annotation)
+        ));
+    }
+    names.join(", ")
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct CallExpression {
     pub token: Token,
@@ -271,6 +703,27 @@ pub struct ArrayLiteral {
     pub elements: Vec<Expression>,
 }
 
+/// Литерал хэша `{ключ: значение, ...}`; пары хранятся в порядке записи.
+#[derive(Debug, PartialEq, Clone)]
+pub struct HashLiteral {
+    pub token: Token,
+    pub pairs: Vec<(Expression, Expression)>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct IndexExpression {
+    pub token: Token,
+    pub left: Box<Expression>,
+    pub index: Box<Expression>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct RangeExpression {
+    pub token: Token,
+    pub start: Box<Expression>,
+    pub end: Box<Expression>,
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub enum AccessModifier {
     Public,
@@ -288,6 +741,7 @@ impl fmt::Display for AccessModifier {
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct ClassDeclaration {
+    pub id: NodeId,
     pub token: Token,
     pub name: Identifier,
     pub super_class: Option<Identifier>,
@@ -314,32 +768,75 @@ impl fmt::Display for ClassDeclaration {
         for method in &self.methods {
             s.push_str(&format!("    {}\n", method));
         }
-        s.push_str("}");
+        s.push('}');
         write!(f, "{}", s)
     }
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct InterfaceDeclaration {
+    pub id: NodeId,
     pub token: Token,
     pub name: Identifier,
-    pub method_signatures: Vec<MethodSignatureDeclaration>,
+    /// Члены интерфейса в порядке объявления.
+    pub members: Vec<InterfaceMember>,
+}
+
+impl InterfaceDeclaration {
+    /// Сигнатуры методов без реализации: `fn area();`.
+    pub fn method_signatures(&self) -> impl Iterator<Item = &MethodSignatureDeclaration> {
+        self.members.iter().filter_map(|member| match member {
+            InterfaceMember::Signature(signature) => Some(signature),
+            InterfaceMember::Default(_) => None,
+        })
+    }
+
+    /// Методы с реализацией по умолчанию: `fn greet() { ... }`.
+    pub fn default_methods(&self) -> impl Iterator<Item = &MethodDeclaration> {
+        self.members.iter().filter_map(|member| match member {
+            InterfaceMember::Default(method) => Some(method),
+            InterfaceMember::Signature(_) => None,
+        })
+    }
 }
 
 impl fmt::Display for InterfaceDeclaration {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut s = String::new();
         s.push_str(&format!("interface {} {{\n", self.name.value));
-        for sig in &self.method_signatures {
-            s.push_str(&format!("    {};\n", sig));
+        for member in &self.members {
+            s.push_str(&format!("    {}\n", member));
         }
-        s.push_str("}");
+        s.push('}');
         write!(f, "{}", s)
     }
 }
 
+/// Член интерфейса: сигнатура или метод с реализацией по умолчанию.
+#[derive(Debug, PartialEq, Clone)]
+pub enum InterfaceMember {
+    Signature(MethodSignatureDeclaration),
+    Default(MethodDeclaration),
+}
+
+impl fmt::Display for InterfaceMember {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InterfaceMember::Signature(signature) => write!(f, "{};", signature),
+            InterfaceMember::Default(method) => write!(
+                f,
+                "fn {}{} {}",
+                method.name.value,
+                method.types.format_signature(&method.parameters, None),
+                method.body
+            ),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct StructDeclaration {
+    pub id: NodeId,
     pub token: Token,
     pub name: Identifier,
     pub properties: Vec<PropertyDeclaration>,
@@ -352,7 +849,7 @@ impl fmt::Display for StructDeclaration {
         for prop in &self.properties {
             s.push_str(&format!("    {};\n", prop));
         }
-        s.push_str("}");
+        s.push('}');
         write!(f, "{}", s)
     }
 }
@@ -386,6 +883,7 @@ pub struct MethodDeclaration {
     pub token: Token,
     pub name: Identifier,
     pub parameters: Vec<Identifier>,
+    pub types: SignatureTypes,
     pub body: BlockStatement,
     pub access_modifier: AccessModifier,
     pub is_static: bool,
@@ -393,16 +891,15 @@ pub struct MethodDeclaration {
 
 impl fmt::Display for MethodDeclaration {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let params: Vec<String> = self.parameters.iter().map(|p| p.value.clone()).collect();
         let mut s = String::new();
         if self.is_static {
             s.push_str("static ");
         }
         s.push_str(&format!(
-            "{} {}({}) {}",
+            "{} {}{} {}",
             self.access_modifier,
             self.name.value,
-            params.join(", "),
+            self.types.format_signature(&self.parameters, None),
             self.body
         ));
         write!(f, "{}", s)
@@ -414,12 +911,17 @@ pub struct MethodSignatureDeclaration {
     pub token: Token,
     pub name: Identifier,
     pub parameters: Vec<Identifier>,
+    pub types: SignatureTypes,
 }
 
 impl fmt::Display for MethodSignatureDeclaration {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        let params: Vec<String> = self.parameters.iter().map(|p| p.value.clone()).collect();
-        write!(f, "{}({})", self.name.value, params.join(", "))
+        write!(
+            f,
+            "{}{}",
+            self.name.value,
+            self.types.format_signature(&self.parameters, None)
+        )
     }
 }
 
@@ -516,6 +1018,21 @@ pub struct MatchArm {
     pub consequence: BlockStatement,
 }
 
+impl MatchArm {
+    /// Записано ли тело ветви блоком `{ ... }`, а не одним выражением.
+    /// Тело-выражение хранится как блок с токеном самого выражения; у
+    /// тела-хэша `{"a": 1}` это тоже `{` — та же скобка, что у хэша.
+    pub fn has_block_body(&self) -> bool {
+        self.consequence.token.token_type == TokenType::LBrace
+            && !matches!(
+                self.consequence.statements.as_slice(),
+                [Statement::Expression(statement)]
+                    if matches!(statement.expression, Expression::HashLiteral(_))
+                        && statement.token.span == self.consequence.token.span
+            )
+    }
+}
+
 impl fmt::Display for MatchArm {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let guard_str = if let Some(guard_expr) = &self.guard {
@@ -523,7 +1040,19 @@ impl fmt::Display for MatchArm {
         } else {
             "".to_string()
         };
-        write!(f, "    {}{} => {}", self.pattern, guard_str, self.consequence)
+        if self.has_block_body() {
+            write!(
+                f,
+                "    {}{} => {{ {} }}",
+                self.pattern, guard_str, self.consequence
+            )
+        } else {
+            write!(
+                f,
+                "    {}{} => {}",
+                self.pattern, guard_str, self.consequence
+            )
+        }
     }
 }
 
@@ -536,6 +1065,7 @@ pub enum Pattern {
     Tuple(Vec<Pattern>),    // Например, (1, x, "test")
     Struct(StructPattern),  // Например, Point { x: 0, y }
     Wildcard,               // Например, _
+    Rest(Identifier),       // Например, ..tail — только последним элементом кортежа
 }
 
 impl fmt::Display for Pattern {
@@ -550,6 +1080,7 @@ impl fmt::Display for Pattern {
             }
             Pattern::Struct(struct_pattern) => write!(f, "{}", struct_pattern),
             Pattern::Wildcard => write!(f, "_"),
+            Pattern::Rest(ident) => write!(f, "..{}", ident.value),
         }
     }
 }
@@ -603,42 +1134,25 @@ mod tests {
 
     #[test]
     fn test_match_expression_display() {
-        let token = Token {
-            token_type: TokenType::Match,
-            literal: "match".to_string(),
-        };
+        let token = Token::new(TokenType::Match, "match".to_string());
         let value = Box::new(Expression::Identifier(Identifier {
-            token: Token {
-                token_type: TokenType::Ident,
-                literal: "x".to_string(),
-            },
+            token: Token::new(TokenType::Ident, "x".to_string()),
             value: "x".to_string(),
         }));
         let arms = vec![
             MatchArm {
                 pattern: Pattern::Literal(Expression::IntegerLiteral(IntegerLiteral {
-                    token: Token {
-                        token_type: TokenType::Int,
-                        literal: "1".to_string(),
-                    },
+                    token: Token::new(TokenType::Int, "1".to_string()),
                     value: 1,
                 })),
                 guard: None,
                 consequence: BlockStatement {
-                    token: Token {
-                        token_type: TokenType::LBrace,
-                        literal: "{".to_string(),
-                    },
+                    token: Token::new(TokenType::Int, "10".to_string()),
                     statements: vec![Statement::Expression(ExpressionStatement {
-                        token: Token {
-                            token_type: TokenType::Int,
-                            literal: "10".to_string(),
-                        },
+                        id: NodeId::default(),
+                        token: Token::new(TokenType::Int, "10".to_string()),
                         expression: Expression::IntegerLiteral(IntegerLiteral {
-                            token: Token {
-                                token_type: TokenType::Int,
-                                literal: "10".to_string(),
-                            },
+                            token: Token::new(TokenType::Int, "10".to_string()),
                             value: 10,
                         }),
                     })],
@@ -646,28 +1160,17 @@ mod tests {
             },
             MatchArm {
                 pattern: Pattern::Identifier(Identifier {
-                    token: Token {
-                        token_type: TokenType::Ident,
-                        literal: "y".to_string(),
-                    },
+                    token: Token::new(TokenType::Ident, "y".to_string()),
                     value: "y".to_string(),
                 }),
                 guard: None,
                 consequence: BlockStatement {
-                    token: Token {
-                        token_type: TokenType::LBrace,
-                        literal: "{".to_string(),
-                    },
+                    token: Token::new(TokenType::Int, "20".to_string()),
                     statements: vec![Statement::Expression(ExpressionStatement {
-                        token: Token {
-                            token_type: TokenType::Int,
-                            literal: "20".to_string(),
-                        },
+                        id: NodeId::default(),
+                        token: Token::new(TokenType::Int, "20".to_string()),
                         expression: Expression::IntegerLiteral(IntegerLiteral {
-                            token: Token {
-                                token_type: TokenType::Int,
-                                literal: "20".to_string(),
-                            },
+                            token: Token::new(TokenType::Int, "20".to_string()),
                             value: 20,
                         }),
                     })],
@@ -677,20 +1180,12 @@ mod tests {
                 pattern: Pattern::Wildcard,
                 guard: None,
                 consequence: BlockStatement {
-                    token: Token {
-                        token_type: TokenType::LBrace,
-                        literal: "{".to_string(),
-                    },
+                    token: Token::new(TokenType::Int, "30".to_string()),
                     statements: vec![Statement::Expression(ExpressionStatement {
-                        token: Token {
-                            token_type: TokenType::Int,
-                            literal: "30".to_string(),
-                        },
+                        id: NodeId::default(),
+                        token: Token::new(TokenType::Int, "30".to_string()),
                         expression: Expression::IntegerLiteral(IntegerLiteral {
-                            token: Token {
-                                token_type: TokenType::Int,
-                                literal: "30".to_string(),
-                            },
+                            token: Token::new(TokenType::Int, "30".to_string()),
                             value: 30,
                         }),
                     })],
@@ -707,17 +1202,11 @@ mod tests {
     #[test]
     fn test_range_pattern_display() {
         let start = Box::new(Expression::IntegerLiteral(IntegerLiteral {
-            token: Token {
-                token_type: TokenType::Int,
-                literal: "1".to_string(),
-            },
+            token: Token::new(TokenType::Int, "1".to_string()),
             value: 1,
         }));
         let end = Box::new(Expression::IntegerLiteral(IntegerLiteral {
-            token: Token {
-                token_type: TokenType::Int,
-                literal: "5".to_string(),
-            },
+            token: Token::new(TokenType::Int, "5".to_string()),
             value: 5,
         }));
 
@@ -739,37 +1228,25 @@ mod tests {
     #[test]
     fn test_struct_pattern_display() {
         let name = Identifier {
-            token: Token {
-                token_type: TokenType::Ident,
-                literal: "Point".to_string(),
-            },
+            token: Token::new(TokenType::Ident, "Point".to_string()),
             value: "Point".to_string(),
         };
         let fields = vec![
             (
                 Identifier {
-                    token: Token {
-                        token_type: TokenType::Ident,
-                        literal: "x".to_string(),
-                    },
+                    token: Token::new(TokenType::Ident, "x".to_string()),
                     value: "x".to_string(),
                 },
                 Some(Pattern::Literal(Expression::IntegerLiteral(
                     IntegerLiteral {
-                        token: Token {
-                            token_type: TokenType::Int,
-                            literal: "0".to_string(),
-                        },
+                        token: Token::new(TokenType::Int, "0".to_string()),
                         value: 0,
                     },
                 ))),
             ),
             (
                 Identifier {
-                    token: Token {
-                        token_type: TokenType::Ident,
-                        literal: "y".to_string(),
-                    },
+                    token: Token::new(TokenType::Ident, "y".to_string()),
                     value: "y".to_string(),
                 },
                 None,
@@ -784,24 +1261,15 @@ mod tests {
     fn test_tuple_pattern_display() {
         let patterns = vec![
             Pattern::Literal(Expression::IntegerLiteral(IntegerLiteral {
-                token: Token {
-                    token_type: TokenType::Int,
-                    literal: "1".to_string(),
-                },
+                token: Token::new(TokenType::Int, "1".to_string()),
                 value: 1,
             })),
             Pattern::Identifier(Identifier {
-                token: Token {
-                    token_type: TokenType::Ident,
-                    literal: "x".to_string(),
-                },
+                token: Token::new(TokenType::Ident, "x".to_string()),
                 value: "x".to_string(),
             }),
             Pattern::Literal(Expression::StringLiteral(StringLiteral {
-                token: Token {
-                    token_type: TokenType::String,
-                    literal: "test".to_string(),
-                },
+                token: Token::new(TokenType::String, "test".to_string()),
                 value: "test".to_string(),
             })),
         ];
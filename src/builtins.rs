@@ -0,0 +1,783 @@
+use crate::evaluator;
+use crate::object::{HashKey, Object};
+use std::num::IntErrorKind;
+use std::rc::Rc;
+
+/// Встроенная функция: имя, число параметров (`-1` — произвольное)
+/// и обработчик.
+struct Builtin {
+    name: &'static str,
+    num_params: i32,
+    handler: fn(Vec<Object>) -> Object,
+}
+
+const fn builtin(
+    name: &'static str,
+    num_params: i32,
+    handler: fn(Vec<Object>) -> Object,
+) -> Builtin {
+    Builtin {
+        name,
+        num_params,
+        handler,
+    }
+}
+
+/// Все встроенные функции. Позиция функции — операнд `GetBuiltin`
+/// в байткоде, поэтому новые функции добавляются в конец. Синонимы
+/// (`copy`, `size`, `str`) — отдельные записи с тем же обработчиком.
+const BUILTINS: &[Builtin] = &[
+    builtin("assert", 1, builtin_assert),
+    builtin("assert_eq", 2, builtin_assert_eq),
+    builtin("collect", 1, builtin_collect),
+    builtin("clone", 1, builtin_clone),
+    builtin("copy", 1, builtin_clone),
+    builtin("split", 2, builtin_split),
+    builtin("join", 2, builtin_join),
+    builtin("trim", 1, builtin_trim),
+    builtin("replace", 3, builtin_replace),
+    builtin("contains", 2, builtin_contains),
+    builtin("index_of", 2, builtin_index_of),
+    builtin("starts_with", 2, builtin_starts_with),
+    builtin("ends_with", 2, builtin_ends_with),
+    builtin("to_string", 1, builtin_to_string),
+    builtin("range", 2, builtin_range),
+    builtin("repeat", 2, builtin_repeat),
+    builtin("map", 2, builtin_map),
+    builtin("filter", 2, builtin_filter),
+    builtin("reduce", 3, builtin_reduce),
+    builtin("is_int", 1, |args| {
+        Object::Boolean(matches!(args[0], Object::Integer(_)))
+    }),
+    builtin("is_string", 1, |args| {
+        Object::Boolean(matches!(args[0], Object::String(_)))
+    }),
+    builtin("is_bool", 1, |args| {
+        Object::Boolean(matches!(args[0], Object::Boolean(_)))
+    }),
+    builtin("is_array", 1, |args| {
+        Object::Boolean(matches!(args[0], Object::Array(_)))
+    }),
+    builtin("is_null", 1, |args| {
+        Object::Boolean(matches!(args[0], Object::Null))
+    }),
+    builtin("is_fn", 1, builtin_is_fn),
+    builtin("len", 1, builtin_len),
+    builtin("size", 1, builtin_len),
+    builtin("is_empty", 1, builtin_is_empty),
+    builtin("str", 1, builtin_to_string),
+    builtin("int", 1, builtin_int),
+    builtin("parse_int", 2, builtin_parse_int),
+    builtin("format", -1, builtin_format),
+    builtin("abs", 1, builtin_abs),
+    builtin("min", -1, |args| builtin_extremum("min", &args, i64::min)),
+    builtin("max", -1, |args| builtin_extremum("max", &args, i64::max)),
+    builtin("keys", 1, builtin_keys),
+];
+
+/// Имена встроенных функций в порядке их индексов `GetBuiltin`.
+pub fn names() -> impl Iterator<Item = &'static str> {
+    BUILTINS.iter().map(|builtin| builtin.name)
+}
+
+/// Ищет встроенную функцию по имени.
+///
+/// Возвращает `Object::BuiltinFunction` или `None`, если такой функции нет.
+pub fn lookup(name: &str) -> Option<Object> {
+    BUILTINS
+        .iter()
+        .find(|builtin| builtin.name == name)
+        .map(Builtin::to_object)
+}
+
+/// Встроенная функция с индексом `index` — операндом `GetBuiltin`.
+pub fn get(index: usize) -> Option<Object> {
+    BUILTINS.get(index).map(Builtin::to_object)
+}
+
+impl Builtin {
+    fn to_object(&self) -> Object {
+        Object::BuiltinFunction {
+            name: self.name.to_string(),
+            num_params: self.num_params,
+            handler: self.handler,
+        }
+    }
+}
+
+/// Вызывает встроенную функцию, проверив число аргументов.
+pub(crate) fn call(
+    name: &str,
+    num_params: i32,
+    handler: fn(Vec<Object>) -> Object,
+    args: Vec<Object>,
+) -> Object {
+    if num_params >= 0 && args.len() != num_params as usize {
+        return Object::error(format!(
+            "wrong number of arguments to {}: got={}, want={}",
+            name,
+            args.len(),
+            num_params
+        ));
+    }
+    handler(args)
+}
+
+/// `assert(cond)` — ошибка, если условие ложно; иначе Null.
+fn builtin_assert(args: Vec<Object>) -> Object {
+    match &args[0] {
+        Object::Null | Object::Boolean(false) => {
+            Object::error(format!("assertion failed: {}", args[0]))
+        }
+        _ => Object::Null,
+    }
+}
+
+/// `assert_eq(a, b)` — ошибка с обоими операндами, если они не равны; иначе Null.
+fn builtin_assert_eq(args: Vec<Object>) -> Object {
+    if args[0] == args[1] {
+        Object::Null
+    } else {
+        Object::error(format!("assertion failed: {} != {}", args[0], args[1]))
+    }
+}
+
+/// `collect(iterable)` — собирает элементы массива, диапазона или
+/// пользовательского итератора в массив. Массив возвращается как есть.
+fn builtin_collect(args: Vec<Object>) -> Object {
+    if let Object::Array(_) = &args[0] {
+        return args[0].clone();
+    }
+    let mut items = Vec::new();
+    match evaluator::iterate(&args[0], |item| {
+        items.push(item);
+        Ok(())
+    }) {
+        Ok(()) => Object::Array(Rc::new(items)),
+        Err(error) => error,
+    }
+}
+
+/// Наибольшая длина массива, который строят `range` и `repeat`.
+const MAX_ARRAY_LEN: i64 = 1_000_000;
+
+/// Длина массива из `count` элементов для встроенной функции `name`:
+/// отрицательное количество даёт пустой массив, слишком большое — ошибку.
+fn array_len(name: &str, count: i64) -> Result<usize, Object> {
+    if count > MAX_ARRAY_LEN {
+        return Err(Object::error(format!(
+            "{}: array of {} elements exceeds the limit of {}",
+            name, count, MAX_ARRAY_LEN
+        )));
+    }
+    Ok(count.max(0) as usize)
+}
+
+/// `range(start, end)` — массив целых от `start` включительно до `end`
+/// не включительно; при `start >= end` массив пуст.
+fn builtin_range(args: Vec<Object>) -> Object {
+    let Object::Integer(start) = args[0] else {
+        return wrong_argument_type("range", "INTEGER", &args[0], 1);
+    };
+    let Object::Integer(end) = args[1] else {
+        return wrong_argument_type("range", "INTEGER", &args[1], 2);
+    };
+    if let Err(error) = array_len("range", end.saturating_sub(start)) {
+        return error;
+    }
+    Object::Array(Rc::new((start..end).map(Object::Integer).collect()))
+}
+
+/// `repeat(value, n)` — массив из `n` копий значения; при `n <= 0` пуст.
+fn builtin_repeat(args: Vec<Object>) -> Object {
+    let Object::Integer(count) = args[1] else {
+        return wrong_argument_type("repeat", "INTEGER", &args[1], 2);
+    };
+    match array_len("repeat", count) {
+        Ok(len) => Object::Array(Rc::new(vec![args[0].clone(); len])),
+        Err(error) => error,
+    }
+}
+
+/// `abs(x)` — модуль целого числа.
+fn builtin_abs(args: Vec<Object>) -> Object {
+    let Object::Integer(value) = args[0] else {
+        return wrong_argument_type("abs", "INTEGER", &args[0], 1);
+    };
+    match value.checked_abs() {
+        Some(result) => Object::Integer(result),
+        None => Object::error(format!("abs: {} is out of range for INTEGER", value)),
+    }
+}
+
+/// `min(a, ...)` и `max(a, ...)` — наименьшее и наибольшее из одного или
+/// нескольких целых чисел; `pick` выбирает из пары.
+fn builtin_extremum(name: &str, args: &[Object], pick: fn(i64, i64) -> i64) -> Object {
+    let mut values = Vec::with_capacity(args.len());
+    for (index, arg) in args.iter().enumerate() {
+        match arg {
+            Object::Integer(value) => values.push(*value),
+            other => return wrong_argument_type(name, "INTEGER", other, index + 1),
+        }
+    }
+    match values.into_iter().reduce(pick) {
+        Some(result) => Object::Integer(result),
+        None => Object::error(format!(
+            "wrong number of arguments to {}: got=0, want at least 1",
+            name
+        )),
+    }
+}
+
+/// Кадр трассировки для функции, переданной встроенной функции аргументом.
+const CALLBACK_FRAME: &str = "<anonymous>()";
+
+/// Элементы массива и функция — аргументы `map`, `filter` и `reduce`.
+fn array_and_function<'a>(
+    name: &str,
+    args: &'a [Object],
+) -> Result<(&'a [Object], &'a Object), Object> {
+    let Object::Array(items) = &args[0] else {
+        return Err(wrong_argument_type(name, "ARRAY", &args[0], 1));
+    };
+    match &args[1] {
+        Object::Function(..) | Object::Method(_) | Object::BuiltinFunction { .. } => {
+            Ok((items, &args[1]))
+        }
+        other => Err(wrong_argument_type(name, "FUNCTION", other, 2)),
+    }
+}
+
+/// `map(arr, f)` — массив результатов `f` для каждого элемента.
+fn builtin_map(args: Vec<Object>) -> Object {
+    let (items, f) = match array_and_function("map", &args) {
+        Ok(pair) => pair,
+        Err(error) => return error,
+    };
+    let mut mapped = Vec::with_capacity(items.len());
+    for item in items {
+        match evaluator::apply_function(f.clone(), vec![item.clone()], CALLBACK_FRAME) {
+            error @ Object::Error(_) => return error,
+            value => mapped.push(value),
+        }
+    }
+    Object::Array(Rc::new(mapped))
+}
+
+/// `filter(arr, pred)` — элементы, для которых предикат истинен (по
+/// правилам `if`: ложны только `false` и `null`).
+fn builtin_filter(args: Vec<Object>) -> Object {
+    let (items, pred) = match array_and_function("filter", &args) {
+        Ok(pair) => pair,
+        Err(error) => return error,
+    };
+    let mut kept = Vec::new();
+    for item in items {
+        match evaluator::apply_function(pred.clone(), vec![item.clone()], CALLBACK_FRAME) {
+            error @ Object::Error(_) => return error,
+            verdict => {
+                if evaluator::is_truthy(verdict) {
+                    kept.push(item.clone());
+                }
+            }
+        }
+    }
+    Object::Array(Rc::new(kept))
+}
+
+/// `reduce(arr, f, init)` — сворачивает массив слева: `f(f(init, a), b)...`.
+fn builtin_reduce(args: Vec<Object>) -> Object {
+    let (items, f) = match array_and_function("reduce", &args) {
+        Ok(pair) => pair,
+        Err(error) => return error,
+    };
+    let mut acc = args[2].clone();
+    for item in items {
+        acc = evaluator::apply_function(f.clone(), vec![acc, item.clone()], CALLBACK_FRAME);
+        if let Object::Error(_) = acc {
+            return acc;
+        }
+    }
+    acc
+}
+
+/// `clone(x)` (синоним `copy`) — глубокая копия массива, хэша или
+/// экземпляра; остальные значения возвращаются без изменений.
+fn builtin_clone(args: Vec<Object>) -> Object {
+    args[0].deep_clone()
+}
+
+/// Текст ошибки для аргумента неверного типа; `position` считается с единицы.
+fn wrong_argument_type(name: &str, expected: &str, got: &Object, position: usize) -> Object {
+    Object::error(format!(
+        "wrong argument type for {}: expected {}, got {} at position {}",
+        name,
+        expected,
+        got.type_str(),
+        position
+    ))
+}
+
+/// Строковые аргументы встроенной функции `name` или ошибка о первом
+/// аргументе другого типа.
+fn string_args<'a>(name: &str, args: &'a [Object]) -> Result<Vec<&'a str>, Object> {
+    args.iter()
+        .enumerate()
+        .map(|(i, arg)| match arg {
+            Object::String(value) => Ok(value.as_str()),
+            other => Err(wrong_argument_type(name, "STRING", other, i + 1)),
+        })
+        .collect()
+}
+
+/// `split(s, sep)` — массив частей строки между разделителями. Пустой
+/// разделитель делит строку на символы.
+fn builtin_split(args: Vec<Object>) -> Object {
+    let (s, sep) = match string_args("split", &args) {
+        Ok(strings) => (strings[0], strings[1]),
+        Err(error) => return error,
+    };
+    let parts: Vec<Object> = if sep.is_empty() {
+        s.chars().map(|c| Object::String(c.to_string())).collect()
+    } else {
+        s.split(sep)
+            .map(|p| Object::String(p.to_string()))
+            .collect()
+    };
+    Object::Array(Rc::new(parts))
+}
+
+/// `join(arr, sep)` — склеивает строки массива через разделитель.
+fn builtin_join(args: Vec<Object>) -> Object {
+    let Object::Array(items) = &args[0] else {
+        return wrong_argument_type("join", "ARRAY", &args[0], 1);
+    };
+    let Object::String(sep) = &args[1] else {
+        return wrong_argument_type("join", "STRING", &args[1], 2);
+    };
+    let mut parts = Vec::with_capacity(items.len());
+    for (index, item) in items.iter().enumerate() {
+        match item {
+            Object::String(value) => parts.push(value.as_str()),
+            other => {
+                return Object::error(format!(
+                    "wrong element type for join: expected STRING, got {} at index {}",
+                    other.type_str(),
+                    index
+                ))
+            }
+        }
+    }
+    Object::String(parts.join(sep))
+}
+
+/// `trim(s)` — строка без пробельных символов Unicode по краям.
+fn builtin_trim(args: Vec<Object>) -> Object {
+    match string_args("trim", &args) {
+        Ok(strings) => Object::String(strings[0].trim().to_string()),
+        Err(error) => error,
+    }
+}
+
+/// `replace(s, from, to)` — заменяет все вхождения `from` на `to`.
+fn builtin_replace(args: Vec<Object>) -> Object {
+    match string_args("replace", &args) {
+        Ok(strings) => Object::String(strings[0].replace(strings[1], strings[2])),
+        Err(error) => error,
+    }
+}
+
+/// Позиция первого вхождения `item` в массив (по равенству `Object`) или
+/// подстроки в строку (в символах); `None`, если вхождения нет.
+fn find(name: &str, collection: &Object, item: &Object) -> Result<Option<usize>, Object> {
+    match (collection, item) {
+        (Object::Array(elements), _) => Ok(elements.iter().position(|e| e == item)),
+        (Object::String(s), Object::String(sub)) => {
+            Ok(s.find(sub.as_str()).map(|byte| s[..byte].chars().count()))
+        }
+        (Object::String(_), other) => Err(wrong_argument_type(name, "STRING", other, 2)),
+        (other, _) => Err(wrong_argument_type(name, "ARRAY or STRING", other, 1)),
+    }
+}
+
+/// `contains(collection, item)` — есть ли элемент в массиве или подстрока
+/// в строке.
+fn builtin_contains(args: Vec<Object>) -> Object {
+    match find("contains", &args[0], &args[1]) {
+        Ok(index) => Object::Boolean(index.is_some()),
+        Err(error) => error,
+    }
+}
+
+/// `index_of(collection, item)` — индекс первого вхождения или -1.
+fn builtin_index_of(args: Vec<Object>) -> Object {
+    match find("index_of", &args[0], &args[1]) {
+        Ok(index) => Object::Integer(index.map_or(-1, |i| i as i64)),
+        Err(error) => error,
+    }
+}
+
+/// `starts_with(s, prefix)` — начинается ли строка с префикса.
+fn builtin_starts_with(args: Vec<Object>) -> Object {
+    match string_args("starts_with", &args) {
+        Ok(strings) => Object::Boolean(strings[0].starts_with(strings[1])),
+        Err(error) => error,
+    }
+}
+
+/// `ends_with(s, suffix)` — заканчивается ли строка суффиксом.
+fn builtin_ends_with(args: Vec<Object>) -> Object {
+    match string_args("ends_with", &args) {
+        Ok(strings) => Object::Boolean(strings[0].ends_with(strings[1])),
+        Err(error) => error,
+    }
+}
+
+/// `to_string(x)` — строковое представление любого значения (как при выводе).
+fn builtin_to_string(args: Vec<Object>) -> Object {
+    evaluator::stringify(&args[0]).map_or_else(|error| error, Object::String)
+}
+
+/// Разбирает целое в системе счисления `radix` (пробелы по краям
+/// отбрасываются); ошибка называет исходную строку.
+fn parse_integer(name: &str, s: &str, radix: u32) -> Result<i64, Object> {
+    i64::from_str_radix(s.trim(), radix).map_err(|error| {
+        Object::error(match error.kind() {
+            IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => {
+                format!("{}: {:?} is out of range for INTEGER", name, s)
+            }
+            _ if radix == 10 => format!("{}: {:?} is not a valid INTEGER", name, s),
+            _ => format!(
+                "{}: {:?} is not a valid INTEGER in radix {}",
+                name, s, radix
+            ),
+        })
+    })
+}
+
+/// `int(x)` — целое из строки (пробелы по краям отбрасываются), целого
+/// или логического значения (`true` — 1, `false` — 0).
+fn builtin_int(args: Vec<Object>) -> Object {
+    match &args[0] {
+        Object::Integer(value) => Object::Integer(*value),
+        Object::Boolean(value) => Object::Integer(i64::from(*value)),
+        Object::String(s) => {
+            parse_integer("int", s, 10).map_or_else(|error| error, Object::Integer)
+        }
+        other => Object::error(format!(
+            "int: cannot convert {} {} to INTEGER",
+            other.type_str(),
+            other.summary()
+        )),
+    }
+}
+
+/// `parse_int(s, radix)` — целое из строки в системе счисления от 2 до 36.
+fn builtin_parse_int(args: Vec<Object>) -> Object {
+    let Object::String(s) = &args[0] else {
+        return wrong_argument_type("parse_int", "STRING", &args[0], 1);
+    };
+    let Object::Integer(radix) = args[1] else {
+        return wrong_argument_type("parse_int", "INTEGER", &args[1], 2);
+    };
+    if !(2..=36).contains(&radix) {
+        return Object::error(format!(
+            "parse_int: radix must be between 2 and 36, got {}",
+            radix
+        ));
+    }
+    parse_integer("parse_int", s, radix as u32).map_or_else(|error| error, Object::Integer)
+}
+
+/// Подставляет аргументы в шаблон по порядку вместо `{}`; `{{` и `}}`
+/// дают literal-скобки. Непарная скобка и несовпадение числа подстановок
+/// с числом аргументов — ошибка, как и ошибка `to_string()` аргумента.
+fn format_template(template: &str, args: &[Object]) -> Result<String, Object> {
+    let mut result = String::with_capacity(template.len());
+    let mut placeholders = 0;
+    let mut chars = template.chars().enumerate().peekable();
+    while let Some((position, c)) = chars.next() {
+        match (c, chars.peek().map(|&(_, next)| next)) {
+            ('{', Some('{')) | ('}', Some('}')) => {
+                chars.next();
+                result.push(c);
+            }
+            ('{', Some('}')) => {
+                chars.next();
+                if let Some(arg) = args.get(placeholders) {
+                    result.push_str(&evaluator::stringify(arg)?);
+                }
+                placeholders += 1;
+            }
+            ('{', _) | ('}', _) => {
+                return Err(Object::error(format!(
+                    "format: unmatched '{}' at position {}",
+                    c, position
+                )))
+            }
+            _ => result.push(c),
+        }
+    }
+    if placeholders != args.len() {
+        return Err(Object::error(format!(
+            "format: template has {} placeholders, got {} arguments",
+            placeholders,
+            args.len()
+        )));
+    }
+    Ok(result)
+}
+
+/// `format(template, ...args)` — строка с аргументами вместо `{}`.
+fn builtin_format(args: Vec<Object>) -> Object {
+    let Some((template, rest)) = args.split_first() else {
+        return Object::error(
+            "wrong number of arguments to format: got=0, want at least 1".to_string(),
+        );
+    };
+    let Object::String(template) = template else {
+        return wrong_argument_type("format", "STRING", template, 1);
+    };
+    format_template(template, rest).map_or_else(|error| error, Object::String)
+}
+
+/// Число элементов коллекции: длина массива, число символов Unicode в
+/// строке или число ключей хэша; для других значений — ошибка.
+fn collection_len(name: &str, value: &Object) -> Result<usize, Object> {
+    match value {
+        Object::Array(items) => Ok(items.len()),
+        Object::String(s) => Ok(s.chars().count()),
+        Object::Hash(pairs) => Ok(pairs.len()),
+        other => Err(wrong_argument_type(name, "ARRAY, STRING or HASH", other, 1)),
+    }
+}
+
+/// `len(x)` (синоним `size`) — число элементов массива, символов строки
+/// или ключей хэша.
+fn builtin_len(args: Vec<Object>) -> Object {
+    match collection_len("len", &args[0]) {
+        Ok(len) => Object::Integer(len as i64),
+        Err(error) => error,
+    }
+}
+
+/// `keys(hash)` — массив ключей хэша в порядке вставки.
+fn builtin_keys(args: Vec<Object>) -> Object {
+    match &args[0] {
+        Object::Hash(pairs) => {
+            Object::Array(Rc::new(pairs.keys().map(HashKey::to_object).collect()))
+        }
+        other => wrong_argument_type("keys", "HASH", other, 1),
+    }
+}
+
+/// `is_empty(x)` — пуст ли массив, строка или хэш.
+fn builtin_is_empty(args: Vec<Object>) -> Object {
+    match collection_len("is_empty", &args[0]) {
+        Ok(len) => Object::Boolean(len == 0),
+        Err(error) => error,
+    }
+}
+
+/// `is_fn(x)` — можно ли вызвать значение: функция, замыкание, метод
+/// или встроенная функция.
+fn builtin_is_fn(args: Vec<Object>) -> Object {
+    Object::Boolean(matches!(
+        args[0],
+        Object::Function(..)
+            | Object::CompiledFunction(_)
+            | Object::Closure(..)
+            | Object::Method(_)
+            | Object::BuiltinFunction { .. }
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::HashKey;
+
+    fn call(name: &str, args: Vec<Object>) -> Object {
+        match lookup(name) {
+            Some(Object::BuiltinFunction { handler, .. }) => handler(args),
+            other => panic!("expected builtin {}, got {:?}", name, other),
+        }
+    }
+
+    #[test]
+    fn test_type_predicates() {
+        let values = [
+            Object::Integer(1),
+            Object::String("s".to_string()),
+            Object::Boolean(false),
+            Object::Array(Rc::new(vec![])),
+            Object::Null,
+            lookup("trim").unwrap(),
+        ];
+        let predicates = [
+            "is_int",
+            "is_string",
+            "is_bool",
+            "is_array",
+            "is_null",
+            "is_fn",
+        ];
+        // Каждый предикат истинен ровно для значения своего типа
+        for (i, predicate) in predicates.iter().enumerate() {
+            for (j, value) in values.iter().enumerate() {
+                assert_eq!(
+                    call(predicate, vec![value.clone()]),
+                    Object::Boolean(i == j),
+                    "{}({})",
+                    predicate,
+                    value
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_len_of_hash() {
+        let hash = |keys: &[&str]| {
+            Object::Hash(Rc::new(
+                keys.iter()
+                    .map(|k| (HashKey::String(k.to_string()), Object::Integer(1)))
+                    .collect(),
+            ))
+        };
+        assert_eq!(call("len", vec![hash(&["a"])]), Object::Integer(1));
+        assert_eq!(call("size", vec![hash(&["a", "b"])]), Object::Integer(2));
+        assert_eq!(call("is_empty", vec![hash(&[])]), Object::Boolean(true));
+        assert_eq!(call("is_empty", vec![hash(&["a"])]), Object::Boolean(false));
+    }
+
+    #[test]
+    fn test_names_unique() {
+        for (index, name) in names().enumerate() {
+            assert_eq!(
+                names().position(|other| other == name),
+                Some(index),
+                "{}",
+                name
+            );
+            assert_eq!(lookup(name), get(index), "{}", name);
+        }
+    }
+
+    fn format(template: &str, args: &[Object]) -> Result<String, String> {
+        format_template(template, args).map_err(|error| match error {
+            Object::Error(error) => error.message,
+            other => other.to_string(),
+        })
+    }
+
+    #[test]
+    fn test_format_placeholders() {
+        let args = [Object::Integer(1), Object::String("два".to_string())];
+        assert_eq!(format("{} и {}", &args), Ok("1 и два".to_string()));
+        assert_eq!(format("{}{}", &args), Ok("1два".to_string()));
+        assert_eq!(
+            format("без подстановок", &[]),
+            Ok("без подстановок".to_string())
+        );
+        assert_eq!(format("", &[]), Ok(String::new()));
+        assert_eq!(
+            format("{}", &[Object::Array(Rc::new(vec![Object::Null]))]),
+            Ok("[null]".to_string())
+        );
+    }
+
+    #[test]
+    fn test_format_escapes() {
+        assert_eq!(format("{{}}", &[]), Ok("{}".to_string()));
+        assert_eq!(
+            format("{{{}}}", &[Object::Integer(7)]),
+            Ok("{7}".to_string())
+        );
+        assert_eq!(format("}}{{", &[]), Ok("}{".to_string()));
+        // Подставленное значение не разбирается как шаблон
+        assert_eq!(
+            format("{}", &[Object::String("{}".to_string())]),
+            Ok("{}".to_string())
+        );
+    }
+
+    #[test]
+    fn test_format_errors() {
+        assert_eq!(
+            format("{} {}", &[Object::Integer(1)]),
+            Err("format: template has 2 placeholders, got 1 arguments".to_string())
+        );
+        assert_eq!(
+            format("x", &[Object::Integer(1)]),
+            Err("format: template has 0 placeholders, got 1 arguments".to_string())
+        );
+        assert_eq!(
+            format("ab{", &[]),
+            Err("format: unmatched '{' at position 2".to_string())
+        );
+        assert_eq!(
+            format("{x}", &[]),
+            Err("format: unmatched '{' at position 0".to_string())
+        );
+        assert_eq!(
+            format("я}", &[]),
+            Err("format: unmatched '}' at position 1".to_string())
+        );
+        assert_eq!(
+            call("format", vec![]),
+            Object::error(
+                "wrong number of arguments to format: got=0, want at least 1".to_string()
+            )
+        );
+        assert_eq!(
+            call("format", vec![Object::Integer(1)]),
+            Object::error(
+                "wrong argument type for format: expected STRING, got INTEGER at position 1"
+                    .to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn test_lookup_unknown() {
+        assert!(lookup("nope").is_none());
+    }
+
+    #[test]
+    fn test_assert() {
+        assert_eq!(call("assert", vec![Object::Boolean(true)]), Object::Null);
+        assert_eq!(
+            call("assert", vec![Object::Boolean(false)]),
+            Object::error("assertion failed: false".to_string())
+        );
+    }
+
+    #[test]
+    fn test_assert_eq() {
+        assert_eq!(
+            call("assert_eq", vec![Object::Integer(1), Object::Integer(1)]),
+            Object::Null
+        );
+        assert_eq!(
+            call("assert_eq", vec![Object::Integer(1), Object::Integer(2)]),
+            Object::error("assertion failed: 1 != 2".to_string())
+        );
+    }
+
+    #[test]
+    fn test_collect() {
+        assert_eq!(
+            call("collect", vec![Object::Range(1, 4)]),
+            Object::Array(Rc::new(vec![
+                Object::Integer(1),
+                Object::Integer(2),
+                Object::Integer(3),
+            ]))
+        );
+        assert_eq!(
+            call("collect", vec![Object::Integer(1)]),
+            Object::error("not iterable: INTEGER".to_string())
+        );
+    }
+}
@@ -0,0 +1,73 @@
+use crate::object::{CompiledFunction, Object};
+use std::fmt;
+use std::rc::Rc;
+
+/// Значение в пуле констант байткода.
+///
+/// В отличие от `Object`, не содержит `Rc` и окружений, поэтому
+/// скомпилированную программу можно передать в другой поток (`Send + Sync`).
+/// В объекты времени выполнения константы превращает `VM::new`.
+#[derive(Debug, PartialEq, Clone)]
+pub enum Constant {
+    Integer(i64),
+    String(String),
+    Boolean(bool),
+    Null,
+    Array(Vec<Constant>),
+    CompiledFunction(CompiledFunction),
+}
+
+impl Constant {
+    /// Создаёт объект времени выполнения с тем же значением.
+    pub fn to_object(&self) -> Object {
+        match self {
+            Constant::Integer(value) => Object::Integer(*value),
+            Constant::String(value) => Object::String(value.clone()),
+            Constant::Boolean(value) => Object::Boolean(*value),
+            Constant::Null => Object::Null,
+            Constant::Array(items) => {
+                Object::Array(Rc::new(items.iter().map(Constant::to_object).collect()))
+            }
+            Constant::CompiledFunction(function) => Object::CompiledFunction(function.clone()),
+        }
+    }
+}
+
+impl fmt::Display for Constant {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.to_object())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_constant_is_send_and_sync() {
+        assert_send_sync::<Constant>();
+        assert_send_sync::<crate::bytecode::instructions::Instructions>();
+    }
+
+    #[test]
+    fn test_constant_to_object() {
+        let constant = Constant::Array(vec![
+            Constant::Integer(1),
+            Constant::String("a".to_string()),
+            Constant::Boolean(true),
+            Constant::Null,
+        ]);
+        assert_eq!(
+            constant.to_object(),
+            Object::Array(Rc::new(vec![
+                Object::Integer(1),
+                Object::String("a".to_string()),
+                Object::Boolean(true),
+                Object::Null,
+            ]))
+        );
+        assert_eq!(constant.to_string(), "[1, a, true, null]");
+    }
+}
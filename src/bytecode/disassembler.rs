@@ -1,10 +1,22 @@
+use crate::ast::{NodeId, Program};
 use crate::bytecode::instructions::Instructions;
+use crate::bytecode::opcode::Opcode;
 
 /// Дизассемблирует последовательность инструкций в читаемую строку.
 ///
 /// Возвращает строку, где каждая строка представляет собой одну инструкцию
 /// с её смещением, мнемоникой и операндами.
 pub fn disassemble(instructions: &Instructions) -> String {
+    disassemble_annotated(instructions, None)
+}
+
+/// Как `disassemble`, но рядом с каждым `MAP_TO_AST` выводит оператор
+/// программы `program`, на который он ссылается.
+pub fn disassemble_with_program(instructions: &Instructions, program: &Program) -> String {
+    disassemble_annotated(instructions, Some(program))
+}
+
+fn disassemble_annotated(instructions: &Instructions, program: Option<&Program>) -> String {
     let mut output = String::new();
     let mut i = 0;
 
@@ -13,10 +25,8 @@ pub fn disassemble(instructions: &Instructions) -> String {
     // Выводим константы, если они есть
     if !instructions.constants.is_empty() {
         output.push_str("=== CONSTANTS POOL ===\n");
-        for (idx, constant) in instructions.constants.iter().enumerate() {
-            output.push_str(&format!("[{}] {}\n", idx, constant));
-        }
-        output.push_str("\n");
+        output.push_str(&instructions.dump_constants());
+        output.push('\n');
     }
 
     output.push_str("=== INSTRUCTIONS ===\n");
@@ -28,12 +38,24 @@ pub fn disassemble(instructions: &Instructions) -> String {
 
             output.push_str(&format!("{:04} {}", i, opcode.mnemonic()));
 
-            // Выводим операнды
-            if !operands.is_empty() {
+            // Выводим операнды; для переходов — знаковое смещение и адрес цели
+            if matches!(
+                opcode,
+                Opcode::Jump | Opcode::JumpIfFalse | Opcode::JumpIfTrue
+            ) {
+                let offset = operands[0] as i16;
+                let target = (i + 1 + read) as isize + offset as isize;
+                output.push_str(&format!(" {:+} (-> {:04})", offset, target));
+            } else if !operands.is_empty() {
                 for operand in &operands {
                     output.push_str(&format!(" {}", operand));
                 }
             }
+            if let (Opcode::MapToAst, Some(program)) = (opcode, program) {
+                if let Some(statement) = program.find_node(NodeId(u32::from(operands[0]))) {
+                    output.push_str(&format!(" ; {}", statement));
+                }
+            }
             output.push('\n');
 
             i += 1 + read; // Смещение + байт опкода + байты операндов
@@ -1,13 +1,109 @@
+use crate::ast::NodeId;
+use crate::bytecode::constant::Constant;
 use crate::bytecode::opcode::Opcode;
-use crate::object::Object;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Ошибка построения байткода.
+#[derive(Debug, PartialEq, Clone)]
+pub enum BytecodeError {
+    /// Число операндов не совпадает с объявленным для опкода.
+    OperandCount {
+        opcode: Opcode,
+        expected: usize,
+        got: usize,
+    },
+    /// Значение операнда не помещается в его ширину.
+    OperandOutOfRange {
+        opcode: Opcode,
+        operand: usize,
+        width: usize,
+    },
+    /// Заглушку перехода можно создать только для опкода перехода.
+    NotAJump(Opcode),
+    /// Цель перехода лежит за концом байткода.
+    JumpTargetOutOfBounds {
+        position: usize,
+        target: usize,
+        len: usize,
+    },
+    /// Смещение перехода не помещается в `i16`.
+    JumpOffsetOverflow { position: usize, offset: i64 },
+}
+
+impl fmt::Display for BytecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BytecodeError::OperandCount {
+                opcode,
+                expected,
+                got,
+            } => write!(
+                f,
+                "{} ожидает операндов: {}, передано: {}",
+                opcode.mnemonic(),
+                expected,
+                got
+            ),
+            BytecodeError::OperandOutOfRange {
+                opcode,
+                operand,
+                width,
+            } => write!(
+                f,
+                "Операнд {} опкода {} не помещается в {} байт(а)",
+                operand,
+                opcode.mnemonic(),
+                width
+            ),
+            BytecodeError::NotAJump(opcode) => {
+                write!(f, "{} не является опкодом перехода", opcode.mnemonic())
+            }
+            BytecodeError::JumpTargetOutOfBounds {
+                position,
+                target,
+                len,
+            } => write!(
+                f,
+                "Цель {} перехода по адресу {} за концом байткода длиной {}",
+                target, position, len
+            ),
+            BytecodeError::JumpOffsetOverflow { position, offset } => write!(
+                f,
+                "Смещение перехода {} по адресу {} выходит за пределы i16",
+                offset, position
+            ),
+        }
+    }
+}
+
+/// Адрес ещё не заполненного перехода, созданного
+/// `Instructions::emit_jump_placeholder`. Заполняется через `patch_jump`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[must_use = "переход нужно заполнить через patch_jump"]
+pub struct PatchHandle {
+    position: usize,
+}
+
+impl PatchHandle {
+    /// Смещение опкода перехода.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+}
 
 /// Представляет последовательность байткода, состоящую из опкодов, операндов и пула констант.
+/// Пул хранит простые данные (`Constant`), поэтому скомпилированную программу
+/// можно передать в другой поток и выполнить там на VM.
 #[derive(Debug, PartialEq, Clone)]
 pub struct Instructions {
     /// Вектор байтов, содержащий инструкции.
     pub bytes: Vec<u8>,
     /// Пул констант для оптимизации хранения литеральных значений.
-    pub constants: Vec<Object>,
+    pub constants: Vec<Constant>,
+    /// Строка и столбец начала оператора по идентификатору из `MapToAst`:
+    /// по ним VM сообщает, где произошла ошибка.
+    pub positions: HashMap<NodeId, (usize, usize)>,
 }
 
 impl Instructions {
@@ -16,6 +112,7 @@ impl Instructions {
         Instructions {
             bytes: Vec::new(),
             constants: Vec::new(),
+            positions: HashMap::new(),
         }
     }
 
@@ -27,26 +124,90 @@ impl Instructions {
     }
 
     /// Добавляет константу в пул и возвращает её индекс.
-    pub fn add_constant(&mut self, obj: Object) -> usize {
-        self.constants.push(obj);
+    pub fn add_constant(&mut self, constant: Constant) -> usize {
+        self.constants.push(constant);
         self.constants.len() - 1
     }
 
     /// Эмитирует опкод и его операнды, добавляя их в список инструкций.
     /// Возвращает смещение, с которого начинается добавленный опкод.
-    pub fn emit(&mut self, op: Opcode, operands: &[u16]) -> usize {
+    ///
+    /// Число операндов и их значения проверяются по `Opcode::operand_widths`:
+    /// индекс константы, число элементов или слот, не помещающиеся в ширину
+    /// операнда, дают ошибку, а не усечённый операнд. При ошибке байткод
+    /// не меняется.
+    pub fn emit(&mut self, op: Opcode, operands: &[usize]) -> Result<usize, BytecodeError> {
+        let widths = op.operand_widths();
+        if operands.len() != widths.len() {
+            return Err(BytecodeError::OperandCount {
+                opcode: op,
+                expected: widths.len(),
+                got: operands.len(),
+            });
+        }
+        for (&operand, &width) in operands.iter().zip(widths) {
+            let max = match width {
+                1 => u8::MAX as usize,
+                _ => u16::MAX as usize,
+            };
+            if operand > max {
+                return Err(BytecodeError::OperandOutOfRange {
+                    opcode: op,
+                    operand,
+                    width: width as usize,
+                });
+            }
+        }
+
         let pos = self.bytes.len();
         self.bytes.push(op as u8); // Добавляем байт опкода
-
-        for (i, &operand) in operands.iter().enumerate() {
-            let width = op.operand_widths()[i];
+        for (&operand, &width) in operands.iter().zip(widths) {
             match width {
                 1 => self.bytes.push(operand as u8),
-                2 => self.bytes.extend_from_slice(&operand.to_be_bytes()),
+                2 => self
+                    .bytes
+                    .extend_from_slice(&(operand as u16).to_be_bytes()),
                 _ => panic!("Неподдерживаемая ширина операнда: {}", width),
             }
         }
-        pos
+        Ok(pos)
+    }
+
+    /// Эмитирует `Constant` с индексом в пуле констант.
+    pub fn emit_constant(&mut self, index: usize) -> Result<usize, BytecodeError> {
+        self.emit(Opcode::Constant, &[index])
+    }
+
+    /// Эмитирует переход `op` с пустым операндом. Цель задаётся позже
+    /// через `patch_jump`.
+    pub fn emit_jump_placeholder(&mut self, op: Opcode) -> Result<PatchHandle, BytecodeError> {
+        if !matches!(op, Opcode::Jump | Opcode::JumpIfFalse | Opcode::JumpIfTrue) {
+            return Err(BytecodeError::NotAJump(op));
+        }
+        let position = self.emit(op, &[0])?;
+        Ok(PatchHandle { position })
+    }
+
+    /// Записывает в операнд перехода смещение до `target` (big-endian).
+    ///
+    /// Смещение отсчитывается от конца инструкции перехода и должно
+    /// помещаться в `i16`; цель не может лежать за концом байткода.
+    pub fn patch_jump(&mut self, handle: PatchHandle, target: usize) -> Result<(), BytecodeError> {
+        let position = handle.position;
+        if target > self.bytes.len() {
+            return Err(BytecodeError::JumpTargetOutOfBounds {
+                position,
+                target,
+                len: self.bytes.len(),
+            });
+        }
+        let offset = target as i64 - (position + 3) as i64;
+        let offset = i16::try_from(offset)
+            .map_err(|_| BytecodeError::JumpOffsetOverflow { position, offset })?;
+        let [high, low] = offset.to_be_bytes();
+        self.bytes[position + 1] = high;
+        self.bytes[position + 2] = low;
+        Ok(())
     }
 
     /// Читает опкод из байтов по заданному смещению.
@@ -88,14 +249,27 @@ impl Instructions {
     }
 
     /// Получить константу по индексу.
-    pub fn get_constant(&self, index: usize) -> Option<&Object> {
+    pub fn get_constant(&self, index: usize) -> Option<&Constant> {
         self.constants.get(index)
     }
 
     /// Получить все константы.
-    pub fn get_constants(&self) -> &[Object] {
+    pub fn get_constants(&self) -> &[Constant] {
         &self.constants
     }
+
+    /// Листинг пула констант: по строке на константу с индексом, типом
+    /// и кратким описанием (`Object::summary`).
+    pub fn dump_constants(&self) -> String {
+        self.constants
+            .iter()
+            .enumerate()
+            .map(|(index, constant)| {
+                let object = constant.to_object();
+                format!("[{}] {} {}\n", index, object.type_str(), object.summary())
+            })
+            .collect()
+    }
 }
 
 impl Default for Instructions {
@@ -120,9 +294,9 @@ mod tests {
         let mut instr = Instructions::new();
 
         // Добавляем несколько констант
-        let idx1 = instr.add_constant(Object::Integer(42));
-        let idx2 = instr.add_constant(Object::String("hello".to_string()));
-        let idx3 = instr.add_constant(Object::Integer(100));
+        let idx1 = instr.add_constant(Constant::Integer(42));
+        let idx2 = instr.add_constant(Constant::String("hello".to_string()));
+        let idx3 = instr.add_constant(Constant::Integer(100));
 
         assert_eq!(idx1, 0);
         assert_eq!(idx2, 1);
@@ -130,12 +304,12 @@ mod tests {
         assert_eq!(instr.constants.len(), 3);
 
         // Проверяем что константы правильно сохранены
-        assert_eq!(instr.get_constant(0), Some(&Object::Integer(42)));
+        assert_eq!(instr.get_constant(0), Some(&Constant::Integer(42)));
         assert_eq!(
             instr.get_constant(1),
-            Some(&Object::String("hello".to_string()))
+            Some(&Constant::String("hello".to_string()))
         );
-        assert_eq!(instr.get_constant(2), Some(&Object::Integer(100)));
+        assert_eq!(instr.get_constant(2), Some(&Constant::Integer(100)));
         assert_eq!(instr.get_constant(3), None);
     }
 
@@ -144,9 +318,9 @@ mod tests {
         let mut instr = Instructions::new();
 
         // Эмитируем опкоды без операндов
-        instr.emit(Opcode::Add, &[]);
-        instr.emit(Opcode::Sub, &[]);
-        instr.emit(Opcode::Pop, &[]);
+        instr.emit(Opcode::Add, &[]).unwrap();
+        instr.emit(Opcode::Sub, &[]).unwrap();
+        instr.emit(Opcode::Pop, &[]).unwrap();
 
         assert_eq!(instr.bytes.len(), 3);
         assert_eq!(instr.bytes[0], Opcode::Add as u8);
@@ -159,8 +333,8 @@ mod tests {
         let mut instr = Instructions::new();
 
         // Эмитируем опкоды с 2-байтовыми операндами
-        let pos1 = instr.emit(Opcode::Constant, &[255]);
-        let pos2 = instr.emit(Opcode::Jump, &[512]);
+        let pos1 = instr.emit(Opcode::Constant, &[255]).unwrap();
+        let pos2 = instr.emit(Opcode::Jump, &[512]).unwrap();
 
         assert_eq!(pos1, 0);
         assert_eq!(pos2, 3); // 1 byte opcode + 2 bytes operand
@@ -179,7 +353,7 @@ mod tests {
     fn test_emit_with_1byte_operand() {
         let mut instr = Instructions::new();
 
-        let pos = instr.emit(Opcode::GetLocal, &[5]);
+        let pos = instr.emit(Opcode::GetLocal, &[5]).unwrap();
 
         assert_eq!(pos, 0);
         assert_eq!(instr.bytes.len(), 2); // 1 byte opcode + 1 byte operand
@@ -190,8 +364,8 @@ mod tests {
     #[test]
     fn test_read_opcode() {
         let mut instr = Instructions::new();
-        instr.emit(Opcode::Add, &[]);
-        instr.emit(Opcode::Constant, &[10]);
+        instr.emit(Opcode::Add, &[]).unwrap();
+        instr.emit(Opcode::Constant, &[10]).unwrap();
 
         assert_eq!(
             Instructions::read_opcode(&instr.bytes, 0),
@@ -207,7 +381,7 @@ mod tests {
     #[test]
     fn test_read_operands() {
         let mut instr = Instructions::new();
-        instr.emit(Opcode::Constant, &[255]);
+        instr.emit(Opcode::Constant, &[255]).unwrap();
 
         // Читаем операнды для Constant (должны быть 2 байта)
         let (operands, bytes_read) = Instructions::read_operands(Opcode::Constant, &instr.bytes, 1);
@@ -220,7 +394,7 @@ mod tests {
     #[test]
     fn test_read_operands_1byte() {
         let mut instr = Instructions::new();
-        instr.emit(Opcode::GetLocal, &[7]);
+        instr.emit(Opcode::GetLocal, &[7]).unwrap();
 
         let (operands, bytes_read) = Instructions::read_operands(Opcode::GetLocal, &instr.bytes, 1);
 
@@ -234,7 +408,7 @@ mod tests {
         let mut instr = Instructions::new();
 
         // Проверяем big-endian кодирование больших чисел
-        instr.emit(Opcode::Jump, &[1024]); // 0x0400
+        instr.emit(Opcode::Jump, &[1024]).unwrap(); // 0x0400
 
         assert_eq!(instr.bytes[1], 4); // Старший байт
         assert_eq!(instr.bytes[2], 0); // Младший байт
@@ -246,11 +420,11 @@ mod tests {
     #[test]
     fn test_append() {
         let mut instr1 = Instructions::new();
-        instr1.emit(Opcode::Add, &[]);
-        instr1.emit(Opcode::Sub, &[]);
+        instr1.emit(Opcode::Add, &[]).unwrap();
+        instr1.emit(Opcode::Sub, &[]).unwrap();
 
         let mut instr2 = Instructions::new();
-        instr2.emit(Opcode::Mul, &[]);
+        instr2.emit(Opcode::Mul, &[]).unwrap();
 
         instr1.append(&instr2);
 
@@ -260,18 +434,52 @@ mod tests {
         assert_eq!(instr1.bytes[2], Opcode::Mul as u8);
     }
 
+    #[test]
+    fn test_dump_constants() {
+        let mut instr = Instructions::new();
+        instr.add_constant(Constant::CompiledFunction(
+            crate::object::CompiledFunction {
+                instructions_offset: 0x40,
+                num_locals: 3,
+                num_params: 2,
+            },
+        ));
+        instr.add_constant(Constant::String("ab".repeat(20)));
+        instr.add_constant(Constant::Array(vec![
+            Constant::Integer(1),
+            Constant::Array(vec![
+                Constant::String("x".to_string()),
+                Constant::Boolean(true),
+            ]),
+            Constant::Null,
+        ]));
+        instr.add_constant(Constant::Array((0..10).map(Constant::Integer).collect()));
+
+        assert_eq!(
+            instr.dump_constants(),
+            format!(
+                "[0] COMPILED_FUNCTION fn/2 @0x0040 (3 locals)\n\
+                 [1] STRING \"{}…\" (40 chars)\n\
+                 [2] ARRAY [1, [\"x\", true], null]\n\
+                 [3] ARRAY [10 elements]\n",
+                "ab".repeat(16)
+            )
+        );
+        assert_eq!(Instructions::new().dump_constants(), "");
+    }
+
     #[test]
     fn test_get_constants() {
         let mut instr = Instructions::new();
-        instr.add_constant(Object::Integer(1));
-        instr.add_constant(Object::Integer(2));
-        instr.add_constant(Object::Integer(3));
+        instr.add_constant(Constant::Integer(1));
+        instr.add_constant(Constant::Integer(2));
+        instr.add_constant(Constant::Integer(3));
 
         let constants = instr.get_constants();
         assert_eq!(constants.len(), 3);
-        assert_eq!(constants[0], Object::Integer(1));
-        assert_eq!(constants[1], Object::Integer(2));
-        assert_eq!(constants[2], Object::Integer(3));
+        assert_eq!(constants[0], Constant::Integer(1));
+        assert_eq!(constants[1], Constant::Integer(2));
+        assert_eq!(constants[2], Constant::Integer(3));
     }
 
     #[test]
@@ -280,4 +488,110 @@ mod tests {
         assert_eq!(instr.bytes.len(), 0);
         assert_eq!(instr.constants.len(), 0);
     }
+
+    #[test]
+    fn test_emit_wrong_operand_count() {
+        let mut instr = Instructions::new();
+        assert_eq!(
+            instr.emit(Opcode::Constant, &[]),
+            Err(BytecodeError::OperandCount {
+                opcode: Opcode::Constant,
+                expected: 1,
+                got: 0,
+            })
+        );
+        assert_eq!(
+            instr.emit(Opcode::Add, &[1]),
+            Err(BytecodeError::OperandCount {
+                opcode: Opcode::Add,
+                expected: 0,
+                got: 1,
+            })
+        );
+        assert_eq!(
+            instr.emit(Opcode::GetLocal, &[256]),
+            Err(BytecodeError::OperandOutOfRange {
+                opcode: Opcode::GetLocal,
+                operand: 256,
+                width: 1,
+            })
+        );
+        assert_eq!(
+            instr.emit(Opcode::GetGlobal, &[u16::MAX as usize + 1]),
+            Err(BytecodeError::OperandOutOfRange {
+                opcode: Opcode::GetGlobal,
+                operand: u16::MAX as usize + 1,
+                width: 2,
+            })
+        );
+        // Ошибочные инструкции не попадают в байткод
+        assert!(instr.bytes.is_empty());
+        assert_eq!(
+            instr.emit(Opcode::Constant, &[]).unwrap_err().to_string(),
+            "CONSTANT ожидает операндов: 1, передано: 0"
+        );
+    }
+
+    #[test]
+    fn test_emit_constant() {
+        let mut instr = Instructions::new();
+        assert_eq!(instr.emit_constant(258), Ok(0));
+        assert_eq!(instr.bytes, vec![Opcode::Constant as u8, 1, 2]);
+        assert!(matches!(
+            instr.emit_constant(70_000),
+            Err(BytecodeError::OperandOutOfRange {
+                operand: 70_000,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn test_patch_jump_big_endian() {
+        let mut instr = Instructions::new();
+        let forward = instr.emit_jump_placeholder(Opcode::JumpIfFalse).unwrap();
+        assert_eq!(forward.position(), 0);
+        for _ in 0..300 {
+            instr.emit(Opcode::Pop, &[]).unwrap();
+        }
+        // Смещение 300 = 0x012C от конца инструкции (адрес 3)
+        instr.patch_jump(forward, 303).unwrap();
+        assert_eq!(&instr.bytes[..3], &[Opcode::JumpIfFalse as u8, 0x01, 0x2C]);
+
+        // Переход назад: смещение -306 = 0xFECE
+        let backward = instr.emit_jump_placeholder(Opcode::Jump).unwrap();
+        instr.patch_jump(backward, 0).unwrap();
+        assert_eq!(&instr.bytes[303..], &[Opcode::Jump as u8, 0xFE, 0xCE]);
+        let (operands, _) = Instructions::read_operands(Opcode::Jump, &instr.bytes, 304);
+        assert_eq!(operands[0] as i16, -306);
+    }
+
+    #[test]
+    fn test_patch_jump_out_of_range() {
+        let mut instr = Instructions::new();
+        assert_eq!(
+            instr.emit_jump_placeholder(Opcode::Add),
+            Err(BytecodeError::NotAJump(Opcode::Add))
+        );
+        let handle = instr.emit_jump_placeholder(Opcode::Jump).unwrap();
+        assert_eq!(
+            instr.patch_jump(handle, 4),
+            Err(BytecodeError::JumpTargetOutOfBounds {
+                position: 0,
+                target: 4,
+                len: 3,
+            })
+        );
+
+        instr.bytes.resize(40_000, Opcode::Pop as u8);
+        assert_eq!(
+            instr.patch_jump(handle, 40_000),
+            Err(BytecodeError::JumpOffsetOverflow {
+                position: 0,
+                offset: 39_997,
+            })
+        );
+        // Операнд не изменился
+        assert_eq!(&instr.bytes[..3], &[Opcode::Jump as u8, 0, 0]);
+    }
 }
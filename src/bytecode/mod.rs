@@ -1,4 +1,6 @@
+pub mod constant;
 pub mod disassembler;
 pub mod instructions;
 /// Модуль, содержащий определения опкодов, инструкции и дизассемблер.
 pub mod opcode;
+pub mod peephole;
@@ -44,11 +44,11 @@ pub enum Opcode {
     LessThanOrEqual = 17,
 
     // === УПРАВЛЕНИЕ ПОТОКОМ ===
-    /// Безусловный переход. Операнд: смещение (2 байта).
+    /// Безусловный переход. Операнд: знаковое смещение относительно конца инструкции (2 байта).
     Jump = 18,
-    /// Условный переход, если верхний элемент стека ложен. Операнд: смещение (2 байта).
+    /// Условный переход, если верхний элемент стека ложен. Операнд: знаковое смещение (2 байта).
     JumpIfFalse = 19,
-    /// Условный переход, если верхний элемент стека истинен. Операнд: смещение (2 байта).
+    /// Условный переход, если верхний элемент стека истинен. Операнд: знаковое смещение (2 байта).
     JumpIfTrue = 20,
     /// Вызов функции. Операнд: количество аргументов (1 байт).
     Call = 21,
@@ -104,6 +104,10 @@ pub enum Opcode {
     // === ФУНКЦИИ И ЗАМЫКАНИЯ ===
     /// Возврат значения из функции (top of stack).
     ReturnValue = 42,
+    /// Переход по таблице для плотных целочисленных ветвей match.
+    /// Операнд: индекс таблицы в пуле констант (2 байта). Таблица — массив
+    /// `[база, смещение_по_умолчанию, смещение_0, смещение_1, ...]`.
+    JumpTable = 43,
     /// Получить free-переменную из замыкания. Операнд: индекс (1 байт).
     GetFree = 45,
     /// Установить free-переменную в замыкании. Операнд: индекс (1 байт).
@@ -114,6 +118,65 @@ pub enum Opcode {
     Closure = 48,
     /// Получить встроенную функцию. Операнд: индекс (1 байт).
     GetBuiltin = 49,
+
+    // === ТИПЫ ===
+    /// Заменяет вершину стека строкой с именем её типа (`typeof`).
+    Typeof = 50,
+
+    // === СТРУКТУРЫ И ЗАПИСЬ ПО ИНДЕКСУ ===
+    /// Объявить структуру. Операнд: индекс константы `[имя, поле_1, поле_2, ...]`
+    /// (2 байта). Значения полей по умолчанию снимаются со стека.
+    Struct = 51,
+    /// Запись по индексу: снимает контейнер, индекс и значение, кладёт
+    /// обновлённый контейнер (массивы копируются при записи).
+    SetIndex = 52,
+
+    // === РЕГИСТРЫ ===
+    /// Снимает вершину стека в регистр. Операнд: номер регистра (1 байт).
+    LoadReg = 53,
+    /// Кладёт на стек значение регистра. Операнд: номер регистра (1 байт).
+    PushReg = 54,
+    /// Складывает два регистра. Операнды: регистр результата и регистры
+    /// левого и правого операндов (по 1 байту).
+    AddReg = 55,
+    /// Вычитает регистры; операнды как у `AddReg`.
+    SubReg = 56,
+    /// Умножает регистры; операнды как у `AddReg`.
+    MulReg = 57,
+    /// Делит регистры; операнды как у `AddReg`.
+    DivReg = 58,
+    /// Остаток от деления регистров; операнды как у `AddReg`.
+    ModReg = 59,
+
+    // === ПРОВЕРКА ТИПОВ ===
+    /// `value is Type`: снимает тип и значение, кладёт булево значение.
+    Is = 60,
+    /// `value is Integer` и другие встроенные типы: заменяет вершину стека
+    /// булевым значением. Операнд: индекс имени типа в пуле констант (2 байта).
+    IsType = 61,
+
+    // === СОПОСТАВЛЕНИЕ С ОБРАЗЦОМ ===
+    /// Сравнивает значение с литеральным паттерном `match`: снимает паттерн и
+    /// значение, кладёт булево значение. В отличие от `Equal`, значения разных
+    /// типов просто не совпадают.
+    MatchLiteral = 62,
+    /// Проверяет диапазонный паттерн `match`: снимает конец, начало и
+    /// значение, кладёт булево значение. Нецелые границы и пустой диапазон
+    /// (`5..1`) — ошибка, нецелое значение не совпадает. Операнд: 1, если
+    /// конец включён (`..=`), иначе 0 (1 байт).
+    MatchRange = 65,
+    /// Снимает значение `match`, к которому не подошла ни одна ветвь, и
+    /// завершает выполнение ошибкой `non-exhaustive match pattern for value`.
+    MatchFail = 66,
+
+    // === ЦИКЛЫ ===
+    /// Кладёт на стек текущую глубину стека операндов (целое число).
+    /// Компилятор сохраняет её в скрытой переменной на входе в цикл.
+    StackDepth = 63,
+    /// Снимает глубину, сохранённую `StackDepth`, и отбрасывает всё, что
+    /// лежит выше неё: `break` и `continue` внутри выражения оставляют на
+    /// стеке недовычисленные операнды.
+    Unwind = 64,
 }
 
 impl Opcode {
@@ -149,10 +212,12 @@ impl Opcode {
             Opcode::Array => "ARRAY",
             Opcode::Hash => "HASH",
             Opcode::Index => "INDEX",
+            Opcode::SetIndex => "SET_INDEX",
             Opcode::Class => "CLASS",
             Opcode::GetProperty => "GET_PROPERTY",
             Opcode::SetProperty => "SET_PROPERTY",
             Opcode::New => "NEW",
+            Opcode::Struct => "STRUCT",
             Opcode::This => "THIS",
             Opcode::Super => "SUPER",
             Opcode::Pop => "POP",
@@ -162,11 +227,27 @@ impl Opcode {
             Opcode::NoOp => "NO_OP",
             Opcode::MapToAst => "MAP_TO_AST",
             Opcode::ReturnValue => "RETURN_VALUE",
+            Opcode::JumpTable => "JUMP_TABLE",
             Opcode::GetFree => "GET_FREE",
             Opcode::SetFree => "SET_FREE",
             Opcode::GetCurrentClosure => "GET_CURRENT_CLOSURE",
             Opcode::Closure => "CLOSURE",
             Opcode::GetBuiltin => "GET_BUILTIN",
+            Opcode::Typeof => "TYPEOF",
+            Opcode::LoadReg => "LOAD_REG",
+            Opcode::PushReg => "PUSH_REG",
+            Opcode::AddReg => "ADD_REG",
+            Opcode::SubReg => "SUB_REG",
+            Opcode::MulReg => "MUL_REG",
+            Opcode::DivReg => "DIV_REG",
+            Opcode::ModReg => "MOD_REG",
+            Opcode::Is => "IS",
+            Opcode::IsType => "IS_TYPE",
+            Opcode::MatchLiteral => "MATCH_LITERAL",
+            Opcode::StackDepth => "STACK_DEPTH",
+            Opcode::Unwind => "UNWIND",
+            Opcode::MatchRange => "MATCH_RANGE",
+            Opcode::MatchFail => "MATCH_FAIL",
         }
     }
 
@@ -179,6 +260,7 @@ impl Opcode {
             | Opcode::Jump
             | Opcode::JumpIfFalse
             | Opcode::JumpIfTrue
+            | Opcode::JumpTable
             | Opcode::GetGlobal
             | Opcode::SetGlobal
             | Opcode::Array
@@ -186,6 +268,8 @@ impl Opcode {
             | Opcode::Class
             | Opcode::GetProperty
             | Opcode::SetProperty
+            | Opcode::Struct
+            | Opcode::IsType
             | Opcode::MapToAst => &[2],
 
             // Опкоды с однобайтовым операндом
@@ -195,10 +279,16 @@ impl Opcode {
             | Opcode::New
             | Opcode::GetFree
             | Opcode::SetFree
-            | Opcode::GetBuiltin => &[1],
+            | Opcode::GetBuiltin
+            | Opcode::MatchRange
+            | Opcode::LoadReg
+            | Opcode::PushReg => &[1],
 
             // Опкоды с несколькими операндами
             Opcode::Closure => &[2, 1],
+            Opcode::AddReg | Opcode::SubReg | Opcode::MulReg | Opcode::DivReg | Opcode::ModReg => {
+                &[1, 1, 1]
+            }
 
             // Опкоды без операндов
             Opcode::Add
@@ -209,6 +299,12 @@ impl Opcode {
             | Opcode::Pow
             | Opcode::Neg
             | Opcode::Not
+            | Opcode::Typeof
+            | Opcode::Is
+            | Opcode::MatchLiteral
+            | Opcode::MatchFail
+            | Opcode::StackDepth
+            | Opcode::Unwind
             | Opcode::And
             | Opcode::Or
             | Opcode::Equal
@@ -218,6 +314,7 @@ impl Opcode {
             | Opcode::GreaterThanOrEqual
             | Opcode::LessThanOrEqual
             | Opcode::Index
+            | Opcode::SetIndex
             | Opcode::Return
             | Opcode::Pop
             | Opcode::True
@@ -276,11 +373,41 @@ impl Opcode {
             40 => Some(Opcode::NoOp),
             41 => Some(Opcode::MapToAst),
             42 => Some(Opcode::ReturnValue),
+            43 => Some(Opcode::JumpTable),
             45 => Some(Opcode::GetFree),
             46 => Some(Opcode::SetFree),
             47 => Some(Opcode::GetCurrentClosure),
             48 => Some(Opcode::Closure),
             49 => Some(Opcode::GetBuiltin),
+            50 => Some(Opcode::Typeof),
+            51 => Some(Opcode::Struct),
+            52 => Some(Opcode::SetIndex),
+            53 => Some(Opcode::LoadReg),
+            54 => Some(Opcode::PushReg),
+            55 => Some(Opcode::AddReg),
+            56 => Some(Opcode::SubReg),
+            57 => Some(Opcode::MulReg),
+            58 => Some(Opcode::DivReg),
+            59 => Some(Opcode::ModReg),
+            60 => Some(Opcode::Is),
+            61 => Some(Opcode::IsType),
+            62 => Some(Opcode::MatchLiteral),
+            63 => Some(Opcode::StackDepth),
+            64 => Some(Opcode::Unwind),
+            65 => Some(Opcode::MatchRange),
+            66 => Some(Opcode::MatchFail),
+            _ => None,
+        }
+    }
+
+    /// Регистровый вариант стековой арифметической операции, если он есть.
+    pub fn register_variant(&self) -> Option<Opcode> {
+        match self {
+            Opcode::Add => Some(Opcode::AddReg),
+            Opcode::Sub => Some(Opcode::SubReg),
+            Opcode::Mul => Some(Opcode::MulReg),
+            Opcode::Div => Some(Opcode::DivReg),
+            Opcode::Mod => Some(Opcode::ModReg),
             _ => None,
         }
     }
@@ -300,7 +427,9 @@ mod tests {
         assert_eq!(Opcode::from_byte(18), Some(Opcode::Jump));
         assert_eq!(Opcode::from_byte(37), Some(Opcode::True));
         assert_eq!(Opcode::from_byte(41), Some(Opcode::MapToAst));
-        assert_eq!(Opcode::from_byte(42), None); // Несуществующий опкод
+        assert_eq!(Opcode::from_byte(42), Some(Opcode::ReturnValue));
+        assert_eq!(Opcode::from_byte(43), Some(Opcode::JumpTable));
+        assert_eq!(Opcode::from_byte(44), None); // Несуществующий опкод
         assert_eq!(Opcode::from_byte(0), None); // Несуществующий опкод
     }
 
@@ -355,6 +484,22 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_register_opcodes() {
+        for byte in 53..=59 {
+            let opcode = Opcode::from_byte(byte).unwrap();
+            assert_eq!(opcode as u8, byte);
+        }
+        assert_eq!(Opcode::LoadReg.operand_widths(), vec![1]);
+        assert_eq!(Opcode::PushReg.operand_widths(), vec![1]);
+        assert_eq!(Opcode::AddReg.operand_widths(), vec![1, 1, 1]);
+        assert_eq!(Opcode::Add.register_variant(), Some(Opcode::AddReg));
+        assert_eq!(Opcode::Mod.register_variant(), Some(Opcode::ModReg));
+        // Степень и сравнения остаются стековыми
+        assert_eq!(Opcode::Pow.register_variant(), None);
+        assert_eq!(Opcode::Equal.register_variant(), None);
+    }
+
     #[test]
     fn test_opcode_equality() {
         // Тест на равенство опкодов
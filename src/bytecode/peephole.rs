@@ -0,0 +1,593 @@
+//! Peephole-оптимизация готового байткода.
+//!
+//! Проход работает после компиляции и до запуска VM. Байткод разбирается
+//! на инструкции, переходы запоминаются как номера целевых инструкций,
+//! затем применяются локальные замены:
+//!
+//! - `Jump` на следующую инструкцию удаляется;
+//! - `JumpIfFalse L1; Jump L2; L1:` сворачивается в `JumpIfTrue L2`
+//!   (и симметрично для `JumpIfTrue`);
+//! - `True; Not` и `False; Not` заменяются на `False` и `True`;
+//! - `Constant x; Pop` удаляется, если на константу `x` больше ничто не
+//!   ссылается.
+//!
+//! Удалённая инструкция, на которую вёл переход, передаёт его следующей
+//! оставшейся. Вторая инструкция заменяемой пары не должна быть целью
+//! перехода: тогда путь, ведущий на неё, изменил бы смысл. После замен
+//! байткод собирается заново по таблице «старый номер → новое смещение»:
+//! пересчитываются смещения переходов, таблицы `JumpTable` и точки входа
+//! функций в пуле констант.
+
+use crate::bytecode::constant::Constant;
+use crate::bytecode::instructions::Instructions;
+use crate::bytecode::opcode::Opcode;
+use std::collections::HashSet;
+
+/// Инструкция, у которой переход записан номером целевой инструкции.
+#[derive(Debug, Clone)]
+struct Instruction {
+    opcode: Opcode,
+    operands: Vec<u16>,
+    /// Цель `Jump`/`JumpIfFalse`/`JumpIfTrue`: номер инструкции или
+    /// число инструкций для перехода в конец байткода.
+    target: Option<usize>,
+    removed: bool,
+}
+
+/// Таблица переходов `JumpTable`: номер инструкции, индекс константы,
+/// начальное значение и номера целей (сначала цель по умолчанию).
+#[derive(Debug)]
+struct JumpTable {
+    instruction: usize,
+    constant: usize,
+    base: i64,
+    targets: Vec<usize>,
+}
+
+/// Разобранный байткод.
+#[derive(Debug)]
+struct Program {
+    code: Vec<Instruction>,
+    constants: Vec<Constant>,
+    tables: Vec<JumpTable>,
+    /// Точки входа функций: индекс константы `CompiledFunction` и номер
+    /// первой инструкции тела.
+    functions: Vec<(usize, usize)>,
+}
+
+/// Оптимизирует байткод. Если байткод не разбирается целиком (неизвестный
+/// опкод, переход не на границу инструкции), он возвращается без изменений.
+pub fn optimize(instructions: &Instructions) -> Instructions {
+    match decode(instructions) {
+        Some(mut program) => {
+            while program.rewrite() {}
+            let mut optimized = program.encode();
+            optimized.positions = instructions.positions.clone();
+            optimized
+        }
+        None => instructions.clone(),
+    }
+}
+
+fn is_conditional_jump(opcode: Opcode) -> bool {
+    matches!(opcode, Opcode::JumpIfFalse | Opcode::JumpIfTrue)
+}
+
+/// Индекс константы, на которую ссылается операнд инструкции.
+fn constant_operand(opcode: Opcode, operands: &[u16]) -> Option<usize> {
+    match opcode {
+        Opcode::Constant
+        | Opcode::Closure
+        | Opcode::GetGlobal
+        | Opcode::SetGlobal
+        | Opcode::Class
+        | Opcode::GetProperty
+        | Opcode::SetProperty
+        | Opcode::Struct
+        | Opcode::IsType
+        | Opcode::JumpTable => Some(operands[0] as usize),
+        _ => None,
+    }
+}
+
+fn decode(instructions: &Instructions) -> Option<Program> {
+    let bytes = &instructions.bytes;
+    let mut offsets = Vec::new();
+    let mut code = Vec::new();
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let opcode = Instructions::read_opcode(bytes, offset)?;
+        let (operands, read) = Instructions::read_operands(opcode, bytes, offset + 1);
+        if operands.len() != opcode.operand_widths().len() {
+            return None;
+        }
+        offsets.push(offset);
+        code.push(Instruction {
+            opcode,
+            operands,
+            target: None,
+            removed: false,
+        });
+        offset += 1 + read;
+    }
+
+    // Номер инструкции по адресу; конец байткода — число инструкций
+    let index_of = |address: i64| -> Option<usize> {
+        if address == bytes.len() as i64 {
+            return Some(offsets.len());
+        }
+        let address = usize::try_from(address).ok()?;
+        offsets.binary_search(&address).ok()
+    };
+
+    let mut tables = Vec::new();
+    for (index, instruction) in code.iter_mut().enumerate() {
+        let end = offsets[index] as i64 + 3;
+        match instruction.opcode {
+            Opcode::Jump | Opcode::JumpIfFalse | Opcode::JumpIfTrue => {
+                let relative = instruction.operands[0] as i16 as i64;
+                instruction.target = Some(index_of(end + relative)?);
+            }
+            Opcode::JumpTable => {
+                let constant = instruction.operands[0] as usize;
+                let Some(Constant::Array(entries)) = instructions.constants.get(constant) else {
+                    return None;
+                };
+                let mut values = entries.iter().map(|entry| match entry {
+                    Constant::Integer(value) => Some(*value),
+                    _ => None,
+                });
+                let base = values.next()??;
+                let targets = values
+                    .map(|relative| index_of(end + relative?))
+                    .collect::<Option<Vec<usize>>>()?;
+                if targets.is_empty() {
+                    return None;
+                }
+                tables.push(JumpTable {
+                    instruction: index,
+                    constant,
+                    base,
+                    targets,
+                });
+            }
+            _ => {}
+        }
+    }
+
+    let mut functions = Vec::new();
+    for (constant, value) in instructions.constants.iter().enumerate() {
+        if let Constant::CompiledFunction(function) = value {
+            functions.push((constant, index_of(function.instructions_offset as i64)?));
+        }
+    }
+
+    Some(Program {
+        code,
+        constants: instructions.constants.clone(),
+        tables,
+        functions,
+    })
+}
+
+impl Program {
+    /// Первая оставшаяся инструкция с номером не меньше `index`.
+    fn resolve(&self, mut index: usize) -> usize {
+        while index < self.code.len() && self.code[index].removed {
+            index += 1;
+        }
+        index
+    }
+
+    fn next_live(&self, index: usize) -> usize {
+        self.resolve(index + 1)
+    }
+
+    fn live(&self, index: usize) -> Option<&Instruction> {
+        self.code
+            .get(index)
+            .filter(|instruction| !instruction.removed)
+    }
+
+    /// Номера инструкций, на которые ведёт переход, таблица переходов или
+    /// точка входа функции.
+    fn targets(&self) -> HashSet<usize> {
+        let jumps = self
+            .code
+            .iter()
+            .filter(|instruction| !instruction.removed)
+            .filter_map(|instruction| instruction.target);
+        let tables = self
+            .tables
+            .iter()
+            .flat_map(|table| table.targets.iter().copied());
+        let functions = self.functions.iter().map(|&(_, entry)| entry);
+        jumps
+            .chain(tables)
+            .chain(functions)
+            .map(|index| self.resolve(index))
+            .collect()
+    }
+
+    /// Число живых инструкций, ссылающихся на каждую константу.
+    fn constant_uses(&self) -> Vec<usize> {
+        let mut uses = vec![0; self.constants.len()];
+        for instruction in self.code.iter().filter(|i| !i.removed) {
+            if let Some(constant) = constant_operand(instruction.opcode, &instruction.operands) {
+                if let Some(count) = uses.get_mut(constant) {
+                    *count += 1;
+                }
+            }
+        }
+        uses
+    }
+
+    /// Результат программы — последнее снятое со стека значение. Пару
+    /// `Constant; Pop` можно удалить, только если после неё по прямой
+    /// (без переходов и вызовов) значение со стека снимет другая инструкция.
+    fn popped_later(&self, index: usize) -> bool {
+        let mut index = self.next_live(index);
+        while let Some(instruction) = self.live(index) {
+            match instruction.opcode {
+                Opcode::Pop
+                | Opcode::SetGlobal
+                | Opcode::SetLocal
+                | Opcode::JumpIfFalse
+                | Opcode::JumpIfTrue
+                | Opcode::JumpTable
+                | Opcode::ReturnValue => return true,
+                Opcode::Jump | Opcode::Return | Opcode::Call => return false,
+                _ => index = self.next_live(index),
+            }
+        }
+        false
+    }
+
+    /// Удаляет инструкцию; переходы на неё теперь ведут на следующую.
+    fn remove(&mut self, index: usize, targets: &mut HashSet<usize>) {
+        self.code[index].removed = true;
+        if targets.remove(&index) {
+            targets.insert(self.next_live(index));
+        }
+    }
+
+    /// Один проход замен по всему байткоду. Возвращает `true`, если что-то
+    /// изменилось — тогда стоит пройти ещё раз.
+    fn rewrite(&mut self) -> bool {
+        let mut targets = self.targets();
+        let mut uses = self.constant_uses();
+        let mut changed = false;
+        let mut index = self.resolve(0);
+
+        while index < self.code.len() {
+            let next = self.next_live(index);
+            let instruction = &self.code[index];
+            let next_opcode = self.live(next).map(|i| i.opcode);
+            let next_is_target = targets.contains(&next);
+
+            match (instruction.opcode, next_opcode) {
+                (Opcode::Jump, _) if instruction.target.map(|t| self.resolve(t)) == Some(next) => {
+                    self.remove(index, &mut targets);
+                    changed = true;
+                }
+                (opcode, Some(Opcode::Jump))
+                    if is_conditional_jump(opcode)
+                        && !next_is_target
+                        && instruction.target.map(|t| self.resolve(t))
+                            == Some(self.next_live(next)) =>
+                {
+                    let inverted = if opcode == Opcode::JumpIfFalse {
+                        Opcode::JumpIfTrue
+                    } else {
+                        Opcode::JumpIfFalse
+                    };
+                    let target = self.code[next].target;
+                    self.code[index].opcode = inverted;
+                    self.code[index].target = target;
+                    self.remove(next, &mut targets);
+                    changed = true;
+                }
+                (Opcode::True | Opcode::False, Some(Opcode::Not)) if !next_is_target => {
+                    self.code[index].opcode = if instruction.opcode == Opcode::True {
+                        Opcode::False
+                    } else {
+                        Opcode::True
+                    };
+                    self.remove(next, &mut targets);
+                    changed = true;
+                }
+                (Opcode::Constant, Some(Opcode::Pop))
+                    if !next_is_target
+                        && uses.get(instruction.operands[0] as usize) == Some(&1)
+                        && self.popped_later(next) =>
+                {
+                    uses[instruction.operands[0] as usize] = 0;
+                    self.remove(index, &mut targets);
+                    self.remove(next, &mut targets);
+                    changed = true;
+                }
+                _ => {}
+            }
+            index = self.next_live(index);
+        }
+        changed
+    }
+
+    /// Собирает байткод заново. Удалённая инструкция получает смещение
+    /// следующей оставшейся — туда и ведут переходы на неё.
+    fn encode(mut self) -> Instructions {
+        let mut offsets = Vec::with_capacity(self.code.len() + 1);
+        let mut offset = 0;
+        for instruction in &self.code {
+            offsets.push(offset);
+            if !instruction.removed {
+                let widths: usize = instruction
+                    .opcode
+                    .operand_widths()
+                    .iter()
+                    .map(|&w| w as usize)
+                    .sum();
+                offset += 1 + widths;
+            }
+        }
+        offsets.push(offset);
+
+        let mut result = Instructions::new();
+        for (index, instruction) in self.code.iter().enumerate() {
+            if instruction.removed {
+                continue;
+            }
+            let mut operands: Vec<usize> = instruction
+                .operands
+                .iter()
+                .map(|&operand| operand as usize)
+                .collect();
+            if let Some(target) = instruction.target {
+                // Код только сокращается, поэтому смещение остаётся в i16
+                let relative = offsets[target] as i64 - (offsets[index] + 3) as i64;
+                operands[0] = relative as i16 as u16 as usize;
+            }
+            result
+                .emit(instruction.opcode, &operands)
+                .expect("операнды взяты из разобранного байткода");
+        }
+
+        for table in &self.tables {
+            let end = offsets[table.instruction] as i64 + 3;
+            let mut entries = vec![Constant::Integer(table.base)];
+            entries.extend(
+                table
+                    .targets
+                    .iter()
+                    .map(|&target| Constant::Integer(offsets[target] as i64 - end)),
+            );
+            self.constants[table.constant] = Constant::Array(entries);
+        }
+        for &(constant, entry) in &self.functions {
+            if let Constant::CompiledFunction(function) = &mut self.constants[constant] {
+                function.instructions_offset = offsets[entry];
+            }
+        }
+        result.constants = self.constants;
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object::CompiledFunction;
+
+    fn assemble(code: &[(Opcode, &[u16])]) -> Instructions {
+        let mut instructions = Instructions::new();
+        for (opcode, operands) in code {
+            let operands: Vec<usize> = operands.iter().map(|&o| o as usize).collect();
+            instructions.emit(*opcode, &operands).unwrap();
+        }
+        instructions
+    }
+
+    /// Относительное смещение перехода как операнд.
+    fn rel(offset: i16) -> u16 {
+        offset as u16
+    }
+
+    #[test]
+    fn test_jump_to_next_instruction_removed() {
+        let before = assemble(&[
+            (Opcode::Jump, &[0]),
+            (Opcode::True, &[]),
+            (Opcode::Pop, &[]),
+        ]);
+        let after = optimize(&before);
+        assert_eq!(after.bytes, vec![Opcode::True as u8, Opcode::Pop as u8]);
+
+        // Переход дальше следующей инструкции остаётся
+        let far = assemble(&[
+            (Opcode::Jump, &[1]),
+            (Opcode::Null, &[]),
+            (Opcode::True, &[]),
+        ]);
+        assert_eq!(optimize(&far).bytes, far.bytes);
+    }
+
+    #[test]
+    fn test_conditional_jump_over_jump_inverted() {
+        // 0: TRUE; 1: JUMP_IF_FALSE +3 (→7); 4: JUMP +2 (→9); 7: NULL; 8: POP; 9: FALSE
+        let before = assemble(&[
+            (Opcode::True, &[]),
+            (Opcode::JumpIfFalse, &[3]),
+            (Opcode::Jump, &[2]),
+            (Opcode::Null, &[]),
+            (Opcode::Pop, &[]),
+            (Opcode::False, &[]),
+        ]);
+        let after = optimize(&before);
+        // 0: TRUE; 1: JUMP_IF_TRUE +2 (→6); 4: NULL; 5: POP; 6: FALSE
+        assert_eq!(
+            after.bytes,
+            assemble(&[
+                (Opcode::True, &[]),
+                (Opcode::JumpIfTrue, &[2]),
+                (Opcode::Null, &[]),
+                (Opcode::Pop, &[]),
+                (Opcode::False, &[]),
+            ])
+            .bytes
+        );
+
+        // Если на Jump ведёт другой переход, пара не сворачивается
+        let targeted = assemble(&[
+            (Opcode::Jump, &[4]), // → 7 (JUMP)
+            (Opcode::True, &[]),
+            (Opcode::JumpIfFalse, &[3]),
+            (Opcode::Jump, &[1]),
+            (Opcode::Null, &[]),
+            (Opcode::False, &[]),
+        ]);
+        assert_eq!(optimize(&targeted).bytes, targeted.bytes);
+    }
+
+    #[test]
+    fn test_boolean_not_folded() {
+        let before = assemble(&[
+            (Opcode::True, &[]),
+            (Opcode::Not, &[]),
+            (Opcode::False, &[]),
+            (Opcode::Not, &[]),
+            (Opcode::Pop, &[]),
+        ]);
+        assert_eq!(
+            optimize(&before).bytes,
+            vec![Opcode::False as u8, Opcode::True as u8, Opcode::Pop as u8]
+        );
+
+        // NOT — цель перехода: на неё приходят со своим значением на стеке
+        let targeted = assemble(&[
+            (Opcode::False, &[]),
+            (Opcode::JumpIfTrue, &[1]), // → 5 (NOT)
+            (Opcode::True, &[]),
+            (Opcode::Not, &[]),
+        ]);
+        assert_eq!(optimize(&targeted).bytes, targeted.bytes);
+    }
+
+    #[test]
+    fn test_unused_constant_pop_removed() {
+        let mut before = assemble(&[
+            (Opcode::Constant, &[0]),
+            (Opcode::Pop, &[]),
+            (Opcode::Constant, &[1]),
+            (Opcode::Pop, &[]),
+        ]);
+        before.constants = vec![Constant::Integer(1), Constant::Integer(2)];
+        // Последняя пара задаёт результат программы и остаётся
+        let after = optimize(&before);
+        assert_eq!(
+            after.bytes,
+            assemble(&[(Opcode::Constant, &[1]), (Opcode::Pop, &[])]).bytes
+        );
+        // Пул констант не перенумеровывается
+        assert_eq!(after.constants, before.constants);
+
+        // Константа, на которую ссылается другая инструкция, остаётся
+        let mut shared = assemble(&[
+            (Opcode::Constant, &[0]),
+            (Opcode::Pop, &[]),
+            (Opcode::Constant, &[0]),
+            (Opcode::Pop, &[]),
+            (Opcode::Null, &[]),
+            (Opcode::Pop, &[]),
+        ]);
+        shared.constants = vec![Constant::Integer(1)];
+        assert_eq!(optimize(&shared).bytes, shared.bytes);
+    }
+
+    #[test]
+    fn test_jump_targets_fixed_across_removal() {
+        // 0: NULL; 1: POP; 2: TRUE; 3: NOT; 4: JUMP +0; 7: JUMP_IF_FALSE +3 (→13);
+        // 10: JUMP -13 (→0); 13: NULL
+        let before = assemble(&[
+            (Opcode::Null, &[]),
+            (Opcode::Pop, &[]),
+            (Opcode::True, &[]),
+            (Opcode::Not, &[]),
+            (Opcode::Jump, &[0]),
+            (Opcode::JumpIfFalse, &[rel(3)]),
+            (Opcode::Jump, &[rel(-13)]),
+            (Opcode::Null, &[]),
+        ]);
+        let after = optimize(&before);
+        // 0: NULL; 1: POP; 2: FALSE; 3: JUMP_IF_TRUE -6 (→0); 6: NULL
+        assert_eq!(
+            after.bytes,
+            assemble(&[
+                (Opcode::Null, &[]),
+                (Opcode::Pop, &[]),
+                (Opcode::False, &[]),
+                (Opcode::JumpIfTrue, &[rel(-6)]),
+                (Opcode::Null, &[]),
+            ])
+            .bytes
+        );
+    }
+
+    #[test]
+    fn test_function_entry_and_jump_table_remapped() {
+        // 0: JUMP +0; 3: JUMP +2 (→8); 6: NULL; 7: RETURN_VALUE (функция с 6);
+        // 8: CONSTANT 0; 11: JUMP_TABLE 1; 14: NULL; 15: TRUE; 16: NOT; 17: NULL
+        let mut before = assemble(&[
+            (Opcode::Jump, &[0]),
+            (Opcode::Jump, &[2]),
+            (Opcode::Null, &[]),
+            (Opcode::ReturnValue, &[]),
+            (Opcode::Constant, &[0]),
+            (Opcode::JumpTable, &[1]),
+            (Opcode::Null, &[]),
+            (Opcode::True, &[]),
+            (Opcode::Not, &[]),
+            (Opcode::Null, &[]),
+        ]);
+        let function = |offset| {
+            Constant::CompiledFunction(CompiledFunction {
+                instructions_offset: offset,
+                num_locals: 0,
+                num_params: 0,
+            })
+        };
+        let table = |entry| {
+            // Значение 0 → последний NULL, по умолчанию → первый
+            Constant::Array(vec![
+                Constant::Integer(0),
+                Constant::Integer(0),
+                Constant::Integer(entry),
+            ])
+        };
+        before.constants = vec![function(6), table(3)];
+        let after = optimize(&before);
+        // 0: JUMP +2 (→5); 3: NULL; 4: RETURN_VALUE; 5: CONSTANT 0; 8: JUMP_TABLE 1;
+        // 11: NULL; 12: FALSE; 13: NULL
+        assert_eq!(
+            after.bytes,
+            assemble(&[
+                (Opcode::Jump, &[2]),
+                (Opcode::Null, &[]),
+                (Opcode::ReturnValue, &[]),
+                (Opcode::Constant, &[0]),
+                (Opcode::JumpTable, &[1]),
+                (Opcode::Null, &[]),
+                (Opcode::False, &[]),
+                (Opcode::Null, &[]),
+            ])
+            .bytes
+        );
+        assert_eq!(after.constants, vec![function(3), table(2)]);
+    }
+
+    #[test]
+    fn test_malformed_bytecode_left_unchanged() {
+        // Переход в середину инструкции
+        let before = assemble(&[(Opcode::Jump, &[1]), (Opcode::Constant, &[0])]);
+        assert_eq!(optimize(&before), before);
+    }
+}
@@ -0,0 +1,883 @@
+use crate::ast::{
+    BlockStatement, Expression, Identifier, MatchArm, MethodDeclaration, Pattern, Program,
+    PropertyDeclaration, SignatureTypes, Statement, TypeAnnotation,
+};
+use std::fmt;
+
+/// Тип выражения, известный без вычисления программы. `Display` совпадает
+/// с `Object::type_str` соответствующих значений.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StaticType {
+    Integer,
+    String,
+    Boolean,
+    Null,
+}
+
+impl fmt::Display for StaticType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let name = match self {
+            StaticType::Integer => "INTEGER",
+            StaticType::String => "STRING",
+            StaticType::Boolean => "BOOLEAN",
+            StaticType::Null => "NULL",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Приближённый тип выражения: известен только для литералов и операторов
+/// над ними (`-1`, `"a" + "b"`, `1 < 2`). Переменные и вызовы не выводятся.
+pub fn static_type(expression: &Expression) -> Option<StaticType> {
+    match expression {
+        Expression::IntegerLiteral(_) => Some(StaticType::Integer),
+        Expression::StringLiteral(_) => Some(StaticType::String),
+        Expression::Boolean(_) => Some(StaticType::Boolean),
+        Expression::Null(_) => Some(StaticType::Null),
+        Expression::Typeof(_) => Some(StaticType::String),
+        Expression::Prefix(p) => match (p.operator.as_str(), static_type(&p.right)?) {
+            ("-", StaticType::Integer) => Some(StaticType::Integer),
+            ("!", _) => Some(StaticType::Boolean),
+            _ => None,
+        },
+        Expression::Infix(i) => {
+            let left = static_type(&i.left)?;
+            let right = static_type(&i.right)?;
+            match (i.operator.as_str(), left, right) {
+                ("+" | "-" | "*" | "/" | "%", StaticType::Integer, StaticType::Integer) => {
+                    Some(StaticType::Integer)
+                }
+                ("+", StaticType::String, StaticType::String) => Some(StaticType::String),
+                ("<" | ">" | "==" | "!=", l, r) if l == r => Some(StaticType::Boolean),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Предупреждение статической проверки.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub message: String,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "warning: {}", self.message)
+    }
+}
+
+/// Проверяет ветви `match`: паттерн-литерал или диапазон, тип которого не
+/// совпадает со статически известным типом значения, и повтор литерала
+/// из более ранней ветви без гарда — такие ветви никогда не выполнятся.
+///
+/// Кроме того, проверяет исчерпываемость `match` над целыми числами и
+/// логическими значениями (см. `check_exhaustiveness`).
+pub fn check_match_arms(value: &Expression, arms: &[MatchArm]) -> Vec<Diagnostic> {
+    let mut diagnostics = check_patterns(
+        value,
+        arms.iter().map(|arm| (&arm.pattern, arm.guard.is_some())),
+    );
+    diagnostics.extend(check_exhaustiveness(value, arms));
+    diagnostics
+}
+
+/// Проверяет паттерн `if let` так же, как ветвь `match`. Исчерпываемость
+/// не проверяется: несовпадение — обычный путь в `else`.
+pub fn check_if_let_pattern(value: &Expression, pattern: &Pattern) -> Vec<Diagnostic> {
+    check_patterns(value, std::iter::once((pattern, false)))
+}
+
+/// Проверяет, что `match` покрывает все значения. Тип значения берётся из
+/// `static_type`, а если он неизвестен — из литералов и диапазонов ветвей.
+/// Без информации о типах полноту набора целых чисел не доказать, поэтому
+/// `match` над целыми требует ветви `_` (или паттерна-переменной);
+/// `match` над логическими значениями — ветвей `true` и `false` или `_`.
+/// Ветви с гардом не учитываются: гард может не выполниться.
+pub fn check_exhaustiveness(value: &Expression, arms: &[MatchArm]) -> Option<Diagnostic> {
+    let unguarded = || arms.iter().filter(|arm| arm.guard.is_none());
+    if unguarded().any(|arm| matches!(arm.pattern, Pattern::Wildcard | Pattern::Identifier(_))) {
+        return None;
+    }
+
+    let value_type = static_type(value).or_else(|| {
+        arms.iter().find_map(|arm| match &arm.pattern {
+            Pattern::Literal(expression) => static_type(expression),
+            Pattern::Range(_) => Some(StaticType::Integer),
+            _ => None,
+        })
+    });
+    let message = match value_type? {
+        StaticType::Integer => "match on INTEGER is not exhaustive: add a `_` arm".to_string(),
+        StaticType::Boolean => {
+            let covers = |expected: bool| {
+                unguarded().any(|arm| {
+                    matches!(&arm.pattern, Pattern::Literal(Expression::Boolean(b)) if b.value == expected)
+                })
+            };
+            let missing: Vec<&str> = [(true, "`true`"), (false, "`false`")]
+                .into_iter()
+                .filter(|(value, _)| !covers(*value))
+                .map(|(_, name)| name)
+                .collect();
+            if missing.is_empty() {
+                return None;
+            }
+            let arms = if missing.len() == 1 { "arm" } else { "arms" };
+            format!(
+                "match on BOOLEAN is not exhaustive: missing {} {}",
+                missing.join(" and "),
+                arms
+            )
+        }
+        StaticType::String | StaticType::Null => return None,
+    };
+    Some(Diagnostic { message })
+}
+
+fn check_patterns<'a>(
+    value: &Expression,
+    arms: impl Iterator<Item = (&'a Pattern, bool)>,
+) -> Vec<Diagnostic> {
+    let value_type = static_type(value);
+    let mut diagnostics = Vec::new();
+    // Литералы ветвей без гарда: (тип, текст, номер ветви)
+    let mut seen: Vec<(StaticType, String, usize)> = Vec::new();
+
+    for (index, (pattern, guarded)) in arms.enumerate() {
+        let number = index + 1;
+        let pattern_type = match pattern {
+            Pattern::Literal(expression) => static_type(expression),
+            Pattern::Range(_) => Some(StaticType::Integer),
+            _ => None,
+        };
+        let Some(pattern_type) = pattern_type else {
+            continue;
+        };
+
+        if let Pattern::Range(range) = pattern {
+            if let (Some(start), Some(end)) =
+                (literal_integer(&range.start), literal_integer(&range.end))
+            {
+                if start > end {
+                    diagnostics.push(Diagnostic {
+                        message: format!(
+                            "match arm {} `{}` can never match: empty range",
+                            number, pattern
+                        ),
+                    });
+                    continue;
+                }
+            }
+        }
+
+        if let Some(value_type) = value_type.filter(|t| *t != pattern_type) {
+            diagnostics.push(Diagnostic {
+                message: format!(
+                    "match arm {} `{}` can never match: pattern is {}, value is {}",
+                    number, pattern, pattern_type, value_type
+                ),
+            });
+            continue;
+        }
+
+        if let Pattern::Literal(expression) = pattern {
+            let text = expression.to_string();
+            let earlier = seen
+                .iter()
+                .find(|(t, s, _)| *t == pattern_type && *s == text);
+            if let Some((_, _, earlier)) = earlier {
+                diagnostics.push(Diagnostic {
+                    message: format!(
+                        "match arm {} `{}` is unreachable: duplicates arm {}",
+                        number, pattern, earlier
+                    ),
+                });
+            } else if !guarded {
+                seen.push((pattern_type, text, number));
+            }
+        }
+    }
+    diagnostics
+}
+
+/// Значение целочисленного литерала, в том числе с унарным минусом;
+/// для остальных выражений границы диапазона статически неизвестны.
+fn literal_integer(expression: &Expression) -> Option<i64> {
+    match expression {
+        Expression::IntegerLiteral(literal) => Some(literal.value),
+        Expression::Prefix(prefix) if prefix.operator == "-" => {
+            literal_integer(&prefix.right)?.checked_neg()
+        }
+        _ => None,
+    }
+}
+
+/// Встроенные имена типов для аннотаций; к ним добавляются объявленные
+/// в программе классы, структуры и интерфейсы.
+const BUILTIN_TYPE_NAMES: [&str; 5] = ["Int", "String", "Bool", "Null", "Array"];
+
+/// Проверяет программу: ветви всех `match` и `if let`, повторные `let`
+/// одного имени в одной области видимости, неиспользуемые `let` и
+/// аннотации типов с неизвестными именами.
+pub fn check_program(program: &Program) -> Vec<Diagnostic> {
+    let mut checker = Checker::default();
+    checker.enter_scope();
+    checker.check_statements(&program.statements);
+    checker.leave_scope();
+    checker.check_type_names();
+    checker.diagnostics
+}
+
+/// Как имя попало в область видимости.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BindingKind {
+    /// `let` — единственный вид, о неиспользовании которого предупреждаем.
+    Let,
+    /// Параметр функции или метода, переменная цикла, привязка паттерна.
+    Parameter,
+    /// Свойство класса.
+    Declaration,
+    /// Класс, структура или интерфейс; строка — ключевое слово объявления.
+    Type(&'static str),
+}
+
+#[derive(Debug)]
+struct Binding {
+    name: String,
+    kind: BindingKind,
+    /// Место объявления: `line 1, column 5`.
+    position: String,
+    used: bool,
+}
+
+#[derive(Debug, Default)]
+struct CheckScope {
+    bindings: Vec<Binding>,
+    /// Имена, на которые сослались до объявления (например, из тела функции,
+    /// объявленной раньше вызываемой). Объявление с таким именем в этой или
+    /// объемлющей области считается использованным.
+    unresolved: Vec<String>,
+}
+
+/// Обходит AST, повторяя правила видимости интерпретатора: новую область
+/// открывают функции, методы, тело класса, цикл `for` и ветви `match` /
+/// `if let`, а блоки `if` и `{ ... }` используют область, в которой стоят.
+#[derive(Default)]
+struct Checker {
+    scopes: Vec<CheckScope>,
+    diagnostics: Vec<Diagnostic>,
+    /// Имена классов, структур и интерфейсов из любой области видимости.
+    declared_types: Vec<String>,
+    /// Имена типов из аннотаций с местом упоминания. Проверяются после
+    /// обхода: тип может быть объявлен ниже аннотации.
+    type_references: Vec<(String, String)>,
+}
+
+fn position(identifier: &Identifier) -> String {
+    format!(
+        "line {}, column {}",
+        identifier.token.span.line, identifier.token.span.column
+    )
+}
+
+impl Checker {
+    fn enter_scope(&mut self) {
+        self.scopes.push(CheckScope::default());
+    }
+
+    /// Закрывает область: предупреждает о неиспользованных `let`, а
+    /// неразрешённые ссылки передаёт объемлющей области.
+    fn leave_scope(&mut self) {
+        let scope = self.scopes.pop().expect("leave_scope без enter_scope");
+        for binding in &scope.bindings {
+            self.warn_if_unused(binding);
+        }
+        if let Some(outer) = self.scopes.last_mut() {
+            for name in scope.unresolved {
+                if !outer.unresolved.contains(&name) {
+                    outer.unresolved.push(name);
+                }
+            }
+        }
+    }
+
+    fn warn_if_unused(&mut self, binding: &Binding) {
+        if binding.kind == BindingKind::Let && !binding.used && !binding.name.starts_with('_') {
+            self.diagnostics.push(Diagnostic {
+                message: format!("unused variable `{}` at {}", binding.name, binding.position),
+            });
+        }
+    }
+
+    fn define(&mut self, identifier: &Identifier, kind: BindingKind) {
+        let scope = self.scopes.last_mut().expect("нет области видимости");
+        let binding = Binding {
+            name: identifier.value.clone(),
+            kind,
+            position: position(identifier),
+            used: scope.unresolved.contains(&identifier.value),
+        };
+        // Имя типа нельзя объявить повторно в той же области — ни типом, ни
+        // `let`, и `let` не может занять имя типа (как в интерпретаторе)
+        let collision = scope.bindings.iter().find_map(|b| {
+            if b.name != binding.name || b.kind == BindingKind::Declaration {
+                return None;
+            }
+            match (b.kind, kind) {
+                (BindingKind::Type(word), _) | (_, BindingKind::Type(word)) => {
+                    Some((word, b.position.clone()))
+                }
+                _ => None,
+            }
+        });
+        if let Some((word, previous)) = collision {
+            self.diagnostics.push(Diagnostic {
+                message: format!(
+                    "{} '{}' at {} is already defined at {}",
+                    word, identifier.value, binding.position, previous
+                ),
+            });
+        }
+        let existing = scope.bindings.iter().position(|b| {
+            b.name == binding.name && matches!(b.kind, BindingKind::Let | BindingKind::Parameter)
+        });
+        match existing {
+            Some(index) => {
+                let previous = std::mem::replace(&mut scope.bindings[index], binding);
+                if kind == BindingKind::Let {
+                    self.diagnostics.push(Diagnostic {
+                        message: format!(
+                            "`let {}` at {} redefines `{}` from {} in the same scope",
+                            identifier.value,
+                            position(identifier),
+                            identifier.value,
+                            previous.position
+                        ),
+                    });
+                }
+                self.warn_if_unused(&previous);
+            }
+            None => scope.bindings.push(binding),
+        }
+    }
+
+    fn reference(&mut self, name: &str) {
+        for scope in self.scopes.iter_mut().rev() {
+            if let Some(binding) = scope.bindings.iter_mut().rev().find(|b| b.name == name) {
+                binding.used = true;
+                return;
+            }
+        }
+        let scope = self.scopes.last_mut().expect("нет области видимости");
+        if !scope.unresolved.iter().any(|n| n == name) {
+            scope.unresolved.push(name.to_string());
+        }
+    }
+
+    fn reference_types<'a>(&mut self, annotations: impl IntoIterator<Item = &'a TypeAnnotation>) {
+        for annotation in annotations.into_iter().flat_map(TypeAnnotation::walk) {
+            let position = format!(
+                "line {}, column {}",
+                annotation.token.span.line, annotation.token.span.column
+            );
+            self.type_references
+                .push((annotation.name.clone(), position));
+        }
+    }
+
+    fn check_type_names(&mut self) {
+        for (name, position) in std::mem::take(&mut self.type_references) {
+            if !BUILTIN_TYPE_NAMES.contains(&name.as_str()) && !self.declared_types.contains(&name)
+            {
+                self.diagnostics.push(Diagnostic {
+                    message: format!("unknown type `{}` in annotation at {}", name, position),
+                });
+            }
+        }
+    }
+
+    fn check_statements(&mut self, statements: &[Statement]) {
+        for statement in statements {
+            self.check_statement(statement);
+        }
+    }
+
+    fn check_statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Let(s) => {
+                self.reference_types(s.type_annotation.as_deref());
+                self.check_expression(&s.value);
+                self.define(&s.name, BindingKind::Let);
+            }
+            Statement::LetDestructure(s) => {
+                self.check_expression(&s.value);
+                self.check_pattern_expressions(&s.pattern);
+                self.define_pattern(&s.pattern, BindingKind::Let);
+            }
+            Statement::Return(s) => self.check_expression(&s.return_value),
+            Statement::Break(_) | Statement::Continue(_) => {}
+            Statement::Expression(s) => self.check_expression(&s.expression),
+            Statement::Block(b) => self.check_statements(&b.statements),
+            Statement::ClassDeclaration(c) => {
+                for parent in c.super_class.iter().chain(&c.interfaces) {
+                    self.reference(&parent.value);
+                }
+                // Свойства видны по имени следующим свойствам и методам
+                self.enter_scope();
+                for property in &c.properties {
+                    if let Some(value) = &property.value {
+                        self.check_expression(value);
+                    }
+                    self.define(&property.name, BindingKind::Declaration);
+                }
+                self.check_methods(&c.methods);
+                self.leave_scope();
+                self.declare_type(&c.name, "class");
+            }
+            Statement::StructDeclaration(s) => {
+                self.check_property_values(&s.properties);
+                self.declare_type(&s.name, "struct");
+            }
+            Statement::InterfaceDeclaration(i) => {
+                for signature in i.method_signatures() {
+                    self.reference_types(signature.types.annotations());
+                }
+                self.check_methods(i.default_methods());
+                self.declare_type(&i.name, "interface");
+            }
+        }
+    }
+
+    fn declare_type(&mut self, name: &Identifier, keyword: &'static str) {
+        self.define(name, BindingKind::Type(keyword));
+        self.declared_types.push(name.value.clone());
+    }
+
+    fn check_property_values(&mut self, properties: &[PropertyDeclaration]) {
+        for value in properties.iter().filter_map(|p| p.value.as_ref()) {
+            self.check_expression(value);
+        }
+    }
+
+    fn check_methods<'a>(&mut self, methods: impl IntoIterator<Item = &'a MethodDeclaration>) {
+        for method in methods {
+            self.check_function(&method.parameters, None, &method.types, &method.body);
+        }
+    }
+
+    /// Параметры и тело функции разделяют одну область видимости.
+    fn check_function(
+        &mut self,
+        parameters: &[Identifier],
+        rest: Option<&Identifier>,
+        types: &SignatureTypes,
+        body: &BlockStatement,
+    ) {
+        self.reference_types(types.annotations());
+        self.enter_scope();
+        for parameter in parameters.iter().chain(rest) {
+            self.define(parameter, BindingKind::Parameter);
+        }
+        self.check_statements(&body.statements);
+        self.leave_scope();
+    }
+
+    fn check_expressions(&mut self, expressions: &[Expression]) {
+        for expression in expressions {
+            self.check_expression(expression);
+        }
+    }
+
+    fn check_block(&mut self, block: Option<&BlockStatement>) {
+        if let Some(block) = block {
+            self.check_statements(&block.statements);
+        }
+    }
+
+    fn check_expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::Identifier(identifier) => self.reference(&identifier.value),
+            Expression::Prefix(p) => self.check_expression(&p.right),
+            Expression::Typeof(t) => self.check_expression(&t.operand),
+            Expression::Infix(i) => {
+                self.check_expression(&i.left);
+                self.check_expression(&i.right);
+            }
+            Expression::If(i) => {
+                self.check_expression(&i.condition);
+                self.check_block(Some(&i.consequence));
+                self.check_block(i.alternative.as_ref());
+            }
+            Expression::IfLet(i) => {
+                self.check_expression(&i.value);
+                self.diagnostics
+                    .extend(check_if_let_pattern(&i.value, &i.pattern));
+                self.check_pattern_expressions(&i.pattern);
+                self.enter_scope();
+                self.define_pattern(&i.pattern, BindingKind::Parameter);
+                self.check_block(Some(&i.consequence));
+                self.leave_scope();
+                self.check_block(i.alternative.as_ref());
+            }
+            Expression::For(f) => {
+                self.check_expression(&f.iterable);
+                self.enter_scope();
+                self.define(&f.variable, BindingKind::Parameter);
+                self.check_block(Some(&f.body));
+                self.leave_scope();
+            }
+            Expression::Assign(a) => {
+                self.check_expression(&a.target);
+                self.check_expression(&a.value);
+            }
+            Expression::FunctionLiteral(f) => {
+                self.check_function(&f.parameters, f.rest.as_ref(), &f.types, &f.body)
+            }
+            Expression::Call(c) => {
+                self.check_expression(&c.function);
+                self.check_expressions(&c.arguments);
+            }
+            Expression::ArrayLiteral(a) => self.check_expressions(&a.elements),
+            Expression::HashLiteral(h) => {
+                for (key, value) in &h.pairs {
+                    self.check_expression(key);
+                    self.check_expression(value);
+                }
+            }
+            Expression::Index(i) => {
+                self.check_expression(&i.left);
+                self.check_expression(&i.index);
+            }
+            Expression::Range(r) => {
+                self.check_expression(&r.start);
+                self.check_expression(&r.end);
+            }
+            Expression::New(n) => {
+                self.reference(&n.class_name.value);
+                self.check_expressions(&n.arguments);
+            }
+            Expression::PropertyAccess(p) => self.check_expression(&p.left),
+            Expression::MethodCall(m) => {
+                self.check_expression(&m.object);
+                self.check_expressions(&m.arguments);
+            }
+            Expression::Match(m) => {
+                self.check_expression(&m.value);
+                self.diagnostics.extend(check_match_arms(&m.value, &m.arms));
+                for arm in &m.arms {
+                    // Гард и тело ветви видят привязки паттерна
+                    self.check_pattern_expressions(&arm.pattern);
+                    self.enter_scope();
+                    self.define_pattern(&arm.pattern, BindingKind::Parameter);
+                    if let Some(guard) = &arm.guard {
+                        self.check_expression(guard);
+                    }
+                    self.check_block(Some(&arm.consequence));
+                    self.leave_scope();
+                }
+            }
+            Expression::IntegerLiteral(_)
+            | Expression::Boolean(_)
+            | Expression::Null(_)
+            | Expression::StringLiteral(_)
+            | Expression::This(_)
+            | Expression::Super(_) => {}
+        }
+    }
+
+    /// Выражения внутри паттерна (литералы, границы диапазонов, имя
+    /// структуры) вычисляются в области, где стоит паттерн.
+    fn check_pattern_expressions(&mut self, pattern: &Pattern) {
+        match pattern {
+            Pattern::Literal(expression) => self.check_expression(expression),
+            Pattern::Range(range) => {
+                self.check_expression(&range.start);
+                self.check_expression(&range.end);
+            }
+            Pattern::Tuple(patterns) => {
+                for pattern in patterns {
+                    self.check_pattern_expressions(pattern);
+                }
+            }
+            Pattern::Struct(struct_pattern) => {
+                self.reference(&struct_pattern.name.value);
+                for pattern in struct_pattern.fields.iter().filter_map(|(_, p)| p.as_ref()) {
+                    self.check_pattern_expressions(pattern);
+                }
+            }
+            Pattern::Identifier(_) | Pattern::Rest(_) | Pattern::Wildcard => {}
+        }
+    }
+
+    fn define_pattern(&mut self, pattern: &Pattern, kind: BindingKind) {
+        match pattern {
+            Pattern::Identifier(identifier) | Pattern::Rest(identifier) => {
+                if identifier.value != "_" {
+                    self.define(identifier, kind);
+                }
+            }
+            Pattern::Tuple(patterns) => {
+                for pattern in patterns {
+                    self.define_pattern(pattern, kind);
+                }
+            }
+            Pattern::Struct(struct_pattern) => {
+                for (field, pattern) in &struct_pattern.fields {
+                    match pattern {
+                        Some(pattern) => self.define_pattern(pattern, kind),
+                        None => self.define(field, kind),
+                    }
+                }
+            }
+            Pattern::Literal(_) | Pattern::Range(_) | Pattern::Wildcard => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+    use crate::parser::Parser;
+
+    fn check(input: &str) -> Vec<String> {
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        check_program(&program)
+            .iter()
+            .map(|d| d.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_static_type() {
+        let tests = [
+            ("1", Some(StaticType::Integer)),
+            ("-(1 + 2) * 3", Some(StaticType::Integer)),
+            (r#""a" + "b""#, Some(StaticType::String)),
+            ("1 < 2", Some(StaticType::Boolean)),
+            ("!x", None),
+            ("!true", Some(StaticType::Boolean)),
+            ("null", Some(StaticType::Null)),
+            ("x", None),
+            (r#"1 + "a""#, None),
+            ("f(1)", None),
+        ];
+        for (input, expected) in tests {
+            let program = Parser::new(Lexer::new(input.to_string()))
+                .parse_program()
+                .unwrap();
+            let Statement::Expression(stmt) = &program.statements[0] else {
+                panic!("expected expression statement for {}", input);
+            };
+            assert_eq!(static_type(&stmt.expression), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_check_mismatched_arm_types() {
+        assert_eq!(
+            check(r#"match "x" { 1 => "one", 1..5 => "few", "x" => "x", _ => "other" }"#),
+            vec![
+                "warning: match arm 1 `1` can never match: pattern is INTEGER, value is STRING",
+                "warning: match arm 2 `1..5` can never match: pattern is INTEGER, value is STRING",
+            ]
+        );
+        assert_eq!(
+            check("if let true = 1 + 2 { 1 }"),
+            vec![
+                "warning: match arm 1 `true` can never match: pattern is BOOLEAN, value is INTEGER"
+            ]
+        );
+        // Тип переменной неизвестен — предупреждений нет
+        assert!(check(r#"let x = "x"; match x { 1 => 1, _ => 0 }"#).is_empty());
+    }
+
+    #[test]
+    fn test_check_duplicate_literal_arms() {
+        assert_eq!(
+            check("let _f = fn(x) { match x { 1 => 1, 2 => 2, 1 => 3, _ => 0 } };"),
+            vec!["warning: match arm 3 `1` is unreachable: duplicates arm 1"]
+        );
+        // Ветвь с гардом не перекрывает повтор
+        assert!(check("let x = 1; match x { 1 if x > 0 => 1, 1 => 2, _ => 0 }").is_empty());
+        assert!(check(r#"match 1 { "1" if true => 1, 1 => 2, _ => 0 }"#).len() == 1);
+    }
+
+    #[test]
+    fn test_check_empty_range_patterns() {
+        assert_eq!(
+            check("match 3 { 5..1 => 1, _ => 0 }"),
+            vec!["warning: match arm 1 `5..1` can never match: empty range"]
+        );
+        assert_eq!(
+            check("match 3 { 0 => 0, 2..=-2 => 1, _ => 2 }"),
+            vec!["warning: match arm 2 `2..=(-2)` can never match: empty range"]
+        );
+        // Пустой диапазон из одной границы — не ошибка, `3..=3` совпадает с 3
+        assert!(check("match 3 { 3..3 => 1, 3..=3 => 2, _ => 0 }").is_empty());
+        // Граница-переменная статически неизвестна
+        assert!(check("let hi = -1; match 3 { 0..hi => 1, _ => 2 }").is_empty());
+    }
+
+    #[test]
+    fn test_check_match_exhaustiveness() {
+        // Тип значения неизвестен, но паттерны — целые числа
+        let flagged = "let f = fn(x) { match x { 1 => 1, 2..5 => 2 } }; f(1)";
+        assert_eq!(
+            check(flagged),
+            vec!["warning: match on INTEGER is not exhaustive: add a `_` arm"]
+        );
+        assert!(check("let f = fn(x) { match x { 1 => 1, 2..5 => 2, _ => 0 } }; f(1)").is_empty());
+        // Паттерн-переменная тоже покрывает все значения, а ветвь с гардом — нет
+        assert!(check("match 1 + 1 { 2 => 1, n => n }").is_empty());
+        assert_eq!(check("match 1 + 1 { 2 => 1, n if n > 2 => n }").len(), 1);
+
+        assert_eq!(
+            check("match 1 < 2 { true => 1 }"),
+            vec!["warning: match on BOOLEAN is not exhaustive: missing `false` arm"]
+        );
+        assert_eq!(
+            check("match 1 < 2 { true if false => 1, _ if true => 2 }"),
+            vec!["warning: match on BOOLEAN is not exhaustive: missing `true` and `false` arms"]
+        );
+        assert!(check("match 1 < 2 { true => 1, false => 0 }").is_empty());
+        assert!(check("match 1 < 2 { false => 0, _ => 1 }").is_empty());
+        // Для строк и структур полнота не проверяется; `if let` не исчерпывающий
+        assert!(check(r#"match "a" { "a" => 1 }"#).is_empty());
+        assert!(check("if let 1 = 1 { 2 }").is_empty());
+    }
+
+    #[test]
+    fn test_check_same_scope_redefinition() {
+        assert_eq!(
+            check("let x = 1;\nlet x = x + 1;\nx"),
+            vec!["warning: `let x` at line 2, column 5 redefines `x` from line 1, column 5 in the same scope"]
+        );
+        // Блок `if` не открывает область видимости: интерпретатор перезапишет x
+        assert_eq!(
+            check("let x = 1; if (x > 0) { let x = 2; x }"),
+            vec!["warning: `let x` at line 1, column 29 redefines `x` from line 1, column 5 in the same scope"]
+        );
+        // Параметр — определение в области функции
+        assert_eq!(
+            check("let f = fn(n) { let n = n * 2; n }; f(1)"),
+            vec!["warning: `let n` at line 1, column 21 redefines `n` from line 1, column 12 in the same scope"]
+        );
+        // Перекрытие во вложенной области — намеренное, без предупреждения
+        let inner = "let x = 1;
+            let f = fn() { let x = 2; x };
+            let g = fn(x) { x };
+            let r = match 3 { x => x };
+            class C { public x = 0; public m() { let x = 4; x } }
+            [x, f(), g(5), r, new C().m()]";
+        assert!(check(inner).is_empty(), "{:?}", check(inner));
+    }
+
+    #[test]
+    fn test_check_type_redeclaration() {
+        assert_eq!(
+            check("class A {}\nclass A {}"),
+            vec!["warning: class 'A' at line 2, column 7 is already defined at line 1, column 7"]
+        );
+        assert_eq!(
+            check("struct S {}\ninterface S { fn f(); }"),
+            vec!["warning: struct 'S' at line 2, column 11 is already defined at line 1, column 8"]
+        );
+        // Столкновение с `let` — в обоих порядках
+        assert_eq!(
+            check("let A = 5;\nclass A {}\nA"),
+            vec![
+                "warning: class 'A' at line 2, column 7 is already defined at line 1, column 5",
+                // Ссылка `A` относится уже к классу
+                "warning: unused variable `A` at line 1, column 5",
+            ]
+        );
+        assert_eq!(
+            check("class A {}\nlet A = 5;\nA"),
+            vec!["warning: class 'A' at line 2, column 5 is already defined at line 1, column 7"]
+        );
+        // Во вложенной области имя типа можно перекрыть
+        let inner = "class A {}
+            let f = fn() { class A {} let B = 1; B };
+            let g = fn(A) { A };
+            [f(), g(1)]";
+        assert!(check(inner).is_empty(), "{:?}", check(inner));
+    }
+
+    #[test]
+    fn test_check_unused_bindings() {
+        assert_eq!(
+            check("let a = 1;\nlet b = 2;\nlet _c = 3;\nb"),
+            vec!["warning: unused variable `a` at line 1, column 5"]
+        );
+        // Неиспользуемые параметры, переменные цикла и привязки паттернов не
+        // считаются ошибкой — о них не предупреждаем
+        let quiet = "let f = fn(unused, ...rest) { 0 };
+            for (i in [1, 2]) { f(1) }
+            match [1, 2] { [a, b] => 0 };
+            if let x = 1 { 2 }";
+        assert!(check(quiet).is_empty(), "{:?}", check(quiet));
+
+        assert_eq!(
+            check("let f = fn(x) { let y = x; let z = y; 0 }; f(1)"),
+            vec!["warning: unused variable `z` at line 1, column 32"]
+        );
+        assert_eq!(
+            check("let [head, ..tail] = [1, 2, 3]; head"),
+            vec!["warning: unused variable `tail` at line 1, column 14"]
+        );
+        // Перезаписанное без чтения значение тоже не использовано
+        assert_eq!(
+            check("let x = 1; let x = 2; x"),
+            vec![
+                "warning: `let x` at line 1, column 16 redefines `x` from line 1, column 5 in the same scope",
+                "warning: unused variable `x` at line 1, column 5",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_references_before_definition() {
+        // Рекурсия и взаимные вызовы функций, объявленных позже, — использование
+        let program = "let even = fn(n) { if (n == 0) { true } else { odd(n - 1) } };
+            let odd = fn(n) { if (n == 0) { false } else { even(n - 1) } };
+            class Counter { public step = 1; public next(n) { n + step } }
+            let c = new Counter();
+            c.next(1)";
+        assert!(check(program).is_empty(), "{:?}", check(program));
+    }
+
+    #[test]
+    fn test_check_unknown_type_names() {
+        // Встроенные типы и объявленные (в том числе ниже по тексту) известны
+        let known = "let n: Int = 1;
+            let f = fn(s: String, ...rest: Array[Bool]): Null { s };
+            fn make(): Point { new Point() }
+            struct Point { let x = 0; }
+            interface Shape { fn area(): Int; }
+            class Box implements Shape { fn area(): Int { n } }
+            f(n, make())";
+        assert!(check(known).is_empty(), "{:?}", check(known));
+
+        assert_eq!(
+            check("let x: Integer = 1; x"),
+            vec!["warning: unknown type `Integer` in annotation at line 1, column 8"]
+        );
+        // Неизвестные имена ищутся и во вложенных аргументах и сигнатурах
+        assert_eq!(
+            check(
+                "let f = fn(a: Array[Strng]): Maybe { a }; f
+                interface I { fn get(k: Key); }"
+            ),
+            vec![
+                "warning: unknown type `Strng` in annotation at line 1, column 21",
+                "warning: unknown type `Maybe` in annotation at line 1, column 30",
+                "warning: unknown type `Key` in annotation at line 2, column 41",
+            ]
+        );
+    }
+}
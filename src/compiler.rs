@@ -1,23 +1,69 @@
-use crate::ast::{Expression, Program, Statement};
-use crate::bytecode::instructions::Instructions;
+use crate::ast::{
+    AssignExpression, BlockStatement, Expression, ForExpression, MatchArm, MatchExpression,
+    Pattern, Program, RangeExpression, Statement, StructDeclaration,
+};
+use crate::builtins;
+use crate::bytecode::constant::Constant;
+use crate::bytecode::instructions::{BytecodeError, Instructions, PatchHandle};
 use crate::bytecode::opcode::Opcode;
-use crate::object::Object;
-use std::collections::HashMap;
+use crate::bytecode::peephole;
+use crate::checker::{self, Diagnostic};
+use crate::object::PRIMITIVE_TYPE_NAMES;
+use crate::token::Token;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+/// Минимальное число целочисленных ветвей match, начиная с которого
+/// компилятор строит таблицу переходов вместо цепочки сравнений.
+const JUMP_TABLE_MIN_CASES: usize = 4;
+/// Максимальный размер таблицы переходов (разброс значений ветвей).
+const JUMP_TABLE_MAX_SPAN: i64 = 256;
 
 /// Представляет ошибку, возникшую в процессе компиляции.
 #[derive(Debug, PartialEq, Clone)]
 pub enum CompilerError {
     /// Указывает на неподдерживаемую конструкцию языка.
     Unsupported(String),
+    /// Конструкция есть в языке, но пока работает только в AST-интерпретаторе.
+    /// `construct` — название во множественном числе («классы»).
+    NotYetImplemented { construct: &'static str },
     /// Ошибка при компиляции выражения.
     ExpressionError(String),
     /// Неизвестный оператор.
     UnknownOperator(String),
+    /// Ошибка построения байткода: неверные операнды или переход
+    /// за пределы допустимого смещения.
+    Bytecode(BytecodeError),
+}
+
+impl fmt::Display for CompilerError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CompilerError::Unsupported(message) | CompilerError::ExpressionError(message) => {
+                write!(f, "{}", message)
+            }
+            CompilerError::Bytecode(error) => write!(f, "{}", error),
+            CompilerError::NotYetImplemented { construct } => write!(
+                f,
+                "{} пока не поддерживаются в режиме VM — используйте --ast",
+                construct
+            ),
+            CompilerError::UnknownOperator(operator) => {
+                write!(f, "Неизвестный оператор: {}", operator)
+            }
+        }
+    }
+}
+
+impl From<BytecodeError> for CompilerError {
+    fn from(err: BytecodeError) -> Self {
+        CompilerError::Bytecode(err)
+    }
 }
 
 impl From<CompilerError> for String {
     fn from(err: CompilerError) -> Self {
-        format!("{:?}", err)
+        err.to_string()
     }
 }
 
@@ -37,7 +83,14 @@ enum SymbolScope {
 struct Symbol {
     name: String,
     scope: SymbolScope,
+    /// Слот локальной или free-переменной, номер встроенной функции, а для
+    /// глобальной переменной — индекс константы с её именем.
     index: usize,
+    /// Объявлен через `const`: присваивание — ошибка компиляции.
+    constant: bool,
+    /// Вид объявления типа (`"struct"`), если символ — структура. Вне
+    /// REPL такое имя нельзя повторно объявить в той же области видимости.
+    type_kind: Option<&'static str>,
 }
 
 /// Таблица символов для отслеживания переменных и областей видимости.
@@ -46,6 +99,8 @@ struct SymbolTable {
     outer: Option<Box<SymbolTable>>,
     store: HashMap<String, Symbol>,
     num_definitions: usize,
+    /// Наибольшее число одновременно занятых слотов — размер кадра функции.
+    max_definitions: usize,
     free_symbols: Vec<Symbol>,
 }
 
@@ -55,6 +110,7 @@ impl SymbolTable {
             outer: None,
             store: HashMap::new(),
             num_definitions: 0,
+            max_definitions: 0,
             free_symbols: Vec::new(),
         }
     }
@@ -64,6 +120,7 @@ impl SymbolTable {
             outer: Some(outer),
             store: HashMap::new(),
             num_definitions: 0,
+            max_definitions: 0,
             free_symbols: Vec::new(),
         }
     }
@@ -78,9 +135,12 @@ impl SymbolTable {
             name: name.clone(),
             scope,
             index: self.num_definitions,
+            constant: false,
+            type_kind: None,
         };
         self.store.insert(name, symbol.clone());
         self.num_definitions += 1;
+        self.max_definitions = self.max_definitions.max(self.num_definitions);
         symbol
     }
 
@@ -108,16 +168,32 @@ impl SymbolTable {
             name: original.name,
             scope: SymbolScope::Free,
             index: self.free_symbols.len() - 1,
+            constant: original.constant,
+            type_kind: original.type_kind,
         };
         self.store.insert(symbol.name.clone(), symbol.clone());
         symbol
     }
 
+    fn define_global(&mut self, name: String, name_constant: usize) -> Symbol {
+        let symbol = Symbol {
+            name: name.clone(),
+            scope: SymbolScope::Global,
+            index: name_constant,
+            constant: false,
+            type_kind: None,
+        };
+        self.store.insert(name, symbol.clone());
+        symbol
+    }
+
     fn define_builtin(&mut self, name: String, index: usize) -> Symbol {
         let symbol = Symbol {
             name: name.clone(),
             scope: SymbolScope::Builtin,
             index,
+            constant: false,
+            type_kind: None,
         };
         self.store.insert(name, symbol.clone());
         symbol
@@ -134,29 +210,105 @@ pub struct Compiler {
     /// Таблица символов для отслеживания переменных.
     symbol_table: SymbolTable,
 
-    /// Стек слоев видимости (scopes).
+    /// Стек слоев видимости (scopes) текущей функции: нулевой слой — тело
+    /// функции (или глобальная область), остальные — вложенные блоки.
     scopes: Vec<Scope>,
 
     /// Индекс текущего слоя видимости.
     scope_index: usize,
+
+    /// Предупреждения, найденные при компиляции (например, недостижимые
+    /// ветви `match`).
+    warnings: Vec<Diagnostic>,
+
+    /// Стек циклов текущей функции, в которых находится компилируемый код.
+    loops: Vec<LoopContext>,
+
+    /// Индексы уже добавленных констант, которые можно переиспользовать.
+    constant_indices: HashMap<ConstantKey, usize>,
+
+    /// Компилировать `+`, `-`, `*`, `/` и `%` через регистры VM
+    /// (экспериментальный режим, см. `enable_register_arithmetic`).
+    register_arithmetic: bool,
+
+    /// Пропускать результат `compile` через peephole-оптимизатор
+    /// (см. `enable_peephole`).
+    peephole: bool,
+
+    /// Разрешить повторно объявлять структуру в той же области видимости
+    /// (режим REPL, см. `allow_redefinition`).
+    redefinition: bool,
+}
+
+/// Ключ для поиска равной константы в пуле. Он есть только у значений,
+/// которые дёшево и однозначно сравнить: целых чисел, строк, логических
+/// значений и массивов из них.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum ConstantKey {
+    Integer(i64),
+    String(String),
+    Boolean(bool),
+    Array(Vec<ConstantKey>),
+}
+
+impl ConstantKey {
+    fn of(constant: &Constant) -> Option<Self> {
+        match constant {
+            Constant::Integer(value) => Some(ConstantKey::Integer(*value)),
+            Constant::String(value) => Some(ConstantKey::String(value.clone())),
+            Constant::Boolean(value) => Some(ConstantKey::Boolean(*value)),
+            Constant::Array(items) => items
+                .iter()
+                .map(ConstantKey::of)
+                .collect::<Option<_>>()
+                .map(ConstantKey::Array),
+            // Null — заглушка таблицы переходов, которую компилятор потом
+            // перезаписывает; каждая функция получает собственную константу
+            Constant::Null | Constant::CompiledFunction(_) => None,
+        }
+    }
+}
+
+/// Незаполненные переходы `break` и `continue` одного цикла. Их цели
+/// известны только после компиляции тела, поэтому они дописываются в конце.
+#[derive(Debug)]
+struct LoopContext {
+    break_jumps: Vec<PatchHandle>,
+    continue_jumps: Vec<PatchHandle>,
+    /// Скрытая переменная с глубиной стека на входе в цикл: перед переходом
+    /// `break`/`continue` стек сбрасывается до неё (`Unwind`).
+    depth: Symbol,
 }
 
 /// Информация о слое видимости (scope).
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 struct Scope {
     /// Локальные переменные в этом слое видимости.
     locals: Vec<LocalVariable>,
-    /// Количество локальных переменных.
+    /// Количество занятых слотов на входе в слой; после выхода слоты
+    /// начиная с этого индекса переиспользуются.
     num_locals: usize,
 }
 
+/// Место записи, получатели и индексы которого уже сохранены в скрытых
+/// переменных: запись обратно их не перевычисляет.
+enum Place<'a> {
+    Variable(Symbol),
+    /// Элемент: место контейнера и переменные с контейнером и индексом.
+    Element(Box<Place<'a>>, Symbol, Symbol),
+    /// Поле: место объекта, переменная с объектом и имя поля.
+    Property(Box<Place<'a>>, Symbol, &'a str),
+    /// Прочие выражения: записать в них нельзя.
+    Expression(&'a Expression),
+}
+
 /// Информация о локальной переменной.
 #[derive(Debug, Clone)]
 struct LocalVariable {
     /// Имя переменной.
     name: String,
-    /// Индекс в стеке локальных переменных.
-    index: usize,
+    /// Символ с тем же именем из внешнего слоя, восстанавливаемый при выходе.
+    shadowed: Option<Symbol>,
 }
 
 impl Compiler {
@@ -165,27 +317,66 @@ impl Compiler {
         let mut symbol_table = SymbolTable::new();
 
         // Регистрируем built-in функции
-        let builtins = vec![
-            "len".to_string(),
-            "puts".to_string(),
-            "first".to_string(),
-            "last".to_string(),
-            "rest".to_string(),
-            "push".to_string(),
-        ];
-        for (i, name) in builtins.iter().enumerate() {
-            symbol_table.define_builtin(name.clone(), i);
+        for (i, name) in builtins::names().enumerate() {
+            symbol_table.define_builtin(name.to_string(), i);
         }
 
         Compiler {
             instructions: Instructions::new(),
             symbol_table,
-            scopes: vec![Scope {
-                locals: Vec::new(),
-                num_locals: 0,
-            }],
+            scopes: vec![Scope::default()],
             scope_index: 0,
+            warnings: Vec::new(),
+            loops: Vec::new(),
+            constant_indices: HashMap::new(),
+            register_arithmetic: false,
+            peephole: false,
+            redefinition: false,
+        }
+    }
+
+    /// Включает регистровую арифметику: операнды `+`, `-`, `*`, `/` и `%`
+    /// снимаются со стека в регистры 0 и 1, операция выполняется над
+    /// регистрами, результат возвращается на стек. Результаты и ошибки те
+    /// же, что у стековых опкодов; режим нужен для сравнения скорости.
+    pub fn enable_register_arithmetic(&mut self) {
+        self.register_arithmetic = true;
+    }
+
+    /// Включает peephole-оптимизацию (`bytecode::peephole`) байткода,
+    /// который возвращает `compile`. Накопленное состояние компилятора не
+    /// оптимизируется, поэтому инкрементальная компиляция работает как
+    /// прежде: каждый вызов оптимизирует весь байткод заново.
+    pub fn enable_peephole(&mut self) {
+        self.peephole = true;
+    }
+
+    /// Разрешает повторно объявлять структуру в той же области видимости,
+    /// как `Environment::allowing_redefinition` в интерпретаторе: новое
+    /// объявление заменяет прежнее. Без этого режима имя структуры нельзя
+    /// объявить второй раз — ни структурой, ни `let`, ни наоборот.
+    pub fn allow_redefinition(&mut self) {
+        self.redefinition = true;
+    }
+
+    /// Предупреждения, накопленные вызовами `compile`.
+    pub fn warnings(&self) -> &[Diagnostic] {
+        &self.warnings
+    }
+
+    /// Добавляет константу в пул. Равная ей целая, строковая или логическая
+    /// константа (или массив из них) переиспользуется; функции и `Null`
+    /// всегда занимают новый слот.
+    fn add_constant(&mut self, constant: Constant) -> usize {
+        let Some(key) = ConstantKey::of(&constant) else {
+            return self.instructions.add_constant(constant);
+        };
+        if let Some(&index) = self.constant_indices.get(&key) {
+            return index;
         }
+        let index = self.instructions.add_constant(constant);
+        self.constant_indices.insert(key, index);
+        index
     }
 
     /// Получить текущий слой видимости.
@@ -193,78 +384,719 @@ impl Compiler {
         &mut self.scopes[self.scope_index]
     }
 
-    /// Добавить локальную переменную в текущий слой видимости.
-    fn add_local(&mut self, name: String) -> usize {
-        let scope = self.current_scope();
-        let index = scope.num_locals;
-        scope.locals.push(LocalVariable {
-            name: name.clone(),
-            index,
+    /// Войти во вложенный блок.
+    fn enter_scope(&mut self) {
+        self.scopes.push(Scope {
+            locals: Vec::new(),
+            num_locals: self.symbol_table.num_definitions,
         });
-        scope.num_locals += 1;
-        self.symbol_table.define(name);
-        index
+        self.scope_index += 1;
     }
 
-    /// Проверить является ли переменная локальной.
-    fn is_local(&self, name: &str) -> bool {
-        self.scopes[self.scope_index]
+    /// Выйти из блока: его переменные становятся невидимы, перекрытые ими
+    /// символы восстанавливаются, а слоты освобождаются для соседних блоков.
+    fn leave_scope(&mut self) {
+        let scope = self.scopes.pop().expect("leave_scope без enter_scope");
+        self.scope_index -= 1;
+
+        for local in scope.locals.into_iter().rev() {
+            match local.shadowed {
+                Some(symbol) => self.symbol_table.store.insert(local.name, symbol),
+                None => self.symbol_table.store.remove(&local.name),
+            };
+        }
+        if self.symbol_table.outer.is_some() {
+            self.symbol_table.num_definitions = scope.num_locals;
+        }
+    }
+
+    /// Объявить переменную в текущем слое видимости.
+    ///
+    /// Блоки верхнего уровня пока не создают собственных переменных:
+    /// объявления в них остаются глобальными.
+    fn add_local(&mut self, name: String) -> Symbol {
+        if self.symbol_table.outer.is_none() {
+            return self.define_global(name);
+        }
+        if self.scope_index == 0 {
+            return self.symbol_table.define(name);
+        }
+
+        let shadowed = self.symbol_table.store.get(&name).cloned();
+        let symbol = self.symbol_table.define(name.clone());
+        self.current_scope()
             .locals
+            .push(LocalVariable { name, shadowed });
+        symbol
+    }
+
+    /// Объявить переменную или структуру (`type_kind`) в текущем слое
+    /// видимости. Вне режима REPL ошибка `struct 'S' is already defined`,
+    /// если имя уже объявлено в этом слое и одно из объявлений — структура.
+    fn declare(
+        &mut self,
+        name: String,
+        type_kind: Option<&'static str>,
+    ) -> Result<Symbol, CompilerError> {
+        if let Some(existing) = self.declared_in_scope(&name) {
+            match existing.type_kind.or(type_kind) {
+                Some(kind) if !self.redefinition => {
+                    return Err(CompilerError::ExpressionError(format!(
+                        "{} '{}' is already defined",
+                        kind, name
+                    )));
+                }
+                _ => {}
+            }
+        }
+        let mut symbol = self.add_local(name);
+        if type_kind.is_some() {
+            symbol.type_kind = type_kind;
+            self.symbol_table
+                .store
+                .insert(symbol.name.clone(), symbol.clone());
+        }
+        Ok(symbol)
+    }
+
+    /// Символ, объявленный в текущем слое видимости под именем `name`.
+    /// Блоки верхнего уровня собственных переменных не создают, поэтому там
+    /// это любая глобальная переменная.
+    fn declared_in_scope(&self, name: &str) -> Option<&Symbol> {
+        let symbol = self.symbol_table.store.get(name)?;
+        let in_scope = match symbol.scope {
+            SymbolScope::Global => true,
+            SymbolScope::Local if self.scope_index == 0 => true,
+            SymbolScope::Local => self.scopes[self.scope_index]
+                .locals
+                .iter()
+                .any(|local| local.name == name),
+            SymbolScope::Free | SymbolScope::Builtin => false,
+        };
+        in_scope.then_some(symbol)
+    }
+
+    /// Объявить глобальную переменную. Имя попадает в пул констант один раз,
+    /// при первом объявлении; все `GetGlobal`/`SetGlobal` этой переменной
+    /// ссылаются на ту же константу.
+    fn define_global(&mut self, name: String) -> Symbol {
+        let name_constant = match self.symbol_table.store.get(&name) {
+            Some(symbol) if symbol.scope == SymbolScope::Global => symbol.index,
+            _ => self.add_constant(Constant::String(name.clone())),
+        };
+        self.symbol_table.define_global(name, name_constant)
+    }
+
+    /// Компилирует операторы блока во вложенном слое видимости.
+    fn compile_scoped_block(&mut self, block: &BlockStatement) -> Result<(), CompilerError> {
+        self.enter_scope();
+        for stmt in &block.statements {
+            self.compile_statement(stmt)?;
+        }
+        self.leave_scope();
+        Ok(())
+    }
+
+    /// Как `compile_block_value`, но во вложенном слое видимости.
+    fn compile_scoped_block_value(&mut self, block: &BlockStatement) -> Result<(), CompilerError> {
+        self.enter_scope();
+        self.compile_block_value(block)?;
+        self.leave_scope();
+        Ok(())
+    }
+
+    /// Загружает значение символа на стек.
+    fn emit_load_symbol(&mut self, symbol: &Symbol) -> Result<(), CompilerError> {
+        match symbol.scope {
+            SymbolScope::Global => {
+                self.instructions.emit(Opcode::GetGlobal, &[symbol.index])?;
+            }
+            SymbolScope::Local => {
+                self.instructions.emit(Opcode::GetLocal, &[symbol.index])?;
+            }
+            SymbolScope::Free => {
+                self.instructions.emit(Opcode::GetFree, &[symbol.index])?;
+            }
+            SymbolScope::Builtin => {
+                self.instructions
+                    .emit(Opcode::GetBuiltin, &[symbol.index])?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Сохраняет вершину стека в переменную, объявленную через `SymbolTable::define`.
+    fn emit_store_symbol(&mut self, symbol: &Symbol) -> Result<(), CompilerError> {
+        if symbol.scope == SymbolScope::Local {
+            // Локальная переменная (внутри функции)
+            self.instructions.emit(Opcode::SetLocal, &[symbol.index])?;
+        } else {
+            // Глобальная переменная: индекс символа — константа с её именем
+            self.instructions.emit(Opcode::SetGlobal, &[symbol.index])?;
+        }
+        Ok(())
+    }
+
+    /// Компилирует присваивание уже объявленной переменной. Как и в
+    /// AST-интерпретаторе, значением выражения служит присвоенное значение.
+    fn compile_assign(&mut self, assign: &AssignExpression) -> Result<(), CompilerError> {
+        match assign.target.as_ref() {
+            Expression::Identifier(ident) => {
+                let symbol = self.resolve_assignable(&ident.value)?;
+                self.compile_expression(&assign.value)?;
+                self.emit_store_symbol(&symbol)?;
+                // Set* снимает значение со стека — возвращаем его как результат
+                self.emit_load_symbol(&symbol)
+            }
+            Expression::PropertyAccess(pae) => {
+                // Получатель вычисляется один раз, как при записи по
+                // индексу; результат — присвоенное значение
+                self.enter_scope();
+                let parent = self.compile_receiver(&pae.left)?;
+                self.compile_expression(&assign.value)?;
+                let value = self.add_local(format!("$value{}", self.instructions.bytes.len()));
+                self.emit_store_symbol(&value)?;
+                self.emit_load_symbol(&value)?;
+                let name_idx = self.add_constant(Constant::String(pae.property.value.clone()));
+                self.instructions.emit(Opcode::SetProperty, &[name_idx])?;
+                self.emit_store_place(parent)?;
+                self.emit_load_symbol(&value)?;
+                self.leave_scope();
+                Ok(())
+            }
+            Expression::Index(ie) => {
+                // Контейнер, индекс и значение вычисляются в этом порядке и по
+                // одному разу, как в интерпретаторе; значение сохраняется как
+                // результат
+                self.enter_scope();
+                let parent = self.compile_receiver(&ie.left)?;
+                self.compile_expression(&ie.index)?;
+                self.compile_expression(&assign.value)?;
+                let value = self.add_local(format!("$value{}", self.instructions.bytes.len()));
+                self.emit_store_symbol(&value)?;
+                self.emit_load_symbol(&value)?;
+                self.instructions.emit(Opcode::SetIndex, &[])?;
+                self.emit_store_place(parent)?;
+                self.emit_load_symbol(&value)?;
+                self.leave_scope();
+                Ok(())
+            }
+            target => Err(CompilerError::ExpressionError(format!(
+                "Недопустимая цель присваивания: {}",
+                target
+            ))),
+        }
+    }
+
+    /// Компилирует получатель записи: его значение остаётся на стеке, а
+    /// контейнеры и индексы на пути к нему сохраняются в скрытых переменных
+    /// для записи обратно.
+    fn compile_receiver<'a>(&mut self, expr: &'a Expression) -> Result<Place<'a>, CompilerError> {
+        match expr {
+            Expression::Identifier(ident) => {
+                let symbol = self.resolve_assignable(&ident.value)?;
+                self.emit_load_symbol(&symbol)?;
+                Ok(Place::Variable(symbol))
+            }
+            Expression::Index(ie) => {
+                let parent = self.compile_receiver(&ie.left)?;
+                let container =
+                    self.add_local(format!("$container{}", self.instructions.bytes.len()));
+                self.emit_store_symbol(&container)?;
+                self.emit_load_symbol(&container)?;
+                self.compile_expression(&ie.index)?;
+                let index = self.add_local(format!("$index{}", self.instructions.bytes.len()));
+                self.emit_store_symbol(&index)?;
+                self.emit_load_symbol(&index)?;
+                self.instructions.emit(Opcode::Index, &[])?;
+                Ok(Place::Element(Box::new(parent), container, index))
+            }
+            Expression::PropertyAccess(pae) => {
+                let parent = self.compile_receiver(&pae.left)?;
+                let object = self.add_local(format!("$object{}", self.instructions.bytes.len()));
+                self.emit_store_symbol(&object)?;
+                self.emit_load_symbol(&object)?;
+                let name_idx = self.add_constant(Constant::String(pae.property.value.clone()));
+                self.instructions.emit(Opcode::GetProperty, &[name_idx])?;
+                Ok(Place::Property(
+                    Box::new(parent),
+                    object,
+                    &pae.property.value,
+                ))
+            }
+            expr => {
+                self.compile_expression(expr)?;
+                Ok(Place::Expression(expr))
+            }
+        }
+    }
+
+    /// Записывает значение с вершины стека в место, собранное
+    /// `compile_receiver`, снимая его.
+    fn emit_store_place(&mut self, place: Place) -> Result<(), CompilerError> {
+        match place {
+            Place::Variable(symbol) => self.emit_store_symbol(&symbol),
+            Place::Element(parent, container, index) => {
+                let updated = self.add_local(format!("$place{}", self.instructions.bytes.len()));
+                self.emit_store_symbol(&updated)?;
+                self.emit_load_symbol(&container)?;
+                self.emit_load_symbol(&index)?;
+                self.emit_load_symbol(&updated)?;
+                self.instructions.emit(Opcode::SetIndex, &[])?;
+                self.emit_store_place(*parent)
+            }
+            // Экземпляры структур — значения: `SetProperty` оставляет
+            // изменённую копию, которая записывается обратно в место объекта
+            Place::Property(parent, object, name) => {
+                let updated = self.add_local(format!("$place{}", self.instructions.bytes.len()));
+                self.emit_store_symbol(&updated)?;
+                self.emit_load_symbol(&object)?;
+                self.emit_load_symbol(&updated)?;
+                let name_idx = self.add_constant(Constant::String(name.to_string()));
+                self.instructions.emit(Opcode::SetProperty, &[name_idx])?;
+                self.emit_store_place(*parent)
+            }
+            Place::Expression(target) => Err(CompilerError::ExpressionError(format!(
+                "Недопустимая цель присваивания: {}",
+                target
+            ))),
+        }
+    }
+
+    /// Символ переменной, которой можно присвоить значение.
+    fn resolve_assignable(&mut self, name: &str) -> Result<Symbol, CompilerError> {
+        let symbol = self.symbol_table.resolve(name).ok_or_else(|| {
+            CompilerError::ExpressionError(format!(
+                "Присваивание необъявленной переменной {}",
+                name
+            ))
+        })?;
+        if symbol.constant {
+            return Err(CompilerError::ExpressionError(format!(
+                "cannot assign to constant {}",
+                name
+            )));
+        }
+        match symbol.scope {
+            SymbolScope::Global | SymbolScope::Local => Ok(symbol),
+            SymbolScope::Free => Err(CompilerError::NotYetImplemented {
+                construct: "присваивания захваченным переменным",
+            }),
+            SymbolScope::Builtin => Err(CompilerError::ExpressionError(format!(
+                "Нельзя присвоить значение встроенной функции {}",
+                name
+            ))),
+        }
+    }
+
+    /// Компилирует объявление структуры: значения полей по умолчанию
+    /// кладутся на стек, `Struct` собирает из них определение, которое
+    /// сохраняется под именем структуры.
+    fn compile_struct_declaration(
+        &mut self,
+        struct_decl: &StructDeclaration,
+    ) -> Result<(), CompilerError> {
+        let mut layout = vec![Constant::String(struct_decl.name.value.clone())];
+        for property in &struct_decl.properties {
+            match &property.value {
+                Some(value) => self.compile_expression(value)?,
+                None => {
+                    self.instructions.emit(Opcode::Null, &[])?;
+                }
+            }
+            layout.push(Constant::String(property.name.value.clone()));
+        }
+        let layout_idx = self.add_constant(Constant::Array(layout));
+        self.instructions.emit(Opcode::Struct, &[layout_idx])?;
+
+        let symbol = self.declare(struct_decl.name.value.clone(), Some("struct"))?;
+        self.emit_store_symbol(&symbol)
+    }
+
+    /// Компилирует блок так, чтобы его значение осталось на стеке:
+    /// значение последнего выражения или Null.
+    fn compile_block_value(&mut self, block: &BlockStatement) -> Result<(), CompilerError> {
+        for stmt in &block.statements {
+            self.compile_statement(stmt)?;
+        }
+        if let Some(Statement::Expression(_)) = block.statements.last() {
+            // Убираем Pop последнего выражения
+            self.instructions.bytes.pop();
+        } else {
+            self.instructions.emit(Opcode::Null, &[])?;
+        }
+        Ok(())
+    }
+
+    /// Компилирует match-выражение.
+    ///
+    /// Ведущие ветви с плотным набором целочисленных литералов компилируются
+    /// в `JumpTable`, остальные — в линейную цепочку проверок.
+    fn compile_match(&mut self, match_expr: &MatchExpression) -> Result<(), CompilerError> {
+        self.compile_expression(&match_expr.value)?;
+        // Значение проверяется несколькими ветвями — сохраняем его в скрытую переменную
+        self.enter_scope();
+        let subject = self.add_local(format!("$match{}", self.instructions.bytes.len()));
+        self.emit_store_symbol(&subject)?;
+
+        let mut end_jumps = Vec::new();
+        if let Some((base, span, values)) = Self::dense_integer_arms(&match_expr.arms) {
+            self.emit_load_symbol(&subject)?;
+            let table_idx = self.add_constant(Constant::Null);
+            let table_end = self.instructions.emit(Opcode::JumpTable, &[table_idx])? + 3;
+
+            // Значения вне таблицы проверяются оставшимися ветвями
+            let default_offset = self.instructions.bytes.len() - table_end;
+            self.compile_match_arms(&subject, &match_expr.arms[values.len()..], &mut end_jumps)?;
+            end_jumps.push(self.instructions.emit_jump_placeholder(Opcode::Jump)?);
+
+            let mut targets = vec![default_offset; span];
+            for (arm, value) in match_expr.arms.iter().zip(&values) {
+                targets[(value - base) as usize] = self.instructions.bytes.len() - table_end;
+                self.enter_scope();
+                self.compile_block_value(&arm.consequence)?;
+                self.leave_scope();
+                end_jumps.push(self.instructions.emit_jump_placeholder(Opcode::Jump)?);
+            }
+
+            let mut table = vec![
+                Constant::Integer(base),
+                Constant::Integer(default_offset as i64),
+            ];
+            table.extend(targets.into_iter().map(|t| Constant::Integer(t as i64)));
+            self.instructions.constants[table_idx] = Constant::Array(table);
+        } else {
+            self.compile_match_arms(&subject, &match_expr.arms, &mut end_jumps)?;
+        }
+
+        let end = self.instructions.bytes.len();
+        for pos in end_jumps {
+            self.instructions.patch_jump(pos, end)?;
+        }
+        self.leave_scope();
+        Ok(())
+    }
+
+    /// Компилирует цикл `for (x in start..end) { ... }`. Границы вычисляются
+    /// один раз и хранятся в скрытых переменных; переменная цикла получает
+    /// копию счётчика, поэтому присваивание ей не влияет на перебор.
+    /// Значение цикла — Null.
+    fn compile_range_loop(
+        &mut self,
+        for_expr: &ForExpression,
+        range: &RangeExpression,
+    ) -> Result<(), CompilerError> {
+        self.enter_scope();
+        let suffix = self.instructions.bytes.len();
+        self.compile_expression(&range.start)?;
+        let counter = self.add_local(format!("$i{}", suffix));
+        self.emit_store_symbol(&counter)?;
+        self.compile_expression(&range.end)?;
+        let end = self.add_local(format!("$end{}", suffix));
+        self.emit_store_symbol(&end)?;
+        let depth = self.add_local(format!("$depth{}", self.instructions.bytes.len()));
+        self.instructions.emit(Opcode::StackDepth, &[])?;
+        self.emit_store_symbol(&depth)?;
+
+        let loop_start = self.instructions.bytes.len();
+        self.emit_load_symbol(&counter)?;
+        self.emit_load_symbol(&end)?;
+        self.instructions.emit(Opcode::LessThan, &[])?;
+        let exit_jump = self
+            .instructions
+            .emit_jump_placeholder(Opcode::JumpIfFalse)?;
+
+        self.enter_scope();
+        let variable = self.add_local(for_expr.variable.value.clone());
+        self.emit_load_symbol(&counter)?;
+        self.emit_store_symbol(&variable)?;
+        self.loops.push(LoopContext {
+            break_jumps: Vec::new(),
+            continue_jumps: Vec::new(),
+            depth,
+        });
+        self.compile_scoped_block(&for_expr.body)?;
+        let context = self.loops.pop().expect("цикл снят со стека раньше времени");
+        self.leave_scope();
+
+        // continue переходит к увеличению счётчика
+        let step = self.instructions.bytes.len();
+        for jump in context.continue_jumps {
+            self.instructions.patch_jump(jump, step)?;
+        }
+        self.emit_load_symbol(&counter)?;
+        let one = self.add_constant(Constant::Integer(1));
+        self.instructions.emit_constant(one)?;
+        self.instructions.emit(Opcode::Add, &[])?;
+        self.emit_store_symbol(&counter)?;
+        let back_jump = self.instructions.emit_jump_placeholder(Opcode::Jump)?;
+        self.instructions.patch_jump(back_jump, loop_start)?;
+
+        let exit = self.instructions.bytes.len();
+        self.instructions.patch_jump(exit_jump, exit)?;
+        for jump in context.break_jumps {
+            self.instructions.patch_jump(jump, exit)?;
+        }
+        self.leave_scope();
+        self.instructions.emit(Opcode::Null, &[])?;
+        Ok(())
+    }
+
+    /// Сбрасывает стек до глубины на входе в ближайший цикл: `break` или
+    /// `continue` внутри выражения (`[1, if (c) { break; } else { 2 }]`)
+    /// покидает его, не сняв уже вычисленные операнды.
+    fn emit_unwind(&mut self, token: &Token) -> Result<(), CompilerError> {
+        let depth = self.current_loop(token)?.depth.clone();
+        self.emit_load_symbol(&depth)?;
+        self.instructions.emit(Opcode::Unwind, &[])?;
+        Ok(())
+    }
+
+    /// Ближайший цикл для `break`/`continue`; парсер не пропускает их вне
+    /// цикла, но AST можно построить и вручную.
+    fn current_loop(&mut self, token: &Token) -> Result<&mut LoopContext, CompilerError> {
+        self.loops
+            .last_mut()
+            .ok_or_else(|| CompilerError::Unsupported(format!("{} вне цикла", token.literal)))
+    }
+
+    /// Ведущие ветви вида `<целое> => ...` без гардов, если их достаточно много
+    /// и значения плотно покрывают диапазон. Возвращает минимальное значение
+    /// (базу таблицы), размер таблицы и значения ветвей по порядку. Разброс,
+    /// не помещающийся в `i64` (`0` и `i64::MAX`), таблицей не компилируется.
+    fn dense_integer_arms(arms: &[MatchArm]) -> Option<(i64, usize, Vec<i64>)> {
+        let values: Vec<i64> = arms
             .iter()
-            .any(|l| l.name == name)
+            .map_while(|arm| match (&arm.pattern, &arm.guard) {
+                (Pattern::Literal(Expression::IntegerLiteral(il)), None) => Some(il.value),
+                _ => None,
+            })
+            .collect();
+        if values.len() < JUMP_TABLE_MIN_CASES {
+            return None;
+        }
+        // Повторяющееся значение недостижимо во второй ветви — оставляем линейную цепочку
+        if values.iter().collect::<HashSet<_>>().len() != values.len() {
+            return None;
+        }
+
+        let base = *values.iter().min()?;
+        let span = values.iter().max()?.checked_sub(base)?.checked_add(1)?;
+        if span > JUMP_TABLE_MAX_SPAN || span > 2 * values.len() as i64 {
+            return None;
+        }
+        Some((base, span as usize, values))
+    }
+
+    /// Компилирует ветви match в цепочку проверок. Если ни одна ветвь не подошла,
+    /// `MatchFail` завершает выполнение ошибкой, как в интерпретаторе.
+    fn compile_match_arms(
+        &mut self,
+        subject: &Symbol,
+        arms: &[MatchArm],
+        end_jumps: &mut Vec<PatchHandle>,
+    ) -> Result<(), CompilerError> {
+        for arm in arms {
+            // Переменные паттерна видны только в гарде и теле ветви
+            self.enter_scope();
+            let mut next_arm_jumps = Vec::new();
+            self.compile_pattern_test(subject, &arm.pattern, &mut next_arm_jumps)?;
+            if let Some(guard) = &arm.guard {
+                self.compile_expression(guard)?;
+                next_arm_jumps.push(
+                    self.instructions
+                        .emit_jump_placeholder(Opcode::JumpIfFalse)?,
+                );
+            }
+
+            self.compile_block_value(&arm.consequence)?;
+            end_jumps.push(self.instructions.emit_jump_placeholder(Opcode::Jump)?);
+            self.leave_scope();
+
+            let next_arm = self.instructions.bytes.len();
+            for pos in next_arm_jumps {
+                self.instructions.patch_jump(pos, next_arm)?;
+            }
+        }
+
+        // Ни одна ветвь не подошла
+        self.emit_load_symbol(subject)?;
+        self.instructions.emit(Opcode::MatchFail, &[])?;
+        Ok(())
+    }
+
+    /// Компилирует проверку паттерна. Каждая неудачная проверка добавляет
+    /// `JumpIfFalse` в `fail_jumps`; переменные паттерна связываются при успехе.
+    fn compile_pattern_test(
+        &mut self,
+        subject: &Symbol,
+        pattern: &Pattern,
+        fail_jumps: &mut Vec<PatchHandle>,
+    ) -> Result<(), CompilerError> {
+        match pattern {
+            Pattern::Wildcard => {}
+            Pattern::Identifier(ident) => {
+                self.emit_load_symbol(subject)?;
+                let symbol = self.add_local(ident.value.clone());
+                self.emit_store_symbol(&symbol)?;
+            }
+            Pattern::Literal(expr) => {
+                self.emit_load_symbol(subject)?;
+                self.compile_expression(expr)?;
+                self.instructions.emit(Opcode::MatchLiteral, &[])?;
+                fail_jumps.push(
+                    self.instructions
+                        .emit_jump_placeholder(Opcode::JumpIfFalse)?,
+                );
+            }
+            Pattern::Range(range) => {
+                // Обе границы вычисляются ровно один раз, слева направо;
+                // проверки границ и попадания — общее правило с интерпретатором
+                self.emit_load_symbol(subject)?;
+                self.compile_expression(&range.start)?;
+                self.compile_expression(&range.end)?;
+                self.instructions
+                    .emit(Opcode::MatchRange, &[usize::from(range.inclusive)])?;
+                fail_jumps.push(
+                    self.instructions
+                        .emit_jump_placeholder(Opcode::JumpIfFalse)?,
+                );
+            }
+            Pattern::Tuple(_) => {
+                return Err(CompilerError::NotYetImplemented {
+                    construct: "кортежные паттерны",
+                })
+            }
+            Pattern::Struct(_) => {
+                return Err(CompilerError::NotYetImplemented {
+                    construct: "паттерны структур и классов",
+                })
+            }
+            Pattern::Rest(_) => {
+                return Err(CompilerError::NotYetImplemented {
+                    construct: "кортежные паттерны",
+                })
+            }
+        }
+        Ok(())
     }
 
     /// Компилирует заданную программу (AST) в последовательность инструкций байткода.
+    ///
+    /// `return` верхнего уровня завершает программу, поэтому операторы после
+    /// него недостижимы и не компилируются.
+    ///
+    /// Компилятор можно переиспользовать: следующий вызов дописывает код к уже
+    /// скомпилированному и видит объявленные ранее глобальные переменные
+    /// (`let x = 1;`, затем `x + 1;`). Возвращаются все инструкции, включая
+    /// прежние — скомпилированные функции ссылаются на смещения в них. При
+    /// ошибке код, константы, позиции и символы неудачной программы
+    /// отбрасываются.
     pub fn compile(&mut self, program: &Program) -> Result<Instructions, CompilerError> {
-        for statement in &program.statements {
-            self.compile_statement(statement)?;
+        let bytes_len = self.instructions.bytes.len();
+        let constants_len = self.instructions.constants.len();
+        let symbol_table = self.symbol_table.clone();
+        let positions = self.instructions.positions.clone();
+
+        let result = self.compile_statements(&program.statements);
+        if let Err(error) = result {
+            self.instructions.bytes.truncate(bytes_len);
+            self.instructions.constants.truncate(constants_len);
+            self.instructions.positions = positions;
+            self.symbol_table = symbol_table;
+            self.scopes = vec![Scope::default()];
+            self.scope_index = 0;
+            self.loops.clear();
+            self.constant_indices
+                .retain(|_, index| *index < constants_len);
+            return Err(error);
+        }
+        if self.peephole {
+            return Ok(peephole::optimize(&self.instructions));
         }
         Ok(self.instructions.clone())
     }
 
+    fn compile_statements(&mut self, statements: &[Statement]) -> Result<(), CompilerError> {
+        for statement in statements {
+            self.compile_statement(statement)?;
+            if let Statement::Return(_) = statement {
+                break;
+            }
+        }
+        Ok(())
+    }
+
     /// Компилировать один оператор.
+    ///
+    /// Перед кодом оператора эмитируется `MapToAst` с его идентификатором,
+    /// а строка и столбец оператора записываются в `Instructions::positions`,
+    /// чтобы VM могла сообщить, где произошла ошибка. Идентификаторы больше
+    /// `u16::MAX` в операнд не помещаются и не отображаются.
     fn compile_statement(&mut self, statement: &Statement) -> Result<(), CompilerError> {
+        let id = statement.id();
+        if id.is_assigned() && id.0 <= u16::MAX as u32 {
+            self.instructions.emit(Opcode::MapToAst, &[id.0 as usize])?;
+            let span = statement.token().span;
+            if span.line > 0 {
+                self.instructions
+                    .positions
+                    .insert(id, (span.line, span.column));
+            }
+        }
         match statement {
             Statement::Expression(expr_stmt) => {
                 self.compile_expression(&expr_stmt.expression)?;
-                self.instructions.emit(Opcode::Pop, &[]);
+                self.instructions.emit(Opcode::Pop, &[])?;
                 Ok(())
             }
             Statement::Let(let_stmt) => {
                 self.compile_expression(&let_stmt.value)?;
                 let var_name = let_stmt.name.value.clone();
 
-                // Определяем переменную в таблице символов
-                let symbol = self.symbol_table.define(var_name.clone());
-
-                if symbol.scope == SymbolScope::Local {
-                    // Локальная переменная (внутри функции)
-                    self.instructions.emit(Opcode::SetLocal, &[symbol.index as u16]);
-                } else {
-                    // Глобальная переменная
-                    let name_idx = self
-                        .instructions
-                        .add_constant(Object::String(var_name));
-                    self.instructions
-                        .emit(Opcode::SetGlobal, &[name_idx as u16]);
+                // Определяем переменную в текущем слое видимости
+                let mut symbol = self.declare(var_name, None)?;
+                if let_stmt.is_constant() {
+                    symbol.constant = true;
+                    self.symbol_table
+                        .store
+                        .insert(symbol.name.clone(), symbol.clone());
                 }
+                self.emit_store_symbol(&symbol)?;
                 Ok(())
             }
+            Statement::LetDestructure(_) => Err(CompilerError::NotYetImplemented {
+                construct: "деструктурирующие let",
+            }),
             Statement::Return(ret_stmt) => {
                 self.compile_expression(&ret_stmt.return_value)?;
-                self.instructions.emit(Opcode::Return, &[]);
+                self.instructions.emit(Opcode::ReturnValue, &[])?;
                 Ok(())
             }
-            Statement::Block(block_stmt) => {
-                for stmt in &block_stmt.statements {
-                    self.compile_statement(stmt)?;
-                }
+            Statement::Break(break_stmt) => {
+                self.emit_unwind(&break_stmt.token)?;
+                let jump = self.instructions.emit_jump_placeholder(Opcode::Jump)?;
+                self.current_loop(&break_stmt.token)?.break_jumps.push(jump);
                 Ok(())
             }
-            _ => Err(CompilerError::Unsupported(format!(
-                "Неподдерживаемый тип оператора: {:?}",
-                statement
-            ))),
+            Statement::Continue(continue_stmt) => {
+                self.emit_unwind(&continue_stmt.token)?;
+                let jump = self.instructions.emit_jump_placeholder(Opcode::Jump)?;
+                self.current_loop(&continue_stmt.token)?
+                    .continue_jumps
+                    .push(jump);
+                Ok(())
+            }
+            Statement::Block(block_stmt) => self.compile_scoped_block(block_stmt),
+            Statement::ClassDeclaration(_) => Err(CompilerError::NotYetImplemented {
+                construct: "классы",
+            }),
+            Statement::InterfaceDeclaration(_) => Err(CompilerError::NotYetImplemented {
+                construct: "интерфейсы",
+            }),
+            Statement::StructDeclaration(struct_decl) => {
+                self.compile_struct_declaration(struct_decl)
+            }
         }
     }
 
@@ -272,122 +1104,124 @@ impl Compiler {
     fn compile_expression(&mut self, expression: &Expression) -> Result<(), CompilerError> {
         match expression {
             Expression::IntegerLiteral(il) => {
-                let const_idx = self.instructions.add_constant(Object::Integer(il.value));
-                self.instructions
-                    .emit(Opcode::Constant, &[const_idx as u16]);
+                let const_idx = self.add_constant(Constant::Integer(il.value));
+                self.instructions.emit_constant(const_idx)?;
                 Ok(())
             }
             Expression::Boolean(bl) => {
                 if bl.value {
-                    self.instructions.emit(Opcode::True, &[]);
+                    self.instructions.emit(Opcode::True, &[])?;
                 } else {
-                    self.instructions.emit(Opcode::False, &[]);
+                    self.instructions.emit(Opcode::False, &[])?;
                 }
                 Ok(())
             }
+            Expression::Null(_) => {
+                self.instructions.emit(Opcode::Null, &[])?;
+                Ok(())
+            }
             Expression::StringLiteral(sl) => {
-                let const_idx = self
-                    .instructions
-                    .add_constant(Object::String(sl.value.clone()));
-                self.instructions
-                    .emit(Opcode::Constant, &[const_idx as u16]);
+                let const_idx = self.add_constant(Constant::String(sl.value.clone()));
+                self.instructions.emit_constant(const_idx)?;
                 Ok(())
             }
             Expression::Identifier(ident) => {
                 if let Some(symbol) = self.symbol_table.resolve(&ident.value) {
-                    match symbol.scope {
-                        SymbolScope::Global => {
-                            let const_idx = self
-                                .instructions
-                                .add_constant(Object::String(ident.value.clone()));
-                            self.instructions
-                                .emit(Opcode::GetGlobal, &[const_idx as u16]);
-                        }
-                        SymbolScope::Local => {
-                            self.instructions
-                                .emit(Opcode::GetLocal, &[symbol.index as u16]);
-                        }
-                        SymbolScope::Free => {
-                            self.instructions
-                                .emit(Opcode::GetFree, &[symbol.index as u16]);
-                        }
-                        SymbolScope::Builtin => {
-                            self.instructions
-                                .emit(Opcode::GetBuiltin, &[symbol.index as u16]);
-                        }
-                    }
+                    self.emit_load_symbol(&symbol)?;
                 } else {
                     // Это может быть ошибка, но давайте пока загружать null
-                    self.instructions.emit(Opcode::Null, &[]);
+                    self.instructions.emit(Opcode::Null, &[])?;
                 }
                 Ok(())
             }
+            Expression::Typeof(te) => {
+                self.compile_expression(&te.operand)?;
+                self.instructions.emit(Opcode::Typeof, &[])?;
+                Ok(())
+            }
             Expression::Prefix(pe) => {
                 self.compile_expression(&pe.right)?;
                 match pe.operator.as_str() {
-                    "!" => self.instructions.emit(Opcode::Not, &[]),
-                    "-" => self.instructions.emit(Opcode::Neg, &[]),
+                    "!" => self.instructions.emit(Opcode::Not, &[])?,
+                    "-" => self.instructions.emit(Opcode::Neg, &[])?,
                     _ => return Err(CompilerError::UnknownOperator(pe.operator.clone())),
                 };
                 Ok(())
             }
+            Expression::Infix(ie) if ie.operator == "is" => {
+                self.compile_expression(&ie.left)?;
+                // Имена встроенных типов не связаны, но пользовательская
+                // переменная с тем же именем их перекрывает
+                if let Expression::Identifier(ident) = ie.right.as_ref() {
+                    if PRIMITIVE_TYPE_NAMES.contains(&ident.value.as_str())
+                        && self.symbol_table.resolve(&ident.value).is_none()
+                    {
+                        let name_idx = self.add_constant(Constant::String(ident.value.clone()));
+                        self.instructions.emit(Opcode::IsType, &[name_idx])?;
+                        return Ok(());
+                    }
+                }
+                self.compile_expression(&ie.right)?;
+                self.instructions.emit(Opcode::Is, &[])?;
+                Ok(())
+            }
             Expression::Infix(ie) => {
                 self.compile_expression(&ie.left)?;
                 self.compile_expression(&ie.right)?;
-                match ie.operator.as_str() {
-                    "+" => self.instructions.emit(Opcode::Add, &[]),
-                    "-" => self.instructions.emit(Opcode::Sub, &[]),
-                    "*" => self.instructions.emit(Opcode::Mul, &[]),
-                    "/" => self.instructions.emit(Opcode::Div, &[]),
-                    "%" => self.instructions.emit(Opcode::Mod, &[]),
-                    "**" => self.instructions.emit(Opcode::Pow, &[]),
-                    "==" => self.instructions.emit(Opcode::Equal, &[]),
-                    "!=" => self.instructions.emit(Opcode::NotEqual, &[]),
-                    ">" => self.instructions.emit(Opcode::GreaterThan, &[]),
-                    "<" => self.instructions.emit(Opcode::LessThan, &[]),
-                    ">=" => self.instructions.emit(Opcode::GreaterThanOrEqual, &[]),
-                    "<=" => self.instructions.emit(Opcode::LessThanOrEqual, &[]),
-                    "&&" => self.instructions.emit(Opcode::And, &[]),
-                    "||" => self.instructions.emit(Opcode::Or, &[]),
+                let opcode = match ie.operator.as_str() {
+                    "+" => Opcode::Add,
+                    "-" => Opcode::Sub,
+                    "*" => Opcode::Mul,
+                    "/" => Opcode::Div,
+                    "%" => Opcode::Mod,
+                    "**" => Opcode::Pow,
+                    "==" => Opcode::Equal,
+                    "!=" => Opcode::NotEqual,
+                    ">" => Opcode::GreaterThan,
+                    "<" => Opcode::LessThan,
+                    ">=" => Opcode::GreaterThanOrEqual,
+                    "<=" => Opcode::LessThanOrEqual,
+                    "&&" => Opcode::And,
+                    "||" => Opcode::Or,
                     _ => return Err(CompilerError::UnknownOperator(ie.operator.clone())),
                 };
+                match opcode.register_variant() {
+                    Some(register_opcode) if self.register_arithmetic => {
+                        // Регистры заняты только внутри этой последовательности,
+                        // поэтому вложенным выражениям хватает тех же двух
+                        self.instructions.emit(Opcode::LoadReg, &[1])?;
+                        self.instructions.emit(Opcode::LoadReg, &[0])?;
+                        self.instructions.emit(register_opcode, &[0, 0, 1])?;
+                        self.instructions.emit(Opcode::PushReg, &[0])?;
+                    }
+                    _ => {
+                        self.instructions.emit(opcode, &[])?;
+                    }
+                }
                 Ok(())
             }
             Expression::If(if_expr) => {
                 self.compile_expression(&if_expr.condition)?;
-                let jump_if_false_pos = self.instructions.bytes.len();
-                self.instructions.emit(Opcode::JumpIfFalse, &[0]); // Placeholder
+                let jump_if_false = self
+                    .instructions
+                    .emit_jump_placeholder(Opcode::JumpIfFalse)?;
 
-                // Компилируем тело if
-                for stmt in &if_expr.consequence.statements {
-                    self.compile_statement(stmt)?;
-                }
+                // if — выражение: каждая ветвь оставляет на стеке ровно
+                // одно значение, без else ложное условие даёт Null
+                self.compile_scoped_block_value(&if_expr.consequence)?;
+                let jump_over_else = self.instructions.emit_jump_placeholder(Opcode::Jump)?;
 
-                // Обновляем адрес прыжка
                 let target = self.instructions.bytes.len();
-                let high = ((target >> 8) & 0xFF) as u8;
-                let low = (target & 0xFF) as u8;
-                self.instructions.bytes[jump_if_false_pos + 1] = high;
-                self.instructions.bytes[jump_if_false_pos + 2] = low;
-
-                // Если есть else, компилируем его
-                if let Some(alt) = &if_expr.alternative {
-                    let jump_pos = self.instructions.bytes.len();
-                    self.instructions.emit(Opcode::Jump, &[0]); // Placeholder для прыжка за else
-
-                    // Компилируем else
-                    for stmt in &alt.statements {
-                        self.compile_statement(stmt)?;
+                self.instructions.patch_jump(jump_if_false, target)?;
+                match &if_expr.alternative {
+                    Some(alt) => self.compile_scoped_block_value(alt)?,
+                    None => {
+                        self.instructions.emit(Opcode::Null, &[])?;
                     }
-
-                    // Обновляем адрес прыжка за else
-                    let target = self.instructions.bytes.len();
-                    let high = ((target >> 8) & 0xFF) as u8;
-                    let low = (target & 0xFF) as u8;
-                    self.instructions.bytes[jump_pos + 1] = high;
-                    self.instructions.bytes[jump_pos + 2] = low;
                 }
 
+                let target = self.instructions.bytes.len();
+                self.instructions.patch_jump(jump_over_else, target)?;
                 Ok(())
             }
             Expression::ArrayLiteral(arr_expr) => {
@@ -395,13 +1229,73 @@ impl Compiler {
                     self.compile_expression(element)?;
                 }
                 self.instructions
-                    .emit(Opcode::Array, &[arr_expr.elements.len() as u16]);
+                    .emit(Opcode::Array, &[arr_expr.elements.len()])?;
+                Ok(())
+            }
+            // Ключ и значение каждой пары в порядке записи, затем Hash
+            Expression::HashLiteral(hash) => {
+                for (key, value) in &hash.pairs {
+                    self.compile_expression(key)?;
+                    self.compile_expression(value)?;
+                }
+                self.instructions.emit(Opcode::Hash, &[hash.pairs.len()])?;
+                Ok(())
+            }
+            Expression::Match(match_expr) => {
+                self.warnings.extend(checker::check_match_arms(
+                    &match_expr.value,
+                    &match_expr.arms,
+                ));
+                self.compile_match(match_expr)
+            }
+            // `if let` — match с одной ветвью и веткой `_` для else
+            Expression::IfLet(if_let) => {
+                self.warnings.extend(checker::check_if_let_pattern(
+                    &if_let.value,
+                    &if_let.pattern,
+                ));
+                let mut arms = vec![MatchArm {
+                    pattern: (*if_let.pattern).clone(),
+                    guard: None,
+                    consequence: if_let.consequence.clone(),
+                }];
+                // Без else несовпадение даёт null, а не ошибку match
+                let alternative = if_let
+                    .alternative
+                    .clone()
+                    .unwrap_or_else(|| BlockStatement {
+                        token: if_let.token.clone(),
+                        statements: Vec::new(),
+                    });
+                arms.push(MatchArm {
+                    pattern: Pattern::Wildcard,
+                    guard: None,
+                    consequence: alternative,
+                });
+                self.compile_match(&MatchExpression {
+                    token: if_let.token.clone(),
+                    value: if_let.value.clone(),
+                    arms,
+                })
+            }
+            Expression::Index(index_expr) => {
+                self.compile_expression(&index_expr.left)?;
+                self.compile_expression(&index_expr.index)?;
+                self.instructions.emit(Opcode::Index, &[])?;
                 Ok(())
             }
             Expression::FunctionLiteral(func) => {
-                // Входим в новый scope
+                if func.rest.is_some() {
+                    return Err(CompilerError::NotYetImplemented {
+                        construct: "rest-параметры",
+                    });
+                }
+                // Входим в новый scope; блоки внешней функции откладываем до выхода
                 self.symbol_table =
                     SymbolTable::new_enclosed(Box::new(self.symbol_table.clone()));
+                let outer_scopes = std::mem::replace(&mut self.scopes, vec![Scope::default()]);
+                let outer_scope_index = std::mem::replace(&mut self.scope_index, 0);
+                let outer_loops = std::mem::take(&mut self.loops);
 
                 // Определяем параметры как локальные переменные
                 for param in &func.parameters {
@@ -409,8 +1303,7 @@ impl Compiler {
                 }
 
                 // Эмитируем Jump placeholder для пропуска тела функции в основном коде
-                let jump_placeholder = self.instructions.bytes.len();
-                self.instructions.emit(Opcode::Jump, &[0]);
+                let jump_placeholder = self.instructions.emit_jump_placeholder(Opcode::Jump)?;
 
                 // Запоминаем начало тела функции
                 let func_offset = self.instructions.bytes.len();
@@ -420,50 +1313,54 @@ impl Compiler {
                     self.compile_statement(stmt)?;
                 }
 
+                // Последнее выражение тела — неявный результат функции:
+                // заменяем его Pop на ReturnValue
+                if let Some(Statement::Expression(_)) = func.body.statements.last() {
+                    let last = self.instructions.bytes.len() - 1;
+                    self.instructions.bytes[last] = Opcode::ReturnValue as u8;
+                }
+
                 // Если в конце тела нет ReturnValue, добавляем Return (возврат Null)
                 let last_byte = self.instructions.bytes.last().copied();
                 if last_byte != Some(Opcode::ReturnValue as u8)
                     && last_byte != Some(Opcode::Return as u8)
                 {
-                    self.instructions.emit(Opcode::Return, &[]);
+                    self.instructions.emit(Opcode::Return, &[])?;
                 }
 
                 // Патчим Jump placeholder на конец тела функции
                 let end_offset = self.instructions.bytes.len();
-                let high = ((end_offset >> 8) & 0xFF) as u8;
-                let low = (end_offset & 0xFF) as u8;
-                self.instructions.bytes[jump_placeholder + 1] = high;
-                self.instructions.bytes[jump_placeholder + 2] = low;
+                self.instructions.patch_jump(jump_placeholder, end_offset)?;
 
                 // Собираем данные о функции
-                let num_locals = self.symbol_table.num_definitions;
+                let num_locals = self.symbol_table.max_definitions;
                 let free_symbols = self.symbol_table.free_symbols.clone();
 
                 // Выходим из scope
                 if let Some(outer) = self.symbol_table.outer.take() {
                     self.symbol_table = *outer;
                 }
+                self.scopes = outer_scopes;
+                self.scope_index = outer_scope_index;
+                self.loops = outer_loops;
 
                 // Создаём CompiledFunction и добавляем в пул констант
-                let compiled_fn = Object::CompiledFunction(
-                    crate::object::CompiledFunction {
-                        instructions_offset: func_offset,
-                        num_locals,
-                        num_params: func.parameters.len(),
-                    },
-                );
-                let const_idx = self.instructions.add_constant(compiled_fn);
+                let compiled_fn = Constant::CompiledFunction(crate::object::CompiledFunction {
+                    instructions_offset: func_offset,
+                    num_locals,
+                    num_params: func.parameters.len(),
+                });
+                let const_idx = self.add_constant(compiled_fn);
 
                 // Загружаем free-переменные на стек
                 for free_sym in &free_symbols {
                     match free_sym.scope {
                         SymbolScope::Local => {
                             self.instructions
-                                .emit(Opcode::GetLocal, &[free_sym.index as u16]);
+                                .emit(Opcode::GetLocal, &[free_sym.index])?;
                         }
                         SymbolScope::Free => {
-                            self.instructions
-                                .emit(Opcode::GetFree, &[free_sym.index as u16]);
+                            self.instructions.emit(Opcode::GetFree, &[free_sym.index])?;
                         }
                         _ => {
                             return Err(CompilerError::Unsupported(
@@ -473,12 +1370,10 @@ impl Compiler {
                     }
                 }
 
-                // Эмитируем опкод функции
-                if free_symbols.is_empty() {
-                    self.instructions.emit(Opcode::Constant, &[const_idx as u16]);
-                } else {
-                    self.instructions.emit(Opcode::Closure, &[const_idx as u16, free_symbols.len() as u16]);
-                }
+                // Замыкание создаётся и без свободных переменных: каждое
+                // вычисление литерала даёт новую функцию, как в интерпретаторе
+                self.instructions
+                    .emit(Opcode::Closure, &[const_idx, free_symbols.len()])?;
 
                 Ok(())
             }
@@ -493,23 +1388,106 @@ impl Compiler {
 
                 // Эмитируем Call опкод с количеством аргументов
                 self.instructions
-                    .emit(Opcode::Call, &[call.arguments.len() as u16]);
+                    .emit(Opcode::Call, &[call.arguments.len()])?;
 
                 Ok(())
             }
-            _ => Err(CompilerError::Unsupported(format!(
-                "Неподдерживаемое выражение: {:?}",
-                expression
-            ))),
+            Expression::Range(_) => Err(CompilerError::NotYetImplemented {
+                construct: "диапазоны вне паттернов match",
+            }),
+            Expression::New(new_expr) => {
+                let symbol = self
+                    .symbol_table
+                    .resolve(&new_expr.class_name.value)
+                    .ok_or_else(|| {
+                        CompilerError::ExpressionError(format!(
+                            "Тип {} не объявлен",
+                            new_expr.class_name.value
+                        ))
+                    })?;
+                self.emit_load_symbol(&symbol)?;
+                for arg in &new_expr.arguments {
+                    self.compile_expression(arg)?;
+                }
+                self.instructions
+                    .emit(Opcode::New, &[new_expr.arguments.len()])?;
+                Ok(())
+            }
+            Expression::This(_) => Err(CompilerError::NotYetImplemented {
+                construct: "выражения this",
+            }),
+            Expression::Super(_) => Err(CompilerError::NotYetImplemented {
+                construct: "выражения super",
+            }),
+            Expression::PropertyAccess(pae) => {
+                self.compile_expression(&pae.left)?;
+                let name_idx = self.add_constant(Constant::String(pae.property.value.clone()));
+                self.instructions.emit(Opcode::GetProperty, &[name_idx])?;
+                Ok(())
+            }
+            Expression::MethodCall(_) => Err(CompilerError::NotYetImplemented {
+                construct: "вызовы методов",
+            }),
+            Expression::For(for_expr) => match for_expr.iterable.as_ref() {
+                Expression::Range(range) => self.compile_range_loop(for_expr, range),
+                _ => Err(CompilerError::NotYetImplemented {
+                    construct: "циклы for не по диапазону",
+                }),
+            },
+            Expression::Assign(assign) => self.compile_assign(assign),
         }
     }
 }
 
+impl Default for Compiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::ast::{BooleanLiteral, ExpressionStatement, Identifier, IntegerLiteral};
+    use crate::ast::{BooleanLiteral, ExpressionStatement, Identifier, IntegerLiteral, NodeId};
+    use crate::lexer::Lexer;
+    use crate::object::{Closure, CompiledFunction, Object};
+    use crate::parser::Parser;
     use crate::token::{Token, TokenType};
+    use std::rc::Rc;
+
+    fn compile_source(input: &str) -> Instructions {
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        Compiler::new().compile(&program).unwrap()
+    }
+
+    /// Опкоды GetLocal/SetLocal с их слотами в порядке эмиссии.
+    fn local_ops(instructions: &Instructions) -> Vec<(Opcode, u16)> {
+        let bytes = &instructions.bytes;
+        let mut ops = Vec::new();
+        let mut i = 0;
+        while i < bytes.len() {
+            let op = Instructions::read_opcode(bytes, i).unwrap();
+            let (operands, read) = Instructions::read_operands(op, bytes, i + 1);
+            if matches!(op, Opcode::GetLocal | Opcode::SetLocal) {
+                ops.push((op, operands[0]));
+            }
+            i += 1 + read;
+        }
+        ops
+    }
+
+    fn first_compiled_function(instructions: &Instructions) -> CompiledFunction {
+        instructions
+            .constants
+            .iter()
+            .find_map(|c| match c {
+                Constant::CompiledFunction(cf) => Some(cf.clone()),
+                _ => None,
+            })
+            .expect("no compiled function in constants")
+    }
 
     fn make_token() -> Token {
         Token::new(TokenType::Int, "".to_string())
@@ -533,6 +1511,7 @@ mod tests {
         })
     }
 
+    #[allow(dead_code)]
     fn make_identifier(value: String) -> Expression {
         Expression::Identifier(Identifier {
             token: make_token(),
@@ -544,6 +1523,7 @@ mod tests {
     fn test_compiler_integer_literal() {
         let mut compiler = Compiler::new();
         let program = make_program(vec![Statement::Expression(ExpressionStatement {
+            id: NodeId::default(),
             token: make_token(),
             expression: make_int_literal(42),
         })]);
@@ -556,7 +1536,7 @@ mod tests {
         assert_eq!(instructions.bytes[0], Opcode::Constant as u8);
         assert_eq!(instructions.bytes[3], Opcode::Pop as u8);
         assert_eq!(instructions.constants.len(), 1);
-        assert_eq!(instructions.constants[0], Object::Integer(42));
+        assert_eq!(instructions.constants[0], Constant::Integer(42));
     }
 
     #[test]
@@ -564,6 +1544,7 @@ mod tests {
         let mut compiler = Compiler::new();
 
         let program_true = make_program(vec![Statement::Expression(ExpressionStatement {
+            id: NodeId::default(),
             token: make_token(),
             expression: make_bool_literal(true),
         })]);
@@ -578,6 +1559,7 @@ mod tests {
         // Тест для false
         let mut compiler2 = Compiler::new();
         let program_false = make_program(vec![Statement::Expression(ExpressionStatement {
+            id: NodeId::default(),
             token: make_token(),
             expression: make_bool_literal(false),
         })]);
@@ -596,6 +1578,7 @@ mod tests {
 
         // Компилируем: -42
         let program = make_program(vec![Statement::Expression(ExpressionStatement {
+            id: NodeId::default(),
             token: make_token(),
             expression: Expression::Prefix(crate::ast::PrefixExpression {
                 token: make_token(),
@@ -619,6 +1602,7 @@ mod tests {
 
         // Компилируем: 10 + 20
         let program = make_program(vec![Statement::Expression(ExpressionStatement {
+            id: NodeId::default(),
             token: make_token(),
             expression: Expression::Infix(crate::ast::InfixExpression {
                 token: make_token(),
@@ -638,8 +1622,8 @@ mod tests {
         // Добавляем 3 (opcode) + 3 (operand) = 6 байт для второй константы
         assert_eq!(instructions.bytes[6], Opcode::Add as u8);
         assert_eq!(instructions.constants.len(), 2);
-        assert_eq!(instructions.constants[0], Object::Integer(10));
-        assert_eq!(instructions.constants[1], Object::Integer(20));
+        assert_eq!(instructions.constants[0], Constant::Integer(10));
+        assert_eq!(instructions.constants[1], Constant::Integer(20));
     }
 
     #[test]
@@ -647,6 +1631,7 @@ mod tests {
         let mut compiler = Compiler::new();
 
         let program = make_program(vec![Statement::Expression(ExpressionStatement {
+            id: NodeId::default(),
             token: make_token(),
             expression: Expression::Infix(crate::ast::InfixExpression {
                 token: make_token(),
@@ -672,14 +1657,17 @@ mod tests {
 
         let program = make_program(vec![
             Statement::Expression(ExpressionStatement {
+                id: NodeId::default(),
                 token: make_token(),
                 expression: make_int_literal(1),
             }),
             Statement::Expression(ExpressionStatement {
+                id: NodeId::default(),
                 token: make_token(),
                 expression: make_int_literal(2),
             }),
             Statement::Expression(ExpressionStatement {
+                id: NodeId::default(),
                 token: make_token(),
                 expression: make_int_literal(3),
             }),
@@ -691,8 +1679,453 @@ mod tests {
         let instructions = result.unwrap();
         // Ожидаем 3 константы + инструкции для каждой
         assert_eq!(instructions.constants.len(), 3);
-        assert_eq!(instructions.constants[0], Object::Integer(1));
-        assert_eq!(instructions.constants[1], Object::Integer(2));
-        assert_eq!(instructions.constants[2], Object::Integer(3));
+        assert_eq!(instructions.constants[0], Constant::Integer(1));
+        assert_eq!(instructions.constants[1], Constant::Integer(2));
+        assert_eq!(instructions.constants[2], Constant::Integer(3));
+    }
+
+    #[test]
+    fn test_compiler_skips_statements_after_top_level_return() {
+        let with_dead_code = compile_source("return 1; 2; let x = 3;");
+        let expected = compile_source("return 1;");
+        assert_eq!(with_dead_code.bytes, expected.bytes);
+        assert_eq!(with_dead_code.constants, vec![Constant::Integer(1)]);
+
+        // `return` внутри if не обрывает компиляцию остальной программы
+        let with_if = compile_source("if (true) { return 1; } 2;");
+        assert!(with_if.constants.contains(&Constant::Integer(2)));
+    }
+
+    #[test]
+    fn test_compiler_is_operator() {
+        let is_ops = |input: &str| {
+            let instructions = compile_source(input);
+            let bytes = &instructions.bytes;
+            let mut ops = Vec::new();
+            let mut i = 0;
+            while i < bytes.len() {
+                let op = Instructions::read_opcode(bytes, i).unwrap();
+                let (operands, read) = Instructions::read_operands(op, bytes, i + 1);
+                match op {
+                    Opcode::IsType => ops.push(format!(
+                        "IS_TYPE {}",
+                        instructions.constants[operands[0] as usize]
+                    )),
+                    Opcode::Is => ops.push("IS".to_string()),
+                    _ => {}
+                }
+                i += 1 + read;
+            }
+            ops
+        };
+
+        // Встроенный тип проверяется по имени из пула констант
+        assert_eq!(is_ops("1 is Integer;"), vec!["IS_TYPE Integer"]);
+        // Пользовательская переменная перекрывает имя встроенного типа
+        assert_eq!(is_ops("let Integer = 5; 1 is Integer;"), vec!["IS"]);
+        assert_eq!(is_ops("struct P {} 1 is P;"), vec!["IS"]);
+    }
+
+    #[test]
+    fn test_compiler_ast_only_constructs() {
+        let tests = vec![
+            (
+                "class Point {}",
+                "классы пока не поддерживаются в режиме VM — используйте --ast",
+            ),
+            (
+                "match [1, 2] { [a, b] => a, _ => 0 }",
+                "кортежные паттерны пока не поддерживаются в режиме VM — используйте --ast",
+            ),
+            (
+                "this;",
+                "выражения this пока не поддерживаются в режиме VM — используйте --ast",
+            ),
+            (
+                "for (x in [1]) { x }",
+                "циклы for не по диапазону пока не поддерживаются в режиме VM — используйте --ast",
+            ),
+            (
+                "let [a, b] = [1, 2];",
+                "деструктурирующие let пока не поддерживаются в режиме VM — используйте --ast",
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let program = Parser::new(Lexer::new(input.to_string()))
+                .parse_program()
+                .unwrap();
+            let message = String::from(Compiler::new().compile(&program).unwrap_err());
+            assert_eq!(message, expected, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_compiler_errors_do_not_leak_ast() {
+        let inputs = [
+            "class A {}",
+            "interface I { fn f(); }",
+            "this;",
+            "super.f();",
+            "1..2;",
+            "match [1] { [a] => a, _ => 0 }",
+            "for (x in [1]) { x }",
+        ];
+
+        for input in inputs {
+            let program = Parser::new(Lexer::new(input.to_string()))
+                .parse_program()
+                .unwrap();
+            let message = String::from(Compiler::new().compile(&program).unwrap_err());
+            assert!(!message.contains("Token {"), "{}: {}", input, message);
+            assert!(message.contains("--ast"), "{}: {}", input, message);
+        }
+    }
+
+    #[test]
+    fn test_compiler_jump_out_of_range() {
+        let mut compiler = Compiler::new();
+
+        // Тело if длиннее i16::MAX байт: каждое выражение даёт Constant + Pop (4 байта)
+        let statements = (0..9000)
+            .map(|i| {
+                Statement::Expression(ExpressionStatement {
+                    id: NodeId::default(),
+                    token: make_token(),
+                    expression: make_int_literal(i),
+                })
+            })
+            .collect();
+        let program = make_program(vec![Statement::Expression(ExpressionStatement {
+            id: NodeId::default(),
+            token: make_token(),
+            expression: Expression::If(crate::ast::IfExpression {
+                token: make_token(),
+                condition: Box::new(make_bool_literal(true)),
+                consequence: crate::ast::BlockStatement {
+                    token: make_token(),
+                    statements,
+                },
+                alternative: None,
+            }),
+        })]);
+
+        let result = compiler.compile(&program);
+        assert!(matches!(
+            result,
+            Err(CompilerError::Bytecode(
+                BytecodeError::JumpOffsetOverflow { .. }
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_compiler_operand_out_of_range() {
+        let compile =
+            |statements: Vec<Statement>| Compiler::new().compile(&make_program(statements));
+        let expression = |expression| {
+            Statement::Expression(ExpressionStatement {
+                id: NodeId::default(),
+                token: make_token(),
+                expression,
+            })
+        };
+
+        // Индекс константы за u16::MAX — ошибка, а не усечённый операнд
+        let constants = (0..=u16::MAX as i64 + 1)
+            .map(|i| expression(make_int_literal(i)))
+            .collect();
+        assert_eq!(
+            compile(constants),
+            Err(CompilerError::Bytecode(BytecodeError::OperandOutOfRange {
+                opcode: Opcode::Constant,
+                operand: u16::MAX as usize + 1,
+                width: 2,
+            }))
+        );
+
+        // То же для числа элементов массива
+        let elements = vec![make_int_literal(0); u16::MAX as usize + 1];
+        let array = expression(Expression::ArrayLiteral(crate::ast::ArrayLiteral {
+            token: make_token(),
+            elements,
+        }));
+        assert_eq!(
+            compile(vec![array]),
+            Err(CompilerError::Bytecode(BytecodeError::OperandOutOfRange {
+                opcode: Opcode::Array,
+                operand: u16::MAX as usize + 1,
+                width: 2,
+            }))
+        );
+    }
+
+    #[test]
+    fn test_compiler_nested_block_locals() {
+        let instructions = compile_source(
+            "fn() { let a = 1; if (true) { let b = 2; if (true) { let c = a + b; c } } }",
+        );
+        assert_eq!(
+            local_ops(&instructions),
+            vec![
+                (Opcode::SetLocal, 0),
+                (Opcode::SetLocal, 1),
+                (Opcode::GetLocal, 0),
+                (Opcode::GetLocal, 1),
+                (Opcode::SetLocal, 2),
+                (Opcode::GetLocal, 2),
+            ]
+        );
+        assert_eq!(first_compiled_function(&instructions).num_locals, 3);
+    }
+
+    #[test]
+    fn test_compiled_function_display() {
+        let instructions = compile_source("fn(a, b) { let c = a + b; c }");
+        let cf = first_compiled_function(&instructions);
+        assert_eq!(
+            Object::CompiledFunction(cf.clone()).to_string(),
+            "compiled function (2 params, 3 locals)"
+        );
+        assert_eq!(
+            Object::Closure(Rc::new(Closure {
+                function: cf,
+                free: vec![Object::Integer(1)],
+            }))
+            .to_string(),
+            "closure (2 params, 3 locals, 1 free)"
+        );
+        assert_eq!(
+            Object::CompiledFunction(first_compiled_function(&compile_source("fn() { 1 }")))
+                .type_str(),
+            "COMPILED_FUNCTION"
+        );
+    }
+
+    #[test]
+    fn test_compiler_block_shadowing() {
+        let instructions = compile_source("fn() { let a = 1; if (true) { let a = 2; a }; a }");
+        assert_eq!(
+            local_ops(&instructions),
+            vec![
+                (Opcode::SetLocal, 0),
+                (Opcode::SetLocal, 1),
+                (Opcode::GetLocal, 1),
+                // После выхода из блока снова видна внешняя a
+                (Opcode::GetLocal, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compiler_sibling_blocks_reuse_slots() {
+        let instructions =
+            compile_source("fn(x) { if (x) { let a = 1; a } else { let b = 2; b }; let c = 3; c }");
+        assert_eq!(
+            local_ops(&instructions),
+            vec![
+                (Opcode::GetLocal, 0),
+                (Opcode::SetLocal, 1),
+                (Opcode::GetLocal, 1),
+                (Opcode::SetLocal, 1),
+                (Opcode::GetLocal, 1),
+                (Opcode::SetLocal, 1),
+                (Opcode::GetLocal, 1),
+            ]
+        );
+        assert_eq!(first_compiled_function(&instructions).num_locals, 2);
+    }
+
+    #[test]
+    fn test_compiler_block_local_not_visible_after_block() {
+        let instructions = compile_source("fn() { if (true) { let a = 1; }; a }");
+        // a вне блока не разрешается как локальная переменная
+        assert_eq!(local_ops(&instructions), vec![(Opcode::SetLocal, 0)]);
+    }
+
+    #[test]
+    fn test_compiler_assignment_to_local() {
+        let instructions = compile_source("fn() { let x = 0; x = x + 5; x }");
+        assert_eq!(
+            local_ops(&instructions),
+            vec![
+                (Opcode::SetLocal, 0),
+                (Opcode::GetLocal, 0),
+                (Opcode::SetLocal, 0),
+                (Opcode::GetLocal, 0),
+                (Opcode::GetLocal, 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compiler_assignment_errors() {
+        let compile = |input: &str| {
+            let program = Parser::new(Lexer::new(input.to_string()))
+                .parse_program()
+                .unwrap();
+            Compiler::new().compile(&program).map(|_| ())
+        };
+        assert_eq!(
+            compile("y = 1;"),
+            Err(CompilerError::ExpressionError(
+                "Присваивание необъявленной переменной y".to_string()
+            ))
+        );
+        assert_eq!(
+            compile("let x = 0; fn() { x = 1 };").map_err(String::from),
+            Ok(())
+        );
+        assert_eq!(
+            compile("const c = 1; c = 2;"),
+            Err(CompilerError::ExpressionError(
+                "cannot assign to constant c".to_string()
+            ))
+        );
+        assert_eq!(
+            compile("const c = 1; if (true) { let c = 2; c = 3; }; c;").map_err(String::from),
+            Ok(())
+        );
+        assert_eq!(
+            compile("fn() { let x = 0; fn() { x = 1 } };"),
+            Err(CompilerError::NotYetImplemented {
+                construct: "присваивания захваченным переменным"
+            })
+        );
+    }
+
+    #[test]
+    fn test_compiler_collects_match_warnings() {
+        let program = Parser::new(Lexer::new(
+            r#"match "x" { 1 => 1, _ => 2 }; if let 1 = 2 { 3 }"#.to_string(),
+        ))
+        .parse_program()
+        .unwrap();
+        let mut compiler = Compiler::new();
+        compiler.compile(&program).unwrap();
+        let warnings: Vec<String> = compiler.warnings().iter().map(|w| w.to_string()).collect();
+        assert_eq!(
+            warnings,
+            vec!["warning: match arm 1 `1` can never match: pattern is INTEGER, value is STRING"]
+        );
+    }
+
+    #[test]
+    fn test_compiler_maps_statements_to_ast() {
+        let instructions = compile_source("let a = 1; fn() { a }; a;");
+        let bytes = &instructions.bytes;
+        let mut ids = Vec::new();
+        let mut i = 0;
+        while i < bytes.len() {
+            let op = Instructions::read_opcode(bytes, i).unwrap();
+            let (operands, read) = Instructions::read_operands(op, bytes, i + 1);
+            if op == Opcode::MapToAst {
+                ids.push(operands[0]);
+            }
+            i += 1 + read;
+        }
+        // Тело функции (#3) компилируется внутри оператора #2
+        assert_eq!(ids, vec![1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn test_constant_deduplication() {
+        let instructions = compile_source("1; \"a\"; 1; 2; \"a\"; 1 + 2;");
+        assert_eq!(
+            instructions.constants,
+            vec![
+                Constant::Integer(1),
+                Constant::String("a".to_string()),
+                Constant::Integer(2),
+            ]
+        );
+
+        // Одинаковые функции получают отдельные слоты, а их общие литералы — нет
+        let instructions = compile_source("fn(x) { x + 1 }; fn(x) { x + 1 };");
+        let functions = instructions
+            .constants
+            .iter()
+            .filter(|c| matches!(c, Constant::CompiledFunction(_)))
+            .count();
+        assert_eq!(functions, 2);
+        assert_eq!(
+            instructions
+                .constants
+                .iter()
+                .filter(|c| **c == Constant::Integer(1))
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_global_name_constant_reused() {
+        let program = Parser::new(Lexer::new("let x = 1; x; x; x;".to_string()))
+            .parse_program()
+            .unwrap();
+        let instructions = Compiler::new().compile(&program).unwrap();
+        assert_eq!(
+            instructions.constants,
+            vec![Constant::Integer(1), Constant::String("x".to_string())]
+        );
+        assert_eq!(
+            crate::vm::VM::new(instructions).run(),
+            Ok(Object::Integer(1))
+        );
+
+        // Повторный let и присваивание используют ту же константу
+        let program = Parser::new(Lexer::new(
+            "let x = 1; let x = x + 1; x = x * 3;".to_string(),
+        ))
+        .parse_program()
+        .unwrap();
+        let instructions = Compiler::new().compile(&program).unwrap();
+        let names = instructions
+            .constants
+            .iter()
+            .filter(|c| **c == Constant::String("x".to_string()))
+            .count();
+        assert_eq!(names, 1);
+    }
+
+    #[test]
+    fn test_compiler_reuse_keeps_globals() {
+        let parse = |input: &str| {
+            Parser::new(Lexer::new(input.to_string()))
+                .parse_program()
+                .unwrap()
+        };
+        let mut compiler = Compiler::new();
+        let first = compiler.compile(&parse("let x = 1;")).unwrap();
+        let second = compiler.compile(&parse("x + 1;")).unwrap();
+
+        // Второй фрагмент дописан к первому и читает тот же глобальный `x`
+        assert_eq!(second.bytes[..first.bytes.len()], first.bytes[..]);
+        assert_eq!(
+            second.constants[..first.constants.len()],
+            first.constants[..]
+        );
+        let listing = crate::bytecode::disassembler::disassemble(&second);
+        assert!(listing.contains("GET_GLOBAL"), "{}", listing);
+        assert_eq!(crate::vm::VM::new(second).run(), Ok(Object::Integer(2)));
+
+        // Неудачная программа не оставляет ни кода, ни констант, а
+        // компилятор остаётся в глобальной области видимости
+        let before = compiler.compile(&parse("")).unwrap();
+        let failing = parse("let y = 2; fn() { if (true) { for (i in [1]) { i } } }");
+        assert!(compiler.compile(&failing).is_err());
+        assert_eq!(compiler.compile(&parse("")).unwrap(), before);
+        assert_eq!(compiler.scope_index, 0);
+        assert!(compiler.symbol_table.outer.is_none());
+        assert!(!compiler.symbol_table.store.contains_key("y"));
+    }
+
+    #[test]
+    fn test_compiler_top_level_block_stays_global() {
+        let instructions = compile_source("if (true) { let g = 1; }; g");
+        assert!(local_ops(&instructions).is_empty());
+        let listing = crate::bytecode::disassembler::disassemble(&instructions);
+        assert!(listing.contains("SET_GLOBAL"), "{}", listing);
+        assert!(listing.contains("GET_GLOBAL"), "{}", listing);
     }
 }
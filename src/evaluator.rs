@@ -1,13 +1,19 @@
 use crate::ast::{
-    BlockStatement, ClassDeclaration, Expression, Identifier, IfExpression, InterfaceDeclaration,
-    MethodCallExpression, NewExpression, Node, Program, PropertyAccessExpression, Statement,
-    StructDeclaration, ThisExpression,
+    AssignExpression, BlockStatement, ClassDeclaration, Expression, ForExpression, Identifier,
+    IfExpression, IfLetExpression, IntegerLiteral, InterfaceDeclaration, InterfaceMember,
+    MethodCallExpression, NewExpression, Node, Pattern, Program, PropertyAccessExpression,
+    RangePattern, Statement, StructDeclaration, StructPattern, ThisExpression,
 };
+use crate::builtins;
 use crate::object::{
-    Class, ClassInstance, Environment, Interface, Method, Object, Struct, StructInstance,
+    arithmetic, compare_values, in_range_pattern, index_value, instance_fields,
+    non_exhaustive_match, range_pattern_bounds, set_index, type_mismatch, unknown_operator,
+    values_equal, Class, ClassInstance, Environment, HashKey, Interface, MemberCache, Method,
+    MethodSignature, Object, OrderedMap, Struct, StructInstance,
 };
+use crate::token::{Token, TokenType};
+use std::borrow::Cow;
 use std::cell::RefCell;
-use std::collections::HashMap;
 use std::rc::Rc;
 
 pub fn eval(node: Node, env: Rc<RefCell<Environment>>) -> Object {
@@ -24,6 +30,11 @@ fn eval_program(program: Program, env: Rc<RefCell<Environment>>) -> Object {
         result = eval_statement(statement, Rc::clone(&env));
         match result {
             Object::ReturnValue(value) => return *value,
+            // Ошибка, поднявшаяся из вызова функции, уже несёт трассировку —
+            // завершаем её кадром верхнего уровня
+            Object::Error(ref error) if !error.trace.is_empty() => {
+                return add_trace_frame(result, "main")
+            }
             Object::Error(_) => return result,
             _ => {}
         }
@@ -35,20 +46,47 @@ fn eval_statement(statement: Statement, env: Rc<RefCell<Environment>>) -> Object
     match statement {
         Statement::Expression(expr_stmt) => eval_expression(expr_stmt.expression, env),
         Statement::Let(let_stmt) => {
+            let constant = let_stmt.is_constant();
             let val = eval_expression(let_stmt.value, Rc::clone(&env));
-            if let Object::Error(_) = val {
+            if is_abrupt(&val) {
                 return val;
             }
-            env.borrow_mut().set(let_stmt.name.value, val);
-            Object::Null
+            if constant {
+                declare_constant(&env, let_stmt.name.value, val)
+            } else {
+                declare(&env, let_stmt.name.value, val)
+            }
+        }
+        Statement::LetDestructure(let_stmt) => {
+            let val = eval_expression(let_stmt.value, Rc::clone(&env));
+            if is_abrupt(&val) {
+                return val;
+            }
+            match pattern_matches(&let_stmt.pattern, &val, Rc::clone(&env)) {
+                Some(bindings) => {
+                    for (name, obj) in bindings {
+                        if let error @ Object::Error(_) = declare(&env, name, obj) {
+                            return error;
+                        }
+                    }
+                    Object::Null
+                }
+                None => Object::error(format!(
+                    "cannot destructure {}: expected {}",
+                    describe_value_shape(&val),
+                    describe_pattern_shape(&let_stmt.pattern)
+                )),
+            }
         }
         Statement::Return(ret_stmt) => {
             let val = eval_expression(ret_stmt.return_value, env);
-            if let Object::Error(_) = val {
+            if is_abrupt(&val) {
                 return val;
             }
             Object::ReturnValue(Box::new(val))
         }
+        Statement::Break(_) => Object::Break,
+        Statement::Continue(_) => Object::Continue,
         Statement::Block(block_stmt) => eval_block_statement(block_stmt, env),
         Statement::ClassDeclaration(class_decl) => eval_class_declaration(class_decl, env),
         Statement::StructDeclaration(struct_decl) => eval_struct_declaration(struct_decl, env),
@@ -67,7 +105,7 @@ fn eval_class_declaration(class_decl: ClassDeclaration, env: Rc<RefCell<Environm
             Object::Class(c) => Some(c),
             Object::Error(_) => return super_class_obj,
             _ => {
-                return Object::Error(format!(
+                return Object::error(format!(
                     "super class must be a class, got {}",
                     super_class_obj.type_str()
                 ))
@@ -77,27 +115,69 @@ fn eval_class_declaration(class_decl: ClassDeclaration, env: Rc<RefCell<Environm
         None
     };
 
-    let mut properties = HashMap::new();
+    // Область видимости класса: свойства суперклассов и самого класса,
+    // доступные по имени в значениях по умолчанию и в телах методов.
+    // Значения по умолчанию вычисляются строго в порядке объявления; более
+    // ранние свойства видны последующим по имени.
+    let class_env = Rc::new(RefCell::new(Environment::new_enclosed(Rc::clone(&env))));
+    let mut ancestors = Vec::new();
+    let mut current = super_class.clone();
+    while let Some(class) = current {
+        current = class.borrow().super_class.clone();
+        ancestors.push(class);
+    }
+    for ancestor in ancestors.iter().rev() {
+        for (prop_name, value) in ancestor.borrow().properties.iter() {
+            bind_local(
+                &mut class_env.borrow_mut(),
+                prop_name.clone(),
+                value.clone(),
+            );
+        }
+    }
+
+    let mut properties = OrderedMap::new();
     for prop_decl in class_decl.properties {
         let value = if let Some(val_expr) = prop_decl.value {
-            let val = eval_expression(val_expr, Rc::clone(&env));
-            if let Object::Error(_) = val {
+            let val = eval_expression(val_expr, Rc::clone(&class_env));
+            if is_abrupt(&val) {
                 return val;
             }
             val
         } else {
             Object::Null
         };
+        bind_local(
+            &mut class_env.borrow_mut(),
+            prop_decl.name.value.clone(),
+            value.clone(),
+        );
         properties.insert(prop_decl.name.value, value);
     }
 
-    let mut methods = HashMap::new();
+    let mut interfaces = Vec::new();
+    for interface_ident in class_decl.interfaces {
+        let name = interface_ident.value.clone();
+        match eval_expression(Expression::Identifier(interface_ident), Rc::clone(&env)) {
+            Object::Interface(interface) => interfaces.push(interface),
+            error @ Object::Error(_) => return error,
+            other => {
+                return Object::error(format!(
+                    "{} is not an interface, got {}",
+                    name,
+                    other.type_str()
+                ))
+            }
+        }
+    }
+
+    let mut methods = OrderedMap::new();
     for method_decl in class_decl.methods {
         let method = Method {
             name: method_decl.name.value.clone(),
             parameters: method_decl.parameters,
-            body: method_decl.body,
-            env: Rc::clone(&env),
+            body: Rc::new(method_decl.body),
+            env: Rc::clone(&class_env),
             this: None,
         };
         methods.insert(method_decl.name.value, Rc::new(RefCell::new(method)));
@@ -106,13 +186,19 @@ fn eval_class_declaration(class_decl: ClassDeclaration, env: Rc<RefCell<Environm
     let class = Rc::new(RefCell::new(Class {
         name: name.clone(),
         super_class,
-        interfaces: vec![],
+        interfaces,
         properties,
         methods,
+        member_cache: MemberCache::default(),
     }));
+    if let Some((method, first, second)) = class.borrow().conflicting_default() {
+        return Object::error(format!(
+            "class {} inherits conflicting default method '{}' from interfaces {} and {}",
+            name, method, first, second
+        ));
+    }
 
-    env.borrow_mut().set(name, Object::Class(Rc::clone(&class)));
-    Object::Null
+    declare(&env, name, Object::Class(Rc::clone(&class)))
 }
 
 fn eval_struct_declaration(
@@ -120,13 +206,26 @@ fn eval_struct_declaration(
     env: Rc<RefCell<Environment>>,
 ) -> Object {
     let name = struct_decl.name.value.clone();
+
+    // Значения полей по умолчанию вычисляются в окружении объявления в
+    // порядке объявления; в отличие от классов, поля не видны друг другу.
+    let mut properties = OrderedMap::new();
+    for prop_decl in struct_decl.properties {
+        let value = match prop_decl.value {
+            Some(val_expr) => eval_expression(val_expr, Rc::clone(&env)),
+            None => Object::Null,
+        };
+        if is_abrupt(&value) {
+            return value;
+        }
+        properties.insert(prop_decl.name.value, value);
+    }
+
     let struct_obj = Rc::new(RefCell::new(Struct {
         name: name.clone(),
-        properties: HashMap::new(),
+        properties,
     }));
-    env.borrow_mut()
-        .set(name, Object::Struct(Rc::clone(&struct_obj)));
-    Object::Null
+    declare(&env, name, Object::Struct(Rc::clone(&struct_obj)))
 }
 
 fn eval_interface_declaration(
@@ -134,62 +233,124 @@ fn eval_interface_declaration(
     env: Rc<RefCell<Environment>>,
 ) -> Object {
     let name = interface_decl.name.value.clone();
+    let mut method_signatures = OrderedMap::default();
+    let mut default_methods = OrderedMap::default();
+    for member in interface_decl.members {
+        match member {
+            InterfaceMember::Signature(sig) => {
+                let signature = MethodSignature {
+                    name: sig.name.value,
+                    parameters: sig.parameters,
+                };
+                method_signatures.insert(signature.name.clone(), signature);
+            }
+            // Реализации по умолчанию замыкают окружение объявления интерфейса,
+            // `this` привязывается при обращении через экземпляр класса
+            InterfaceMember::Default(method_decl) => {
+                let method = Method {
+                    name: method_decl.name.value.clone(),
+                    parameters: method_decl.parameters,
+                    body: Rc::new(method_decl.body),
+                    env: Rc::clone(&env),
+                    this: None,
+                };
+                default_methods.insert(method_decl.name.value, Rc::new(RefCell::new(method)));
+            }
+        }
+    }
     let interface = Rc::new(RefCell::new(Interface {
         name: name.clone(),
-        method_signatures: HashMap::new(),
+        method_signatures,
+        default_methods,
     }));
-    env.borrow_mut()
-        .set(name, Object::Interface(Rc::clone(&interface)));
-    Object::Null
+    declare(&env, name, Object::Interface(Rc::clone(&interface)))
 }
 
 fn eval_expression(expression: Expression, env: Rc<RefCell<Environment>>) -> Object {
     match expression {
         Expression::IntegerLiteral(il) => Object::Integer(il.value),
         Expression::Boolean(b) => Object::Boolean(b.value),
+        Expression::Null(_) => Object::Null,
         Expression::Prefix(pe) => {
             let right = eval_expression(*pe.right, env);
-            if let Object::Error(_) = right {
+            if is_abrupt(&right) {
                 return right;
             }
             eval_prefix_expression(&pe.operator, right)
         }
+        Expression::Typeof(te) => {
+            // Ошибка операнда распространяется, а не превращается в "ERROR"
+            let operand = eval_expression(*te.operand, env);
+            if is_abrupt(&operand) {
+                return operand;
+            }
+            Object::String(operand.type_str().to_string())
+        }
         Expression::Infix(ie) => {
             let left = eval_expression(*ie.left, Rc::clone(&env));
-            if let Object::Error(_) = left {
+            if is_abrupt(&left) {
                 return left;
             }
+            if ie.operator == "is" {
+                return eval_is_expression(&left, *ie.right, env);
+            }
             let right = eval_expression(*ie.right, env);
-            if let Object::Error(_) = right {
+            if is_abrupt(&right) {
                 return right;
             }
-            eval_infix_expression(&ie.operator, left, right)
+            with_position(eval_infix_expression(&ie.operator, left, right), &ie.token)
         }
         Expression::If(ie) => eval_if_expression(ie, env),
+        Expression::IfLet(ie) => eval_if_let_expression(ie, env),
         Expression::Identifier(i) => eval_identifier(i, env),
-        Expression::FunctionLiteral(fl) => Object::Function(fl.parameters, fl.body, env),
+        Expression::FunctionLiteral(fl) => {
+            Object::Function(fl.parameters, fl.rest.map(Box::new), Rc::new(fl.body), env)
+        }
         Expression::Call(ce) => {
+            let frame = call_frame_name(&ce.function);
             let function = eval_expression(*ce.function, Rc::clone(&env));
-            if let Object::Error(_) = function {
+            if is_abrupt(&function) {
                 return function;
             }
-            let args = eval_expressions(ce.arguments, env);
-            if args.len() == 1 {
-                if let Object::Error(_) = args[0] {
-                    return args[0].clone();
-                }
+            match eval_expressions(ce.arguments, env) {
+                Ok(args) => apply_function(function, args, &frame),
+                Err(signal) => signal,
             }
-            apply_function(function, args)
         }
         Expression::StringLiteral(s) => Object::String(s.value),
-        Expression::ArrayLiteral(al) => {
-            let elements = eval_expressions(al.elements, env);
-            if elements.len() == 1 {
-                if let Object::Error(_) = elements[0] {
-                    return elements[0].clone();
-                }
+        Expression::ArrayLiteral(al) => match eval_expressions(al.elements, env) {
+            Ok(elements) => Object::Array(Rc::new(elements)),
+            Err(signal) => signal,
+        },
+        Expression::HashLiteral(hl) => eval_hash_literal(hl.pairs, env),
+        Expression::Index(ie) => {
+            let left = eval_expression(*ie.left, Rc::clone(&env));
+            if is_abrupt(&left) {
+                return left;
+            }
+            let index = eval_expression(*ie.index, env);
+            if is_abrupt(&index) {
+                return index;
+            }
+            eval_index_expression(left, index)
+        }
+        Expression::Range(re) => {
+            let start = eval_expression(*re.start, Rc::clone(&env));
+            if is_abrupt(&start) {
+                return start;
+            }
+            let end = eval_expression(*re.end, env);
+            if is_abrupt(&end) {
+                return end;
+            }
+            match (&start, &end) {
+                (Object::Integer(s), Object::Integer(e)) => Object::Range(*s, *e),
+                _ => Object::error(format!(
+                    "range bounds must be integers: {}..{}",
+                    start.type_str(),
+                    end.type_str()
+                )),
             }
-            Object::Array(elements)
         }
         Expression::New(ne) => eval_new_expression(ne, env),
         Expression::This(te) => eval_this_expression(te, env),
@@ -198,6 +359,244 @@ fn eval_expression(expression: Expression, env: Rc<RefCell<Environment>>) -> Obj
         Expression::MethodCall(mce) => eval_method_call_expression(mce, env),
         // Добавляем обработку match-выражений, чтобы устранить ошибку компиляции.
         Expression::Match(me) => eval_match_expression(me, env),
+        Expression::For(fe) => eval_for_expression(fe, env),
+        Expression::Assign(ae) => eval_assign_expression(ae, env),
+    }
+}
+
+/// Выполняет тело цикла для каждого элемента; каждая итерация получает
+/// собственное окружение с переменной цикла. Значение цикла — Null.
+/// `break` прекращает перебор, `continue` завершает текущую итерацию.
+fn eval_for_expression(fe: ForExpression, env: Rc<RefCell<Environment>>) -> Object {
+    let iterable = eval_expression(*fe.iterable, Rc::clone(&env));
+    if is_abrupt(&iterable) {
+        return iterable;
+    }
+
+    let outcome = iterate(&iterable, |item| {
+        let loop_env = Rc::new(RefCell::new(Environment::new_enclosed(Rc::clone(&env))));
+        bind_local(&mut loop_env.borrow_mut(), fe.variable.value.clone(), item);
+        match eval_block_statement(fe.body.clone(), loop_env) {
+            result @ (Object::ReturnValue(_) | Object::Error(_) | Object::Break) => Err(result),
+            _ => Ok(()),
+        }
+    });
+    match outcome {
+        Ok(()) | Err(Object::Break) => Object::Null,
+        Err(result) => result,
+    }
+}
+
+/// Перебирает элементы итерируемого значения: массива, диапазона или
+/// экземпляра класса с методом `next()`. Итератор вызывается, пока `next()`
+/// не вернёт Null; ошибка из `next()` прерывает перебор.
+///
+/// `visit` может прервать перебор, вернув `Err` — он передаётся наружу.
+pub(crate) fn iterate(
+    iterable: &Object,
+    mut visit: impl FnMut(Object) -> Result<(), Object>,
+) -> Result<(), Object> {
+    match iterable {
+        Object::Array(elements) => elements.iter().try_for_each(|e| visit(e.clone())),
+        Object::Range(start, end) => (*start..*end).try_for_each(|i| visit(Object::Integer(i))),
+        Object::ClassInstance(instance) => {
+            let class = Rc::clone(&instance.borrow().class);
+            let class_name = class.borrow().name.clone();
+            let next = find_method_in_class(class, "next").ok_or_else(|| {
+                Object::error(format!(
+                    "object of class '{}' is not iterable: no next() method",
+                    class_name
+                ))
+            })?;
+            if !next.borrow().parameters.is_empty() {
+                return Err(Object::error(format!(
+                    "{}.next() must take no arguments",
+                    class_name
+                )));
+            }
+            loop {
+                match apply_function(bind_method(Rc::clone(&next), instance), vec![], "next()") {
+                    Object::Null => return Ok(()),
+                    error @ Object::Error(_) => return Err(error),
+                    item => visit(item)?,
+                }
+            }
+        }
+        other => Err(Object::error(format!("not iterable: {}", other.type_str()))),
+    }
+}
+
+fn eval_assign_expression(ae: AssignExpression, env: Rc<RefCell<Environment>>) -> Object {
+    match *ae.target {
+        Expression::Identifier(ident) => {
+            let value = eval_expression(*ae.value, Rc::clone(&env));
+            if is_abrupt(&value) {
+                return value;
+            }
+            assign_variable(&ident, value, &env)
+        }
+        Expression::PropertyAccess(pae) => {
+            let (parent, object) = match resolve_receiver(*pae.left, Rc::clone(&env)) {
+                Ok(receiver) => receiver,
+                Err(error) => return error,
+            };
+            let value = eval_expression(*ae.value, Rc::clone(&env));
+            if is_abrupt(&value) {
+                return value;
+            }
+            store_place(
+                Place::Property(Box::new(parent), object, pae.property.value),
+                value,
+                env,
+            )
+        }
+        Expression::Index(ie) => {
+            let (parent, container) = match resolve_receiver(*ie.left, Rc::clone(&env)) {
+                Ok(receiver) => receiver,
+                Err(error) => return error,
+            };
+            let index = eval_expression(*ie.index, Rc::clone(&env));
+            if is_abrupt(&index) {
+                return index;
+            }
+            let value = eval_expression(*ae.value, Rc::clone(&env));
+            if is_abrupt(&value) {
+                return value;
+            }
+            store_place(
+                Place::Element(Box::new(parent), container, index),
+                value,
+                env,
+            )
+        }
+        target => Object::error(format!("invalid assignment target: {}", target)),
+    }
+}
+
+/// Место записи, в котором получатели и индексы уже вычислены. При
+/// `a[next()][1] = v` и `a[next()].x = v` `next()` вызывается один раз,
+/// а обновлённый контейнер записывается обратно по тому же пути.
+enum Place {
+    Variable(Identifier),
+    /// Элемент: место контейнера, сам контейнер и индекс.
+    Element(Box<Place>, Object, Object),
+    /// Поле: место объекта, сам объект и имя поля.
+    Property(Box<Place>, Object, String),
+    /// Прочие выражения (вызов, `this`, ...): записать в них нельзя.
+    Expression(Expression),
+}
+
+/// Вычисляет получатель записи `expr` один раз: возвращает его место и
+/// текущее значение.
+fn resolve_receiver(
+    expr: Expression,
+    env: Rc<RefCell<Environment>>,
+) -> Result<(Place, Object), Object> {
+    match expr {
+        Expression::Identifier(ident) => {
+            let value = eval_identifier(ident.clone(), Rc::clone(&env));
+            if is_abrupt(&value) {
+                return Err(value);
+            }
+            Ok((Place::Variable(ident), value))
+        }
+        Expression::Index(ie) => {
+            let (parent, container) = resolve_receiver(*ie.left, Rc::clone(&env))?;
+            let index = eval_expression(*ie.index, env);
+            if is_abrupt(&index) {
+                return Err(index);
+            }
+            let value = eval_index_expression(container.clone(), index.clone());
+            if is_abrupt(&value) {
+                return Err(value);
+            }
+            Ok((Place::Element(Box::new(parent), container, index), value))
+        }
+        Expression::PropertyAccess(pae) => {
+            let (parent, object) = resolve_receiver(*pae.left, env)?;
+            let value = property_value(object.clone(), &pae.property.value);
+            if is_abrupt(&value) {
+                return Err(value);
+            }
+            Ok((
+                Place::Property(Box::new(parent), object, pae.property.value),
+                value,
+            ))
+        }
+        expr => {
+            let value = eval_expression(expr.clone(), env);
+            if is_abrupt(&value) {
+                return Err(value);
+            }
+            Ok((Place::Expression(expr), value))
+        }
+    }
+}
+
+/// Записывает значение в место и возвращает его. Массивы и экземпляры
+/// структур — значения, поэтому обновлённый контейнер записывается обратно
+/// в своё место; экземпляр класса меняется по ссылке.
+fn store_place(place: Place, value: Object, env: Rc<RefCell<Environment>>) -> Object {
+    match place {
+        Place::Variable(ident) => assign_variable(&ident, value, &env),
+        Place::Element(parent, container, index) => {
+            match set_index(container, &index, value.clone()) {
+                Ok(updated) => match store_place(*parent, updated, env) {
+                    error @ Object::Error(_) => error,
+                    _ => value,
+                },
+                Err(message) => Object::error(message),
+            }
+        }
+        Place::Property(parent, object, name) => {
+            let Object::StructInstance(instance) = &object else {
+                return assign_property(object, name, value);
+            };
+            let Some(updated) = instance.borrow().with_field(&name, value.clone()) else {
+                return Object::error(format!(
+                    "property '{}' not found on struct '{}'",
+                    name,
+                    instance.borrow().struct_def.borrow().name
+                ));
+            };
+            let updated = Object::StructInstance(Rc::new(RefCell::new(updated)));
+            match store_place(*parent, updated, env) {
+                error @ Object::Error(_) => error,
+                _ => value,
+            }
+        }
+        Place::Expression(target) => {
+            Object::error(format!("invalid assignment target: {}", target))
+        }
+    }
+}
+
+fn assign_variable(ident: &Identifier, value: Object, env: &Rc<RefCell<Environment>>) -> Object {
+    let result = env.borrow_mut().assign(&ident.value, value.clone());
+    match result {
+        Ok(()) => value,
+        Err(message) => with_position(Object::error(message), &ident.token),
+    }
+}
+
+fn assign_property(object: Object, name: String, value: Object) -> Object {
+    match object {
+        Object::ClassInstance(instance_rc) => {
+            let mut instance = instance_rc.borrow_mut();
+            if !instance.fields.contains_key(&name) {
+                return Object::error(format!(
+                    "property '{}' not found on class '{}'",
+                    name,
+                    instance.class.borrow().name
+                ));
+            }
+            instance.fields.insert(name, value.clone());
+            value
+        }
+        _ => Object::error(format!(
+            "property access not supported for type '{}'",
+            object.type_str()
+        )),
     }
 }
 
@@ -206,7 +605,9 @@ fn eval_block_statement(block: BlockStatement, env: Rc<RefCell<Environment>>) ->
     for statement in block.statements {
         result = eval_statement(statement, Rc::clone(&env));
         match result {
-            Object::ReturnValue(_) | Object::Error(_) => return result,
+            Object::ReturnValue(_) | Object::Error(_) | Object::Break | Object::Continue => {
+                return result
+            }
             _ => {}
         }
     }
@@ -217,7 +618,7 @@ fn eval_prefix_expression(operator: &str, right: Object) -> Object {
     match operator {
         "!" => eval_bang_operator_expression(right),
         "-" => eval_minus_prefix_operator_expression(right),
-        _ => Object::Error(format!(
+        _ => Object::error(format!(
             "unknown operator: {}{}",
             operator,
             right.type_str()
@@ -237,77 +638,170 @@ fn eval_bang_operator_expression(right: Object) -> Object {
 fn eval_minus_prefix_operator_expression(right: Object) -> Object {
     match right {
         Object::Integer(i) => Object::Integer(-i),
-        _ => Object::Error(format!("unknown operator: -{}", right.type_str())),
+        _ => Object::error(format!("unknown operator: -{}", right.type_str())),
+    }
+}
+
+/// Имена специальных методов, перегружающих инфиксный оператор,
+/// в порядке поиска: полное имя, затем короткое (`sub`, `mul`, ...).
+fn operator_method_names(operator: &str) -> &'static [&'static str] {
+    match operator {
+        "+" => &["add"],
+        "-" => &["subtract", "sub"],
+        "*" => &["multiply", "mul"],
+        "/" => &["divide", "div"],
+        "%" => &["modulo", "mod"],
+        "==" | "!=" => &["equals"],
+        "<" => &["lessThan"],
+        ">" => &["greaterThan"],
+        _ => &[],
+    }
+}
+
+/// Строковое представление экземпляра, который определяет метод
+/// `to_string()` без параметров; `None`, если такого метода нет.
+/// Результат — строка или ошибка, в том числе когда метод вернул не строку.
+fn instance_to_string(instance: &Rc<RefCell<ClassInstance>>) -> Option<Object> {
+    let class = Rc::clone(&instance.borrow().class);
+    let method_rc = find_method_in_class(class, "to_string")?;
+    if !method_rc.borrow().parameters.is_empty() {
+        return None;
+    }
+    match apply_function(bind_method(method_rc, instance), vec![], "to_string()") {
+        result @ (Object::String(_) | Object::Error(_)) => Some(result),
+        other => Some(Object::error(format!(
+            "to_string() must return STRING, got {}",
+            other.type_str()
+        ))),
+    }
+}
+
+/// Текст значения для вывода (REPL, `to_string`, `format`): экземпляры
+/// классов, в том числе вложенные, выводятся своим методом `to_string()`.
+/// Ошибка этого метода возвращается как `Err`, а не заменяется именем класса.
+pub fn stringify(obj: &Object) -> Result<String, Object> {
+    obj.render(&mut |instance| match instance_to_string(instance) {
+        Some(Object::String(text)) => Ok(Some(text)),
+        Some(error) => Err(error),
+        None => Ok(None),
+    })
+}
+
+/// `строка + экземпляр` и `экземпляр + строка`: экземпляр приводится
+/// к строке своим методом `to_string()`.
+fn eval_instance_concatenation(left: &Object, right: &Object) -> Option<Object> {
+    let (instance, other) = match (left, right) {
+        (Object::ClassInstance(instance), Object::String(other))
+        | (Object::String(other), Object::ClassInstance(instance)) => (instance, other),
+        _ => return None,
+    };
+    let text = match instance_to_string(instance)? {
+        Object::String(text) => text,
+        error => return Some(error),
+    };
+    Some(match left {
+        Object::ClassInstance(_) => Object::String(format!("{}{}", text, other)),
+        _ => Object::String(format!("{}{}", other, text)),
+    })
+}
+
+/// `value is Interface` — реализует ли класс экземпляра интерфейс
+/// (см. `Class::implements`). Для прочих значений — false.
+fn eval_is_expression(left: &Object, right: Expression, env: Rc<RefCell<Environment>>) -> Object {
+    // Имена встроенных типов не связаны в окружении, но пользовательская
+    // переменная с тем же именем их перекрывает.
+    if let Expression::Identifier(ident) = &right {
+        if env.borrow().get(&ident.value).is_none() {
+            if let Some(matches) = left.is_primitive_type(&ident.value) {
+                return Object::Boolean(matches);
+            }
+        }
+    }
+    let right = eval_expression(right, env);
+    if is_abrupt(&right) {
+        return right;
+    }
+    match left.is_instance_of(&right) {
+        Some(matches) => Object::Boolean(matches),
+        None => Object::error(format!(
+            "right operand of 'is' must be a type, got {}",
+            right.type_str()
+        )),
+    }
+}
+
+/// Вызывает перегруженный оператор у экземпляра класса, если класс его определяет.
+/// Результат `equals` приводится к булеву значению; `!=` — его отрицание.
+fn eval_operator_overload(
+    operator: &str,
+    instance: &Rc<RefCell<ClassInstance>>,
+    right: &Object,
+) -> Option<Object> {
+    let class = Rc::clone(&instance.borrow().class);
+    let (method_name, method_rc) = operator_method_names(operator)
+        .iter()
+        .find_map(|name| find_method_in_class(Rc::clone(&class), name).map(|m| (*name, m)))?;
+    let frame = format!("{}()", method_name);
+    let result = apply_function(
+        bind_method(method_rc, instance),
+        vec![right.clone()],
+        &frame,
+    );
+    match result {
+        Object::Error(_) => Some(result),
+        _ if operator == "==" => Some(Object::Boolean(is_truthy(result))),
+        _ if operator == "!=" => Some(Object::Boolean(!is_truthy(result))),
+        _ => Some(result),
     }
 }
 
+/// Операторы, которые вычисляет общее правило `object::arithmetic`.
+const ARITHMETIC_OPERATORS: [&str; 6] = ["+", "-", "*", "/", "%", "**"];
+
 fn eval_infix_expression(operator: &str, left: Object, right: Object) -> Object {
+    if let Object::ClassInstance(instance) = &left {
+        if let Some(result) = eval_operator_overload(operator, instance, &right) {
+            return result;
+        }
+    }
+    if operator == "+" {
+        if let Some(result) = eval_instance_concatenation(&left, &right) {
+            return result;
+        }
+    }
+
+    if operator == "==" || operator == "!=" {
+        return match values_equal(operator, &left, &right) {
+            Ok(equal) => Object::Boolean(equal),
+            Err(message) => Object::error(message),
+        };
+    }
+    if ARITHMETIC_OPERATORS.contains(&operator) {
+        return match arithmetic(operator, &left, &right) {
+            Ok(result) => result,
+            Err(message) => Object::error(message),
+        };
+    }
+
     match (&left, &right) {
         (Object::Integer(l), Object::Integer(r)) => eval_integer_infix_expression(operator, *l, *r),
         (Object::Boolean(l), Object::Boolean(r)) => eval_boolean_infix_expression(operator, *l, *r),
         (Object::String(l), Object::String(r)) => eval_string_infix_expression(operator, l, r),
-        (Object::String(l), Object::Integer(r)) if operator == "*" => {
-            eval_string_integer_infix_expression(operator, l, *r)
-        }
-        // Сравнение экземпляров классов
-        (Object::ClassInstance(l), Object::ClassInstance(r)) => match operator {
-            "==" => Object::Boolean(Rc::ptr_eq(l, r)),
-            "!=" => Object::Boolean(!Rc::ptr_eq(l, r)),
-            _ => Object::Error(format!(
-                "unknown operator: {} {} {}",
-                left.type_str(),
-                operator,
-                right.type_str()
-            )),
-        },
-        // Сравнение экземпляров структур
-        (Object::StructInstance(l), Object::StructInstance(r)) => match operator {
-            "==" => Object::Boolean(Rc::ptr_eq(l, r)),
-            "!=" => Object::Boolean(!Rc::ptr_eq(l, r)),
-            _ => Object::Error(format!(
-                "unknown operator: {} {} {}",
-                left.type_str(),
-                operator,
-                right.type_str()
-            )),
-        },
-        _ => Object::Error(format!(
-            "type mismatch: {} {} {}",
-            left.type_str(),
-            operator,
-            right.type_str()
-        )),
+        (Object::ClassInstance(_), Object::ClassInstance(_))
+        | (Object::StructInstance(_), Object::StructInstance(_)) => {
+            Object::error(unknown_operator(&left, operator, &right))
+        }
+        _ => Object::error(type_mismatch(&left, operator, &right)),
     }
 }
 
 fn eval_integer_infix_expression(operator: &str, left: i64, right: i64) -> Object {
     match operator {
-        "+" => Object::Integer(left + right),
-        "-" => Object::Integer(left - right),
-        "*" => Object::Integer(left * right),
-        "/" => {
-            if right == 0 {
-                return Object::Error("division by zero".to_string());
-            }
-            Object::Integer(left / right)
-        }
-        "**" => {
-            if right < 0 {
-                return Object::Error("negative exponent not supported".to_string());
-            }
-            Object::Integer(left.pow(right as u32))
-        }
-        "%" => {
-            if right == 0 {
-                return Object::Error("modulo by zero".to_string());
-            }
-            Object::Integer(left % right)
-        }
         "<" => Object::Boolean(left < right),
         ">" => Object::Boolean(left > right),
         "==" => Object::Boolean(left == right),
         "!=" => Object::Boolean(left != right),
-        _ => Object::Error(format!(
+        _ => Object::error(format!(
             "unknown operator: {} {} {}",
             "INTEGER", operator, "INTEGER"
         )),
@@ -320,7 +814,7 @@ fn eval_boolean_infix_expression(operator: &str, left: bool, right: bool) -> Obj
         "!=" => Object::Boolean(left != right),
         "&&" => Object::Boolean(left && right),
         "||" => Object::Boolean(left || right),
-        _ => Object::Error(format!(
+        _ => Object::error(format!(
             "unknown operator: {} {} {}",
             "BOOLEAN", operator, "BOOLEAN"
         )),
@@ -328,34 +822,18 @@ fn eval_boolean_infix_expression(operator: &str, left: bool, right: bool) -> Obj
 }
 
 fn eval_string_infix_expression(operator: &str, left: &str, right: &str) -> Object {
-    match operator {
-        "+" => Object::String(format!("{}{}", left, right)),
-        _ => Object::Error(format!(
-            "unknown operator: {} {} {}",
-            "STRING", operator, "STRING"
-        )),
-    }
-}
-
-fn eval_string_integer_infix_expression(operator: &str, left: &str, right: i64) -> Object {
-    match operator {
-        "*" => {
-            if right < 0 {
-                return Object::Error(
-                    "negative multiplier not supported for string multiplication".to_string(),
-                );
-            }
-            Object::String(left.repeat(right as usize))
-        }
-        _ => Object::Error(format!(
-            "unknown operator: {} {} {}",
-            "STRING", operator, "INTEGER"
-        )),
+    let (left, right) = (Object::String(left.into()), Object::String(right.into()));
+    match compare_values(operator, &left, &right) {
+        Ok(result) => Object::Boolean(result),
+        Err(message) => Object::error(message),
     }
 }
 
 fn eval_if_expression(ie: IfExpression, env: Rc<RefCell<Environment>>) -> Object {
     let condition = eval_expression(*ie.condition, Rc::clone(&env));
+    if is_abrupt(&condition) {
+        return condition;
+    }
     if is_truthy(condition) {
         eval_block_statement(ie.consequence, env)
     } else if let Some(alt) = ie.alternative {
@@ -365,7 +843,37 @@ fn eval_if_expression(ie: IfExpression, env: Rc<RefCell<Environment>>) -> Object
     }
 }
 
-fn is_truthy(obj: Object) -> bool {
+/// `if let`: при совпадении паттерна выполняет consequence в окружении
+/// с привязками паттерна, иначе — alternative.
+fn eval_if_let_expression(ie: IfLetExpression, env: Rc<RefCell<Environment>>) -> Object {
+    let value = eval_expression(*ie.value, Rc::clone(&env));
+    if is_abrupt(&value) {
+        return value;
+    }
+    let pattern = match resolve_range_bounds(&ie.pattern, &env) {
+        Ok(pattern) => pattern,
+        Err(error) => return error,
+    };
+    match pattern_matches(&pattern, &value, Rc::clone(&env)) {
+        Some(bindings) => {
+            let branch_env = Rc::new(RefCell::new(Environment::new_enclosed(env)));
+            for (name, obj) in bindings {
+                bind_local(&mut branch_env.borrow_mut(), name, obj);
+            }
+            eval_block_statement(ie.consequence, branch_env)
+        }
+        None => match ie.alternative {
+            Some(alt) => eval_block_statement(alt, env),
+            None => Object::Null,
+        },
+    }
+}
+
+fn eval_index_expression(left: Object, index: Object) -> Object {
+    index_value(&left, &index).unwrap_or_else(Object::error)
+}
+
+pub(crate) fn is_truthy(obj: Object) -> bool {
     match obj {
         Object::Null => false,
         Object::Boolean(true) => true,
@@ -374,58 +882,258 @@ fn is_truthy(obj: Object) -> bool {
     }
 }
 
+/// Имя ищется в окружении, затем среди встроенных функций, а внутри метода —
+/// среди методов экземпляра `this` (с унаследованными): `helper()` значит то
+/// же, что `this.helper()`. Встроенные функции идут раньше, чтобы
+/// `to_string(this.x)` в методе `to_string()` вызывал встроенную функцию;
+/// метод с именем встроенной вызывается только через `this.`. Метод
+/// привязывается только при обращении. Поля экземпляра без `this.` не видны.
 fn eval_identifier(ident: Identifier, env: Rc<RefCell<Environment>>) -> Object {
-    match env.borrow().get(&ident.value) {
-        Some(o) => o,
-        None => Object::Error(format!("identifier not found: {}", ident.value)),
+    if let Some(o) = env.borrow().get(&ident.value) {
+        return o;
+    }
+    if let Some(builtin) = builtins::lookup(&ident.value) {
+        return builtin;
+    }
+    if let Some(method) = lookup_sibling_method(&ident.value, &env) {
+        return method;
     }
+    let error = Object::error(format!("identifier not found: {}", ident.value));
+    with_position(error, &ident.token)
 }
 
-fn eval_expressions(exps: Vec<Expression>, env: Rc<RefCell<Environment>>) -> Vec<Object> {
+/// Метод `name` экземпляра, к которому привязан `this` текущего метода.
+fn lookup_sibling_method(name: &str, env: &Rc<RefCell<Environment>>) -> Option<Object> {
+    let Some(Object::ClassInstance(instance)) = env.borrow().get("this") else {
+        return None;
+    };
+    let class = Rc::clone(&instance.borrow().class);
+    let method = find_method_in_class(class, name)?;
+    Some(bind_method(method, &instance))
+}
+
+/// Вычисляет пары литерала хэша слева направо: ключ, затем значение. При
+/// повторе ключа остаётся последнее значение, как в опкоде VM `Hash`.
+fn eval_hash_literal(
+    pairs: Vec<(Expression, Expression)>,
+    env: Rc<RefCell<Environment>>,
+) -> Object {
+    let mut hash = OrderedMap::new();
+    for (key_expression, value_expression) in pairs {
+        let key = eval_expression(key_expression, Rc::clone(&env));
+        if is_abrupt(&key) {
+            return key;
+        }
+        let key = match HashKey::from_object(&key) {
+            Ok(key) => key,
+            Err(message) => return Object::error(message),
+        };
+        let value = eval_expression(value_expression, Rc::clone(&env));
+        if is_abrupt(&value) {
+            return value;
+        }
+        hash.insert(key, value);
+    }
+    Object::Hash(Rc::new(hash))
+}
+
+/// Вычисляет выражения слева направо. Первая ошибка или сигнал
+/// `return`/`break`/`continue` прерывает вычисление и возвращается как `Err`.
+fn eval_expressions(
+    exps: Vec<Expression>,
+    env: Rc<RefCell<Environment>>,
+) -> Result<Vec<Object>, Object> {
     exps.into_iter()
-        .map(|e| eval_expression(e, Rc::clone(&env)))
+        .map(|e| match eval_expression(e, Rc::clone(&env)) {
+            value if is_abrupt(&value) => Err(value),
+            value => Ok(value),
+        })
         .collect()
 }
 
-fn apply_function(func: Object, args: Vec<Object>) -> Object {
+/// Значение прерывает вычисление объемлющего выражения: ошибка или сигнал
+/// `return`/`break`/`continue`, который поднимается до функции или цикла.
+fn is_abrupt(obj: &Object) -> bool {
+    matches!(
+        obj,
+        Object::Error(_) | Object::ReturnValue(_) | Object::Break | Object::Continue
+    )
+}
+
+/// Имя кадра вызова для трассировки: имя переменной или свойства, через
+/// которое вызывается функция.
+fn call_frame_name(function: &Expression) -> String {
+    match function {
+        Expression::Identifier(ident) => format!("{}()", ident.value),
+        Expression::PropertyAccess(pae) => format!("{}()", pae.property.value),
+        _ => "<anonymous>()".to_string(),
+    }
+}
+
+/// Дописывает к ошибке, возникшей в узле, позицию его токена в исходном
+/// коде. Ошибки, пришедшие из вызова (с трассировкой), уже её несут.
+fn with_position(obj: Object, token: &Token) -> Object {
+    match obj {
+        Object::Error(mut error) if token.span.has_position() && error.trace.is_empty() => {
+            error.message = format!(
+                "{} at line {}, column {}",
+                error.message, token.span.line, token.span.column
+            );
+            Object::Error(error)
+        }
+        _ => obj,
+    }
+}
+
+/// Добавляет кадр трассировки к ошибке; остальные объекты возвращаются без изменений.
+fn add_trace_frame(obj: Object, frame: &str) -> Object {
+    match obj {
+        Object::Error(error) => Object::Error(error.with_frame(frame)),
+        _ => obj,
+    }
+}
+
+pub(crate) fn apply_function(func: Object, args: Vec<Object>, frame: &str) -> Object {
     match func {
-        Object::Function(params, body, env) => {
-            let extended_env = extend_function_env(&params, args, &env);
-            let evaluated = eval_block_statement(body, extended_env);
-            unwrap_return_value(evaluated)
+        Object::Function(params, rest, body, env) => {
+            if args.len() < params.len() {
+                let expected = if rest.is_some() { "at least " } else { "" };
+                return Object::error(format!(
+                    "wrong number of arguments: expected {}{}, got {}",
+                    expected,
+                    params.len(),
+                    args.len()
+                ));
+            }
+            let extended_env = extend_function_env(&params, rest.as_deref(), args, &env);
+            let evaluated = eval_block_statement((*body).clone(), extended_env);
+            add_trace_frame(unwrap_return_value(evaluated), frame)
         }
         Object::Method(method_rc) => {
             let method = method_rc.borrow();
+            if args.len() < method.parameters.len() {
+                return Object::error(format!(
+                    "wrong number of arguments: expected {}, got {}",
+                    method.parameters.len(),
+                    args.len()
+                ));
+            }
             let instance = method
                 .this
                 .as_ref()
                 .expect("method.this should be set before calling")
                 .clone();
+            let frame = format!(
+                "{}.{}()",
+                instance.borrow().class.borrow().name,
+                method.name
+            );
             let mut extended_env = Environment::new_enclosed(Rc::clone(&method.env));
-            extended_env.set("this".to_string(), Object::ClassInstance(instance));
+            bind_local(
+                &mut extended_env,
+                "this".to_string(),
+                Object::ClassInstance(instance),
+            );
             for (i, param) in method.parameters.iter().enumerate() {
-                extended_env.set(param.value.clone(), args[i].clone());
+                bind_local(&mut extended_env, param.value.clone(), args[i].clone());
             }
             let evaluated =
-                eval_block_statement(method.body.clone(), Rc::new(RefCell::new(extended_env)));
-            unwrap_return_value(evaluated)
+                eval_block_statement((*method.body).clone(), Rc::new(RefCell::new(extended_env)));
+            add_trace_frame(unwrap_return_value(evaluated), &frame)
         }
-        _ => Object::Error(format!("not a function: {}", func.type_str())),
+        Object::BuiltinFunction {
+            name,
+            num_params,
+            handler,
+        } => builtins::call(&name, num_params, handler, args),
+        _ => Object::error(format!("not a function: {}", func.type_str())),
     }
 }
 
+/// Связывает параметры с аргументами; аргументы сверх `params` попадают
+/// массивом в rest-параметр.
 fn extend_function_env(
     params: &[Identifier],
-    args: Vec<Object>,
+    rest: Option<&Identifier>,
+    mut args: Vec<Object>,
     env: &Rc<RefCell<Environment>>,
 ) -> Rc<RefCell<Environment>> {
     let mut new_env = Environment::new_enclosed(Rc::clone(env));
-    for (i, param) in params.iter().enumerate() {
-        new_env.set(param.value.clone(), args[i].clone());
+    let remaining = args.split_off(params.len().min(args.len()));
+    for (param, arg) in params.iter().zip(args) {
+        bind_local(&mut new_env, param.value.clone(), arg);
+    }
+    if let Some(rest) = rest {
+        bind_local(
+            &mut new_env,
+            rest.value.clone(),
+            Object::Array(Rc::new(remaining)),
+        );
     }
     Rc::new(RefCell::new(new_env))
 }
 
+/// Объявляет имя в окружении `let`, `fn` или объявления типа. Окружение
+/// может быть ограничено (`Environment::with_limit`): превышение лимита
+/// становится `Object::Error`, иначе результат — `null`. Вне REPL
+/// имя класса, структуры или интерфейса нельзя объявить повторно в той же
+/// области видимости — ни другим типом, ни `let`, ни наоборот.
+fn declare(env: &Rc<RefCell<Environment>>, name: String, value: Object) -> Object {
+    bind_declared(env, name, value, Environment::set)
+}
+
+/// Как `declare`, но для `const`: привязку нельзя перезаписать присваиванием.
+fn declare_constant(env: &Rc<RefCell<Environment>>, name: String, value: Object) -> Object {
+    bind_declared(env, name, value, Environment::set_constant)
+}
+
+fn bind_declared(
+    env: &Rc<RefCell<Environment>>,
+    name: String,
+    value: Object,
+    bind: fn(&mut Environment, String, Object) -> Result<(), String>,
+) -> Object {
+    if let Some(error) = redeclaration_error(&env.borrow(), &name, &value) {
+        return error;
+    }
+    match bind(&mut env.borrow_mut(), name, value) {
+        Ok(()) => Object::Null,
+        Err(message) => Object::error(message),
+    }
+}
+
+/// Ошибка `class 'A' is already defined`, если `name` уже объявлено в этой
+/// области и одно из значений — класс, структура или интерфейс. В
+/// сообщении — вид прежнего объявления, если это тип, иначе нового.
+fn redeclaration_error(env: &Environment, name: &str, value: &Object) -> Option<Object> {
+    if env.allows_redefinition() {
+        return None;
+    }
+    let existing = env.get_local(name)?;
+    let kind = type_kind(existing).or_else(|| type_kind(value))?;
+    Some(Object::error(format!(
+        "{} '{}' is already defined",
+        kind, name
+    )))
+}
+
+fn type_kind(value: &Object) -> Option<&'static str> {
+    match value {
+        Object::Class(_) => Some("class"),
+        Object::Struct(_) => Some("struct"),
+        Object::Interface(_) => Some("interface"),
+        _ => None,
+    }
+}
+
+/// Привязывает параметр, переменную цикла или привязку паттерна в только
+/// что созданном вложенном окружении. Вложенные окружения лимит не
+/// наследуют, поэтому `set` здесь ошибкой не завершается.
+fn bind_local(env: &mut Environment, name: String, value: Object) {
+    env.set(name, value)
+        .expect("вложенное окружение не ограничено");
+}
+
 fn unwrap_return_value(obj: Object) -> Object {
     match obj {
         Object::ReturnValue(val) => *val,
@@ -435,28 +1143,37 @@ fn unwrap_return_value(obj: Object) -> Object {
 
 fn eval_new_expression(new_expr: NewExpression, env: Rc<RefCell<Environment>>) -> Object {
     let class_name = &new_expr.class_name.value;
-    match env.borrow().get(class_name) {
-        Some(Object::Class(class_obj)) => {
-            let mut fields = HashMap::new();
-            for (name, value) in &class_obj.borrow().properties {
-                fields.insert(name.clone(), value.clone());
-            }
+    let type_obj = env.borrow().get(class_name);
+    let defaults = match &type_obj {
+        Some(Object::Class(class_obj)) => class_obj.borrow().properties.clone(),
+        Some(Object::Struct(struct_obj)) => struct_obj.borrow().properties.clone(),
+        Some(_) => return Object::error(format!("not a class or struct: {}", class_name)),
+        None => return Object::error(format!("type not found: {}", class_name)),
+    };
 
-            let instance = Rc::new(RefCell::new(ClassInstance {
-                class: Rc::clone(&class_obj),
+    let args = match eval_expressions(new_expr.arguments, Rc::clone(&env)) {
+        Ok(args) => args,
+        Err(signal) => return signal,
+    };
+    let fields = match instance_fields(class_name, defaults, args) {
+        Ok(fields) => fields,
+        Err(message) => return Object::error(message),
+    };
+
+    match type_obj {
+        Some(Object::Class(class_obj)) => {
+            Object::ClassInstance(Rc::new(RefCell::new(ClassInstance {
+                class: class_obj,
                 fields,
-            }));
-            Object::ClassInstance(instance)
+            })))
         }
         Some(Object::Struct(struct_obj)) => {
-            let instance = Rc::new(RefCell::new(StructInstance {
-                struct_def: Rc::clone(&struct_obj),
-                fields: HashMap::new(),
-            }));
-            Object::StructInstance(instance)
+            Object::StructInstance(Rc::new(RefCell::new(StructInstance {
+                struct_def: struct_obj,
+                fields,
+            })))
         }
-        Some(_) => Object::Error(format!("not a class or struct: {}", class_name)),
-        None => Object::Error(format!("type not found: {}", class_name)),
+        _ => unreachable!("тип проверен выше"),
     }
 }
 
@@ -465,19 +1182,22 @@ fn eval_property_access_expression(
     env: Rc<RefCell<Environment>>,
 ) -> Object {
     let left = eval_expression(*pae.left, Rc::clone(&env));
-    if let Object::Error(_) = left {
+    if is_abrupt(&left) {
         return left;
     }
+    property_value(left, &pae.property.value)
+}
 
-    let property_name = &pae.property.value;
-
+/// Значение свойства `property_name` объекта `left`: поле, привязанный
+/// метод или свойство класса.
+fn property_value(left: Object, property_name: &str) -> Object {
     match left {
         Object::StructInstance(instance_rc) => {
             let instance = instance_rc.borrow();
             if let Some(value) = instance.fields.get(property_name) {
                 return value.clone();
             }
-            Object::Error(format!(
+            Object::error(format!(
                 "property '{}' not found on struct '{}'",
                 property_name,
                 instance.struct_def.borrow().name
@@ -493,17 +1213,26 @@ fn eval_property_access_expression(
                 return bind_method(method, &instance_rc);
             }
 
-            if let Some(value) = instance.class.borrow().properties.get(property_name) {
-                return value.clone();
+            if let Some(value) = instance.class.borrow().find_property(property_name) {
+                return value;
             }
 
-            Object::Error(format!(
+            Object::error(format!(
                 "property '{}' not found on class '{}'",
                 property_name,
                 instance.class.borrow().name
             ))
         }
-        _ => Object::Error(format!(
+        Object::Class(class_rc) => {
+            let class = class_rc.borrow();
+            class.find_property(property_name).unwrap_or_else(|| {
+                Object::error(format!(
+                    "property '{}' not found on class '{}'",
+                    property_name, class.name
+                ))
+            })
+        }
+        _ => Object::error(format!(
             "property access not supported for type '{}'",
             left.type_str()
         )),
@@ -513,11 +1242,12 @@ fn eval_property_access_expression(
 fn eval_this_expression(_this_expr: ThisExpression, env: Rc<RefCell<Environment>>) -> Object {
     match env.borrow().get("this") {
         Some(this_obj) => this_obj,
-        None => Object::Error("'this' can only be used inside a method".to_string()),
+        None => Object::error("'this' can only be used inside a method".to_string()),
     }
 }
 
 fn eval_method_call_expression(mce: MethodCallExpression, env: Rc<RefCell<Environment>>) -> Object {
+    let frame = format!("{}()", mce.method.value);
     let method = eval_expression(
         Expression::PropertyAccess(PropertyAccessExpression {
             token: mce.token.clone(),
@@ -527,34 +1257,21 @@ fn eval_method_call_expression(mce: MethodCallExpression, env: Rc<RefCell<Enviro
         Rc::clone(&env),
     );
 
-    if let Object::Error(_) = method {
+    if is_abrupt(&method) {
         return method;
     }
 
-    let args = eval_expressions(mce.arguments, env);
-    if args.len() == 1 {
-        if let Object::Error(_) = args[0] {
-            return args[0].clone();
-        }
+    match eval_expressions(mce.arguments, env) {
+        Ok(args) => apply_function(method, args, &frame),
+        Err(signal) => signal,
     }
-
-    apply_function(method, args)
 }
 
 fn find_method_in_class(
     class_rc: Rc<RefCell<Class>>,
     method_name: &str,
 ) -> Option<Rc<RefCell<Method>>> {
-    let class = class_rc.borrow();
-    if let Some(method) = class.methods.get(method_name) {
-        return Some(Rc::clone(method));
-    }
-
-    if let Some(super_class_rc) = &class.super_class {
-        return find_method_in_class(Rc::clone(super_class_rc), method_name);
-    }
-
-    None
+    class_rc.borrow().find_method(method_name)
 }
 
 fn eval_match_expression(
@@ -564,26 +1281,33 @@ fn eval_match_expression(
     // Вычисляем значение, которое сопоставляем
     let value = eval_expression(*match_expr.value, Rc::clone(&env));
 
-    if let Object::Error(_) = value {
+    if is_abrupt(&value) {
         return value;
     }
 
     // Итерируем по всем ветвям match
     for arm in match_expr.arms {
+        // Границы диапазонов ветви вычисляются ровно один раз, до проверки
+        let pattern = match resolve_range_bounds(&arm.pattern, &env) {
+            Ok(pattern) => pattern,
+            Err(error) => return error,
+        };
         // Проверяем, совпадает ли паттерн
-        if let Some(bindings) = pattern_matches(&arm.pattern, &value, Rc::clone(&env)) {
-            // Если есть гард, проверяем его
-            if let Some(guard_expr) = &arm.guard {
-                let guard_env = Rc::new(RefCell::new(Environment::new_enclosed(Rc::clone(&env))));
-
-                // Применяем привязки из паттерна в окружение гарда
-                for (name, obj) in &bindings {
-                    guard_env.borrow_mut().set(name.clone(), obj.clone());
-                }
+        if let Some(bindings) = pattern_matches(&pattern, &value, Rc::clone(&env)) {
+            // Привязки паттерна живут в одном окружении ветви: гард и тело
+            // видят одни и те же переменные (как локальные в VM), а внешние
+            // переменные с теми же именами лишь перекрываются. Если гард не
+            // прошёл, окружение вместе с привязками отбрасывается
+            let arm_env = Rc::new(RefCell::new(Environment::new_enclosed(Rc::clone(&env))));
+            for (name, obj) in bindings {
+                bind_local(&mut arm_env.borrow_mut(), name, obj);
+            }
 
-                let guard_result = eval_expression(guard_expr.clone(), Rc::clone(&guard_env));
+            // Если есть гард, проверяем его
+            if let Some(guard_expr) = arm.guard {
+                let guard_result = eval_expression(guard_expr, Rc::clone(&arm_env));
 
-                if let Object::Error(_) = guard_result {
+                if is_abrupt(&guard_result) {
                     return guard_result;
                 }
 
@@ -592,19 +1316,88 @@ fn eval_match_expression(
                 }
             }
 
-            // Создаем новое окружение с привязками из паттерна
-            let arm_env = Rc::new(RefCell::new(Environment::new_enclosed(Rc::clone(&env))));
-            for (name, obj) in bindings {
-                arm_env.borrow_mut().set(name, obj);
-            }
-
             // Выполняем consequence для этой ветви
             return eval_block_statement(arm.consequence, arm_env);
         }
     }
 
     // Если ни один паттерн не совпал, генерируем ошибку (проверка исчерпаемости)
-    Object::Error(format!("non-exhaustive match pattern for value: {}", value))
+    Object::error(non_exhaustive_match(&value))
+}
+
+/// Вычисляет границы диапазонных паттернов (в том числе вложенных) слева
+/// направо и подставляет их как целочисленные литералы, чтобы при проверке
+/// они не вычислялись повторно. Паттерн без диапазонов не копируется.
+/// Граница, не являющаяся целым числом, — ошибка.
+fn resolve_range_bounds<'p>(
+    pattern: &'p Pattern,
+    env: &Rc<RefCell<Environment>>,
+) -> Result<Cow<'p, Pattern>, Object> {
+    if !contains_range_pattern(pattern) {
+        return Ok(Cow::Borrowed(pattern));
+    }
+    let resolved = match pattern {
+        Pattern::Range(range) => {
+            let start = eval_range_bound(&range.start, env)?;
+            let end = eval_range_bound(&range.end, env)?;
+            let (start, end) =
+                range_pattern_bounds(&start, &end, range.inclusive).map_err(Object::error)?;
+            Pattern::Range(RangePattern {
+                start: Box::new(integer_literal(start)),
+                end: Box::new(integer_literal(end)),
+                inclusive: range.inclusive,
+            })
+        }
+        Pattern::Tuple(patterns) => Pattern::Tuple(
+            patterns
+                .iter()
+                .map(|p| resolve_range_bounds(p, env).map(Cow::into_owned))
+                .collect::<Result<_, _>>()?,
+        ),
+        Pattern::Struct(struct_pattern) => {
+            let mut fields = Vec::with_capacity(struct_pattern.fields.len());
+            for (name, field_pattern) in &struct_pattern.fields {
+                let field_pattern = match field_pattern {
+                    Some(p) => Some(resolve_range_bounds(p, env)?.into_owned()),
+                    None => None,
+                };
+                fields.push((name.clone(), field_pattern));
+            }
+            Pattern::Struct(StructPattern {
+                name: struct_pattern.name.clone(),
+                fields,
+            })
+        }
+        other => other.clone(),
+    };
+    Ok(Cow::Owned(resolved))
+}
+
+fn contains_range_pattern(pattern: &Pattern) -> bool {
+    match pattern {
+        Pattern::Range(_) => true,
+        Pattern::Tuple(patterns) => patterns.iter().any(contains_range_pattern),
+        Pattern::Struct(struct_pattern) => struct_pattern
+            .fields
+            .iter()
+            .any(|(_, p)| p.as_ref().is_some_and(contains_range_pattern)),
+        _ => false,
+    }
+}
+
+/// Граница диапазона; ошибка её вычисления возвращается как `Err`.
+fn eval_range_bound(expr: &Expression, env: &Rc<RefCell<Environment>>) -> Result<Object, Object> {
+    match eval_expression(expr.clone(), Rc::clone(env)) {
+        value if is_abrupt(&value) => Err(value),
+        value => Ok(value),
+    }
+}
+
+fn integer_literal(value: i64) -> Expression {
+    Expression::IntegerLiteral(IntegerLiteral {
+        token: Token::new(TokenType::Int, value.to_string()),
+        value,
+    })
 }
 
 /// Проверяет, совпадает ли паттерн со значением.
@@ -633,37 +1426,40 @@ fn pattern_matches(
             // Wildcard всегда совпадает, но не создает привязок
             Some(vec![])
         }
+        // Вне кортежа парсер остаток не допускает; ведёт себя как идентификатор
+        crate::ast::Pattern::Rest(ident) => Some(vec![(ident.value.clone(), value.clone())]),
         crate::ast::Pattern::Range(range_pattern) => {
-            // Проверяем, попадает ли значение в диапазон
+            // Проверяем, попадает ли значение в диапазон. Границы уже
+            // вычислены resolve_range_bounds и подставлены как литералы.
             let start_val = eval_expression(*range_pattern.start.clone(), Rc::clone(&env));
             let end_val = eval_expression(*range_pattern.end.clone(), env);
-
-            if let (Object::Integer(start), Object::Integer(end), Object::Integer(n)) =
-                (&start_val, &end_val, value)
-            {
-                let in_range = if range_pattern.inclusive {
-                    n >= start && n <= end
-                } else {
-                    n >= start && n < end
-                };
-
-                if in_range {
-                    Some(vec![])
-                } else {
-                    None
-                }
-            } else {
-                None // Типы не совпадают для диапазонного сопоставления
-            }
+            let (Object::Integer(start), Object::Integer(end)) = (start_val, end_val) else {
+                return None;
+            };
+            in_range_pattern(value, start, end, range_pattern.inclusive).then(Vec::new)
         }
         crate::ast::Pattern::Tuple(patterns) => {
             // Проверяем, если значение - это массив с нужным количеством элементов
             if let Object::Array(elements) = value {
-                if patterns.len() != elements.len() {
+                // Остаток `..имя` забирает хвост массива
+                let (patterns, rest) = match patterns.split_last() {
+                    Some((crate::ast::Pattern::Rest(ident), head)) => (head, Some(ident)),
+                    _ => (patterns.as_slice(), None),
+                };
+                if rest.is_none() && patterns.len() != elements.len() {
+                    return None;
+                }
+                if elements.len() < patterns.len() {
                     return None;
                 }
 
                 let mut all_bindings = vec![];
+                if let Some(ident) = rest {
+                    if ident.value != "_" {
+                        let tail = elements[patterns.len()..].to_vec();
+                        all_bindings.push((ident.value.clone(), Object::Array(Rc::new(tail))));
+                    }
+                }
 
                 // Проверяем каждый элемент кортежа
                 for (pattern_elem, value_elem) in patterns.iter().zip(elements.iter()) {
@@ -681,45 +1477,80 @@ fn pattern_matches(
                 None
             }
         }
-        crate::ast::Pattern::Struct(struct_pattern) => {
-            // Проверяем сопоставление структуры
-            if let Object::StructInstance(instance_rc) = value {
+        crate::ast::Pattern::Struct(struct_pattern) => match value {
+            Object::StructInstance(instance_rc) => {
                 let instance = instance_rc.borrow();
-                let struct_def = instance.struct_def.borrow();
-
                 // Проверяем имя структуры
-                if struct_def.name != struct_pattern.name.value {
+                if instance.struct_def.borrow().name != struct_pattern.name.value {
+                    return None;
+                }
+                match_pattern_fields(struct_pattern, &instance.fields, env)
+            }
+            Object::ClassInstance(instance_rc) => {
+                let instance = instance_rc.borrow();
+                // Класс экземпляра или один из его суперклассов
+                if !class_has_name(&instance.class, &struct_pattern.name.value) {
                     return None;
                 }
+                match_pattern_fields(struct_pattern, &instance.fields, env)
+            }
+            _ => None,
+        },
+    }
+}
 
-                let mut all_bindings = vec![];
+/// Сопоставляет поля паттерна `Имя { ... }` с полями экземпляра.
+/// Поле без паттерна привязывается к переменной с тем же именем.
+fn match_pattern_fields(
+    struct_pattern: &crate::ast::StructPattern,
+    fields: &OrderedMap<Object>,
+    env: Rc<RefCell<Environment>>,
+) -> Option<Vec<(String, Object)>> {
+    let mut all_bindings = vec![];
+    for (field_name, field_pattern_opt) in &struct_pattern.fields {
+        // Поле не найдено — паттерн не совпадает
+        let field_value = fields.get(&field_name.value)?;
+        match field_pattern_opt {
+            Some(field_pattern) => all_bindings.extend(pattern_matches(
+                field_pattern,
+                field_value,
+                Rc::clone(&env),
+            )?),
+            None => all_bindings.push((field_name.value.clone(), field_value.clone())),
+        }
+    }
+    Some(all_bindings)
+}
 
-                // Проверяем каждое поле
-                for (field_name, field_pattern_opt) in &struct_pattern.fields {
-                    if let Some(field_value) = instance.fields.get(&field_name.value) {
-                        if let Some(field_pattern) = field_pattern_opt {
-                            // Если для поля указан паттерн, проверяем его
-                            if let Some(bindings) =
-                                pattern_matches(field_pattern, field_value, Rc::clone(&env))
-                            {
-                                all_bindings.extend(bindings);
-                            } else {
-                                return None;
-                            }
-                        } else {
-                            // Если паттерн не указан, просто привязываем переменную
-                            all_bindings.push((field_name.value.clone(), field_value.clone()));
-                        }
-                    } else {
-                        return None; // Поле не найдено
-                    }
-                }
+/// Проверяет, называется ли класс или один из его суперклассов `name`.
+fn class_has_name(class: &Rc<RefCell<Class>>, name: &str) -> bool {
+    let mut current = Some(Rc::clone(class));
+    while let Some(class) = current {
+        if class.borrow().name == name {
+            return true;
+        }
+        current = class.borrow().super_class.clone();
+    }
+    false
+}
 
-                Some(all_bindings)
-            } else {
-                None
-            }
+/// Форма значения для сообщений о неудачной деструктуризации.
+fn describe_value_shape(value: &Object) -> String {
+    match value {
+        Object::Array(elements) => format!("array of {} elements", elements.len()),
+        other => other.type_str().to_string(),
+    }
+}
+
+/// Ожидаемая форма паттерна `let`: `[_, [_, _], ..]`.
+fn describe_pattern_shape(pattern: &crate::ast::Pattern) -> String {
+    match pattern {
+        crate::ast::Pattern::Tuple(patterns) => {
+            let elements: Vec<String> = patterns.iter().map(describe_pattern_shape).collect();
+            format!("[{}]", elements.join(", "))
         }
+        crate::ast::Pattern::Rest(_) => "..".to_string(),
+        _ => "_".to_string(),
     }
 }
 
@@ -733,18 +1564,30 @@ fn bind_method(method_rc: Rc<RefCell<Method>>, instance_rc: &Rc<RefCell<ClassIns
 mod tests {
     use super::*;
     use crate::lexer::Lexer;
-    use crate::object::{Environment, Object};
+    use crate::object::{
+        Closure, Environment, HashKey, Object, RuntimeError, ENVIRONMENT_LIMIT_EXCEEDED,
+    };
     use crate::parser::Parser;
     use std::cell::RefCell;
     use std::rc::Rc;
 
+    /// Ошибка с трассировкой вызовов, как её строит интерпретатор.
+    fn traced_error(message: &str, frames: &[&str]) -> Object {
+        let error = frames
+            .iter()
+            .fold(RuntimeError::new(message), |error, frame| {
+                error.with_frame(*frame)
+            });
+        Object::Error(error)
+    }
+
     fn test_eval(input: &str) -> Object {
         let lexer = Lexer::new(input.to_string());
         let mut parser = Parser::new(lexer);
         let program = match parser.parse_program() {
             Ok(prog) => prog,
             Err(errors) => {
-                return Object::Error(format!(
+                return Object::error(format!(
                     "Parse errors: {:?}",
                     errors
                         .iter()
@@ -778,6 +1621,28 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_null_literal() {
+        let tests = vec![
+            ("null", Object::Null),
+            ("let x = null; x == null", Object::Boolean(true)),
+            ("null != 1", Object::Boolean(true)),
+            ("1 == null", Object::Boolean(false)),
+            (
+                "null < 1",
+                Object::error("type mismatch: NULL < INTEGER at line 1, column 6".to_string()),
+            ),
+            ("!null", Object::Boolean(true)),
+            ("if (null) { 1 } else { 2 }", Object::Integer(2)),
+            ("match null { null => 1, _ => 2 }", Object::Integer(1)),
+            ("match 0 { null => 1, _ => 2 }", Object::Integer(2)),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input), expected, "input: {}", input);
+        }
+    }
+
     #[test]
     fn test_bang_operator() {
         let tests = vec![
@@ -839,7 +1704,7 @@ mod tests {
             ("1 ** 5", Object::Integer(1)),
             (
                 "2 ** -3",
-                Object::Error("negative exponent not supported".to_string()),
+                Object::error("negative exponent not supported at line 1, column 3".to_string()),
             ),
         ];
 
@@ -856,8 +1721,8 @@ mod tests {
             ("\"abc\" * 0", Object::String("".to_string())),
             (
                 "\"abc\" * -1",
-                Object::Error(
-                    "negative multiplier not supported for string multiplication".to_string(),
+                Object::error(
+                    "negative multiplier not supported for string multiplication at line 1, column 7".to_string(),
                 ),
             ),
         ];
@@ -887,6 +1752,28 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_word_logical_operators() {
+        let tests = vec![
+            ("true and false", "true && false"),
+            ("false or true", "false || true"),
+            ("not true", "!true"),
+            (
+                "not false and 1 + 2 == 3 or false",
+                "!false && 1 + 2 == 3 || false",
+            ),
+            (
+                "let x = 2; x > 0 and 10 / x > 1",
+                "let x = 2; x > 0 && 10 / x > 1",
+            ),
+        ];
+        for (words, symbols) in tests {
+            assert_eq!(test_eval(words), test_eval(symbols), "{}", words);
+        }
+        // Слова зарезервированы и не могут быть именами переменных
+        assert!(matches!(test_eval("let and = 1;"), Object::Error(_)));
+    }
+
     #[test]
     fn test_modulo_operator() {
         let tests = vec![
@@ -894,7 +1781,10 @@ mod tests {
             ("-10 % 3", Object::Integer(-1)),
             ("10 % -3", Object::Integer(1)),
             ("-10 % -3", Object::Integer(-1)),
-            ("10 % 0", Object::Error("modulo by zero".to_string())),
+            (
+                "10 % 0",
+                Object::error("modulo by zero at line 1, column 4".to_string()),
+            ),
         ];
 
         for (input, expected) in tests {
@@ -952,6 +1842,21 @@ mod tests {
                 "if (true) { if (true) { return 10; } return 1; }",
                 Object::Integer(10),
             ),
+            ("return;", Object::Null),
+            ("let f = fn() { return; 1 }; f()", Object::Null),
+            (
+                "let f = fn(x) { if (x == null) { return } x }; [f(null), f(2)]",
+                Object::Array(Rc::new(vec![Object::Null, Object::Integer(2)])),
+            ),
+            // return внутри выражения завершает функцию, а не становится значением
+            (
+                "let f = fn() { let x = if (true) { return 5; } else { 1 }; 10 }; f()",
+                Object::Integer(5),
+            ),
+            (
+                "let f = fn() { 1 + if (true) { return 5; } else { 1 } }; f()",
+                Object::Integer(5),
+            ),
         ];
 
         for (input, expected) in tests {
@@ -960,6 +1865,37 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_method_without_return_value() {
+        // Метод, как и функция, возвращает значение последнего выражения;
+        // голый `return` и тело без выражения в конце дают null
+        let input = r#"
+            class Counter {
+                public count = 0;
+                public add(n) {
+                    if (n == null) { return; }
+                    this.count = this.count + n;
+                    this.count
+                }
+                public reset() {
+                    let old = this.count;
+                }
+            }
+            let c = new Counter();
+            [c.add(2), c.add(null), c.add(3), c.reset(), c.count]
+        "#;
+        assert_eq!(
+            test_eval(input),
+            Object::Array(Rc::new(vec![
+                Object::Integer(2),
+                Object::Null,
+                Object::Integer(5),
+                Object::Null,
+                Object::Integer(5),
+            ]))
+        );
+    }
+
     #[test]
     fn test_let_statements() {
         let tests = vec![
@@ -1010,6 +1946,42 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_rest_parameters() {
+        let tests = vec![
+            (
+                "fn sum(...xs) { let total = 0; for (x in xs) { total = total + x; }; total }; sum(1, 2, 3)",
+                Object::Integer(6),
+            ),
+            (
+                "fn f(...xs) { xs }; f(1, 2, 3)",
+                Object::Array(Rc::new(vec![
+                    Object::Integer(1),
+                    Object::Integer(2),
+                    Object::Integer(3),
+                ])),
+            ),
+            ("fn(a, ...rest) { rest }(1)", Object::Array(Rc::new(vec![]))),
+            (
+                "fn(a, ...rest) { rest }()",
+                Object::error("wrong number of arguments: expected at least 1, got 0".to_string()),
+            ),
+            (
+                "fn(a, b) { a }(1)",
+                Object::error("wrong number of arguments: expected 2, got 1".to_string()),
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_empty_program() {
+        assert_eq!(test_eval(""), Object::Null);
+        assert_eq!(test_eval("  // только комментарий\n"), Object::Null);
+    }
+
     #[test]
     fn test_closures() {
         let input = "
@@ -1022,32 +1994,88 @@ mod tests {
         assert_eq!(test_eval(input), Object::Integer(4));
     }
 
+    #[test]
+    fn test_function_display_and_identity() {
+        let function = test_eval("fn(x, y, ...rest) { let z = x + y; z * 2 }");
+        assert_eq!(function.to_string(), "fn(x, y, ...rest) { ... }");
+        assert_eq!(
+            function.function_source().unwrap(),
+            "fn(x, y, ...rest) {\nlet z = (x + y);(z * 2)\n}"
+        );
+        assert_eq!(Object::Integer(1).function_source(), None);
+
+        let tests = vec![
+            ("let f = fn(x) { x }; let g = f; f == g", true),
+            ("let f = fn(x) { x }; let g = f; f != g", false),
+            // Одинаковый текст, но разные функции
+            ("fn(x) { x } == fn(x) { x }", false),
+            ("let f = fn(x) { x }; let g = fn(x) { x }; f != g", true),
+            // Каждый вызов создаёт новое замыкание
+            ("let make = fn() { fn() { 1 } }; make() == make()", false),
+            (
+                "let make = fn() { fn() { 1 } }; let h = make(); [h][0] == h",
+                true,
+            ),
+            // Метод, привязанный к одному экземпляру, равен самому себе
+            (
+                "class C { public m() { 1 } } let c = new C(); c.m == c.m",
+                true,
+            ),
+            ("class C { public m() { 1 } } new C().m == new C().m", false),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input), Object::Boolean(expected), "{}", input);
+        }
+
+        // Сравнение не обходит окружение: функция, видящая саму себя
+        let input = "let f = fn() { f }; [f() == f, f() == fn() { f }]";
+        assert_eq!(test_eval(input).to_string(), "[true, false]");
+    }
+
     #[test]
     fn test_error_handling() {
         let tests = vec![
-            ("5 + true;", "type mismatch: INTEGER + BOOLEAN"),
-            ("5 + true; 5;", "type mismatch: INTEGER + BOOLEAN"),
+            (
+                "5 + true;",
+                "type mismatch: INTEGER + BOOLEAN at line 1, column 3",
+            ),
+            (
+                "5 + true; 5;",
+                "type mismatch: INTEGER + BOOLEAN at line 1, column 3",
+            ),
             ("-true", "unknown operator: -BOOLEAN"),
-            ("true + false", "unknown operator: BOOLEAN + BOOLEAN"),
-            ("5; true + false; 5", "unknown operator: BOOLEAN + BOOLEAN"),
+            (
+                "true + false",
+                "unknown operator: BOOLEAN + BOOLEAN at line 1, column 6",
+            ),
+            (
+                "5; true + false; 5",
+                "unknown operator: BOOLEAN + BOOLEAN at line 1, column 9",
+            ),
             (
                 "if (10 > 1) { true + false; }",
-                "unknown operator: BOOLEAN + BOOLEAN",
+                "unknown operator: BOOLEAN + BOOLEAN at line 1, column 20",
             ),
             (
                 "if (10 > 1) { if (10 > 1) { return true + false; } return 1; }",
-                "unknown operator: BOOLEAN + BOOLEAN",
+                "unknown operator: BOOLEAN + BOOLEAN at line 1, column 41",
             ),
-            ("foobar", "identifier not found: foobar"),
+            ("foobar", "identifier not found: foobar at line 1, column 1"),
             ("let foo = 10; foo();", "not a function: INTEGER"),
-            ("\"abc\" + 1;", "type mismatch: STRING + INTEGER"),
-            ("1 + \"abc\";", "type mismatch: INTEGER + STRING"),
+            (
+                "\"abc\" + 1;",
+                "type mismatch: STRING + INTEGER at line 1, column 7",
+            ),
+            (
+                "1 + \"abc\";",
+                "type mismatch: INTEGER + STRING at line 1, column 3",
+            ),
         ];
 
         for (input, expected_message) in tests {
             let evaluated = test_eval(input);
-            if let Object::Error(msg) = evaluated {
-                assert_eq!(msg, expected_message);
+            if let Object::Error(error) = evaluated {
+                assert_eq!(error.message, expected_message);
             } else {
                 panic!("expected error, got {:?}", evaluated);
             }
@@ -1066,96 +2094,692 @@ mod tests {
         }
     }
 
+    fn class_of(input: &str) -> Rc<RefCell<Class>> {
+        match test_eval(input) {
+            Object::Class(class_obj) => class_obj,
+            other => panic!("expected class object, got {:?}", other),
+        }
+    }
+
     #[test]
-    fn test_struct_declaration() {
-        let input = "struct B {}; B;";
-        let evaluated = test_eval(input);
-        match evaluated {
-            Object::Struct(struct_obj) => {
-                assert_eq!(struct_obj.borrow().name, "B");
+    fn test_dependent_property_defaults() {
+        let input = r#"
+            class Box {
+                public a = 1;
+                public b = a + 1;
+                public c = b * 10;
             }
-            _ => panic!("expected struct object, got {:?}", evaluated),
+            let x = new Box();
+            let y = new Box(5);
+            [x.a, x.b, x.c, y.a, y.b];
+        "#;
+        for _ in 0..100 {
+            assert_eq!(test_eval(input).to_string(), "[1, 2, 20, 5, 2]");
         }
+
+        // Свойство не видит объявленные после него
+        assert_eq!(
+            test_eval("class Late { public a = b; public b = 1; }"),
+            Object::error("identifier not found: b at line 1, column 25".to_string())
+        );
     }
 
     #[test]
-    fn test_new_expression() {
+    fn test_class_properties_in_method_scope() {
+        let circle = r#"
+            let scale = 100;
+            let limit = 7;
+            class Circle {
+                public pi = 3;
+                public r = 1;
+                public area() { return pi * this.r * this.r; }
+                public defaultArea() { return pi * r * r; }
+                public withPi(pi) { return pi; }
+                public scaled() { return scale * pi; }
+                public bound() { return limit; }
+            }
+            class Ring extends Circle {
+                public limit = 9;
+                public perimeter() { return 2 * pi * this.r; }
+                public bound() { return limit; }
+            }
+        "#;
         let tests = vec![
-            ("class MyClass {}; new MyClass();", "instance of MyClass"),
+            // Свойство класса по имени внутри метода
+            ("new Circle(3, 2).area();", Object::Integer(12)),
+            // Поле экземпляра через this перекрывает значение класса
             (
-                "struct MyStruct {}; new MyStruct();",
-                "instance of struct MyStruct",
+                "let c = new Circle(3, 5); [c.defaultArea(), c.area()];",
+                Object::Array(Rc::new(vec![Object::Integer(3), Object::Integer(75)])),
+            ),
+            // Параметр перекрывает свойство класса
+            ("new Circle().withPi(4);", Object::Integer(4)),
+            // Свойство класса перекрывает внешнее окружение, остальное видно
+            ("new Circle().scaled();", Object::Integer(300)),
+            ("new Circle().bound();", Object::Integer(7)),
+            ("new Ring().bound();", Object::Integer(9)),
+            // Метод подкласса видит свойство суперкласса
+            ("new Ring().perimeter();", Object::Integer(6)),
+            // Явное обращение через имя класса
+            (
+                "[Circle.pi, Ring.pi, Ring.limit];",
+                Object::Array(Rc::new(vec![
+                    Object::Integer(3),
+                    Object::Integer(3),
+                    Object::Integer(9),
+                ])),
+            ),
+            (
+                "Circle.limit;",
+                Object::error("property 'limit' not found on class 'Circle'".to_string()),
             ),
-            ("let a = 10; new a();", "ERROR: not a class or struct: a"),
-            ("new NonExistent();", "ERROR: type not found: NonExistent"),
         ];
 
         for (input, expected) in tests {
-            let evaluated = test_eval(input);
-            assert_eq!(evaluated.to_string(), expected);
+            let source = format!("{}{}", circle, input);
+            assert_eq!(test_eval(&source), expected, "input: {}", input);
         }
     }
 
     #[test]
-    fn test_interface_declaration() {
-        let input = "interface C {}; C;";
-        let evaluated = test_eval(input);
-        match evaluated {
-            Object::Interface(interface_obj) => {
-                assert_eq!(interface_obj.borrow().name, "C");
+    fn test_bare_sibling_method_calls() {
+        let classes = r#"
+            class Shape {
+                public size = 1;
+                public unit() { return 10; }
+                private secret() { return 5; }
+                public twice() { return 2 * unit(); }
+                public shadowed() { let unit = fn() { 1 }; return unit(); }
+                public hidden() { return secret(); }
+                public field() { return extra; }
+                public stored() { this.size = 3; return [size, this.size]; }
+                public reference() { let f = unit; return f(); }
+                public len() { return 7; }
+                public measured() { return this.len() + len([1, 2]); }
             }
-            _ => panic!("expected interface object, got {:?}", evaluated),
-        }
-    }
-
-    #[test]
-    fn test_class_member_evaluation() {
+            class Square extends Shape {
+                public unit() { return 20; }
+                public inherited() { return twice() + size; }
+                public nested() { let g = fn() { unit() }; return g(); }
+                public scaled(k) { return k * unit(); }
+                public unscaled() { return scaled(); }
+            }
+        "#;
         let tests = vec![
+            ("new Shape().twice();", Object::Integer(20)),
+            // Вызов без this. идёт через экземпляр: переопределение подкласса
+            ("new Square().twice();", Object::Integer(40)),
+            ("new Square().inherited();", Object::Integer(41)),
+            // Локальная переменная перекрывает метод
+            ("new Shape().shadowed();", Object::Integer(1)),
+            // Закрытый метод доступен изнутри класса
+            ("new Shape().hidden();", Object::Integer(5)),
+            ("new Square().nested();", Object::Integer(20)),
+            ("new Shape().reference();", Object::Integer(10)),
+            // Вызов соседнего метода проверяет число аргументов
             (
-                r#"
-                class Point {
-                    public x = 10;
-                    public y;
-                    public getX() {
-                        return this.x;
-                    }
-                }
-                let p = new Point();
-                p.x;
-                "#,
-                Object::Integer(10),
+                "new Square().unscaled();",
+                traced_error(
+                    "wrong number of arguments: expected 1, got 0",
+                    &["Square.unscaled()", "main"],
+                ),
             ),
+            // Встроенная функция перекрывает метод с тем же именем: сам
+            // метод вызывается через this.
+            ("new Shape().measured();", Object::Integer(9)),
+            // Поля экземпляра без this. не видны, в отличие от методов
             (
-                r#"
-                class Point {
-                    public x = 10;
-                    public y;
-                    public getX() {
-                        return this.x;
-                    }
-                }
-                let p = new Point();
-                p.y;
-                "#,
-                Object::Null,
+                "new Shape().field();",
+                traced_error(
+                    "identifier not found: extra at line 9, column 41",
+                    &["Shape.field()", "main"],
+                ),
             ),
+            // Имя поля — значение по умолчанию из класса, а не поле экземпляра
             (
-                r#"
-                class Point {
-                    public x = 10;
-                    public y = 20;
-                    public getX() {
-                        return this.x;
-                    }
-                }
-                let p = new Point();
-                p.getX();
-                "#,
-                Object::Integer(10),
+                "new Shape().stored();",
+                Object::Array(Rc::new(vec![Object::Integer(1), Object::Integer(3)])),
             ),
+            // Вне методов имя метода не определено
             (
-                r#"
-                class Adder {
+                "unit();",
+                Object::error("identifier not found: unit at line 22, column 17".to_string()),
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let source = format!("{}        {}", classes, input);
+            assert_eq!(test_eval(&source), expected, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_class_display_order() {
+        let class_obj = class_of(
+            r#"
+            class Point {
+                public z = 3;
+                public a = 1;
+                public m = "mid";
+                public norm() { return 0; }
+                public add(other) { return this; }
+                public zero() { return 0; }
+            }
+            Point;
+        "#,
+        );
+        let class_obj = class_obj.borrow();
+        let properties: Vec<&String> = class_obj.properties.keys().collect();
+        assert_eq!(properties, vec!["z", "a", "m"]);
+        assert_eq!(
+            class_obj.to_string(),
+            "class Point { z = 3; a = 1; m = mid; norm(); add(other); zero() }"
+        );
+        assert_eq!(
+            class_of("class Empty {}; Empty;").borrow().to_string(),
+            "class Empty {}"
+        );
+    }
+
+    #[test]
+    fn test_inherited_method_order() {
+        let input = r#"
+            class Animal {
+                public speak() { return "..."; }
+                public eat(food) { return food; }
+            }
+            class Dog extends Animal {
+                public fetch(item) { return item; }
+                public speak() { return "woof"; }
+            }
+        "#;
+        let dog = class_of(&format!("{} Dog;", input));
+        assert_eq!(dog.borrow().method_names(), vec!["speak", "eat", "fetch"]);
+        assert_eq!(
+            dog.borrow().to_string(),
+            "class Dog extends Animal { speak(); eat(food); fetch(item) }"
+        );
+        assert_eq!(
+            test_eval(&format!("{} new Dog().speak();", input)),
+            Object::String("woof".to_string())
+        );
+    }
+
+    #[test]
+    fn test_method_resolution_cache() {
+        let input = r#"
+            class A { public base = 1; public m() { "A" } public n() { "A" } }
+            class B extends A { public m() { "B" } }
+            class C extends B {}
+            class D extends C {}
+        "#;
+        let d = class_of(&format!("{} D;", input));
+        for _ in 0..3 {
+            let method = d.borrow().find_method("n").unwrap();
+            assert_eq!(method.borrow().name, "n");
+            assert_eq!(d.borrow().find_property("base"), Some(Object::Integer(1)));
+            assert_eq!(d.borrow().find_method("missing"), None);
+        }
+        // По одному проходу цепочки на каждое имя
+        assert_eq!(d.borrow().member_cache.chain_walks(), 3);
+
+        // Родитель разрешил `m` раньше, но переопределение в подклассе важнее
+        let program = format!(
+            "{} let a = new A(); let d = new D(); [a.m(), d.m(), a.m(), d.m(), d.n(), d.base]",
+            input
+        );
+        assert_eq!(test_eval(&program).to_string(), "[A, B, A, B, A, 1]");
+    }
+
+    #[test]
+    fn test_struct_fields() {
+        let prelude = "let base = 10; struct P { let x = base; let y = base + 1; let z; };";
+        let tests = vec![
+            ("let p = new P(); [p.x, p.y, p.z]", "[10, 11, null]"),
+            ("let p = new P(1, 2); [p.x, p.y, p.z]", "[1, 2, null]"),
+            ("let p = new P(); p.z = 5; p.z", "5"),
+            (
+                "new P(1, 2, 3, 4)",
+                "ERROR: too many arguments to new P: got=4, want<=3",
+            ),
+            ("new P().w", "ERROR: property 'w' not found on struct 'P'"),
+        ];
+        for (input, expected) in tests {
+            let source = format!("{} {}", prelude, input);
+            assert_eq!(test_eval(&source).to_string(), expected, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_struct_value_semantics() {
+        let prelude = "struct P { let x = 0; let y = 0; }; \
+                       struct In { let v = 0; }; struct Out { let inner; }; \
+                       class C { let n = 0; }; struct H { let c; };";
+        let tests = vec![
+            // Присваивание и передача в функцию копируют экземпляр
+            (
+                "let a = new P(1, 2); let b = a; b.x = 10; [a.x, b.x]",
+                "[1, 10]",
+            ),
+            (
+                "let f = fn(p) { p.x = 99; p.x }; let a = new P(1); [f(a), a.x]",
+                "[99, 1]",
+            ),
+            (
+                "let a = new P(); let b = new P(); a.x = 1; [a.x, b.x]",
+                "[1, 0]",
+            ),
+            // Вложенная структура копируется вместе с внешней
+            (
+                "let o = new Out(new In(1)); let c = o; c.inner.v = 5; [o.inner.v, c.inner.v]",
+                "[1, 5]",
+            ),
+            (
+                "let arr = [new P(1)]; let arr2 = arr; arr2[0].x = 7; [arr[0].x, arr2[0].x]",
+                "[1, 7]",
+            ),
+            // Экземпляр класса в поле остаётся общим
+            ("let h = new H(new C()); let h2 = h; h2.c.n = 3; h.c.n", "3"),
+            // Результат присваивания — значение; временный экземпляр изменить нельзя
+            ("let p = new P(); p.y = 4", "4"),
+            ("new P().x = 1", "ERROR: invalid assignment target: new P()"),
+            (
+                "let p = new P(); p.w = 1",
+                "ERROR: property 'w' not found on struct 'P'",
+            ),
+            // Равенство — по полям
+            ("new P(1, 2) == new P(1, 2)", "true"),
+            ("new P(1, 2) != new P(1, 3)", "true"),
+            ("let a = new P(); let b = a; b.x = 0; a == b", "true"),
+            ("new Out(new In(1)) == new Out(new In(1))", "true"),
+            ("new Out(new In(1)) == new Out(new In(2))", "false"),
+            ("new Out([new In()]) == new Out([new In()])", "true"),
+            ("new H(new C()) == new H(new C())", "false"),
+            ("let c = new C(); new H(c) == new H(c)", "true"),
+            ("new P() == new In()", "false"),
+            ("new P(1, \"a\")", "P { x: 1, y: a }"),
+            ("new Out(new In())", "Out { inner: In { v: 0 } }"),
+        ];
+        for (input, expected) in tests {
+            let source = format!("{} {}", prelude, input);
+            assert_eq!(test_eval(&source).to_string(), expected, "input: {}", input);
+        }
+
+        // Экземпляр структуры не может быть ключом хэша
+        let key = test_eval(&format!("{} new P()", prelude));
+        let hash = Object::Hash(Rc::new(OrderedMap::new()));
+        assert_eq!(
+            index_value(&hash, &key),
+            Err("unusable as hash key: STRUCT_INSTANCE".to_string())
+        );
+    }
+
+    #[test]
+    fn test_ordered_map() {
+        let mut map = OrderedMap::new();
+        assert!(map.is_empty());
+        assert_eq!(map.insert("b".to_string(), 1), None);
+        assert_eq!(map.insert("a".to_string(), 2), None);
+        assert_eq!(map.insert("b".to_string(), 3), Some(1));
+        assert_eq!(map.len(), 2);
+        assert_eq!(map["b"], 3);
+        assert!(map.contains_key("a"));
+        assert_eq!(map.get("missing"), None);
+        let entries: Vec<(&String, &i32)> = map.iter().collect();
+        assert_eq!(
+            entries,
+            vec![(&"b".to_string(), &3), (&"a".to_string(), &2)]
+        );
+    }
+
+    #[test]
+    fn test_struct_declaration() {
+        let input = "struct B {}; B;";
+        let evaluated = test_eval(input);
+        match evaluated {
+            Object::Struct(struct_obj) => {
+                assert_eq!(struct_obj.borrow().name, "B");
+            }
+            _ => panic!("expected struct object, got {:?}", evaluated),
+        }
+    }
+
+    #[test]
+    fn test_new_expression() {
+        let tests = vec![
+            ("class MyClass {}; new MyClass();", "instance of MyClass"),
+            ("struct MyStruct {}; new MyStruct();", "MyStruct {}"),
+            ("let a = 10; new a();", "ERROR: not a class or struct: a"),
+            ("new NonExistent();", "ERROR: type not found: NonExistent"),
+        ];
+
+        for (input, expected) in tests {
+            let evaluated = test_eval(input);
+            assert_eq!(evaluated.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn test_interface_declaration() {
+        let input = "interface C {}; C;";
+        let evaluated = test_eval(input);
+        match evaluated {
+            Object::Interface(interface_obj) => {
+                assert_eq!(interface_obj.borrow().name, "C");
+            }
+            _ => panic!("expected interface object, got {:?}", evaluated),
+        }
+    }
+
+    #[test]
+    fn test_interface_records_signatures() {
+        let input = "interface Shape { fn area(); fn scale(k); } Shape;";
+        match test_eval(input) {
+            Object::Interface(interface) => {
+                let interface = interface.borrow();
+                assert_eq!(interface.method_signatures.len(), 2);
+                assert_eq!(interface.method_signatures["scale"].parameters.len(), 1);
+            }
+            other => panic!("expected interface object, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_deep_hierarchy_calls_use_method_cache() {
+        let input = r#"
+            class L1 { public base() { 1 } public step(x) { x + base() } }
+            class L2 extends L1 {}
+            class L3 extends L2 { public base() { 3 } }
+            class L4 extends L3 {}
+            class L5 extends L4 {}
+            let obj = new L5();
+            let total = 0;
+            for (i in 0..100) { total = obj.step(total); }
+            total;
+        "#;
+        // Окружение REPL: ниже L1 объявляется повторно
+        let env = Rc::new(RefCell::new(Environment::allowing_redefinition()));
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        assert_eq!(
+            eval(Node::Program(program), Rc::clone(&env)),
+            Object::Integer(300)
+        );
+
+        // 200 обращений к методам, но цепочка из пяти классов пройдена
+        // по одному разу на имя: `step` и `base`
+        let Some(Object::Class(l5)) = env.borrow().get("L5") else {
+            panic!("L5 is not a class");
+        };
+        assert_eq!(l5.borrow().member_cache.chain_walks(), 2);
+        // Промежуточные классы поиск L5 не заполняет
+        let Some(Object::Class(l3)) = env.borrow().get("L3") else {
+            panic!("L3 is not a class");
+        };
+        assert_eq!(l3.borrow().member_cache.chain_walks(), 0);
+
+        // Новое объявление L1 — новый класс со своим кэшем; L5 по-прежнему
+        // наследует от прежнего L1
+        let redefined = "class L1 { public base() { 10 } public step(x) { x * base() } }
+            class L6 extends L1 {}
+            [new L5().step(1), new L6().step(1)];";
+        let program = Parser::new(Lexer::new(redefined.to_string()))
+            .parse_program()
+            .unwrap();
+        assert_eq!(eval(Node::Program(program), env).to_string(), "[4, 10]");
+    }
+
+    #[test]
+    fn test_type_redeclaration_policy() {
+        let tests = [
+            ("class A {} class A {}", "ERROR: class 'A' is already defined"),
+            ("struct S {} struct S {}", "ERROR: struct 'S' is already defined"),
+            (
+                "interface I { fn f(); } interface I { fn g(); }",
+                "ERROR: interface 'I' is already defined",
+            ),
+            // Имя уже занято типом другого вида — в сообщении прежний вид
+            ("struct S {} class S {}", "ERROR: struct 'S' is already defined"),
+            // Столкновение с `let` — в обоих порядках
+            ("let A = 5; class A {}", "ERROR: class 'A' is already defined"),
+            ("class A {} let A = 5;", "ERROR: class 'A' is already defined"),
+            ("let f = fn() { 1 }; interface f {}", "ERROR: interface 'f' is already defined"),
+            // Блок `if` не открывает область видимости
+            ("class A {} if (true) { class A {} }", "ERROR: class 'A' is already defined"),
+            // Повторный `let` по-прежнему разрешён
+            ("let x = 1; let x = 2; x", "2"),
+            // Во вложенной области имя можно перекрыть
+            (
+                "class A { public v = 1; } let f = fn() { class A { public v = 2; } new A().v }; [f(), new A().v]",
+                "[2, 1]",
+            ),
+            (
+                "let A = 5; let f = fn() { class A {} 1 }; f() + A",
+                "6",
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).to_string(), expected, "input: {}", input);
+        }
+
+        // В окружении REPL повторное объявление заменяет прежнее
+        let env = Rc::new(RefCell::new(Environment::allowing_redefinition()));
+        let input = "class A { public v = 1; } class A { public v = 2; } let v = new A().v; let A = 5; [v, A]";
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        assert_eq!(eval(Node::Program(program), env).to_string(), "[2, 5]");
+    }
+
+    #[test]
+    fn test_interface_default_methods() {
+        let prelude = r#"
+            interface Greeter {
+                fn name();
+                fn greet() { return "hi, " + this.name(); }
+            }
+            interface Polite { fn greet() { return "good day"; } }
+            class Plain implements Greeter { public name() { return "plain"; } }
+            class Custom implements Greeter {
+                public name() { return "custom"; }
+                public greet() { return "yo"; }
+            }
+            class Heir extends Plain {}
+        "#;
+        let tests = vec![
+            ("new Plain().greet()", "hi, plain"),
+            ("new Custom().greet()", "yo"),
+            ("new Heir().greet()", "hi, plain"),
+            ("new Plain() is Greeter", "true"),
+            (
+                "class Both implements Greeter, Polite { public name() { return 1; } }",
+                "ERROR: class Both inherits conflicting default method 'greet' from interfaces Greeter and Polite",
+            ),
+            (
+                "class Both implements Polite, Greeter { public name() { return 1; } }",
+                "ERROR: class Both inherits conflicting default method 'greet' from interfaces Polite and Greeter",
+            ),
+            (
+                "class Both implements Greeter, Polite { public greet() { return 2; } } new Both().greet()",
+                "2",
+            ),
+        ];
+        for (input, expected) in tests {
+            let source = format!("{}{}", prelude, input);
+            assert_eq!(test_eval(&source).to_string(), expected, "input: {}", input);
+        }
+
+        match test_eval(&format!("{} Greeter", prelude)) {
+            Object::Interface(interface) => {
+                let interface = interface.borrow();
+                assert_eq!(interface.method_signatures.len(), 1);
+                assert!(interface.default_methods.contains_key("greet"));
+            }
+            other => panic!("expected interface object, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_is_operator() {
+        let prelude = r#"
+            interface Speaker { fn speak(); }
+            class Declared implements Speaker { public speak() { return "declared"; } }
+            class Child extends Declared {}
+            class Structural { public speak() { return "structural"; } }
+            class WrongArity { public speak(loudly) { return "wrong"; } }
+            class Silent {}
+        "#;
+        let tests = vec![
+            ("new Declared() is Speaker", Object::Boolean(true)),
+            ("new Child() is Speaker", Object::Boolean(true)),
+            // Структурное соответствие без `implements` тоже засчитывается
+            ("new Structural() is Speaker", Object::Boolean(true)),
+            ("new WrongArity() is Speaker", Object::Boolean(false)),
+            ("new Silent() is Speaker", Object::Boolean(false)),
+            ("5 is Speaker", Object::Boolean(false)),
+            (
+                "if (new Silent() is Speaker) { 1 } else { 2 }",
+                Object::Integer(2),
+            ),
+            ("new Declared() is Declared", Object::Boolean(true)),
+            ("new Child() is Declared", Object::Boolean(true)),
+            ("new Declared() is Child", Object::Boolean(false)),
+            (
+                "new Declared() is 5",
+                Object::error("right operand of 'is' must be a type, got INTEGER".to_string()),
+            ),
+            (
+                "class Bad implements Silent {}",
+                Object::error("Silent is not an interface, got CLASS".to_string()),
+            ),
+            (
+                "class Bad implements Missing {}",
+                Object::error("identifier not found: Missing at line 8, column 30".to_string()),
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let source = format!("{}{}", prelude, input);
+            assert_eq!(test_eval(&source), expected, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_is_type_test() {
+        let prelude = r#"
+            class Animal {}
+            class Dog extends Animal {}
+            class Cat extends Animal {}
+            struct Point {}
+        "#;
+        let tests = vec![
+            ("5 is Integer", true),
+            ("5 is String", false),
+            ("\"a\" is String", true),
+            ("true is Boolean", true),
+            ("[1, 2] is Array", true),
+            ("(1..3) is Range", true),
+            ("let nothing = if (false) { 1 }; nothing is Null", true),
+            ("fn(x) { x } is Function", true),
+            ("collect is Function", true),
+            ("new Dog() is Animal", true),
+            ("new Dog() is Dog", true),
+            ("new Dog() is Cat", false),
+            ("new Animal() is Dog", false),
+            ("new Dog() is Integer", false),
+            ("5 is Animal", false),
+            ("new Point() is Point", true),
+            ("new Dog() is Point", false),
+            // Пользовательская переменная перекрывает имя встроенного типа
+            ("let Integer = Animal; new Dog() is Integer", true),
+        ];
+
+        for (input, expected) in tests {
+            let source = format!("{}{}", prelude, input);
+            assert_eq!(
+                test_eval(&source),
+                Object::Boolean(expected),
+                "input: {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_is_operator_in_match_guard() {
+        let input = r#"
+            interface Speaker { fn speak(); }
+            interface Counter { fn count(); }
+            class Dog implements Speaker { public speak() { return "woof"; } }
+            class Clock { public count() { return 12; } }
+            let describe = fn(x) {
+                match x {
+                    s if s is Speaker => s.speak(),
+                    c if c is Counter => c.count(),
+                    _ => "unknown",
+                }
+            };
+            [describe(new Dog()), describe(new Clock()), describe(1)];
+        "#;
+        assert_eq!(
+            test_eval(input),
+            Object::Array(Rc::new(vec![
+                Object::String("woof".to_string()),
+                Object::Integer(12),
+                Object::String("unknown".to_string()),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_class_member_evaluation() {
+        let tests = vec![
+            (
+                r#"
+                class Point {
+                    public x = 10;
+                    public y;
+                    public getX() {
+                        return this.x;
+                    }
+                }
+                let p = new Point();
+                p.x;
+                "#,
+                Object::Integer(10),
+            ),
+            (
+                r#"
+                class Point {
+                    public x = 10;
+                    public y;
+                    public getX() {
+                        return this.x;
+                    }
+                }
+                let p = new Point();
+                p.y;
+                "#,
+                Object::Null,
+            ),
+            (
+                r#"
+                class Point {
+                    public x = 10;
+                    public y = 20;
+                    public getX() {
+                        return this.x;
+                    }
+                }
+                let p = new Point();
+                p.getX();
+                "#,
+                Object::Integer(10),
+            ),
+            (
+                r#"
+                class Adder {
                     public a = 1;
                     public b = 2;
                     public sum() {
@@ -1204,7 +2828,7 @@ mod tests {
                 let t = new Test();
                 t.nonexistent;
                 "#,
-                Object::Error("property 'nonexistent' not found on class 'Test'".to_string()),
+                Object::error("property 'nonexistent' not found on class 'Test'".to_string()),
             ),
         ];
 
@@ -1240,7 +2864,7 @@ mod tests {
         let evaluated = test_eval(input);
         assert_eq!(
             evaluated,
-            Object::Error("'this' can only be used inside a method".to_string())
+            Object::error("'this' can only be used inside a method".to_string())
         );
     }
 
@@ -1282,13 +2906,13 @@ mod tests {
                 let NotAClass = 10;
                 class B extends NotAClass {}
                 "#,
-                Object::Error("super class must be a class, got INTEGER".to_string()),
+                Object::error("super class must be a class, got INTEGER".to_string()),
             ),
             (
                 r#"
                 class B extends NonExistent {}
                 "#,
-                Object::Error("identifier not found: NonExistent".to_string()),
+                Object::error("identifier not found: NonExistent at line 2, column 33".to_string()),
             ),
             (
                 r#"
@@ -1326,6 +2950,22 @@ mod tests {
         assert_eq!(evaluated, Object::Integer(20));
     }
 
+    #[test]
+    fn test_match_block_bodies() {
+        let input = r#"
+            let double = fn(x) { x * 2 };
+            let f = fn(x) {
+                match x {
+                    1 => { let t = double(x); t * 10 }
+                    n if n > 5 => { let big = n; }
+                    _ => x,
+                }
+            };
+            [f(1), f(7), f(3)]
+        "#;
+        assert_eq!(test_eval(input).to_string(), "[20, null, 3]");
+    }
+
     #[test]
     fn test_match_with_identifier_pattern() {
         // Тест сопоставления с идентификаторным паттерном (привязка переменной)
@@ -1370,18 +3010,58 @@ mod tests {
     }
 
     #[test]
-    fn test_match_non_exhaustive() {
-        // Тест проверки исчерпаемости паттернов (non-exhaustive match)
-        let input = r#"
-            let x = 99;
-            match x {
-                1 => 10,
+    fn test_match_guard_bindings() {
+        let tests = vec![
+            // Гард и тело видят одну и ту же привязку; внешняя n не меняется
+            (
+                "let n = 100; let r = match 5 { n if n == 5 => n * 2, _ => 0 }; [r, n]",
+                "[10, 100]",
+            ),
+            // Присваивание привязке в гарде видно в теле
+            (
+                "let r = match 1 { n if (n = n + 1) > 1 => n, _ => 0 }; r",
+                "2",
+            ),
+            // Гарды проверяются по порядку, каждый не более одного раза;
+            // привязки неудачной ветви не попадают в следующую
+            (
+                "let calls = 0; let r = match 3 { a if (calls = calls + a) > 100 => 1, b if b > 2 => b, _ => 0 }; [r, calls]",
+                "[3, 3]",
+            ),
+            (
+                "match 3 { a if false => 1, _ => a }",
+                "ERROR: identifier not found: a at line 1, column 33",
+            ),
+            // Гард видит все привязки кортежного паттерна, тело — их
+            // изменения в гарде
+            (
+                "match [1, 2] { [a, b] if (a = a + b) > b => [a, b], _ => 0 }",
+                "[3, 2]",
+            ),
+            // Гард и тело получают один и тот же экземпляр, а не копию
+            (
+                "class Box { public v; } let o = new Box(1); let seen = null; match o { p if (seen = p) == o => seen == p, _ => false }",
+                "true",
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).to_string(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_match_non_exhaustive() {
+        // Тест проверки исчерпаемости паттернов (non-exhaustive match)
+        let input = r#"
+            let x = 99;
+            match x {
+                1 => 10,
                 2 => 20,
             }
         "#;
         let evaluated = test_eval(input);
-        if let Object::Error(msg) = evaluated {
-            assert!(msg.contains("non-exhaustive match"));
+        if let Object::Error(error) = evaluated {
+            assert!(error.message.contains("non-exhaustive match"));
         } else {
             panic!(
                 "expected error for non-exhaustive match, got {:?}",
@@ -1406,6 +3086,101 @@ mod tests {
         assert_eq!(evaluated, Object::Integer(20));
     }
 
+    #[test]
+    fn test_range_pattern_bounds_evaluated_once() {
+        // Границы вычисляются один раз на попытку ветви и только до совпадения
+        let input = r#"
+            let calls = 0;
+            let hi = fn() { calls = calls + 1; 10 };
+            let classify = fn(n) {
+                match n {
+                    0..(hi()) => "low",
+                    10..(hi() + 10) => "mid",
+                    _ => "high",
+                }
+            };
+            [classify(5), classify(15), classify(25), calls, [classify(3)][0], calls];
+        "#;
+        assert_eq!(test_eval(input).to_string(), "[low, mid, high, 5, low, 6]");
+
+        let input = r#"
+            let calls = 0;
+            let hi = fn() { calls = calls + 1; 10 };
+            let hit = if let [0..(hi()), 0..(hi())] = [1, 2] { 1 } else { 0 };
+            [hit, calls];
+        "#;
+        assert_eq!(test_eval(input).to_string(), "[1, 2]");
+    }
+
+    #[test]
+    fn test_range_pattern_non_integer_bounds() {
+        let tests = vec![
+            (
+                r#"match 5 { "a".."z" => 1, _ => 0 }"#,
+                "range pattern bounds must be integers, got STRING..STRING",
+            ),
+            (
+                r#"match 5 { 1..true => 1, _ => 0 }"#,
+                "range pattern bounds must be integers, got INTEGER..BOOLEAN",
+            ),
+            (
+                r#"if let [_, 1.."b"] = [1, 2] { 1 } else { 0 }"#,
+                "range pattern bounds must be integers, got INTEGER..STRING",
+            ),
+            // Ветвь, до которой дело не дошло, границы не вычисляет
+            (r#"match 5 { 5 => 1, "a".."z" => 2 }"#, "1"),
+        ];
+        for (input, expected) in tests {
+            let evaluated = test_eval(input);
+            let actual = match &evaluated {
+                Object::Error(error) => error.to_string(),
+                other => other.to_string(),
+            };
+            assert_eq!(actual, expected, "input: {}", input);
+        }
+        // Нецелое значение по-прежнему просто не попадает в диапазон
+        assert_eq!(
+            test_eval(r#"match "s" { 1..5 => 1, _ => 0 }"#),
+            Object::Integer(0)
+        );
+    }
+
+    #[test]
+    fn test_empty_range_pattern() {
+        let tests = vec![
+            ("match 3 { 5..1 => 1, _ => 0 }", "empty range pattern 5..1"),
+            (
+                "match 3 { 5..=1 => 1, _ => 0 }",
+                "empty range pattern 5..=1",
+            ),
+            // Границы из переменных проверяются при первом вычислении ветви
+            (
+                "let hi = -1; match 3 { 0 => 0, 1..hi => 1, _ => 2 }",
+                "empty range pattern 1..-1",
+            ),
+            (
+                "if let [_, 4..2] = [1, 2] { 1 } else { 0 }",
+                "empty range pattern 4..2",
+            ),
+            // Ветвь до совпадения не вычисляется; пустой, но не обратный
+            // диапазон допустим
+            ("match 3 { 3 => 1, 5..1 => 2 }", "1"),
+            ("match 3 { 3..3 => 1, 3..=3 => 2, _ => 0 }", "2"),
+        ];
+        for (input, expected) in tests {
+            let actual = match test_eval(input) {
+                Object::Error(error) => error.to_string(),
+                other => other.to_string(),
+            };
+            assert_eq!(actual, expected, "input: {}", input);
+        }
+
+        // Диапазон-значение с обратными границами создать можно, он просто
+        // пуст: обход не выполняет ни одной итерации
+        let input = "let r = 5..1; let n = 0; for (i in r) { n = n + 1; }; [r, collect(r), n]";
+        assert_eq!(test_eval(input).to_string(), "[5..1, [], 0]");
+    }
+
     #[test]
     fn test_match_boolean_patterns() {
         // Тест сопоставления с булевыми паттернами
@@ -1449,6 +3224,143 @@ mod tests {
         assert_eq!(evaluated, Object::Integer(3));
     }
 
+    #[test]
+    fn test_match_class_pattern() {
+        let prelude = r#"
+            class Point {
+                public x = 0;
+                public y = 0;
+            }
+            class Point3 extends Point {
+                public z = 0;
+            }
+            class Other {
+                public x = 0;
+            }
+            struct Marker {}
+            let sum = fn(v) {
+                match v {
+                    Point { x: 0, y } => y * 100,
+                    Point { x, y } => x + y,
+                    Other { x } => x * 10,
+                    Marker {} => -1,
+                    _ => 0,
+                }
+            };
+        "#;
+        let tests = vec![
+            ("sum(new Point(1, 2))", 3),
+            ("sum(new Point(0, 2))", 200),
+            // Экземпляр подкласса совпадает с паттерном суперкласса
+            ("match new Point3(6) { Point { z } => z, _ => 0 }", 6),
+            ("sum(new Other(7))", 70),
+            ("sum(new Marker())", -1),
+            ("sum(5)", 0),
+            ("match new Other(1) { Other { missing } => 1, _ => 2 }", 2),
+        ];
+        for (input, expected) in tests {
+            let source = format!("{}{}", prelude, input);
+            assert_eq!(
+                test_eval(&source),
+                Object::Integer(expected),
+                "input: {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_if_let_expression() {
+        let tests = vec![
+            (
+                "if let [a, b] = [1, 2] { a + b } else { 0 }",
+                Object::Integer(3),
+            ),
+            (
+                "if let [a, b] = [1, 2, 3] { a + b } else { 0 }",
+                Object::Integer(0),
+            ),
+            (
+                "if let [a, 2] = [5, 2] { a } else { 0 }",
+                Object::Integer(5),
+            ),
+            (
+                "if let [a, 2] = [5, 3] { a } else { 0 }",
+                Object::Integer(0),
+            ),
+            ("if let [a, b] = 7 { a } else { -1 }", Object::Integer(-1)),
+            ("if let [a, b] = 7 { a }", Object::Null),
+            (
+                "if let 1..5 = 3 { true } else { false }",
+                Object::Boolean(true),
+            ),
+            // Привязки видны только в consequence
+            ("let a = 10; if let [a] = [1] { a }; a", Object::Integer(10)),
+            (
+                "if let [a] = 1 / 0 { a }",
+                Object::error("division by zero at line 1, column 16".to_string()),
+            ),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input), expected, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_let_destructuring() {
+        let tests = vec![
+            ("let [a, b, c] = [1, 2, 3]; a + b * c", "7"),
+            ("let [a, [b, c]] = [1, [2, 3]]; [a, b, c]", "[1, 2, 3]"),
+            (
+                "let [first, ..rest] = [1, 2, 3]; [first, rest]",
+                "[1, [2, 3]]",
+            ),
+            ("let [first, ..rest] = [1]; rest", "[]"),
+            ("let [_, second, .._] = [1, 2, 3, 4]; second", "2"),
+            ("let xs = [4, 5]; let [x, y] = xs; x * y", "20"),
+            (
+                "let [a, b] = [1, 2, 3];",
+                "ERROR: cannot destructure array of 3 elements: expected [_, _]",
+            ),
+            (
+                "let [a, b, ..rest] = [1];",
+                "ERROR: cannot destructure array of 1 elements: expected [_, _, ..]",
+            ),
+            (
+                "let [a, [b, c]] = [1, 2];",
+                "ERROR: cannot destructure array of 2 elements: expected [_, [_, _]]",
+            ),
+            (
+                "let [a] = 5;",
+                "ERROR: cannot destructure INTEGER: expected [_]",
+            ),
+            (
+                "let [a] = 1 / 0;",
+                "ERROR: division by zero at line 1, column 13",
+            ),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).to_string(), expected, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_match_tuple_rest_pattern() {
+        let input = r#"
+            let describe = fn(xs) {
+                match xs {
+                    [] => "empty",
+                    [x] => "one",
+                    [x, ..tail] => tail,
+                }
+            };
+            [describe([]), describe([1]), describe([1, 2, 3])];
+        "#;
+        assert_eq!(test_eval(input).to_string(), "[empty, one, [2, 3]]");
+    }
+
     #[test]
     fn test_match_nested_guards() {
         // Тест вложенных гардов
@@ -1464,4 +3376,1306 @@ mod tests {
         let evaluated = test_eval(input);
         assert_eq!(evaluated, Object::Integer(2));
     }
+
+    #[test]
+    fn test_error_positions() {
+        let input = "let a = 1;\nlet b = 2;\nlet c = 5 + true;\n";
+        assert_eq!(
+            test_eval(input),
+            Object::error("type mismatch: INTEGER + BOOLEAN at line 3, column 11".to_string())
+        );
+
+        let tests = vec![
+            (
+                "let x = 1;\n  x / 0;",
+                "division by zero at line 2, column 5",
+            ),
+            (
+                "let x = 1;\n\nx + missing;",
+                "identifier not found: missing at line 3, column 5",
+            ),
+            // Столбец считается в символах, а не в байтах
+            (
+                "\"привет\" + 1",
+                "type mismatch: STRING + INTEGER at line 1, column 10",
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(
+                test_eval(input),
+                Object::error(expected.to_string()),
+                "input: {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_error_stack_trace() {
+        let input = r#"
+            let sum = fn(a, b) { a / b };
+            let run = fn() { sum(1, 0) };
+            run();
+        "#;
+        assert_eq!(
+            test_eval(input),
+            traced_error(
+                "division by zero at line 2, column 36",
+                &["sum()", "run()", "main"],
+            )
+        );
+    }
+
+    #[test]
+    fn test_error_stack_trace_in_method() {
+        let input = r#"
+            class Calc {
+                public broken() {
+                    return 1 + true;
+                }
+            }
+            let c = new Calc();
+            c.broken();
+        "#;
+        assert_eq!(
+            test_eval(input),
+            traced_error(
+                "type mismatch: INTEGER + BOOLEAN at line 4, column 30",
+                &["Calc.broken()", "main"],
+            )
+        );
+    }
+
+    #[test]
+    fn test_assert_builtins() {
+        let tests = vec![
+            ("assert(1 < 2)", Object::Null),
+            ("assert(1 == 1)", Object::Null),
+            (
+                "assert_eq(1, 2)",
+                Object::error("assertion failed: 1 != 2".to_string()),
+            ),
+            (
+                "assert(if (false) { 1 })",
+                Object::error("assertion failed: null".to_string()),
+            ),
+            // Проваленное утверждение останавливает вычисление программы
+            (
+                "let x = 1; assert_eq(x, 2); x + 100;",
+                Object::error("assertion failed: 1 != 2".to_string()),
+            ),
+            ("let assert = 7; assert", Object::Integer(7)),
+            (
+                "assert(1 > 2)",
+                Object::error("assertion failed: false".to_string()),
+            ),
+            ("assert_eq(2 + 2, 4)", Object::Null),
+            (
+                "assert_eq(2 + 2, 5)",
+                Object::error("assertion failed: 4 != 5".to_string()),
+            ),
+            (
+                "assert_eq(1)",
+                Object::error("wrong number of arguments to assert_eq: got=1, want=2".to_string()),
+            ),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input), expected, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_hash_insertion_order() {
+        let tests = [
+            (r#"let h = {"c": 1, "a": 2, "b": 3}; keys(h)"#, "[c, a, b]"),
+            (
+                r#"let h = {"c": 1}; h["a"] = 2; h["b"] = 3; [keys(h), h]"#,
+                r#"[[c, a, b], {"c": 1, "a": 2, "b": 3}]"#,
+            ),
+            // Перезапись и повтор ключа в литерале не меняют его позиции
+            (r#"let h = {2: 0, 1: 0, 2: 5}; h[1] = 7; h"#, "{2: 5, 1: 7}"),
+            ("keys({})", "[]"),
+            (
+                "keys([1])",
+                "ERROR: wrong argument type for keys: expected HASH, got ARRAY at position 1",
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).to_string(), expected, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_numeric_builtins() {
+        let error = |message: &str| Object::error(message.to_string());
+        let tests = vec![
+            ("abs(-5)", Object::Integer(5)),
+            ("abs(5)", Object::Integer(5)),
+            ("abs(0)", Object::Integer(0)),
+            ("min(3, 7)", Object::Integer(3)),
+            ("max(3, 7)", Object::Integer(7)),
+            ("min(-2, 9, -4, 0)", Object::Integer(-4)),
+            ("max(-2, 9, -4, 0)", Object::Integer(9)),
+            ("min(42)", Object::Integer(42)),
+            (
+                "abs(-9223372036854775807 - 1)",
+                error("abs: -9223372036854775808 is out of range for INTEGER"),
+            ),
+            (
+                r#"abs("5")"#,
+                error("wrong argument type for abs: expected INTEGER, got STRING at position 1"),
+            ),
+            (
+                "max(1, true)",
+                error("wrong argument type for max: expected INTEGER, got BOOLEAN at position 2"),
+            ),
+            (
+                "min()",
+                error("wrong number of arguments to min: got=0, want at least 1"),
+            ),
+            (
+                "abs(1, 2)",
+                error("wrong number of arguments to abs: got=2, want=1"),
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_string_builtins() {
+        let string = |s: &str| Object::String(s.to_string());
+        let strings = |items: &[&str]| {
+            Object::Array(Rc::new(
+                items
+                    .iter()
+                    .map(|s| Object::String(s.to_string()))
+                    .collect(),
+            ))
+        };
+        let error = |message: &str| Object::error(message.to_string());
+        let tests = vec![
+            (r#"split("a,b,c", ",")"#, strings(&["a", "b", "c"])),
+            (r#"split("a,b,,c", ",")"#, strings(&["a", "b", "", "c"])),
+            ("split(`a\nb\\n\nc`, `\n`)", strings(&["a", "b\\n", "c"])),
+            (r#"split("", ",")"#, strings(&[""])),
+            (
+                r#"split("привет", "")"#,
+                strings(&["п", "р", "и", "в", "е", "т"]),
+            ),
+            (r#"split("α→β→γ", "→")"#, strings(&["α", "β", "γ"])),
+            (
+                r#"split("a", 1)"#,
+                error("wrong argument type for split: expected STRING, got INTEGER at position 2"),
+            ),
+            (
+                r#"split("a")"#,
+                error("wrong number of arguments to split: got=1, want=2"),
+            ),
+            (r#"join(["a", "b"], "-")"#, string("a-b")),
+            (r#"join(["a", "b", "c"], "-")"#, string("a-b-c")),
+            (r#"join([], "-")"#, string("")),
+            (r#"join(split("a b", " "), "")"#, string("ab")),
+            (
+                r#"join(["a", 1], "-")"#,
+                error("wrong element type for join: expected STRING, got INTEGER at index 1"),
+            ),
+            (
+                r#"join("ab", "-")"#,
+                error("wrong argument type for join: expected ARRAY, got STRING at position 1"),
+            ),
+            ("trim(\"  hi \t\n\")", string("hi")),
+            ("trim(\" \t\n \")", string("")),
+            // Неразрывный и идеографический пробелы — тоже пробельные символы
+            ("trim(\"\u{a0}тест\u{3000}\")", string("тест")),
+            (
+                "trim(true)",
+                error("wrong argument type for trim: expected STRING, got BOOLEAN at position 1"),
+            ),
+            (r#"replace("a-b-c", "-", "+")"#, string("a+b+c")),
+            (r#"replace("ёжик", "ё", "е")"#, string("ежик")),
+            (
+                r#"replace("a", "b", null)"#,
+                error("wrong argument type for replace: expected STRING, got NULL at position 3"),
+            ),
+            (r#"contains("hello", "ell")"#, Object::Boolean(true)),
+            (r#"contains("hello", "x")"#, Object::Boolean(false)),
+            (
+                r#"contains("hello", 1)"#,
+                error(
+                    "wrong argument type for contains: expected STRING, got INTEGER at position 2",
+                ),
+            ),
+            (r#"starts_with("hello", "he")"#, Object::Boolean(true)),
+            (r#"starts_with("hello", "lo")"#, Object::Boolean(false)),
+            (r#"ends_with("hello", "lo")"#, Object::Boolean(true)),
+            (
+                r#"ends_with("hello", 0)"#,
+                error(
+                    "wrong argument type for ends_with: expected STRING, got INTEGER at position 2",
+                ),
+            ),
+            ("to_string(42)", string("42")),
+            ("to_string([1, true, null])", string("[1, true, null]")),
+            (r#"to_string("s") + "!""#, string("s!")),
+            (
+                "to_string(1, 2)",
+                error("wrong number of arguments to to_string: got=2, want=1"),
+            ),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input), expected, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_deep_property_chains() {
+        let input = r#"
+            class Engine {
+                public power;
+                public boost(k) { return this.power * k; }
+            }
+            class Car { public engine; public parts; }
+            class Garage {
+                public car;
+                public main() { return this.car.engine; }
+            }
+            let g = new Garage(new Car(new Engine(100), [new Engine(5)]));
+            [
+                g.car.engine.power,
+                g.car.engine.boost(2),
+                g.car.parts[0].boost(3),
+                g.car.parts[-1].power,
+                g.main().boost(4),
+                g.main().power
+            ];
+        "#;
+        assert_eq!(
+            test_eval(input),
+            Object::Array(Rc::new(vec![
+                Object::Integer(100),
+                Object::Integer(200),
+                Object::Integer(15),
+                Object::Integer(5),
+                Object::Integer(400),
+                Object::Integer(100),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_assign_expression() {
+        let tests = vec![
+            ("let x = 1; x = x + 1; x;", Object::Integer(2)),
+            ("let x = 1; let y = x = 5; [x, y];", Object::Array(Rc::new(vec![Object::Integer(5), Object::Integer(5)]))),
+            ("let x = 1; let f = fn() { x = 10; }; f(); x;", Object::Integer(10)),
+            ("y = 1;", Object::error("identifier not found: y at line 1, column 1".to_string())),
+            (
+                "class C { public n; public bump() { this.n = this.n + 1; } } let c = new C(1); c.bump(); c.bump(); c.n;",
+                Object::Integer(3),
+            ),
+            (
+                "class C { public n; } let c = new C(1); c.m = 2;",
+                Object::error("property 'm' not found on class 'C'".to_string()),
+            ),
+            ("let a = [1]; a[0] = 2;", Object::Integer(2)),
+            (
+                "let f = fn() { [1] }; f()[0] = 2;",
+                Object::error("invalid assignment target: f()".to_string()),
+            ),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input), expected, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_const_bindings() {
+        let tests = vec![
+            ("const c = 1; c;", Object::Integer(1)),
+            (
+                "const c = 1; c = 2;",
+                Object::error("cannot assign to constant c at line 1, column 14".to_string()),
+            ),
+            (
+                "const c = 1; let f = fn() { c = 2; }; f();",
+                traced_error(
+                    "cannot assign to constant c at line 1, column 29",
+                    &["f()", "main"],
+                ),
+            ),
+            (
+                "const a = [1]; a[0] = 2;",
+                Object::error("cannot assign to constant a at line 1, column 16".to_string()),
+            ),
+            // Локальная переменная перекрывает константу и изменяема
+            (
+                "const c = 1; let f = fn() { let c = 5; c = 6; c }; f() + c;",
+                Object::Integer(7),
+            ),
+            // Повторное объявление заменяет привязку целиком
+            ("const c = 1; let c = 2; c = 3; c;", Object::Integer(3)),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input), expected, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_index_boundaries_and_assignment() {
+        let tests = vec![
+            ("[1, 2, 3][-1]", "3"),
+            ("[1, 2, 3][0]", "1"),
+            ("[1, 2, 3][2]", "3"),
+            ("[1, 2, 3][3]", "null"),
+            ("[1, 2, 3][-4]", "null"),
+            (r#""héllo"[1]"#, "é"),
+            (r#""abc"[-1]"#, "c"),
+            (r#""abc"[3]"#, "null"),
+            (
+                "1[0]",
+                "ERROR: index operator not supported: INTEGER[INTEGER]",
+            ),
+            (
+                r#"[1]["0"]"#,
+                "ERROR: index operator not supported: ARRAY[STRING]",
+            ),
+            ("let a = [1, 2, 3]; a[-1] = 9; a", "[1, 2, 9]"),
+            ("let a = [[1, 2], [3]]; a[0][1] = 5; a", "[[1, 5], [3]]"),
+            // Массивы — значения: копия не видит запись в оригинал
+            ("let a = [1]; let b = a; b[0] = 2; [a, b]", "[[1], [2]]"),
+            (
+                "let a = [1]; let f = fn(x) { x[0] = 5; x }; [f(a), a]",
+                "[[5], [1]]",
+            ),
+            (
+                "struct S { let items = [0, 0]; }; let s = new S(); s.items[1] = 7; s.items",
+                "[0, 7]",
+            ),
+            (
+                "let a = [1, 2, 3]; a[3] = 4",
+                "ERROR: index out of range: 3 for ARRAY of length 3",
+            ),
+            (
+                "let a = [1, 2, 3]; a[-4] = 4",
+                "ERROR: index out of range: -4 for ARRAY of length 3",
+            ),
+            (
+                r#"let s = "abc"; s[0] = "x""#,
+                "ERROR: index assignment not supported: STRING[INTEGER]",
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).to_string(), expected, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_search_builtins() {
+        let tests = vec![
+            ("contains([1, 2, 3], 2)", Object::Boolean(true)),
+            ("contains([1, 2, 3], 4)", Object::Boolean(false)),
+            (r#"contains([1, "2"], 2)"#, Object::Boolean(false)),
+            ("contains([[1], [2]], [2])", Object::Boolean(true)),
+            ("contains([], null)", Object::Boolean(false)),
+            (r#"index_of("hello", "ll")"#, Object::Integer(2)),
+            (r#"index_of("hello", "x")"#, Object::Integer(-1)),
+            (r#"index_of("привет", "ве")"#, Object::Integer(3)),
+            (r#"index_of("abc", "")"#, Object::Integer(0)),
+            ("index_of([1, 2, 3, 2], 2)", Object::Integer(1)),
+            ("index_of([1, 2, 3], 5)", Object::Integer(-1)),
+            (
+                "index_of(1, 1)",
+                Object::error(
+                    "wrong argument type for index_of: expected ARRAY or STRING, got INTEGER at position 1"
+                        .to_string(),
+                ),
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_collection_size_builtins() {
+        let tests = vec![
+            ("len([1, 2, 3])", Object::Integer(3)),
+            ("size([])", Object::Integer(0)),
+            (r#"len("привет")"#, Object::Integer(6)),
+            ("is_empty([])", Object::Boolean(true)),
+            ("is_empty([0])", Object::Boolean(false)),
+            (r#"is_empty("")"#, Object::Boolean(true)),
+            (r#"is_empty("x")"#, Object::Boolean(false)),
+            (
+                "len(1)",
+                Object::error(
+                    "wrong argument type for len: expected ARRAY, STRING or HASH, got INTEGER at position 1"
+                        .to_string(),
+                ),
+            ),
+            (
+                "is_empty(null)",
+                Object::error(
+                    "wrong argument type for is_empty: expected ARRAY, STRING or HASH, got NULL at position 1"
+                        .to_string(),
+                ),
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_conversion_builtins() {
+        let tests = vec![
+            (r#"int("42")"#, "42"),
+            (r#"int(" -17  ")"#, "-17"),
+            (r#"int("+5") + 1"#, "6"),
+            ("int(9)", "9"),
+            ("int(true) + int(false)", "1"),
+            (r#"str(12) + "3""#, "123"),
+            ("str([1, true])", "[1, true]"),
+            (r#"parse_int("ff", 16)"#, "255"),
+            (r#"parse_int(" 777 ", 8)"#, "511"),
+            (r#"parse_int("-zz", 36)"#, "-1295"),
+            (r#"format("{}/{}", 3, "4")"#, "3/4"),
+            (
+                r#"int("12a")"#,
+                r#"ERROR: int: "12a" is not a valid INTEGER"#,
+            ),
+            (r#"int("")"#, r#"ERROR: int: "" is not a valid INTEGER"#),
+            (
+                r#"int("-9223372036854775809")"#,
+                r#"ERROR: int: "-9223372036854775809" is out of range for INTEGER"#,
+            ),
+            ("int([1])", "ERROR: int: cannot convert ARRAY [1] to INTEGER"),
+            ("int(null)", "ERROR: int: cannot convert NULL null to INTEGER"),
+            (
+                r#"parse_int("12", 2)"#,
+                r#"ERROR: parse_int: "12" is not a valid INTEGER in radix 2"#,
+            ),
+            (
+                r#"parse_int("1", 1)"#,
+                "ERROR: parse_int: radix must be between 2 and 36, got 1",
+            ),
+            (
+                "parse_int(10, 10)",
+                "ERROR: wrong argument type for parse_int: expected STRING, got INTEGER at position 1",
+            ),
+            (
+                r#"format("{}")"#,
+                "ERROR: format: template has 1 placeholders, got 0 arguments",
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).to_string(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_array_constructor_builtins() {
+        let tests = vec![
+            ("range(0, 5)", "[0, 1, 2, 3, 4]"),
+            ("range(-2, 1)", "[-2, -1, 0]"),
+            ("range(3, 3)", "[]"),
+            ("range(5, 0)", "[]"),
+            (r#"repeat("x", 3)"#, "[x, x, x]"),
+            ("repeat([1], 2)", "[[1], [1]]"),
+            ("repeat(0, -1)", "[]"),
+            (
+                "range(0, 100000000)",
+                "ERROR: range: array of 100000000 elements exceeds the limit of 1000000",
+            ),
+            (
+                "repeat(0, 9223372036854775807)",
+                "ERROR: repeat: array of 9223372036854775807 elements exceeds the limit of 1000000",
+            ),
+            (
+                r#"range(0, "5")"#,
+                "ERROR: wrong argument type for range: expected INTEGER, got STRING at position 2",
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).to_string(), expected, "{}", input);
+        }
+        assert_eq!(
+            test_eval(r#"repeat("x", 3)"#),
+            Object::Array(Rc::new(vec![Object::String("x".to_string()); 3]))
+        );
+    }
+
+    #[test]
+    fn test_higher_order_builtins() {
+        let tests = vec![
+            ("map([1, 2, 3], fn(x) { x * 2 })", "[2, 4, 6]"),
+            ("map([], fn(x) { x })", "[]"),
+            (r#"map(["a", " b "], trim)"#, "[a, b]"),
+            ("filter([1, 2, 3, 4], fn(x) { x % 2 == 0 })", "[2, 4]"),
+            // Результат предиката приводится к булеву как в if
+            ("filter([0, null, 1, false], fn(x) { x })", "[0, 1]"),
+            ("reduce([1, 2, 3, 4], fn(acc, x) { acc + x }, 0)", "10"),
+            ("reduce([], fn(acc, x) { acc + x }, 7)", "7"),
+            (
+                "reduce([1, 2], fn(acc, x) { acc + [x] }, [])",
+                "ERROR: type mismatch: ARRAY + ARRAY at line 1, column 33\n  in <anonymous>()\n  in main",
+            ),
+            (
+                "map([1], 5)",
+                "ERROR: wrong argument type for map: expected FUNCTION, got INTEGER at position 2",
+            ),
+            (
+                "filter(1, fn(x) { x })",
+                "ERROR: wrong argument type for filter: expected ARRAY, got INTEGER at position 1",
+            ),
+            (
+                "map([1], fn(a, b) { a })",
+                "ERROR: wrong number of arguments: expected 2, got 1",
+            ),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input).to_string(), expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_typeof() {
+        let describe = r#"
+            let describe = fn(x) {
+                match typeof x {
+                    "INTEGER" => "int " + to_string(x),
+                    "STRING" => "string " + x,
+                    "ARRAY" => "array",
+                    _ => "other",
+                }
+            };
+        "#;
+        let tests = vec![
+            (r#"describe(1)"#, "int 1"),
+            (r#"describe("a")"#, "string a"),
+            (r#"describe([1, 2])"#, "array"),
+            (r#"describe(true)"#, "other"),
+            ("typeof typeof 1", "STRING"),
+            ("typeof null", "NULL"),
+            ("typeof fn() {}", "FUNCTION"),
+            ("typeof trim", "BUILTIN_FUNCTION"),
+            ("typeof (1..3)", "RANGE"),
+            ("class A { public m() { 1 } }; typeof A", "CLASS"),
+            (
+                "class A { public m() { 1 } }; typeof new A()",
+                "CLASS_INSTANCE",
+            ),
+            ("class A { public m() { 1 } }; typeof new A().m", "METHOD"),
+            ("struct P { let x; }; typeof P", "STRUCT"),
+        ];
+        for (input, expected) in tests {
+            assert_eq!(
+                test_eval(&format!("{}{}", describe, input)),
+                Object::String(expected.to_string()),
+                "{}",
+                input
+            );
+        }
+
+        // Ошибка операнда распространяется, а не превращается в "ERROR"
+        assert_eq!(
+            test_eval("typeof (1 + true)"),
+            Object::error("type mismatch: INTEGER + BOOLEAN at line 1, column 11".to_string())
+        );
+        assert_eq!(
+            test_eval("[is_int(1), is_string(1), is_fn(fn() {}), is_fn(is_fn), is_null(null)]")
+                .to_string(),
+            "[true, false, true, true, true]"
+        );
+    }
+
+    #[test]
+    fn test_clone_builtin() {
+        let tests = vec![
+            ("clone(5)", Object::Integer(5)),
+            (r#"copy("s")"#, Object::String("s".to_string())),
+            // Изменение экземпляра внутри скопированного массива не видно в оригинале
+            (
+                "class C { public n; } let a = [new C(1)]; let b = clone(a); let e = b[0]; e.n = 5; [a[0].n, b[0].n];",
+                Object::Array(Rc::new(vec![Object::Integer(1), Object::Integer(5)])),
+            ),
+            (
+                "class C { public n; } let c = new C(1); let d = copy(c); d.n = 2; [c.n, d.n];",
+                Object::Array(Rc::new(vec![Object::Integer(1), Object::Integer(2)])),
+            ),
+            // Вложенные экземпляры копируются рекурсивно
+            (
+                "class C { public n; } class Box { public inner; } let b = new Box(new C(1)); let i = clone(b).inner; i.n = 9; b.inner.n;",
+                Object::Integer(1),
+            ),
+            // Циклическая ссылка копируется в цикл на копию
+            (
+                "class Node { public next; } let n = new Node(null); n.next = n; let m = clone(n); let inner = m.next; inner.next = 7; [m.next, n.next is Node];",
+                Object::Array(Rc::new(vec![Object::Integer(7), Object::Boolean(true)])),
+            ),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input), expected, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_for_expression() {
+        let tests = vec![
+            (
+                "let sum = 0; for (x in [1, 2, 3]) { sum = sum + x; } sum;",
+                Object::Integer(6),
+            ),
+            (
+                "let sum = 0; for (i in 0..5) { sum = sum + i; } sum;",
+                Object::Integer(10),
+            ),
+            ("for (x in []) { x }", Object::Null),
+            // Переменные тела не видны после цикла
+            (
+                "for (x in [1]) { let inner = x; } inner;",
+                Object::error("identifier not found: inner at line 1, column 35".to_string()),
+            ),
+            (
+                "let f = fn() { for (x in [1, 2, 3]) { if (x == 2) { return x * 10; } } 0 }; f();",
+                Object::Integer(20),
+            ),
+            (
+                "for (x in [1, true]) { -x }",
+                Object::error("unknown operator: -BOOLEAN".to_string()),
+            ),
+            (
+                "for (x in 5) { x }",
+                Object::error("not iterable: INTEGER".to_string()),
+            ),
+            (
+                "let s = 0; for (i in 0..10) { if (i == 4) { break; } s = s + i; } s;",
+                Object::Integer(6),
+            ),
+            (
+                "let s = 0; for (x in [1, 2, 3, 4]) { if (x % 2 == 0) { continue; } s = s * 10 + x; } s;",
+                Object::Integer(13),
+            ),
+            // break во вложенном цикле прерывает только его
+            (
+                "let n = 0; for (i in 0..3) { for (j in 0..10) { if (j == i) { break; } n = n + 1; } } n;",
+                Object::Integer(3),
+            ),
+            ("for (i in 0..3) { break; }", Object::Null),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input), expected, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_user_iterators() {
+        let counter = r#"
+            class Counter {
+                public current;
+                public max;
+                public next() {
+                    if (this.current < this.max) {
+                        this.current = this.current + 1;
+                        return this.current;
+                    }
+                    // Null завершает перебор
+                    return if (false) { 0 };
+                }
+            }
+            class Faulty {
+                public calls;
+                public next() {
+                    this.calls = this.calls + 1;
+                    if (this.calls > 2) { return 1 / 0; }
+                    return this.calls;
+                }
+            }
+            class Plain {}
+        "#;
+        let tests = vec![
+            (
+                "let total = 0; for (x in new Counter(0, 3)) { total = total * 10 + x; } total;",
+                Object::Integer(123),
+            ),
+            (
+                "collect(new Counter(0, 3));",
+                Object::Array(Rc::new(vec![
+                    Object::Integer(1),
+                    Object::Integer(2),
+                    Object::Integer(3),
+                ])),
+            ),
+            (
+                "collect(new Counter(5, 5));",
+                Object::Array(Rc::new(vec![])),
+            ),
+            (
+                "collect(1..3);",
+                Object::Array(Rc::new(vec![Object::Integer(1), Object::Integer(2)])),
+            ),
+            (
+                "collect([7]);",
+                Object::Array(Rc::new(vec![Object::Integer(7)])),
+            ),
+            (
+                "let total = 0; for (x in new Faulty(0)) { total = total + x; } total;",
+                traced_error(
+                    "division by zero at line 18, column 52",
+                    &["Faulty.next()", "main"],
+                ),
+            ),
+            (
+                "collect(new Faulty(0));",
+                traced_error(
+                    "division by zero at line 18, column 52",
+                    &["Faulty.next()", "main"],
+                ),
+            ),
+            (
+                "for (x in new Plain()) { x }",
+                Object::error(
+                    "object of class 'Plain' is not iterable: no next() method".to_string(),
+                ),
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let source = format!("{}{}", counter, input);
+            assert_eq!(test_eval(&source), expected, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_named_function_declaration() {
+        let input = "fn add(a, b) { a + b } add(2, 3);";
+        assert_eq!(test_eval(input), Object::Integer(5));
+    }
+
+    #[test]
+    fn test_operator_overloading() {
+        let vector = r#"
+            class Vector {
+                public x;
+                public y;
+                public add(other) {
+                    return new Vector(this.x + other.x, this.y + other.y);
+                }
+                public equals(other) {
+                    return this.x == other.x && this.y == other.y;
+                }
+                public lessThan(other) {
+                    return this.x * this.x + this.y * this.y < other.x * other.x + other.y * other.y;
+                }
+            }
+        "#;
+        let tests = vec![
+            (
+                "let v = new Vector(1, 2) + new Vector(3, 4); v.x;",
+                Object::Integer(4),
+            ),
+            (
+                "let v = new Vector(1, 2) + new Vector(3, 4); v.y;",
+                Object::Integer(6),
+            ),
+            (
+                "new Vector(1, 2) == new Vector(1, 2);",
+                Object::Boolean(true),
+            ),
+            (
+                "new Vector(1, 2) != new Vector(1, 2);",
+                Object::Boolean(false),
+            ),
+            (
+                "new Vector(1, 2) < new Vector(3, 4);",
+                Object::Boolean(true),
+            ),
+            (
+                "new Vector(1, 2) - new Vector(3, 4);",
+                Object::error(
+                    "unknown operator: CLASS_INSTANCE - CLASS_INSTANCE at line 15, column 26"
+                        .to_string(),
+                ),
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let source = format!("{}{}", vector, input);
+            assert_eq!(test_eval(&source), expected, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_object_summaries() {
+        let declarations = r#"
+            class P { public to_string() { return "custom"; } }
+            struct S { let x = 1; }
+            interface I { fn f(); }
+        "#;
+        let eval_summary = |input: &str| test_eval(&format!("{}{}", declarations, input)).summary();
+        let evaluated = [
+            ("5", "5"),
+            ("true", "true"),
+            ("null", "null"),
+            ("\"hi\"", "\"hi\""),
+            ("1..3", "1..3"),
+            ("fn(a, b) { a + b }", "fn/2"),
+            ("fn(a, ...xs) { a }", "fn/1+"),
+            ("[1, \"a\", [true]]", "[1, \"a\", [true]]"),
+            ("range(0, 9)", "[9 elements]"),
+            ("P", "class P"),
+            // Пользовательский to_string() не вызывается
+            ("new P()", "instance of P"),
+            ("new P().to_string", "method to_string"),
+            ("S", "struct S"),
+            ("new S()", "S { x: 1 }"),
+            ("I", "interface I"),
+            ("trim", "builtin fn trim"),
+            ("-true", "ERROR: unknown operator: -BOOLEAN"),
+        ];
+        for (input, expected) in evaluated {
+            assert_eq!(eval_summary(input), expected, "input: {}", input);
+        }
+
+        let long = "x".repeat(33);
+        let function = crate::object::CompiledFunction {
+            instructions_offset: 0x1a,
+            num_locals: 2,
+            num_params: 1,
+        };
+        let hash = |n: i64| {
+            Object::Hash(Rc::new(
+                (0..n)
+                    .map(|i| (HashKey::String(format!("k{}", i)), Object::Integer(i)))
+                    .collect(),
+            ))
+        };
+        let constructed = [
+            (
+                Object::ReturnValue(Box::new(Object::Integer(1))),
+                "1".to_string(),
+            ),
+            (Object::Break, "break".to_string()),
+            (Object::Continue, "continue".to_string()),
+            (
+                Object::String(long),
+                format!("\"{}…\" (33 chars)", "x".repeat(32)),
+            ),
+            (hash(2), "{\"k0\": 0, \"k1\": 1}".to_string()),
+            (hash(9), "{9 entries}".to_string()),
+            (
+                Object::CompiledFunction(function.clone()),
+                "fn/1 @0x001a (2 locals)".to_string(),
+            ),
+            (
+                Object::Closure(Rc::new(Closure {
+                    function,
+                    free: vec![Object::Null],
+                })),
+                "closure fn/1 @0x001a (2 locals, 1 free)".to_string(),
+            ),
+        ];
+        for (object, expected) in constructed {
+            assert_eq!(object.summary(), expected);
+        }
+    }
+
+    #[test]
+    fn test_instance_to_string() {
+        let point = r#"
+            class Point {
+                public x;
+                public y;
+                public to_string() {
+                    return "P(" + to_string(this.x) + "," + to_string(this.y) + ")";
+                }
+            }
+            class Broken {
+                public to_string() { return 42; }
+            }
+            class Plain {}
+        "#;
+        let tests = vec![
+            ("new Point(1, 2);", "P(1,2)"),
+            ("\"at \" + new Point(1, 2);", "at P(1,2)"),
+            ("new Point(1, 2) + \"!\";", "P(1,2)!"),
+            (
+                "to_string([new Point(1, 2), new Plain()]);",
+                "[P(1,2), instance of Plain]",
+            ),
+            // Без to_string() — прежние имя класса и ошибка конкатенации
+            ("new Plain();", "instance of Plain"),
+            ("{\"p\": new Point(3, 4)};", "{\"p\": P(3,4)}"),
+        ];
+        for (input, expected) in tests {
+            let source = format!("{}{}", point, input);
+            assert_eq!(
+                stringify(&test_eval(&source)),
+                Ok(expected.to_string()),
+                "input: {}",
+                input
+            );
+        }
+        // `Display` пользовательский код не выполняет
+        assert_eq!(
+            test_eval(&format!("{}new Point(1, 2);", point)).to_string(),
+            "instance of Point"
+        );
+
+        let errors = [
+            (
+                "\"x\" + new Plain();",
+                "type mismatch: STRING + CLASS_INSTANCE",
+            ),
+            (
+                "\"x\" + new Broken();",
+                "to_string() must return STRING, got INTEGER",
+            ),
+            (
+                "to_string([new Broken()]);",
+                "to_string() must return STRING, got INTEGER",
+            ),
+            (
+                "format(\"{}\", new Broken());",
+                "to_string() must return STRING, got INTEGER",
+            ),
+        ];
+        for (input, expected) in errors {
+            let source = format!("{}{}", point, input);
+            let Object::Error(error) = test_eval(&source) else {
+                panic!("expected error for {}", input);
+            };
+            assert!(error.message.starts_with(expected), "{}: {}", input, error);
+        }
+        // Ошибка to_string() при выводе не подменяется именем класса
+        let Err(Object::Error(error)) = stringify(&test_eval(&format!("{}new Broken();", point)))
+        else {
+            panic!("expected to_string() error for new Broken()");
+        };
+        assert_eq!(error.message, "to_string() must return STRING, got INTEGER");
+    }
+
+    #[test]
+    fn test_operator_overloading_short_names() {
+        let vector = r#"
+            class Vector {
+                public x;
+                public y;
+                public sub(other) { return new Vector(this.x - other.x, this.y - other.y); }
+                public mul(k) { return new Vector(this.x * k, this.y * k); }
+                public div(k) { return new Vector(this.x / k, this.y / k); }
+                public subtract(other) { return "subtract"; }
+                public mod(other, extra) { return other; }
+            }
+        "#;
+        let tests = vec![
+            ("(new Vector(2, 4) * 3).y;", Object::Integer(12)),
+            ("(new Vector(9, 6) / 3).x;", Object::Integer(3)),
+            (
+                "new Vector(5, 5) - new Vector(1, 2);",
+                Object::String("subtract".to_string()),
+            ),
+            (
+                "new Vector(1, 2) + new Vector(3, 4);",
+                Object::error(
+                    "unknown operator: CLASS_INSTANCE + CLASS_INSTANCE at line 11, column 26"
+                        .to_string(),
+                ),
+            ),
+            // Перегрузка получает один аргумент: метод с двумя параметрами —
+            // ошибка числа аргументов
+            (
+                "new Vector(1, 2) % new Vector(3, 4);",
+                Object::error(
+                    "wrong number of arguments: expected 2, got 1 at line 11, column 26"
+                        .to_string(),
+                ),
+            ),
+        ];
+
+        for (input, expected) in tests {
+            let source = format!("{}{}", vector, input);
+            assert_eq!(test_eval(&source), expected, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_equals_method_on_class_instances() {
+        let point = r#"
+            class Point {
+                public x;
+                public y;
+                public equals(other) {
+                    if (this.x == other.x) { this.y == other.y } else { false }
+                }
+            }
+        "#;
+        let tests = vec![
+            ("new Point(1, 2) == new Point(1, 2);", true),
+            ("new Point(1, 2) == new Point(2, 1);", false),
+            ("new Point(1, 2) != new Point(1, 2);", false),
+            ("new Point(1, 2) != new Point(1, 3);", true),
+            ("let p = new Point(5, 5); p == p;", true),
+        ];
+
+        for (input, expected) in tests {
+            let source = format!("{}{}", point, input);
+            assert_eq!(
+                test_eval(&source),
+                Object::Boolean(expected),
+                "input: {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_operator_overloading_falls_back_to_identity() {
+        let input = r#"
+            class Point { public x; }
+            let p = new Point(1);
+            [p == p, p == new Point(1), p != new Point(1)];
+        "#;
+        assert_eq!(
+            test_eval(input),
+            Object::Array(Rc::new(vec![
+                Object::Boolean(true),
+                Object::Boolean(false),
+                Object::Boolean(true),
+            ]))
+        );
+    }
+
+    #[test]
+    fn test_array_index_expressions() {
+        let tests = vec![
+            ("[1, 2, 3][0]", Object::Integer(1)),
+            ("[1, 2, 3][2]", Object::Integer(3)),
+            ("let i = 0; [1][i];", Object::Integer(1)),
+            ("[1, 2, 3][1 + 1];", Object::Integer(3)),
+            ("let a = [1, 2, 3]; a[0] + a[1] + a[2];", Object::Integer(6)),
+            ("[1, 2, 3][3]", Object::Null),
+            ("[10, 20, 30][-1]", Object::Integer(30)),
+            ("[10, 20, 30][-3]", Object::Integer(10)),
+            ("[10, 20, 30][-4]", Object::Null),
+            ("[1][-5]", Object::Null),
+            (
+                "1[0]",
+                Object::error("index operator not supported: INTEGER[INTEGER]".to_string()),
+            ),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input), expected, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_slice_expressions() {
+        let tests = vec![
+            (
+                "[1, 2, 3, 4][1..3]",
+                Object::Array(Rc::new(vec![Object::Integer(2), Object::Integer(3)])),
+            ),
+            ("\"hello\"[0..2]", Object::String("he".to_string())),
+            ("\"hello\"[3..100]", Object::String("lo".to_string())),
+            (
+                "[1, 2, 3][-5..1]",
+                Object::Array(Rc::new(vec![Object::Integer(1)])),
+            ),
+            ("[1, 2, 3][2..1]", Object::Array(Rc::new(vec![]))),
+            ("\"hello\"[4..2]", Object::String("".to_string())),
+            ("let r = 1..3; r;", Object::Range(1, 3)),
+            (
+                "1..true",
+                Object::error("range bounds must be integers: INTEGER..BOOLEAN".to_string()),
+            ),
+        ];
+
+        for (input, expected) in tests {
+            assert_eq!(test_eval(input), expected, "input: {}", input);
+        }
+    }
+
+    #[test]
+    fn test_array_reads_share_storage() {
+        // Чтение переменной с массивом не копирует его элементы
+        let input = "let xs = [1, 2, 3, 4, 5]; [xs, xs, xs, xs];";
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        let env = Rc::new(RefCell::new(Environment::new()));
+        let reads = match eval(Node::Program(program), Rc::clone(&env)) {
+            Object::Array(reads) => reads,
+            other => panic!("expected array, got {:?}", other),
+        };
+        let stored = match env.borrow().get("xs") {
+            Some(Object::Array(stored)) => stored,
+            other => panic!("expected array, got {:?}", other),
+        };
+
+        assert_eq!(reads.len(), 4);
+        for read in reads.iter() {
+            match read {
+                Object::Array(elements) => assert!(Rc::ptr_eq(elements, &stored)),
+                other => panic!("expected array, got {:?}", other),
+            }
+        }
+        // Переменная, четыре чтения и `stored`
+        assert_eq!(Rc::strong_count(&stored), 6);
+    }
+
+    #[test]
+    fn test_environment_get_ref() {
+        let outer = Rc::new(RefCell::new(Environment::new()));
+        outer
+            .borrow_mut()
+            .set("s".to_string(), Object::String("hello".to_string()))
+            .unwrap();
+        let inner = Environment::new_enclosed(outer);
+
+        assert_eq!(
+            inner.get_ref("s", |obj| match obj {
+                Object::String(s) => s.len(),
+                _ => 0,
+            }),
+            Some(5)
+        );
+        assert_eq!(inner.get_ref("missing", |_| ()), None);
+    }
+
+    fn eval_in(input: &str, env: &Rc<RefCell<Environment>>) -> Object {
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        eval(Node::Program(program), Rc::clone(env))
+    }
+
+    #[test]
+    fn test_environment_iteration() {
+        let env = Rc::new(RefCell::new(Environment::new()));
+        eval_in("let b = 1; let a = 2; fn c() { a } let b = 3;", &env);
+
+        // Порядок первого объявления; повторный `let` не сдвигает имя
+        let env_ref = env.borrow();
+        let names: Vec<&str> = env_ref.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, vec!["b", "a", "c"]);
+        assert_eq!(env_ref.len(), 3);
+        assert_eq!(env_ref.iter().next().unwrap().1, &Object::Integer(3));
+        assert!(Environment::new().is_empty());
+
+        // Внутреннее имя перекрывает внешнее; внешние идут после своих
+        let mut inner = Environment::new_enclosed(Rc::clone(&env));
+        inner.set("a".to_string(), Object::Integer(10)).unwrap();
+        inner.set("d".to_string(), Object::Null).unwrap();
+        assert_eq!(inner.len(), 2);
+        let all: Vec<String> = inner
+            .iter_all()
+            .map(|(name, value)| format!("{}={}", name, value.summary()))
+            .collect();
+        assert_eq!(all, vec!["a=10", "d=null", "b=3", "c=fn/0"]);
+    }
+
+    #[test]
+    fn test_environment_debug_is_compact() {
+        let env = Rc::new(RefCell::new(Environment::new()));
+        eval_in(
+            "let x = 1;
+            let make = fn(a) { let inner = fn(b) { a + b }; inner };
+            let add = make(2);
+            class C { public m(y) { y } }
+            let m = new C().m;",
+            &env,
+        );
+        // Функция в собственном захваченном окружении: вывод конечен
+        let add = env.borrow().get("add").unwrap();
+        let Object::Function(_, _, _, captured) = &add else {
+            panic!("add is not a function: {}", add);
+        };
+        captured
+            .borrow_mut()
+            .set("self".to_string(), add.clone())
+            .unwrap();
+
+        assert_eq!(format!("{:?}", add), "Function(fn(b) { ... })");
+        assert_eq!(
+            format!("{:?}", captured.borrow()),
+            r#"Environment { names: ["a", "inner", "self"], outer_depth: 1 }"#
+        );
+        assert_eq!(
+            format!("{:?}", env.borrow()),
+            r#"Environment { names: ["x", "make", "add", "C", "m"], outer_depth: 0 }"#
+        );
+        assert_eq!(
+            format!(
+                "{:?}",
+                Object::Array(Rc::new(vec![add, Object::Integer(1)]))
+            ),
+            "Array([Function(fn(b) { ... }), Integer(1)])"
+        );
+
+        let method = env.borrow().get("m").unwrap();
+        assert_eq!(
+            format!("{:?}", method),
+            r#"Method(RefCell { value: Method { name: "m", parameters: ["y"], this: Some("instance of C"), .. } })"#
+        );
+        assert_eq!(
+            format!("{:?}", Environment::with_limit(2)),
+            "Environment { names: [], outer_depth: 0, limit: 2 }"
+        );
+    }
+
+    #[test]
+    fn test_environment_limit() {
+        // По умолчанию лимита нет
+        let env = Rc::new(RefCell::new(Environment::new()));
+        assert_eq!(env.borrow().limit(), None);
+        let lets: String = (0..200).map(|i| format!("let v{} = {};", i, i)).collect();
+        assert_eq!(eval_in(&lets, &env), Object::Null);
+        assert_eq!(env.borrow().len(), 200);
+
+        let limited = || Rc::new(RefCell::new(Environment::with_limit(2)));
+        let env = limited();
+        assert_eq!(
+            eval_in("let a = 1; let b = 2; let a = 3; a + b", &env),
+            Object::Integer(5)
+        );
+        let cases = [
+            "let a = 1; let b = 2; let c = 3; c",
+            "fn f() { 1 } fn g() { 2 } fn h() { 3 }",
+            "let [a, b, c] = [1, 2, 3];",
+            "let a = 1; let b = 2; struct P { }",
+            "let a = 1; if (true) { let b = 2; let c = 3; }",
+        ];
+        for input in cases {
+            assert_eq!(
+                eval_in(input, &limited()),
+                Object::error("environment variable limit exceeded".to_string()),
+                "{}",
+                input
+            );
+        }
+        // Лимит касается только своего окружения: локальные переменные
+        // функции его не расходуют
+        assert_eq!(
+            eval_in("fn f(x) { let y = x; let z = y; z * 2 } f(21)", &limited()),
+            Object::Integer(42)
+        );
+
+        let mut env = Environment::with_limit(1);
+        assert_eq!(env.set("x".to_string(), Object::Null), Ok(()));
+        assert_eq!(env.set("x".to_string(), Object::Integer(1)), Ok(()));
+        assert_eq!(
+            env.set("y".to_string(), Object::Null),
+            Err(ENVIRONMENT_LIMIT_EXCEEDED.to_string())
+        );
+    }
 }
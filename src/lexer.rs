@@ -1,21 +1,39 @@
-use crate::token::{Token, TokenType};
+use crate::token::{Span, Token, TokenType};
+use std::borrow::Cow;
 
-// Лексер для языка SOFIA
-pub struct Lexer {
-    input: Vec<char>,
-    position: usize,      // текущая позиция (указывает на текущий символ)
-    read_position: usize, // следующая позиция для чтения (после текущей)
+// Лексер для языка SOFIA.
+// Проходит по исходной строке напрямую, позиции — байтовые смещения.
+pub struct Lexer<'a> {
+    input: Cow<'a, str>,
+    position: usize,      // байтовое смещение текущего символа
+    read_position: usize, // байтовое смещение следующего символа (после текущего)
     ch: char,             // текущий символ
+    line: usize,          // строка текущего символа (с единицы)
+    column: usize,        // столбец текущего символа в символах (с единицы)
 }
 
-impl Lexer {
-    // Создает новый лексер
+impl Lexer<'static> {
+    // Создает новый лексер, владеющий исходным кодом
     pub fn new(input: String) -> Self {
+        Lexer::from_cow(Cow::Owned(input))
+    }
+}
+
+impl<'a> Lexer<'a> {
+    // Создает лексер, заимствующий исходный код (без копирования)
+    #[allow(clippy::should_implement_trait)]
+    pub fn from_str(input: &'a str) -> Self {
+        Lexer::from_cow(Cow::Borrowed(input))
+    }
+
+    fn from_cow(input: Cow<'a, str>) -> Self {
         let mut lexer = Lexer {
-            input: input.chars().collect(),
+            input,
             position: 0,
             read_position: 0,
             ch: '\0',
+            line: 1,
+            column: 0,
         };
         lexer.read_char();
         lexer
@@ -23,22 +41,48 @@ impl Lexer {
 
     // Считывает следующий символ и сдвигает позиции
     fn read_char(&mut self) {
-        if self.read_position >= self.input.len() {
-            self.ch = '\0'; // Нулевой символ как признак конца ввода
+        if self.ch == '\n' {
+            self.line += 1;
+            self.column = 1;
         } else {
-            self.ch = self.input[self.read_position];
+            self.column += 1;
         }
         self.position = self.read_position;
-        self.read_position += 1;
+        match self.input[self.read_position..].chars().next() {
+            Some(ch) => {
+                self.ch = ch;
+                self.read_position += ch.len_utf8();
+            }
+            None => self.ch = '\0', // Нулевой символ как признак конца ввода
+        }
     }
 
     // "Подглядывает" следующий символ, не сдвигая позиций
     fn peek_char(&self) -> char {
-        if self.read_position >= self.input.len() {
-            '\0'
-        } else {
-            self.input[self.read_position]
-        }
+        self.input[self.read_position..]
+            .chars()
+            .next()
+            .unwrap_or('\0')
+    }
+
+    // Следующий токен без сдвига: позиции сохраняются и восстанавливаются
+    pub fn peek_token(&mut self) -> Token {
+        let saved = (
+            self.position,
+            self.read_position,
+            self.ch,
+            self.line,
+            self.column,
+        );
+        let token = self.next_token();
+        (
+            self.position,
+            self.read_position,
+            self.ch,
+            self.line,
+            self.column,
+        ) = saved;
+        token
     }
 
     // Основной метод, возвращающий следующий токен
@@ -52,7 +96,13 @@ impl Lexer {
             self.skip_comments();
         }
 
-        let token = match self.ch {
+        let start = Span {
+            start: self.position,
+            end: self.position,
+            line: self.line,
+            column: self.column,
+        };
+        let mut token = match self.ch {
             '=' => {
                 if self.peek_char() == '=' {
                     self.read_char();
@@ -104,10 +154,19 @@ impl Lexer {
             '%' => Token::new(TokenType::Modulo, "%".to_string()),
             ';' => Token::new(TokenType::Semicolon, ";".to_string()),
             ',' => Token::new(TokenType::Comma, ",".to_string()),
+            ':' => Token::new(TokenType::Colon, ":".to_string()),
             '.' => {
                 if self.peek_char() == '.' {
                     self.read_char();
-                    Token::new(TokenType::Range, "..".to_string())
+                    if self.peek_char() == '.' {
+                        self.read_char();
+                        Token::new(TokenType::Ellipsis, "...".to_string())
+                    } else if self.peek_char() == '=' {
+                        self.read_char();
+                        Token::new(TokenType::RangeInclusive, "..=".to_string())
+                    } else {
+                        Token::new(TokenType::Range, "..".to_string())
+                    }
                 } else {
                     Token::new(TokenType::Dot, ".".to_string())
                 }
@@ -118,22 +177,40 @@ impl Lexer {
             '}' => Token::new(TokenType::RBrace, "}".to_string()),
             '[' => Token::new(TokenType::LBracket, "[".to_string()),
             ']' => Token::new(TokenType::RBracket, "]".to_string()),
-            '"' => self.read_string(),
+            '"' | '\'' => self.read_string(),
+            '`' => self.read_raw_string(),
             '\0' => Token::new(TokenType::Eof, "".to_string()),
             _ => {
                 if self.is_letter() {
-                    let literal = self.read_identifier();
+                    let mut literal = self.read_identifier();
                     let token_type = Self::lookup_ident(&literal);
-                    return Token::new(token_type, literal);
+                    // Словесные операторы получают литерал символьной формы:
+                    // дальше `a and b` ничем не отличается от `a && b`
+                    if let Some(symbol) = Self::word_operator_symbol(&literal) {
+                        literal = symbol.to_string();
+                    }
+                    let span = Span {
+                        end: self.position,
+                        ..start
+                    };
+                    return Token::with_span(token_type, literal, span);
                 }
                 if self.is_digit() {
                     let literal = self.read_number();
-                    return Token::new(TokenType::Int, literal);
+                    let span = Span {
+                        end: self.position,
+                        ..start
+                    };
+                    return Token::with_span(TokenType::Int, literal, span);
                 }
                 Token::new(TokenType::Illegal, self.ch.to_string())
             }
         };
 
+        token.span = Span {
+            end: self.read_position,
+            ..start
+        };
         self.read_char();
         token
     }
@@ -167,7 +244,7 @@ impl Lexer {
         while self.is_letter() || self.is_digit() {
             self.read_char();
         }
-        self.input[start_pos..self.position].iter().collect()
+        self.input[start_pos..self.position].to_string()
     }
 
     // Считывает число
@@ -176,20 +253,118 @@ impl Lexer {
         while self.is_digit() {
             self.read_char();
         }
-        self.input[start_pos..self.position].iter().collect()
+        self.input[start_pos..self.position].to_string()
     }
 
-    // Считывает строку в кавычках
+    // Считывает строку в двойных или одинарных кавычках. Строка может
+    // занимать несколько строк и закрывается только той же кавычкой, которой
+    // открыта (`"it's"`, `'say "hi"'`). Незакрытая строка — токен Illegal
+    // с текстом от открывающей кавычки до конца ввода. Внутри строки
+    // действуют escape-последовательности (см. `read_escape`); неверная
+    // последовательность — токен Illegal с её текстом.
     fn read_string(&mut self) -> Token {
-        let start_pos = self.position + 1;
+        self.read_delimited(self.ch, true)
+    }
+
+    // Считывает сырую строку в обратных кавычках; ведёт себя так же, как
+    // строка в кавычках, но без escape-последовательностей: `\` остаётся
+    // обычным символом
+    fn read_raw_string(&mut self) -> Token {
+        self.read_delimited('`', false)
+    }
+
+    fn read_delimited(&mut self, delimiter: char, escapes: bool) -> Token {
+        let open_pos = self.position;
+        let mut literal = String::new();
+        // Первая неверная escape-последовательность; строка дочитывается до
+        // закрывающей кавычки, чтобы разбор продолжился после неё
+        let mut invalid_escape = None;
         loop {
             self.read_char();
-            if self.ch == '"' || self.ch == '\'' || self.ch == '`' || self.ch == '\0' {
-                break;
+            if self.ch == delimiter {
+                return match invalid_escape {
+                    Some(escape) => Token::new(TokenType::Illegal, escape),
+                    None => Token::new(TokenType::String, literal),
+                };
+            }
+            if self.ch == '\0' {
+                let literal = self.input[open_pos..self.position].to_string();
+                return Token::new(TokenType::Illegal, literal);
             }
+            if escapes && self.ch == '\\' {
+                let escape_pos = self.position;
+                match self.read_escape() {
+                    Some(ch) => literal.push(ch),
+                    None => {
+                        invalid_escape.get_or_insert_with(|| {
+                            self.input[escape_pos..self.read_position].to_string()
+                        });
+                    }
+                }
+                continue;
+            }
+            literal.push(self.ch);
+        }
+    }
+
+    // Разбирает escape-последовательность, начиная с текущего `\`:
+    // `\n`, `\t`, `\r`, `\0`, `\\`, `\"`, `\'`, `\xHH` (код до 0xFF) и
+    // `\u{H..}` (от 1 до 6 шестнадцатеричных цифр, скалярное значение
+    // Unicode). Текущим остаётся последний символ последовательности;
+    // `None` — последовательность неверна.
+    fn read_escape(&mut self) -> Option<char> {
+        self.read_char();
+        match self.ch {
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            'r' => Some('\r'),
+            '0' => Some('\0'),
+            '\\' | '"' | '\'' => Some(self.ch),
+            'x' => {
+                let digits = self.read_hex_digits(2);
+                if digits.len() != 2 {
+                    return None;
+                }
+                u32::from_str_radix(&digits, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+            }
+            'u' => {
+                if self.peek_char() != '{' {
+                    return None;
+                }
+                self.read_char();
+                let digits = self.read_hex_digits(6);
+                if digits.is_empty() || self.peek_char() != '}' {
+                    return None;
+                }
+                self.read_char();
+                u32::from_str_radix(&digits, 16)
+                    .ok()
+                    .and_then(char::from_u32)
+            }
+            _ => None,
+        }
+    }
+
+    // Считывает до `max` шестнадцатеричных цифр, следующих за текущим символом
+    fn read_hex_digits(&mut self, max: usize) -> String {
+        let mut digits = String::new();
+        while digits.len() < max && self.peek_char().is_ascii_hexdigit() {
+            self.read_char();
+            digits.push(self.ch);
+        }
+        digits
+    }
+
+    // Символьная форма словесного оператора (`and`, `or`, `not`)
+    fn word_operator_symbol(word: &str) -> Option<&'static str> {
+        match word {
+            "and" => Some("&&"),
+            "or" => Some("||"),
+            "not" => Some("!"),
+            _ => None,
         }
-        let literal: String = self.input[start_pos..self.position].iter().collect();
-        Token::new(TokenType::String, literal)
     }
 
     // Проверяет, является ли символ буквой (или '_')
@@ -205,16 +380,26 @@ impl Lexer {
     // Определяет, является ли идентификатор ключевым словом
     fn lookup_ident(ident: &str) -> TokenType {
         match ident {
+            "and" => TokenType::And,
+            "break" => TokenType::Break,
             "class" => TokenType::Class,
+            "const" => TokenType::Const,
+            "continue" => TokenType::Continue,
             "else" => TokenType::Else,
             "extends" => TokenType::Extends,
             "false" => TokenType::False,
             "fn" => TokenType::Function,
+            "for" => TokenType::For,
             "if" => TokenType::If,
             "implements" => TokenType::Implements,
+            "in" => TokenType::In,
             "interface" => TokenType::Interface,
+            "is" => TokenType::Is,
             "let" => TokenType::Let,
             "new" => TokenType::New,
+            "not" => TokenType::Bang,
+            "null" => TokenType::Null,
+            "or" => TokenType::Or,
             "private" => TokenType::Private,
             "public" => TokenType::Public,
             "return" => TokenType::Return,
@@ -223,6 +408,7 @@ impl Lexer {
             "super" => TokenType::Super,
             "this" => TokenType::This,
             "true" => TokenType::True,
+            "typeof" => TokenType::Typeof,
             "match" => TokenType::Match,
             _ => TokenType::Ident,
         }
@@ -273,6 +459,10 @@ mod tests {
                 1 => true,
                 _ => false,
              };
+             dog is Speaker;
+             for (x in xs) {}
+             Point { x: 0 }
+             null;
              "#;
 
         let tests = vec![
@@ -412,6 +602,26 @@ mod tests {
             (TokenType::Comma, ","),
             (TokenType::RBrace, "}"),
             (TokenType::Semicolon, ";"),
+            (TokenType::Ident, "dog"),
+            (TokenType::Is, "is"),
+            (TokenType::Ident, "Speaker"),
+            (TokenType::Semicolon, ";"),
+            (TokenType::For, "for"),
+            (TokenType::LParen, "("),
+            (TokenType::Ident, "x"),
+            (TokenType::In, "in"),
+            (TokenType::Ident, "xs"),
+            (TokenType::RParen, ")"),
+            (TokenType::LBrace, "{"),
+            (TokenType::RBrace, "}"),
+            (TokenType::Ident, "Point"),
+            (TokenType::LBrace, "{"),
+            (TokenType::Ident, "x"),
+            (TokenType::Colon, ":"),
+            (TokenType::Int, "0"),
+            (TokenType::RBrace, "}"),
+            (TokenType::Null, "null"),
+            (TokenType::Semicolon, ";"),
             (TokenType::Eof, ""),
         ];
 
@@ -431,4 +641,383 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_ellipsis_and_range() {
+        let mut lexer = Lexer::new("fn(a, ...rest) 1..2 3..=4 x.y".to_string());
+        let expected = [
+            (TokenType::Function, "fn"),
+            (TokenType::LParen, "("),
+            (TokenType::Ident, "a"),
+            (TokenType::Comma, ","),
+            (TokenType::Ellipsis, "..."),
+            (TokenType::Ident, "rest"),
+            (TokenType::RParen, ")"),
+            (TokenType::Int, "1"),
+            (TokenType::Range, ".."),
+            (TokenType::Int, "2"),
+            (TokenType::Int, "3"),
+            (TokenType::RangeInclusive, "..="),
+            (TokenType::Int, "4"),
+            (TokenType::Ident, "x"),
+            (TokenType::Dot, "."),
+            (TokenType::Ident, "y"),
+            (TokenType::Eof, ""),
+        ];
+        for (token_type, literal) in expected {
+            let token = lexer.next_token();
+            assert_eq!(token.token_type, token_type);
+            assert_eq!(token.literal, literal);
+        }
+    }
+
+    #[test]
+    fn test_colon() {
+        // `:` — отдельный токен и без пробелов вокруг, и в `::`
+        let mut lexer = Lexer::new("{\"a\":1} x: Int ::".to_string());
+        let expected = [
+            (TokenType::LBrace, "{"),
+            (TokenType::String, "a"),
+            (TokenType::Colon, ":"),
+            (TokenType::Int, "1"),
+            (TokenType::RBrace, "}"),
+            (TokenType::Ident, "x"),
+            (TokenType::Colon, ":"),
+            (TokenType::Ident, "Int"),
+            (TokenType::Colon, ":"),
+            (TokenType::Colon, ":"),
+            (TokenType::Eof, ""),
+        ];
+        for (token_type, literal) in expected {
+            let token = lexer.next_token();
+            assert_eq!(token.token_type, token_type);
+            assert_eq!(token.literal, literal);
+        }
+    }
+
+    #[test]
+    fn test_word_operators() {
+        let mut lexer = Lexer::new("a and not b or android".to_string());
+        let expected = [
+            (TokenType::Ident, "a"),
+            (TokenType::And, "&&"),
+            (TokenType::Bang, "!"),
+            (TokenType::Ident, "b"),
+            (TokenType::Or, "||"),
+            (TokenType::Ident, "android"),
+            (TokenType::Eof, ""),
+        ];
+        for (token_type, literal) in expected {
+            let token = lexer.next_token();
+            assert_eq!(token.token_type, token_type);
+            assert_eq!(token.literal, literal);
+        }
+    }
+
+    #[test]
+    fn test_string_escapes() {
+        let tests = [
+            (r#""\x41""#, "A"),
+            (r#""\u{1F600}""#, "😀"),
+            (r#""\u{41}\u{44f}""#, "Aя"),
+            (r#""\xe9\xFF""#, "éÿ"),
+            (r#""a\tb\nc\r\0""#, "a\tb\nc\r\0"),
+            (r#""\\ \" \'""#, r#"\ " '"#),
+            (r#"'\'' "#, "'"),
+            // В сырой строке `\` — обычный символ
+            (r#"`\x41\n`"#, r"\x41\n"),
+        ];
+        for (input, expected) in tests {
+            let token = Lexer::from_str(input).next_token();
+            assert_eq!(token.token_type, TokenType::String, "{}", input);
+            assert_eq!(token.literal, expected, "{}", input);
+        }
+    }
+
+    #[test]
+    fn test_invalid_string_escapes() {
+        // Illegal с текстом первой неверной последовательности; строка
+        // дочитывается до закрывающей кавычки
+        let tests = [
+            (r#""\q" 1"#, r"\q"),
+            (r#""\x4" 1"#, r"\x4"),
+            (r#""\xZZ" 1"#, r"\x"),
+            (r#""\u41" 1"#, r"\u"),
+            (r#""\u{}" 1"#, r"\u{"),
+            (r#""\u{1234567}" 1"#, r"\u{123456"),
+            (r#""\u{110000}" 1"#, r"\u{110000}"),
+            (r#""\u{D800}" 1"#, r"\u{D800}"),
+            (r#""ok \q \w" 1"#, r"\q"),
+        ];
+        for (input, expected) in tests {
+            let mut lexer = Lexer::from_str(input);
+            let token = lexer.next_token();
+            assert_eq!(token.token_type, TokenType::Illegal, "{}", input);
+            assert_eq!(token.literal, expected, "{}", input);
+            assert_eq!(lexer.next_token().literal, "1", "{}", input);
+        }
+
+        // Обратная косая черта в конце ввода — незакрытая строка
+        let token = Lexer::from_str(r#""abc\"#).next_token();
+        assert_eq!(token.token_type, TokenType::Illegal);
+        assert_eq!(token.literal, r#""abc\"#);
+    }
+
+    #[test]
+    fn test_raw_multiline_string() {
+        let input = "let s = `первая\n  \"вторая\"\\n\n`; s";
+        let mut lexer = Lexer::from_str(input);
+        let tokens: Vec<Token> = (0..6).map(|_| lexer.next_token()).collect();
+        assert_eq!(tokens[3].token_type, TokenType::String);
+        assert_eq!(tokens[3].literal, "первая\n  \"вторая\"\\n\n");
+        assert_eq!(tokens[3].literal.chars().count(), 20);
+        assert_eq!((tokens[3].span.line, tokens[3].span.column), (1, 9));
+        assert_eq!(
+            &input[tokens[3].span.start..tokens[3].span.end],
+            "`первая\n  \"вторая\"\\n\n`"
+        );
+        // Позиции после литерала учитывают его переводы строк
+        assert_eq!(tokens[4].token_type, TokenType::Semicolon);
+        assert_eq!((tokens[5].literal.as_str(), tokens[5].span.line), ("s", 3));
+    }
+
+    #[test]
+    fn test_string_delimiters() {
+        let input = r#""it's" 'say "hi"' `a'b"c` "" ''"#;
+        let mut lexer = Lexer::from_str(input);
+        for expected in ["it's", r#"say "hi""#, r#"a'b"c"#, "", ""] {
+            let token = lexer.next_token();
+            assert_eq!(token.token_type, TokenType::String);
+            assert_eq!(token.literal, expected);
+        }
+        assert_eq!(lexer.next_token().token_type, TokenType::Eof);
+
+        // Незакрытая строка — Illegal с текстом до конца ввода
+        for input in ["x = \"abc'", "'abc\"", "`a\nb"] {
+            let mut lexer = Lexer::new(input.to_string());
+            let token = loop {
+                let token = lexer.next_token();
+                if token.token_type != TokenType::Ident && token.token_type != TokenType::Assign {
+                    break token;
+                }
+            };
+            assert_eq!(token.token_type, TokenType::Illegal, "{}", input);
+            assert_eq!(token.literal, input.trim_start_matches("x = "));
+            assert_eq!(lexer.next_token().token_type, TokenType::Eof);
+        }
+    }
+
+    #[test]
+    fn test_token_spans_and_positions() {
+        let input = "let имя = \"привет\"; // комментарий\nx == 10";
+        let mut lexer = Lexer::from_str(input);
+
+        let mut tokens = Vec::new();
+        loop {
+            let tok = lexer.next_token();
+            let eof = tok.token_type == TokenType::Eof;
+            tokens.push(tok);
+            if eof {
+                break;
+            }
+        }
+
+        let spans: Vec<(&str, usize, usize, usize, usize)> = tokens
+            .iter()
+            .map(|t| {
+                let span = t.span;
+                (
+                    t.literal.as_str(),
+                    span.start,
+                    span.end,
+                    span.line,
+                    span.column,
+                )
+            })
+            .collect();
+        assert_eq!(
+            spans,
+            vec![
+                ("let", 0, 3, 1, 1),
+                ("имя", 4, 10, 1, 5),
+                ("=", 11, 12, 1, 9),
+                ("привет", 13, 27, 1, 11),
+                (";", 27, 28, 1, 19),
+                ("x", 55, 56, 2, 1),
+                ("==", 57, 59, 2, 3),
+                ("10", 60, 62, 2, 6),
+                ("", 62, 62, 2, 8),
+            ]
+        );
+        for tok in &tokens[..tokens.len() - 1] {
+            let text = &input[tok.span.start..tok.span.end];
+            assert!(text.contains(tok.literal.as_str()), "{:?}", text);
+        }
+    }
+
+    // Прежняя реализация лексера поверх Vec<char> — эталон для сравнения
+    // потоков токенов с текущей реализацией.
+    struct CharVecLexer {
+        input: Vec<char>,
+        position: usize,
+        read_position: usize,
+        ch: char,
+    }
+
+    impl CharVecLexer {
+        fn new(input: &str) -> Self {
+            let mut lexer = CharVecLexer {
+                input: input.chars().collect(),
+                position: 0,
+                read_position: 0,
+                ch: '\0',
+            };
+            lexer.read_char();
+            lexer
+        }
+
+        fn read_char(&mut self) {
+            self.ch = self.input.get(self.read_position).copied().unwrap_or('\0');
+            self.position = self.read_position;
+            self.read_position += 1;
+        }
+
+        fn peek_char(&self) -> char {
+            self.input.get(self.read_position).copied().unwrap_or('\0')
+        }
+
+        fn next_token(&mut self) -> (TokenType, String) {
+            loop {
+                while self.ch.is_whitespace() {
+                    self.read_char();
+                }
+                if !(self.ch == '/' && self.peek_char() == '/') {
+                    break;
+                }
+                while self.ch != '\n' && self.ch != '\0' {
+                    self.read_char();
+                }
+            }
+
+            let two = |first: char, second: char| -> Option<&'static str> {
+                match (first, second) {
+                    ('=', '=') => Some("=="),
+                    ('=', '>') => Some("=>"),
+                    ('!', '=') => Some("!="),
+                    ('*', '*') => Some("**"),
+                    ('&', '&') => Some("&&"),
+                    ('|', '|') => Some("||"),
+                    ('.', '.') => Some(".."),
+                    _ => None,
+                }
+            };
+            if let Some(op) = two(self.ch, self.peek_char()) {
+                self.read_char();
+                self.read_char();
+                let token_type = match op {
+                    "==" => TokenType::Eq,
+                    "=>" => TokenType::Arrow,
+                    "!=" => TokenType::NotEq,
+                    "**" => TokenType::Power,
+                    "&&" => TokenType::And,
+                    "||" => TokenType::Or,
+                    _ => TokenType::Range,
+                };
+                return (token_type, op.to_string());
+            }
+
+            let token_type = match self.ch {
+                '=' => TokenType::Assign,
+                '!' => TokenType::Bang,
+                '+' => TokenType::Plus,
+                '-' => TokenType::Minus,
+                '/' => TokenType::Slash,
+                '*' => TokenType::Asterisk,
+                '<' => TokenType::Lt,
+                '>' => TokenType::Gt,
+                '%' => TokenType::Modulo,
+                ';' => TokenType::Semicolon,
+                ',' => TokenType::Comma,
+                ':' => TokenType::Colon,
+                '.' => TokenType::Dot,
+                '(' => TokenType::LParen,
+                ')' => TokenType::RParen,
+                '{' => TokenType::LBrace,
+                '}' => TokenType::RBrace,
+                '[' => TokenType::LBracket,
+                ']' => TokenType::RBracket,
+                '"' => {
+                    let start = self.position + 1;
+                    loop {
+                        self.read_char();
+                        if matches!(self.ch, '"' | '\'' | '`' | '\0') {
+                            break;
+                        }
+                    }
+                    let literal = self.input[start..self.position].iter().collect();
+                    self.read_char();
+                    return (TokenType::String, literal);
+                }
+                '\0' => return (TokenType::Eof, String::new()),
+                ch if ch.is_alphabetic() || ch == '_' => {
+                    let start = self.position;
+                    while self.ch.is_alphabetic() || self.ch == '_' || self.ch.is_ascii_digit() {
+                        self.read_char();
+                    }
+                    let literal: String = self.input[start..self.position].iter().collect();
+                    return (Lexer::lookup_ident(&literal), literal);
+                }
+                ch if ch.is_ascii_digit() => {
+                    let start = self.position;
+                    while self.ch.is_ascii_digit() {
+                        self.read_char();
+                    }
+                    let literal = self.input[start..self.position].iter().collect();
+                    return (TokenType::Int, literal);
+                }
+                _ => TokenType::Illegal,
+            };
+            let literal = self.ch.to_string();
+            self.read_char();
+            (token_type, literal)
+        }
+    }
+
+    #[test]
+    fn test_large_source_matches_char_vec_lexer() {
+        let chunk = r#"
+            // комментарий с юникодом: λ → ∞
+            let счёт_1 = fn(x, y) { if (x < y) { x ** 2 } else { y % 3 } };
+            class Точка extends База { let x = 0; move(dx) { this.x = this.x + dx; } }
+            match (1..5) { 1 => true, _ => false };
+            let s = "строка с пробелами"; let t = "ok";
+            [1, 2, 3][0] != 4 && !false || a == b => c;
+            obj.public: @ & | #
+        "#;
+        let mut source = String::new();
+        while source.len() < 1024 * 1024 {
+            source.push_str(chunk);
+        }
+
+        let mut expected = CharVecLexer::new(&source);
+        let mut lexer = Lexer::from_str(&source);
+        let mut count = 0;
+        loop {
+            let (expected_type, expected_literal) = expected.next_token();
+            let tok = lexer.next_token();
+            assert_eq!(
+                (tok.token_type, tok.literal.as_str()),
+                (expected_type, expected_literal.as_str()),
+                "токен #{} расходится с эталоном",
+                count
+            );
+            assert!(source.is_char_boundary(tok.span.start));
+            assert!(source.is_char_boundary(tok.span.end));
+            count += 1;
+            if expected_type == TokenType::Eof {
+                break;
+            }
+        }
+        assert!(count > 100_000, "слишком мало токенов: {}", count);
+    }
 }
@@ -2,11 +2,16 @@
 // Экспортирует все публичные модули для использования в тестах и других крейтах
 
 pub mod ast;
+pub mod builtins;
 pub mod bytecode;
+pub mod checker;
 pub mod compiler;
 pub mod evaluator;
 pub mod lexer;
 pub mod object;
 pub mod parser;
+pub mod repl;
+pub mod runner;
+pub mod test_runner;
 pub mod token;
 pub mod vm;
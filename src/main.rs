@@ -1,78 +1,164 @@
 pub mod ast;
+pub mod builtins;
 pub mod bytecode;
+pub mod checker;
 pub mod compiler;
 pub mod evaluator;
 pub mod lexer;
 pub mod object;
 pub mod parser;
+pub mod repl;
+pub mod runner;
+pub mod test_runner;
 pub mod token;
 pub mod vm;
 
 use crate::compiler::Compiler;
-use crate::evaluator::eval;
 use crate::lexer::Lexer;
-use crate::object::Environment;
 use crate::parser::Parser;
+use crate::repl::{History, Repl};
 use crate::vm::VM;
-use std::cell::RefCell;
 use std::env;
-use std::io::{self, Write};
-use std::rc::Rc;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::process;
 
 fn main() {
     // Проверяем аргументы командной строки для выбора исполнителя
     let args: Vec<String> = env::args().collect();
-    let use_vm = !args.contains(&"--ast".to_string());
 
-    let env_ref = Rc::new(RefCell::new(Environment::new()));
+    // Режим запуска тестов: sofia --test <каталог>
+    if let Some(pos) = args.iter().position(|a| a == "--test") {
+        let dir = args.get(pos + 1).map(String::as_str).unwrap_or(".");
+        process::exit(run_test_mode(Path::new(dir)));
+    }
+
+    // Режим статической проверки: sofia --check <файл>
+    if let Some(pos) = args.iter().position(|a| a == "--check") {
+        let Some(file) = args.get(pos + 1) else {
+            eprintln!("ERROR: --check requires a file");
+            process::exit(2);
+        };
+        process::exit(run_check_mode(Path::new(file)));
+    }
+
+    // Профилирование на VM: sofia --profile <файл>
+    if let Some(pos) = args.iter().position(|a| a == "--profile") {
+        let Some(file) = args.get(pos + 1) else {
+            eprintln!("ERROR: --profile requires a file");
+            process::exit(2);
+        };
+        process::exit(run_profile_mode(Path::new(file)));
+    }
+
+    let use_vm = !args.contains(&"--ast".to_string());
 
     println!(
         "SOFIA Interpreter (Bytecode VM: {})",
         if use_vm { "ON" } else { "OFF" }
     );
 
-    loop {
-        print!(">> ");
-        io::stdout().flush().unwrap();
+    // История не обязательна: при ошибке чтения работаем без файла
+    let history = match repl::default_history_path() {
+        Some(path) => History::load(path, repl::HISTORY_CAPACITY).unwrap_or_else(|e| {
+            eprintln!("warning: could not load history: {}", e);
+            History::new(repl::HISTORY_CAPACITY)
+        }),
+        None => History::new(repl::HISTORY_CAPACITY),
+    };
 
-        let mut input = String::new();
-        if io::stdin().read_line(&mut input).is_err() {
-            break;
-        }
+    let stdin = io::stdin();
+    let mut repl = Repl::new(stdin.lock(), io::stdout(), use_vm, history);
+    if let Err(e) = repl.run() {
+        eprintln!("ERROR: {}", e);
+        process::exit(1);
+    }
+}
 
-        let lexer = Lexer::new(input);
-        let mut parser = Parser::new(lexer);
-        let program = match parser.parse_program() {
-            Ok(program) => program,
-            Err(errors) => {
-                for error in errors {
-                    println!("\t{:?}", error);
-                }
-                continue;
+/// Запустить тесты из каталога и вернуть код завершения процесса.
+fn run_test_mode(dir: &Path) -> i32 {
+    match test_runner::run_tests(dir) {
+        Ok(report) => {
+            print!("{}", report.output);
+            if report.success() {
+                0
+            } else {
+                1
             }
-        };
+        }
+        Err(e) => {
+            eprintln!("ERROR: {}: {}", dir.display(), e);
+            2
+        }
+    }
+}
 
-        if use_vm {
-            // Используем VM
-            match run_with_vm(program) {
-                Ok(result) => println!("{}", result),
-                Err(e) => println!("ERROR: {}", e),
+/// Проверить файл без выполнения и вернуть код завершения процесса:
+/// 0 — программа разобрана (предупреждения не влияют), 1 — ошибки разбора.
+fn run_check_mode(path: &Path) -> i32 {
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("ERROR: {}: {}", path.display(), e);
+            return 2;
+        }
+    };
+    let program = match Parser::new(Lexer::new(source)).parse_program() {
+        Ok(program) => program,
+        Err(errors) => {
+            for error in errors {
+                println!("{}: {:?}", path.display(), error);
             }
-        } else {
-            // Используем AST-интерпретатор
-            let evaluated = eval(ast::Node::Program(program), Rc::clone(&env_ref));
-            println!("{}", evaluated);
+            return 1;
         }
+    };
+    for diagnostic in checker::check_program(&program) {
+        println!("{}: {}", path.display(), diagnostic);
     }
+    0
 }
 
-/// Запустить программу на VM.
-fn run_with_vm(program: ast::Program) -> Result<String, String> {
-    let mut compiler = Compiler::new();
-    let instructions = compiler.compile(&program)?;
-
+/// Выполнить файл на VM с профилированием, вывести результат и отчёт и
+/// вернуть код завершения процесса: 0 — успех, 1 — ошибка разбора,
+/// компиляции или выполнения. Отчёт выводится и после ошибки выполнения.
+fn run_profile_mode(path: &Path) -> i32 {
+    let source = match fs::read_to_string(path) {
+        Ok(source) => source,
+        Err(e) => {
+            eprintln!("ERROR: {}: {}", path.display(), e);
+            return 2;
+        }
+    };
+    let program = match Parser::new(Lexer::new(source)).parse_program() {
+        Ok(program) => program,
+        Err(errors) => {
+            for error in errors {
+                println!("{}: {:?}", path.display(), error);
+            }
+            return 1;
+        }
+    };
+    let instructions = match Compiler::new().compile(&program) {
+        Ok(instructions) => instructions,
+        Err(e) => {
+            println!("ERROR: {}", e);
+            return 1;
+        }
+    };
     let mut vm = VM::new(instructions);
-    let result = vm.run()?;
-
-    Ok(result.to_string())
+    vm.enable_profiling();
+    let code = match vm.run() {
+        Ok(result) => {
+            println!("{}", result);
+            0
+        }
+        Err(e) => {
+            println!("ERROR: {}", e);
+            1
+        }
+    };
+    println!();
+    print!("{}", vm.profile_report());
+    code
 }
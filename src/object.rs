@@ -1,7 +1,9 @@
-use crate::ast::{BlockStatement, Identifier};
+use crate::ast::{format_parameters, BlockStatement, Identifier};
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::convert::Infallible;
 use std::fmt;
+use std::hash::Hash;
 use std::rc::Rc;
 
 /// Represents a compiled function's bytecode metadata.
@@ -12,16 +14,69 @@ pub struct CompiledFunction {
     pub num_params: usize,
 }
 
+impl CompiledFunction {
+    /// Краткая запись для листингов: `fn/2 @0x0040 (3 locals)` — число
+    /// параметров, смещение тела и размер кадра.
+    pub fn summary(&self) -> String {
+        format!(
+            "fn/{} @{:#06x} ({} locals)",
+            self.num_params, self.instructions_offset, self.num_locals
+        )
+    }
+}
+
+/// Замыкание VM: скомпилированная функция и значения её свободных
+/// переменных. Опкод `Closure` создаёт новое замыкание при каждом
+/// вычислении литерала функции.
 #[derive(Debug, PartialEq, Clone)]
+pub struct Closure {
+    pub function: CompiledFunction,
+    pub free: Vec<Object>,
+}
+
+impl fmt::Display for CompiledFunction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "compiled function ({} params, {} locals)",
+            self.num_params, self.num_locals
+        )
+    }
+}
+
+/// Значение SOFIA. Функции и методы сравниваются по идентичности (см.
+/// `PartialEq`), остальные значения — по содержимому.
+#[derive(Clone)]
 pub enum Object {
     Integer(i64),
     Boolean(bool),
     Null,
     ReturnValue(Box<Object>),
-    Error(String),
-    Function(Vec<Identifier>, BlockStatement, Rc<RefCell<Environment>>),
+    /// Сигналы `break` и `continue`: поднимаются из блоков до ближайшего
+    /// цикла и наружу не выходят.
+    Break,
+    Continue,
+    /// Ошибка выполнения интерпретатора вместе с трассировкой вызовов.
+    Error(RuntimeError),
+    /// Параметры, необязательный rest-параметр, тело и окружение замыкания.
+    /// Rest-параметр лежит в `Box`, чтобы не раздувать размер `Object`. Тело
+    /// общее у всех копий значения: каждое вычисление литерала `fn` создаёт
+    /// новое тело, по нему и сравниваются функции.
+    Function(
+        Vec<Identifier>,
+        Option<Box<Identifier>>,
+        Rc<BlockStatement>,
+        Rc<RefCell<Environment>>,
+    ),
     String(String),
-    Array(Vec<Object>),
+    /// Полуоткрытый диапазон целых чисел `start..end`.
+    Range(i64, i64),
+    /// Элементы хранятся за `Rc`: чтение переменной с массивом не копирует его.
+    Array(Rc<Vec<Object>>),
+    /// Хэш с ключами-целыми, логическими значениями или строками (`HashKey`).
+    /// Равенство сравнивает содержимое без учёта порядка вставки, `Display`
+    /// выводит ключи в порядке вставки.
+    Hash(Rc<OrderedMap<Object, HashKey>>),
     Class(Rc<RefCell<Class>>),
     ClassInstance(Rc<RefCell<ClassInstance>>),
     Struct(Rc<RefCell<Struct>>),
@@ -29,7 +84,7 @@ pub enum Object {
     Interface(Rc<RefCell<Interface>>),
     Method(Rc<RefCell<Method>>),
     CompiledFunction(CompiledFunction),
-    Closure(Box<CompiledFunction>, Vec<Object>),
+    Closure(Rc<Closure>),
     BuiltinFunction {
         name: String,
         num_params: i32,
@@ -37,6 +92,197 @@ pub enum Object {
     },
 }
 
+/// Ошибка выполнения: сообщение и кадры вызовов, через которые она
+/// поднялась, от внутреннего к внешнему (`f()`, ..., `main`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuntimeError {
+    pub message: String,
+    pub trace: Vec<String>,
+}
+
+impl RuntimeError {
+    pub fn new(message: impl Into<String>) -> Self {
+        RuntimeError {
+            message: message.into(),
+            trace: Vec::new(),
+        }
+    }
+
+    /// Та же ошибка с ещё одним, более внешним кадром трассировки.
+    pub fn with_frame(mut self, frame: impl Into<String>) -> Self {
+        self.trace.push(frame.into());
+        self
+    }
+}
+
+/// Сообщение и под ним по строке `  in <кадр>` на каждый кадр трассировки.
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.message)?;
+        for frame in &self.trace {
+            write!(f, "\n  in {}", frame)?;
+        }
+        Ok(())
+    }
+}
+
+/// Как производный `Debug`, но функция выводится без тела и окружения
+/// (`Function(fn(x) { ... })`): окружение замыкания может содержать саму
+/// функцию, и вывод не закончился бы.
+impl fmt::Debug for Object {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Object::Integer(value) => f.debug_tuple("Integer").field(value).finish(),
+            Object::Boolean(value) => f.debug_tuple("Boolean").field(value).finish(),
+            Object::Null => write!(f, "Null"),
+            Object::ReturnValue(value) => f.debug_tuple("ReturnValue").field(value).finish(),
+            Object::Break => write!(f, "Break"),
+            Object::Continue => write!(f, "Continue"),
+            Object::Error(error) => f.debug_tuple("Error").field(&error.to_string()).finish(),
+            Object::Function(..) => write!(f, "Function({})", self),
+            Object::String(value) => f.debug_tuple("String").field(value).finish(),
+            Object::Range(start, end) => f.debug_tuple("Range").field(start).field(end).finish(),
+            Object::Array(elements) => f.debug_tuple("Array").field(elements).finish(),
+            Object::Hash(pairs) => f.debug_tuple("Hash").field(pairs).finish(),
+            Object::Class(class) => f.debug_tuple("Class").field(class).finish(),
+            Object::ClassInstance(instance) => {
+                f.debug_tuple("ClassInstance").field(instance).finish()
+            }
+            Object::Struct(struct_def) => f.debug_tuple("Struct").field(struct_def).finish(),
+            Object::StructInstance(instance) => {
+                f.debug_tuple("StructInstance").field(instance).finish()
+            }
+            Object::Interface(interface) => f.debug_tuple("Interface").field(interface).finish(),
+            Object::Method(method) => f.debug_tuple("Method").field(method).finish(),
+            Object::CompiledFunction(function) => {
+                f.debug_tuple("CompiledFunction").field(function).finish()
+            }
+            Object::Closure(closure) => f
+                .debug_tuple("Closure")
+                .field(&closure.function)
+                .field(&closure.free)
+                .finish(),
+            Object::BuiltinFunction {
+                name,
+                num_params,
+                handler: _,
+            } => f
+                .debug_struct("BuiltinFunction")
+                .field("name", name)
+                .field("num_params", num_params)
+                .finish_non_exhaustive(),
+        }
+    }
+}
+
+// Функции и методы сравниваются по идентичности: сравнение тел и
+// захваченных окружений было бы дорогим, а для замыкания, которое видит
+// само себя, — бесконечным
+#[allow(unpredictable_function_pointer_comparisons)]
+impl PartialEq for Object {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Object::Integer(a), Object::Integer(b)) => a == b,
+            (Object::Boolean(a), Object::Boolean(b)) => a == b,
+            (Object::Null, Object::Null)
+            | (Object::Break, Object::Break)
+            | (Object::Continue, Object::Continue) => true,
+            (Object::ReturnValue(a), Object::ReturnValue(b)) => a == b,
+            (Object::Error(a), Object::Error(b)) => a == b,
+            // Копии одного значения: то же вычисление литерала в том же окружении
+            (Object::Function(_, _, body_a, env_a), Object::Function(_, _, body_b, env_b)) => {
+                Rc::ptr_eq(body_a, body_b) && Rc::ptr_eq(env_a, env_b)
+            }
+            (Object::String(a), Object::String(b)) => a == b,
+            (Object::Range(a_start, a_end), Object::Range(b_start, b_end)) => {
+                a_start == b_start && a_end == b_end
+            }
+            (Object::Array(a), Object::Array(b)) => a == b,
+            // Порядок вставки на равенство не влияет
+            (Object::Hash(a), Object::Hash(b)) => a.same_entries(b),
+            (Object::Class(a), Object::Class(b)) => a == b,
+            (Object::ClassInstance(a), Object::ClassInstance(b)) => a == b,
+            (Object::Struct(a), Object::Struct(b)) => a == b,
+            (Object::StructInstance(a), Object::StructInstance(b)) => a == b,
+            (Object::Interface(a), Object::Interface(b)) => a == b,
+            (Object::Method(a), Object::Method(b)) => Rc::ptr_eq(a, b) || a == b,
+            (Object::CompiledFunction(a), Object::CompiledFunction(b)) => a == b,
+            // Как у функций интерпретатора: одно и то же вычисление литерала
+            (Object::Closure(a), Object::Closure(b)) => Rc::ptr_eq(a, b),
+            (
+                Object::BuiltinFunction {
+                    name: a_name,
+                    num_params: a_params,
+                    handler: a_handler,
+                },
+                Object::BuiltinFunction {
+                    name: b_name,
+                    num_params: b_params,
+                    handler: b_handler,
+                },
+            ) => a_name == b_name && a_params == b_params && a_handler == b_handler,
+            _ => false,
+        }
+    }
+}
+
+impl Object {
+    /// Текст значения, как у `Display`, но текст экземпляров классов,
+    /// в том числе вложенных в массивы, хэши и структуры, даёт `instance`;
+    /// `Ok(None)` оставляет `instance of Имя`. Ошибка `instance` прерывает
+    /// вывод и возвращается как есть.
+    pub fn render<E, F>(&self, instance: &mut F) -> Result<String, E>
+    where
+        F: FnMut(&Rc<RefCell<ClassInstance>>) -> Result<Option<String>, E>,
+    {
+        match self {
+            Object::ReturnValue(value) => value.render(instance),
+            Object::Array(elements) => {
+                let elements = elements
+                    .iter()
+                    .map(|e| e.render(instance))
+                    .collect::<Result<Vec<_>, E>>()?;
+                Ok(format!("[{}]", elements.join(", ")))
+            }
+            Object::Hash(pairs) => {
+                let pairs = pairs
+                    .iter()
+                    .map(|(key, value)| Ok(format!("{}: {}", key, value.render(instance)?)))
+                    .collect::<Result<Vec<_>, E>>()?;
+                Ok(format!("{{{}}}", pairs.join(", ")))
+            }
+            Object::ClassInstance(i) => match instance(i)? {
+                Some(text) => Ok(text),
+                None => Ok(format!("instance of {}", i.borrow().class.borrow().name)),
+            },
+            // Как литерал структуры в Rust: `Point { x: 1, y: 2 }`
+            Object::StructInstance(i) => {
+                let instance_ref = i.borrow();
+                let fields = instance_ref
+                    .fields
+                    .iter()
+                    .map(|(name, value)| Ok(format!("{}: {}", name, value.render(instance)?)))
+                    .collect::<Result<Vec<_>, E>>()?;
+                let name = &instance_ref.struct_def.borrow().name;
+                if fields.is_empty() {
+                    Ok(format!("{} {{}}", name))
+                } else {
+                    Ok(format!("{} {{ {} }}", name, fields.join(", ")))
+                }
+            }
+            other => Ok(other.to_string()),
+        }
+    }
+
+    /// `render` без пользовательских `to_string()`.
+    fn render_plain(&self) -> String {
+        match self.render(&mut |_| Ok::<_, Infallible>(None)) {
+            Ok(text) => text,
+            Err(never) => match never {},
+        }
+    }
+}
+
 impl fmt::Display for Object {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
@@ -44,36 +290,35 @@ impl fmt::Display for Object {
             Object::Boolean(value) => write!(f, "{}", value),
             Object::Null => write!(f, "null"),
             Object::ReturnValue(value) => write!(f, "{}", value),
-            Object::Error(message) => write!(f, "ERROR: {}", message),
-            Object::Function(parameters, body, _) => {
-                let params: Vec<String> = parameters.iter().map(|p| p.value.clone()).collect();
-                write!(f, "fn({}) {{\n{}\n}}", params.join(", "), body)
-            }
+            Object::Break => write!(f, "break"),
+            Object::Continue => write!(f, "continue"),
+            Object::Error(error) => write!(f, "ERROR: {}", error),
+            // Тело не выводится: большая функция в сообщении об ошибке или
+            // в REPL заняла бы весь экран; полный текст — `function_source`
+            Object::Function(parameters, rest, _, _) => write!(
+                f,
+                "fn({}) {{ ... }}",
+                format_parameters(parameters, rest.as_deref(), None)
+            ),
             Object::String(value) => write!(f, "{}", value),
-            Object::Array(elements) => {
-                let elements: Vec<String> = elements.iter().map(|e| e.to_string()).collect();
-                write!(f, "[{}]", elements.join(", "))
-            }
+            Object::Range(start, end) => write!(f, "{}..{}", start, end),
+            Object::Array(_) | Object::Hash(_) => f.write_str(&self.render_plain()),
             Object::Class(c) => write!(f, "class {}", c.borrow().name),
-            Object::ClassInstance(i) => write!(f, "instance of {}", i.borrow().class.borrow().name),
+            // Без пользовательского to_string(): его вызывает только
+            // интерпретатор при выводе (см. `Object::render`)
+            Object::ClassInstance(_) | Object::StructInstance(_) => {
+                f.write_str(&self.render_plain())
+            }
             Object::Struct(s) => write!(f, "struct {}", s.borrow().name),
-            Object::StructInstance(i) => write!(
-                f,
-                "instance of struct {}",
-                i.borrow().struct_def.borrow().name
-            ),
             Object::Interface(i) => write!(f, "interface {}", i.borrow().name),
             Object::Method(m) => write!(f, "method {}", m.borrow().name),
-            Object::CompiledFunction(cf) => write!(
+            Object::CompiledFunction(cf) => write!(f, "{}", cf),
+            Object::Closure(closure) => write!(
                 f,
-                "compiled fn(offset={}, locals={}, params={})",
-                cf.instructions_offset, cf.num_locals, cf.num_params
-            ),
-            Object::Closure(cf, free) => write!(
-                f,
-                "closure(offset={}, free={})",
-                cf.instructions_offset,
-                free.len()
+                "closure ({} params, {} locals, {} free)",
+                closure.function.num_params,
+                closure.function.num_locals,
+                closure.free.len()
             ),
             Object::BuiltinFunction { name, .. } => write!(f, "builtin fn {}", name),
         }
@@ -109,17 +354,31 @@ const INTERFACE: &str = "INTERFACE";
 #[allow(dead_code)]
 const METHOD: &str = "METHOD";
 
+/// Имена встроенных типов, которые понимает `is` (`value is Integer`).
+pub const PRIMITIVE_TYPE_NAMES: &[&str] = &[
+    "Integer", "Boolean", "String", "Array", "Hash", "Range", "Null", "Function",
+];
+
 impl Object {
+    /// Ошибка выполнения без трассировки.
+    pub fn error(message: impl Into<String>) -> Object {
+        Object::Error(RuntimeError::new(message))
+    }
+
     pub fn type_str(&self) -> &str {
         match self {
             Object::Integer(_) => INTEGER,
             Object::Boolean(_) => BOOLEAN,
             Object::Null => NULL,
             Object::ReturnValue(_) => RETURN_VALUE,
+            Object::Break => "BREAK",
+            Object::Continue => "CONTINUE",
             Object::Error(_) => ERROR,
-            Object::Function(_, _, _) => FUNCTION,
+            Object::Function(..) => FUNCTION,
             Object::String(_) => STRING,
             Object::Array(_) => ARRAY,
+            Object::Hash(_) => "HASH",
+            Object::Range(_, _) => "RANGE",
             Object::Class(_) => "CLASS",
             Object::ClassInstance(_) => "CLASS_INSTANCE",
             Object::Struct(_) => "STRUCT",
@@ -127,10 +386,612 @@ impl Object {
             Object::Interface(_) => "INTERFACE",
             Object::Method(_) => "METHOD",
             Object::CompiledFunction(_) => "COMPILED_FUNCTION",
-            Object::Closure(_, _) => "CLOSURE",
+            Object::Closure(_) => "CLOSURE",
             Object::BuiltinFunction { .. } => "BUILTIN_FUNCTION",
         }
     }
+
+    /// `value is Type` для встроенного типа по имени (`Integer`, `String`, ...).
+    /// Возвращает `None`, если имя не обозначает встроенный тип.
+    pub fn is_primitive_type(&self, type_name: &str) -> Option<bool> {
+        let matches = match type_name {
+            "Integer" => matches!(self, Object::Integer(_)),
+            "Boolean" => matches!(self, Object::Boolean(_)),
+            "String" => matches!(self, Object::String(_)),
+            "Array" => matches!(self, Object::Array(_)),
+            "Hash" => matches!(self, Object::Hash(_)),
+            "Range" => matches!(self, Object::Range(_, _)),
+            "Null" => matches!(self, Object::Null),
+            "Function" => matches!(
+                self,
+                Object::Function(..)
+                    | Object::CompiledFunction(_)
+                    | Object::Closure(..)
+                    | Object::BuiltinFunction { .. }
+            ),
+            _ => return None,
+        };
+        Some(matches)
+    }
+
+    /// `value is Type` для пользовательского типа: экземпляр класса проверяется
+    /// против интерфейса (`Class::implements`) и против класса с учётом
+    /// наследования, экземпляр структуры — против своей структуры. Для прочих
+    /// значений — false. Возвращает `None`, если `type_object` не тип.
+    pub fn is_instance_of(&self, type_object: &Object) -> Option<bool> {
+        let matches = match (type_object, self) {
+            (Object::Interface(interface), Object::ClassInstance(instance)) => {
+                instance.borrow().class.borrow().implements(interface)
+            }
+            (Object::Class(class), Object::ClassInstance(instance)) => {
+                is_subclass(&instance.borrow().class, class)
+            }
+            (Object::Struct(struct_def), Object::StructInstance(instance)) => {
+                Rc::ptr_eq(&instance.borrow().struct_def, struct_def)
+            }
+            (Object::Interface(_) | Object::Class(_) | Object::Struct(_), _) => false,
+            _ => return None,
+        };
+        Some(matches)
+    }
+
+    /// Глубокая копия значения: массивы, хэши и экземпляры классов и структур
+    /// копируются рекурсивно, остальные значения (числа, строки, функции,
+    /// сами классы) возвращаются как есть. Экземпляр, на который копируемое
+    /// значение ссылается несколько раз (в том числе циклически), копируется
+    /// один раз, и копии ссылаются на него так же.
+    pub fn deep_clone(&self) -> Object {
+        self.deep_clone_with(&mut HashMap::new())
+    }
+
+    /// `copies` — уже скопированные экземпляры по адресу оригинала.
+    fn deep_clone_with(&self, copies: &mut HashMap<usize, Object>) -> Object {
+        match self {
+            Object::Array(items) => Object::Array(Rc::new(
+                items
+                    .iter()
+                    .map(|item| item.deep_clone_with(copies))
+                    .collect(),
+            )),
+            Object::Hash(pairs) => Object::Hash(Rc::new(
+                pairs
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.deep_clone_with(copies)))
+                    .collect(),
+            )),
+            Object::ClassInstance(instance) => {
+                deep_clone_instance(instance, copies, Object::ClassInstance, |i| &mut i.fields)
+            }
+            Object::StructInstance(instance) => {
+                deep_clone_instance(instance, copies, Object::StructInstance, |i| &mut i.fields)
+            }
+            _ => self.clone(),
+        }
+    }
+
+    /// Полный текст функции с телом: `fn(x) {\n<тело>\n}`. `Display`
+    /// тело опускает; для остальных значений — `None`.
+    pub fn function_source(&self) -> Option<String> {
+        match self {
+            Object::Function(parameters, rest, body, _) => Some(format!(
+                "fn({}) {{\n{}\n}}",
+                format_parameters(parameters, rest.as_deref(), None),
+                body
+            )),
+            _ => None,
+        }
+    }
+
+    /// Однострочное описание значения для листингов байткода и отладки.
+    /// В отличие от `Display`, не вызывает пользовательский `to_string()`:
+    /// строки выводятся в кавычках и обрезаются, большие массивы и хэши
+    /// сводятся к числу элементов, функции — к числу параметров.
+    pub fn summary(&self) -> String {
+        match self {
+            Object::ReturnValue(value) => value.summary(),
+            Object::String(value) => summarize_string(value),
+            Object::Array(elements) if elements.len() > SUMMARY_COLLECTION_LIMIT => {
+                format!("[{} elements]", elements.len())
+            }
+            Object::Array(elements) => {
+                let elements: Vec<String> = elements.iter().map(Object::summary).collect();
+                format!("[{}]", elements.join(", "))
+            }
+            Object::Hash(pairs) if pairs.len() > SUMMARY_COLLECTION_LIMIT => {
+                format!("{{{} entries}}", pairs.len())
+            }
+            Object::Hash(pairs) => {
+                let pairs: Vec<String> = pairs
+                    .iter()
+                    .map(|(key, value)| {
+                        let key_text = match key {
+                            HashKey::String(text) => summarize_string(text),
+                            other => other.to_string(),
+                        };
+                        format!("{}: {}", key_text, value.summary())
+                    })
+                    .collect();
+                format!("{{{}}}", pairs.join(", "))
+            }
+            Object::Function(parameters, rest, _, _) => format!(
+                "fn/{}{}",
+                parameters.len(),
+                if rest.is_some() { "+" } else { "" }
+            ),
+            Object::ClassInstance(i) => format!("instance of {}", i.borrow().class.borrow().name),
+            Object::CompiledFunction(cf) => cf.summary(),
+            Object::Closure(closure) => format!(
+                "closure fn/{} @{:#06x} ({} locals, {} free)",
+                closure.function.num_params,
+                closure.function.instructions_offset,
+                closure.function.num_locals,
+                closure.free.len()
+            ),
+            _ => self.to_string(),
+        }
+    }
+}
+
+/// Копирует экземпляр класса или структуры для `Object::deep_clone_with`:
+/// один раз на адрес оригинала, затем рекурсивно его поля. `wrap` заворачивает
+/// копию в `Object`, `fields` даёт доступ к полям экземпляра.
+fn deep_clone_instance<T: Clone>(
+    instance: &Rc<RefCell<T>>,
+    copies: &mut HashMap<usize, Object>,
+    wrap: fn(Rc<RefCell<T>>) -> Object,
+    fields: fn(&mut T) -> &mut OrderedMap<Object>,
+) -> Object {
+    let key = Rc::as_ptr(instance) as usize;
+    if let Some(copy) = copies.get(&key) {
+        return copy.clone();
+    }
+    let copy = Rc::new(RefCell::new(instance.borrow().clone()));
+    copies.insert(key, wrap(Rc::clone(&copy)));
+    let mut copied = fields(&mut copy.borrow_mut()).clone();
+    for value in copied.values_mut() {
+        *value = value.deep_clone_with(copies);
+    }
+    *fields(&mut copy.borrow_mut()) = copied;
+    wrap(copy)
+}
+
+/// Ключ хэша. Ключами могут быть только значения с устойчивым равенством:
+/// целые числа, логические значения и строки. Хэш хранит ключи в порядке
+/// вставки (`OrderedMap`), в нём же их выводит и перечисляет.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum HashKey {
+    Integer(i64),
+    Boolean(bool),
+    String(String),
+}
+
+impl HashKey {
+    /// Ключ для значения — общее правило интерпретатора и VM. Для массивов,
+    /// функций, экземпляров и прочих значений — ошибка `unusable as hash key`.
+    pub fn from_object(value: &Object) -> Result<HashKey, String> {
+        match value {
+            Object::Integer(value) => Ok(HashKey::Integer(*value)),
+            Object::Boolean(value) => Ok(HashKey::Boolean(*value)),
+            Object::String(value) => Ok(HashKey::String(value.clone())),
+            other => Err(format!("unusable as hash key: {}", other.type_str())),
+        }
+    }
+
+    /// Значение языка для ключа — обратное к `from_object`.
+    pub fn to_object(&self) -> Object {
+        match self {
+            HashKey::Integer(value) => Object::Integer(*value),
+            HashKey::Boolean(value) => Object::Boolean(*value),
+            HashKey::String(value) => Object::String(value.clone()),
+        }
+    }
+}
+
+impl fmt::Display for HashKey {
+    /// Строки выводятся в кавычках, чтобы `{"1": a}` отличался от `{1: a}`.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            HashKey::Integer(value) => write!(f, "{}", value),
+            HashKey::Boolean(value) => write!(f, "{}", value),
+            HashKey::String(value) => write!(f, "{:?}", value),
+        }
+    }
+}
+
+/// Строки длиннее этого числа символов `Object::summary` обрезает.
+const SUMMARY_STRING_LIMIT: usize = 32;
+/// Массивы и хэши длиннее этого числа элементов `Object::summary` не раскрывает.
+const SUMMARY_COLLECTION_LIMIT: usize = 8;
+
+/// Строка в кавычках; длинная — обрезанная с многоточием и длиной в символах.
+fn summarize_string(value: &str) -> String {
+    let length = value.chars().count();
+    if length <= SUMMARY_STRING_LIMIT {
+        return format!("{:?}", value);
+    }
+    let head: String = value.chars().take(SUMMARY_STRING_LIMIT).collect();
+    format!("\"{}…\" ({} chars)", head.escape_debug(), length)
+}
+
+/// Сравнивает два значения оператором `<`, `>`, `<=`, `>=`, `==` или `!=`.
+///
+/// Общие правила интерпретатора и VM: целые числа сравниваются численно,
+/// строки — лексикографически по байтам UTF-8. Значения разных типов дают
+/// ошибку `type mismatch`, а упорядочивание других однотипных значений —
+/// `unknown operator`.
+pub fn compare_values(operator: &str, left: &Object, right: &Object) -> Result<bool, String> {
+    let ordering = match (left, right) {
+        (Object::Integer(l), Object::Integer(r)) => l.cmp(r),
+        (Object::String(l), Object::String(r)) => l.cmp(r),
+        _ if left.type_str() != right.type_str() => {
+            return Err(type_mismatch(left, operator, right));
+        }
+        _ => return Err(unknown_operator(left, operator, right)),
+    };
+    match operator {
+        "<" => Ok(ordering.is_lt()),
+        ">" => Ok(ordering.is_gt()),
+        "<=" => Ok(ordering.is_le()),
+        ">=" => Ok(ordering.is_ge()),
+        "==" => Ok(ordering.is_eq()),
+        "!=" => Ok(ordering.is_ne()),
+        _ => Err(unknown_operator(left, operator, right)),
+    }
+}
+
+/// Вычисляет `==` или `!=` (`operator`) одинаково в интерпретаторе и VM.
+/// null сравним с любым значением. Числа, строки и булевы значения
+/// сравниваются по значению, экземпляры структур — по полям, экземпляры
+/// классов и функции — по идентичности. Значения разных типов дают ошибку
+/// `type mismatch`, прочие значения одного типа — `unknown operator`.
+pub fn values_equal(operator: &str, left: &Object, right: &Object) -> Result<bool, String> {
+    let equal = match (left, right) {
+        (Object::Null, _) | (_, Object::Null) => {
+            matches!((left, right), (Object::Null, Object::Null))
+        }
+        (Object::Integer(_) | Object::String(_), _) => {
+            return compare_values(operator, left, right)
+        }
+        (Object::Boolean(l), Object::Boolean(r)) => l == r,
+        (Object::ClassInstance(l), Object::ClassInstance(r)) => Rc::ptr_eq(l, r),
+        (Object::StructInstance(l), Object::StructInstance(r)) => structs_equal(l, r),
+        (Object::Function(..), Object::Function(..))
+        | (Object::Method(_), Object::Method(_))
+        | (Object::CompiledFunction(_), Object::CompiledFunction(_))
+        | (Object::Closure(..), Object::Closure(..))
+        | (Object::BuiltinFunction { .. }, Object::BuiltinFunction { .. }) => left == right,
+        _ if left.type_str() != right.type_str() => {
+            return Err(type_mismatch(left, operator, right));
+        }
+        _ => return Err(unknown_operator(left, operator, right)),
+    };
+    Ok(equal == (operator == "=="))
+}
+
+/// Вычисляет арифметический оператор (`+`, `-`, `*`, `/`, `%`, `**`)
+/// одинаково в интерпретаторе и VM: целые числа, склейка строк и повтор
+/// строки (`"ab" * 3`). Над парой булевых значений, строк или экземпляров
+/// одного вида — ошибка `unknown operator`, над прочими — `type mismatch`.
+pub fn arithmetic(operator: &str, left: &Object, right: &Object) -> Result<Object, String> {
+    match (left, right) {
+        (Object::Integer(l), Object::Integer(r)) => match operator {
+            "+" => Ok(Object::Integer(l + r)),
+            "-" => Ok(Object::Integer(l - r)),
+            "*" => Ok(Object::Integer(l * r)),
+            "/" if *r == 0 => Err("division by zero".to_string()),
+            "/" => Ok(Object::Integer(l / r)),
+            "%" if *r == 0 => Err("modulo by zero".to_string()),
+            "%" => Ok(Object::Integer(l % r)),
+            "**" if *r < 0 => Err("negative exponent not supported".to_string()),
+            "**" => Ok(Object::Integer(l.pow(*r as u32))),
+            _ => Err(unknown_operator(left, operator, right)),
+        },
+        (Object::String(l), Object::String(r)) if operator == "+" => {
+            Ok(Object::String(format!("{}{}", l, r)))
+        }
+        (Object::String(_), Object::Integer(r)) if operator == "*" && *r < 0 => {
+            Err("negative multiplier not supported for string multiplication".to_string())
+        }
+        (Object::String(l), Object::Integer(r)) if operator == "*" => {
+            Ok(Object::String(l.repeat(*r as usize)))
+        }
+        (Object::Boolean(_), Object::Boolean(_))
+        | (Object::String(_), Object::String(_))
+        | (Object::ClassInstance(_), Object::ClassInstance(_))
+        | (Object::StructInstance(_), Object::StructInstance(_)) => {
+            Err(unknown_operator(left, operator, right))
+        }
+        _ => Err(type_mismatch(left, operator, right)),
+    }
+}
+
+/// Границы диапазонного паттерна `match` — общее правило интерпретатора и
+/// VM. Обе границы должны быть целыми, а диапазон — непустым: ветвь `5..1`
+/// никогда не совпадёт, это почти наверняка опечатка.
+pub fn range_pattern_bounds(
+    start: &Object,
+    end: &Object,
+    inclusive: bool,
+) -> Result<(i64, i64), String> {
+    match (start, end) {
+        (Object::Integer(start), Object::Integer(end)) if start > end => Err(format!(
+            "empty range pattern {}{}{}",
+            start,
+            if inclusive { "..=" } else { ".." },
+            end
+        )),
+        (Object::Integer(start), Object::Integer(end)) => Ok((*start, *end)),
+        _ => Err(format!(
+            "range pattern bounds must be integers, got {}..{}",
+            start.type_str(),
+            end.type_str()
+        )),
+    }
+}
+
+/// Попадает ли значение в диапазон паттерна; нецелое значение не попадает.
+pub fn in_range_pattern(value: &Object, start: i64, end: i64, inclusive: bool) -> bool {
+    match value {
+        Object::Integer(n) if inclusive => (start..=end).contains(n),
+        Object::Integer(n) => (start..end).contains(n),
+        _ => false,
+    }
+}
+
+/// Текст ошибки `match`, ни одна ветвь которого не подошла к значению.
+pub fn non_exhaustive_match(value: &Object) -> String {
+    format!("non-exhaustive match pattern for value: {}", value)
+}
+
+/// Текст ошибки для операции над значениями несовместимых типов.
+pub fn type_mismatch(left: &Object, operator: &str, right: &Object) -> String {
+    format!(
+        "type mismatch: {} {} {}",
+        left.type_str(),
+        operator,
+        right.type_str()
+    )
+}
+
+/// Текст ошибки для оператора, не определённого для типов операндов.
+pub fn unknown_operator(left: &Object, operator: &str, right: &Object) -> String {
+    format!(
+        "unknown operator: {} {} {}",
+        left.type_str(),
+        operator,
+        right.type_str()
+    )
+}
+
+/// Позиция элемента по индексу `index` в последовательности длины `len`:
+/// отрицательный индекс отсчитывается с конца (`-1` — последний элемент).
+/// `None`, если индекс вне `-len..len`.
+fn element_position(index: i64, len: usize) -> Option<usize> {
+    let len = len as i64;
+    let position = if index < 0 { len + index } else { index };
+    (0..len).contains(&position).then_some(position as usize)
+}
+
+/// Приводит границы среза к `0..=len`; перевёрнутый диапазон даёт пустой срез.
+fn clamp_slice_bounds(start: i64, end: i64, len: usize) -> (usize, usize) {
+    let from = start.clamp(0, len as i64) as usize;
+    let to = end.clamp(0, len as i64) as usize;
+    (from, to.max(from))
+}
+
+/// Чтение `left[index]` — общие правила интерпретатора и VM.
+///
+/// Массивы и строки (посимвольно) индексируются целым числом, отрицательный
+/// индекс считается с конца, индекс вне границ даёт Null; диапазон
+/// возвращает срез. Хэш индексируется строкой, отсутствующий ключ — Null.
+/// Остальные сочетания типов — ошибка, называющая оба типа.
+pub fn index_value(left: &Object, index: &Object) -> Result<Object, String> {
+    match (left, index) {
+        (Object::Array(elements), Object::Integer(idx)) => {
+            Ok(element_position(*idx, elements.len())
+                .map_or(Object::Null, |position| elements[position].clone()))
+        }
+        (Object::Array(elements), Object::Range(start, end)) => {
+            let (from, to) = clamp_slice_bounds(*start, *end, elements.len());
+            Ok(Object::Array(Rc::new(elements[from..to].to_vec())))
+        }
+        (Object::String(s), Object::Integer(idx)) => {
+            let chars: Vec<char> = s.chars().collect();
+            Ok(
+                element_position(*idx, chars.len()).map_or(Object::Null, |position| {
+                    Object::String(chars[position].to_string())
+                }),
+            )
+        }
+        (Object::String(s), Object::Range(start, end)) => {
+            let chars: Vec<char> = s.chars().collect();
+            let (from, to) = clamp_slice_bounds(*start, *end, chars.len());
+            Ok(Object::String(chars[from..to].iter().collect()))
+        }
+        (Object::Hash(pairs), key) => {
+            let key = HashKey::from_object(key)?;
+            Ok(pairs.get(&key).cloned().unwrap_or(Object::Null))
+        }
+        _ => Err(format!(
+            "index operator not supported: {}[{}]",
+            left.type_str(),
+            index.type_str()
+        )),
+    }
+}
+
+/// Запись `container[index] = value` — общие правила интерпретатора и VM.
+/// Возвращает обновлённый контейнер.
+///
+/// Массивы — значения: если массив разделяют несколько переменных,
+/// изменяется копия (`Rc::make_mut`), остальные её не видят. Индекс массива
+/// считается как при чтении, но запись вне границ — ошибка: массив не
+/// растёт. В хэш можно добавить новый ключ. Строки неизменяемы.
+pub fn set_index(container: Object, index: &Object, value: Object) -> Result<Object, String> {
+    match (container, index) {
+        (Object::Array(mut elements), Object::Integer(idx)) => {
+            let Some(position) = element_position(*idx, elements.len()) else {
+                return Err(format!(
+                    "index out of range: {} for ARRAY of length {}",
+                    idx,
+                    elements.len()
+                ));
+            };
+            Rc::make_mut(&mut elements)[position] = value;
+            Ok(Object::Array(elements))
+        }
+        (Object::Hash(mut pairs), key) => {
+            let key = HashKey::from_object(key)?;
+            Rc::make_mut(&mut pairs).insert(key, value);
+            Ok(Object::Hash(pairs))
+        }
+        (container, _) => Err(format!(
+            "index assignment not supported: {}[{}]",
+            container.type_str(),
+            index.type_str()
+        )),
+    }
+}
+
+/// Поля нового экземпляра: копия значений по умолчанию, которым по порядку
+/// объявления присвоены аргументы `new`. Общее правило интерпретатора и VM.
+pub fn instance_fields(
+    type_name: &str,
+    mut fields: OrderedMap<Object>,
+    args: Vec<Object>,
+) -> Result<OrderedMap<Object>, String> {
+    if args.len() > fields.len() {
+        return Err(format!(
+            "too many arguments to new {}: got={}, want<={}",
+            type_name,
+            args.len(),
+            fields.len()
+        ));
+    }
+    for (slot, value) in fields.values_mut().zip(args) {
+        *slot = value;
+    }
+    Ok(fields)
+}
+
+/// Отображение ключей в значения, сохраняющее порядок вставки. По
+/// умолчанию ключи — имена (`String`); хэши языка используют `HashKey`.
+///
+/// Итерация идёт в порядке объявления, поиск по ключу — за O(1) через индекс.
+/// Повторная вставка существующего ключа заменяет значение, не меняя позиции.
+#[derive(Debug, Clone)]
+pub struct OrderedMap<V, K = String> {
+    entries: Vec<(K, V)>,
+    index: HashMap<K, usize>,
+}
+
+/// Индекс однозначно следует из записей, поэтому сравниваются только они —
+/// вместе с порядком. Без учёта порядка — `same_entries`.
+impl<V: PartialEq, K: PartialEq> PartialEq for OrderedMap<V, K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.entries == other.entries
+    }
+}
+
+impl<V, K> Default for OrderedMap<V, K> {
+    fn default() -> Self {
+        OrderedMap {
+            entries: Vec::new(),
+            index: HashMap::new(),
+        }
+    }
+}
+
+impl<V, K: Hash + Eq + Clone> OrderedMap<V, K> {
+    pub fn new() -> Self {
+        Default::default()
+    }
+
+    /// Вставляет значение и возвращает предыдущее, если ключ уже был.
+    pub fn insert(&mut self, key: K, value: V) -> Option<V> {
+        match self.index.get(&key) {
+            Some(&i) => Some(std::mem::replace(&mut self.entries[i].1, value)),
+            None => {
+                self.index.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value));
+                None
+            }
+        }
+    }
+
+    pub fn get<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> Option<&V>
+    where
+        K: std::borrow::Borrow<Q>,
+    {
+        self.index.get(key).map(|&i| &self.entries[i].1)
+    }
+
+    pub fn get_mut<Q: Hash + Eq + ?Sized>(&mut self, key: &Q) -> Option<&mut V>
+    where
+        K: std::borrow::Borrow<Q>,
+    {
+        self.index.get(key).map(|&i| &mut self.entries[i].1)
+    }
+
+    pub fn contains_key<Q: Hash + Eq + ?Sized>(&self, key: &Q) -> bool
+    where
+        K: std::borrow::Borrow<Q>,
+    {
+        self.index.contains_key(key)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&K, &V)> {
+        self.entries.iter().map(|(k, v)| (k, v))
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &K> {
+        self.entries.iter().map(|(k, _)| k)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = &V> {
+        self.entries.iter().map(|(_, v)| v)
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut V> {
+        self.entries.iter_mut().map(|(_, v)| v)
+    }
+}
+
+impl<V: PartialEq, K: Hash + Eq + Clone> OrderedMap<V, K> {
+    /// Те же пары ключ–значение, возможно в другом порядке.
+    pub fn same_entries(&self, other: &Self) -> bool {
+        self.len() == other.len()
+            && self
+                .iter()
+                .all(|(key, value)| other.get(key) == Some(value))
+    }
+}
+
+impl<V, K: Hash + Eq + Clone> FromIterator<(K, V)> for OrderedMap<V, K> {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        let mut map = OrderedMap::new();
+        for (key, value) in iter {
+            map.insert(key, value);
+        }
+        map
+    }
+}
+
+impl<V> std::ops::Index<&str> for OrderedMap<V> {
+    type Output = V;
+
+    fn index(&self, key: &str) -> &V {
+        self.get(key)
+            .unwrap_or_else(|| panic!("key not found: {}", key))
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -138,53 +999,429 @@ pub struct Class {
     pub name: String,
     pub super_class: Option<Rc<RefCell<Class>>>,
     pub interfaces: Vec<Rc<RefCell<Interface>>>,
-    pub properties: HashMap<String, Object>,
-    pub methods: HashMap<String, Rc<RefCell<Method>>>,
+    /// Свойства в порядке объявления — в нём `new` раскладывает аргументы.
+    pub properties: OrderedMap<Object>,
+    pub methods: OrderedMap<Rc<RefCell<Method>>>,
+    /// Уже разрешённые `find_method` и `find_property` имена.
+    pub member_cache: MemberCache,
+}
+
+/// Результаты поиска членов класса по цепочке наследования, включая
+/// неудачные. Класс не меняется после объявления, поэтому кэш заполняется
+/// один раз и никогда не сбрасывается. Повторное объявление класса создаёт
+/// новый `Class` с пустым кэшем, а объявленные ранее подклассы продолжают
+/// ссылаться на прежний суперкласс. Если классы станут изменяемыми,
+/// при изменении нужно очищать кэш самого класса и всех его подклассов.
+#[derive(Clone, Default)]
+pub struct MemberCache {
+    methods: RefCell<HashMap<String, Option<Rc<RefCell<Method>>>>>,
+    properties: RefCell<HashMap<String, Option<Object>>>,
+    #[cfg(test)]
+    chain_walks: std::cell::Cell<usize>,
+}
+
+impl MemberCache {
+    fn resolve<T: Clone>(
+        &self,
+        cache: &RefCell<HashMap<String, Option<T>>>,
+        name: &str,
+        walk: impl FnOnce() -> Option<T>,
+    ) -> Option<T> {
+        if let Some(resolved) = cache.borrow().get(name) {
+            return resolved.clone();
+        }
+        #[cfg(test)]
+        self.chain_walks.set(self.chain_walks.get() + 1);
+        let resolved = walk();
+        cache
+            .borrow_mut()
+            .insert(name.to_string(), resolved.clone());
+        resolved
+    }
+
+    /// Сколько раз поиск прошёл по цепочке наследования, а не по кэшу.
+    #[cfg(test)]
+    pub(crate) fn chain_walks(&self) -> usize {
+        self.chain_walks.get()
+    }
+}
+
+// Кэш выводится из остальных полей класса и не влияет на его сравнение и
+// отладочный вывод
+impl PartialEq for MemberCache {
+    fn eq(&self, _: &Self) -> bool {
+        true
+    }
+}
+
+impl fmt::Debug for MemberCache {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MemberCache").finish_non_exhaustive()
+    }
+}
+
+/// Проверяет, совпадает ли класс с `target` или наследует от него.
+fn is_subclass(class: &Rc<RefCell<Class>>, target: &Rc<RefCell<Class>>) -> bool {
+    let mut current = Some(Rc::clone(class));
+    while let Some(class) = current {
+        if Rc::ptr_eq(&class, target) {
+            return true;
+        }
+        current = class.borrow().super_class.clone();
+    }
+    false
+}
+
+impl Class {
+    /// Ищет метод в классе и его суперклассах, а если цепочка классов его
+    /// не содержит — среди реализаций по умолчанию объявленных интерфейсов.
+    /// Результат запоминается в `member_cache`.
+    pub fn find_method(&self, name: &str) -> Option<Rc<RefCell<Method>>> {
+        self.member_cache
+            .resolve(&self.member_cache.methods, name, || {
+                self.find_class_method(name)
+                    .or_else(|| self.find_default_method(name).map(|(_, method)| method))
+            })
+    }
+
+    /// Ищет метод только в классе и его суперклассах.
+    pub fn find_class_method(&self, name: &str) -> Option<Rc<RefCell<Method>>> {
+        match self.methods.get(name) {
+            Some(method) => Some(Rc::clone(method)),
+            None => self
+                .super_class
+                .as_ref()
+                .and_then(|sc| sc.borrow().find_class_method(name)),
+        }
+    }
+
+    /// Ищет реализацию по умолчанию в интерфейсах класса (в порядке
+    /// `implements`), затем в интерфейсах суперклассов. Возвращает и имя
+    /// интерфейса, предоставившего метод.
+    pub fn find_default_method(&self, name: &str) -> Option<(String, Rc<RefCell<Method>>)> {
+        self.interfaces
+            .iter()
+            .find_map(|interface| {
+                let interface = interface.borrow();
+                interface
+                    .default_methods
+                    .get(name)
+                    .map(|method| (interface.name.clone(), Rc::clone(method)))
+            })
+            .or_else(|| {
+                self.super_class
+                    .as_ref()
+                    .and_then(|sc| sc.borrow().find_default_method(name))
+            })
+    }
+
+    /// Первый метод по умолчанию, который класс получает сразу из двух
+    /// интерфейсов и не переопределяет: `(метод, первый интерфейс, второй)`.
+    pub fn conflicting_default(&self) -> Option<(String, String, String)> {
+        let mut interfaces = self.interfaces.clone();
+        let mut current = self.super_class.clone();
+        while let Some(class) = current {
+            interfaces.extend(class.borrow().interfaces.iter().cloned());
+            current = class.borrow().super_class.clone();
+        }
+
+        let mut providers: Vec<(String, String)> = Vec::new();
+        for interface in &interfaces {
+            let interface = interface.borrow();
+            for name in interface.default_methods.keys() {
+                if self.find_class_method(name).is_some() {
+                    continue;
+                }
+                match providers.iter().find(|(method, _)| method == name) {
+                    Some((_, first)) if *first != interface.name => {
+                        return Some((name.clone(), first.clone(), interface.name.clone()))
+                    }
+                    Some(_) => {}
+                    None => providers.push((name.clone(), interface.name.clone())),
+                }
+            }
+        }
+        None
+    }
+
+    /// Ищет свойство класса (значение по умолчанию) в классе и его
+    /// суперклассах; ближайшее объявление перекрывает унаследованные.
+    /// Результат запоминается в `member_cache`.
+    pub fn find_property(&self, name: &str) -> Option<Object> {
+        self.member_cache
+            .resolve(&self.member_cache.properties, name, || {
+                match self.properties.get(name) {
+                    Some(value) => Some(value.clone()),
+                    None => self
+                        .super_class
+                        .as_ref()
+                        .and_then(|sc| sc.borrow().find_property(name)),
+                }
+            })
+    }
+
+    /// Проверяет, реализует ли класс интерфейс.
+    ///
+    /// Класс, объявивший интерфейс через `implements` (сам или в суперклассе),
+    /// реализует его без проверок. Иначе интерфейс считается реализованным
+    /// структурно: для каждой сигнатуры в цепочке классов есть метод с тем же
+    /// именем и числом параметров.
+    pub fn implements(&self, interface: &Rc<RefCell<Interface>>) -> bool {
+        if self.declares(interface) {
+            return true;
+        }
+        interface
+            .borrow()
+            .method_signatures
+            .values()
+            .all(|signature| {
+                self.find_method(&signature.name).is_some_and(|method| {
+                    method.borrow().parameters.len() == signature.parameters.len()
+                })
+            })
+    }
+
+    /// Имена методов с учётом наследования: сначала методы суперкласса, затем
+    /// новые методы класса. Переопределённый метод остаётся на позиции,
+    /// на которой его объявил суперкласс.
+    pub fn method_names(&self) -> Vec<String> {
+        let mut names = match &self.super_class {
+            Some(sc) => sc.borrow().method_names(),
+            None => Vec::new(),
+        };
+        for name in self.methods.keys() {
+            if !names.contains(name) {
+                names.push(name.clone());
+            }
+        }
+        names
+    }
+
+    fn declares(&self, interface: &Rc<RefCell<Interface>>) -> bool {
+        self.interfaces.iter().any(|i| Rc::ptr_eq(i, interface))
+            || self
+                .super_class
+                .as_ref()
+                .is_some_and(|sc| sc.borrow().declares(interface))
+    }
+}
+
+/// Описание класса: собственные свойства со значениями по умолчанию и
+/// все методы (включая унаследованные) в порядке объявления.
+impl fmt::Display for Class {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "class {}", self.name)?;
+        if let Some(sc) = &self.super_class {
+            write!(f, " extends {}", sc.borrow().name)?;
+        }
+        let mut members: Vec<String> = self
+            .properties
+            .iter()
+            .map(|(name, value)| format!("{} = {}", name, value))
+            .collect();
+        for name in self.method_names() {
+            if let Some(method) = self.find_method(&name) {
+                let params: Vec<String> = method
+                    .borrow()
+                    .parameters
+                    .iter()
+                    .map(|p| p.value.clone())
+                    .collect();
+                members.push(format!("{}({})", name, params.join(", ")));
+            }
+        }
+        if members.is_empty() {
+            write!(f, " {{}}")
+        } else {
+            write!(f, " {{ {} }}", members.join("; "))
+        }
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct ClassInstance {
     pub class: Rc<RefCell<Class>>,
-    pub fields: HashMap<String, Object>,
+    pub fields: OrderedMap<Object>,
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Struct {
     pub name: String,
-    pub properties: HashMap<String, Object>,
+    pub properties: OrderedMap<Object>,
 }
 
+/// Экземпляр структуры — значение, а не объект с идентичностью.
+///
+/// Экземпляр не изменяется на месте: присваивание полю строит копию с
+/// новым значением (`with_field`) и записывает её обратно в переменную,
+/// поле или элемент, через которые к нему обратились, — так же, как
+/// массив при записи по индексу. Поэтому `Rc` можно разделять между
+/// переменными: копирование при присваивании и передаче в функцию
+/// происходит лениво, в момент изменения. Вложенные структуры копируются
+/// вместе с внешней, экземпляры классов в полях остаются общими (`Rc`).
+/// VM использует то же представление: `SetProperty` оставляет на стеке
+/// изменённую копию, и компилятор записывает её обратно. Константой
+/// экземпляр при необходимости можно описать как `Constant::Array` из
+/// имени структуры и значений полей — по образцу описания `Struct`.
 #[derive(Debug, PartialEq, Clone)]
 pub struct StructInstance {
     pub struct_def: Rc<RefCell<Struct>>,
-    pub fields: HashMap<String, Object>,
+    pub fields: OrderedMap<Object>,
+}
+
+impl StructInstance {
+    /// Копия экземпляра, в которой поле `name` равно `value`; `None`,
+    /// если у структуры нет такого поля.
+    pub fn with_field(&self, name: &str, value: Object) -> Option<StructInstance> {
+        if !self.fields.contains_key(name) {
+            return None;
+        }
+        let mut copy = self.clone();
+        copy.fields.insert(name.to_string(), value);
+        Some(copy)
+    }
+
+    /// Структурное равенство для `==`: та же структура и попарно равные
+    /// поля. Вложенные структуры и массивы сравниваются поэлементно,
+    /// экземпляры классов — по идентичности.
+    pub fn structurally_eq(&self, other: &StructInstance) -> bool {
+        Rc::ptr_eq(&self.struct_def, &other.struct_def)
+            && self.fields.len() == other.fields.len()
+            && self
+                .fields
+                .iter()
+                .zip(other.fields.iter())
+                .all(|((lk, lv), (rk, rv))| lk == rk && field_values_eq(lv, rv))
+    }
+}
+
+/// `==` для экземпляров структур — общее правило интерпретатора и VM.
+pub fn structs_equal(
+    left: &Rc<RefCell<StructInstance>>,
+    right: &Rc<RefCell<StructInstance>>,
+) -> bool {
+    Rc::ptr_eq(left, right) || left.borrow().structurally_eq(&right.borrow())
+}
+
+/// Равенство значений полей структуры (см. `StructInstance::structurally_eq`).
+fn field_values_eq(left: &Object, right: &Object) -> bool {
+    match (left, right) {
+        (Object::ClassInstance(l), Object::ClassInstance(r)) => Rc::ptr_eq(l, r),
+        (Object::StructInstance(l), Object::StructInstance(r)) => structs_equal(l, r),
+        (Object::Array(l), Object::Array(r)) => {
+            l.len() == r.len() && l.iter().zip(r.iter()).all(|(l, r)| field_values_eq(l, r))
+        }
+        _ => left == right,
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
 pub struct Interface {
     pub name: String,
-    pub method_signatures: HashMap<String, MethodSignature>,
+    pub method_signatures: OrderedMap<MethodSignature>,
+    /// Реализации по умолчанию; класс получает их, если сам и его
+    /// суперклассы не объявляют метод с тем же именем.
+    pub default_methods: OrderedMap<Rc<RefCell<Method>>>,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+/// Метод класса. Привязка к экземпляру (`this`) копирует метод, разделяя
+/// с ним тело.
+#[derive(Clone)]
 pub struct Method {
     pub name: String,
     pub parameters: Vec<Identifier>,
-    pub body: BlockStatement,
+    pub body: Rc<BlockStatement>,
     pub env: Rc<RefCell<Environment>>,
     pub this: Option<Rc<RefCell<ClassInstance>>>,
 }
 
+/// Методы равны, если это одно объявление, привязанное к одному и тому же
+/// экземпляру (или оба не привязаны).
+impl PartialEq for Method {
+    fn eq(&self, other: &Self) -> bool {
+        let same_this = match (&self.this, &other.this) {
+            (Some(a), Some(b)) => Rc::ptr_eq(a, b),
+            (None, None) => true,
+            _ => false,
+        };
+        Rc::ptr_eq(&self.body, &other.body) && same_this
+    }
+}
+
+/// Только имя, параметры и класс привязанного экземпляра: тело и окружение
+/// метода в отладочном выводе заняли бы страницы.
+impl fmt::Debug for Method {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let parameters: Vec<&str> = self.parameters.iter().map(|p| p.value.as_str()).collect();
+        let this = self
+            .this
+            .as_ref()
+            .map(|instance| match instance.try_borrow() {
+                Ok(instance) => format!("instance of {}", instance.class.borrow().name),
+                Err(_) => "instance (borrowed)".to_string(),
+            });
+        f.debug_struct("Method")
+            .field("name", &self.name)
+            .field("parameters", &parameters)
+            .field("this", &this)
+            .finish_non_exhaustive()
+    }
+}
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct MethodSignature {
     pub name: String,
     pub parameters: Vec<Identifier>,
 }
 
-#[derive(Debug, PartialEq, Clone, Default)]
+/// Значение переменной и флаг изменяемости: привязку `const` нельзя
+/// перезаписать присваиванием.
+#[derive(Debug, PartialEq, Clone)]
+struct Binding {
+    value: Object,
+    mutable: bool,
+}
+
+#[derive(PartialEq, Clone, Default)]
 pub struct Environment {
-    store: HashMap<String, Object>,
+    store: OrderedMap<Binding>,
     outer: Option<Rc<RefCell<Environment>>>,
+    /// Наибольшее число переменных в этом окружении (без учёта внешних).
+    /// `None` — без ограничения; вложенные окружения лимит не наследуют.
+    limit: Option<usize>,
+    /// Разрешено ли повторно объявлять классы, структуры и интерфейсы
+    /// в одной области видимости. Действует флаг внешнего окружения.
+    allow_redefinition: bool,
+}
+
+/// Сообщение об ошибке `Environment::set` при превышении лимита.
+pub const ENVIRONMENT_LIMIT_EXCEEDED: &str = "environment variable limit exceeded";
+
+/// Имена переменных этого окружения (без значений) и число внешних
+/// окружений: значения могут ссылаться на само окружение, а цепочка
+/// внешних окружений у вложенных замыканий длинная.
+impl fmt::Debug for Environment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names: Vec<&String> = self.store.keys().collect();
+        let mut outer_depth = 0;
+        let mut outer = self.outer.clone();
+        while let Some(env) = outer {
+            outer_depth += 1;
+            // Окружение, занятое на запись, дальше не обходим
+            outer = env.try_borrow().ok().and_then(|env| env.outer.clone());
+        }
+        let mut debug = f.debug_struct("Environment");
+        debug
+            .field("names", &names)
+            .field("outer_depth", &outer_depth);
+        if let Some(limit) = self.limit {
+            debug.field("limit", &limit);
+        }
+        if self.allow_redefinition {
+            debug.field("allow_redefinition", &true);
+        }
+        debug.finish()
+    }
 }
 
 impl Environment {
@@ -192,6 +1429,74 @@ impl Environment {
         Default::default()
     }
 
+    /// Окружение, в котором можно объявить не больше `limit` переменных.
+    /// Нужно при встраивании: недоверенный скрипт не сможет бесконечно
+    /// наращивать глобальные переменные. Перезапись существующего имени
+    /// лимит не расходует.
+    pub fn with_limit(limit: usize) -> Self {
+        Environment {
+            limit: Some(limit),
+            ..Default::default()
+        }
+    }
+
+    pub fn limit(&self) -> Option<usize> {
+        self.limit
+    }
+
+    /// Окружение для REPL: повторное объявление класса, структуры или
+    /// интерфейса заменяет прежнее, как и повторный `let`. В режиме
+    /// программы (`Environment::new`) это ошибка.
+    pub fn allowing_redefinition() -> Self {
+        Environment {
+            allow_redefinition: true,
+            ..Default::default()
+        }
+    }
+
+    /// Флаг самого внешнего окружения: вложенные окружения функций и
+    /// блоков следуют режиму, в котором создано глобальное.
+    pub fn allows_redefinition(&self) -> bool {
+        match &self.outer {
+            Some(outer) => outer.borrow().allows_redefinition(),
+            None => self.allow_redefinition,
+        }
+    }
+
+    /// Число переменных в этом окружении, без внешних.
+    pub fn len(&self) -> usize {
+        self.store.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.store.is_empty()
+    }
+
+    /// Переменные этого окружения (без внешних) в порядке первого объявления.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &Object)> {
+        self.store
+            .iter()
+            .map(|(name, binding)| (name, &binding.value))
+    }
+
+    /// Все видимые переменные с учётом внешних окружений: перекрытое имя
+    /// встречается один раз, со значением из ближайшего окружения. Сначала
+    /// идут переменные этого окружения, затем внешних — каждое в порядке
+    /// объявления.
+    pub fn iter_all(&self) -> impl Iterator<Item = (String, Object)> + '_ {
+        let outer = std::iter::successors(self.outer.clone(), |env| env.borrow().outer.clone());
+        let mut seen = HashSet::new();
+        self.iter()
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .chain(outer.flat_map(|env| {
+                let env = env.borrow();
+                env.iter()
+                    .map(|(name, value)| (name.clone(), value.clone()))
+                    .collect::<Vec<_>>()
+            }))
+            .filter(move |(name, _)| seen.insert(name.clone()))
+    }
+
     pub fn new_enclosed(outer: Rc<RefCell<Environment>>) -> Self {
         let mut env = Environment::new();
         env.outer = Some(outer);
@@ -199,13 +1504,66 @@ impl Environment {
     }
 
     pub fn get(&self, name: &str) -> Option<Object> {
+        self.get_ref(name, Object::clone)
+    }
+
+    /// Значение переменной этого окружения, без поиска во внешних.
+    pub fn get_local(&self, name: &str) -> Option<&Object> {
+        self.store.get(name).map(|binding| &binding.value)
+    }
+
+    /// Передаёт значение переменной в `f` по ссылке, не копируя его.
+    ///
+    /// Возвращает `None`, если переменная не определена ни в одном из окружений.
+    pub fn get_ref<R>(&self, name: &str, f: impl FnOnce(&Object) -> R) -> Option<R> {
         match self.store.get(name) {
-            Some(obj) => Some(obj.clone()),
-            None => self.outer.as_ref().and_then(|o| o.borrow().get(name)),
+            Some(binding) => Some(f(&binding.value)),
+            None => self
+                .outer
+                .as_ref()
+                .and_then(|o| o.borrow().get_ref(name, f)),
         }
     }
 
-    pub fn set(&mut self, name: String, val: Object) {
-        self.store.insert(name, val);
+    /// Объявляет переменную в этом окружении или перезаписывает её.
+    /// Повторное объявление заменяет и значение, и изменяемость.
+    ///
+    /// Возвращает ошибку `ENVIRONMENT_LIMIT_EXCEEDED`, если новое имя
+    /// превысило бы лимит окружения.
+    pub fn set(&mut self, name: String, val: Object) -> Result<(), String> {
+        self.bind(name, val, true)
+    }
+
+    /// Как `set`, но объявляет константу: `assign` её не перезапишет.
+    pub fn set_constant(&mut self, name: String, val: Object) -> Result<(), String> {
+        self.bind(name, val, false)
+    }
+
+    fn bind(&mut self, name: String, value: Object, mutable: bool) -> Result<(), String> {
+        let is_new = !self.store.contains_key(&name);
+        if is_new && self.limit.is_some_and(|limit| self.store.len() >= limit) {
+            return Err(ENVIRONMENT_LIMIT_EXCEEDED.to_string());
+        }
+        self.store.insert(name, Binding { value, mutable });
+        Ok(())
+    }
+
+    /// Перезаписывает существующую переменную в том окружении, где она определена.
+    ///
+    /// Возвращает ошибку `identifier not found: x`, если переменная не
+    /// определена ни в одном из окружений, и `cannot assign to constant x`,
+    /// если ближайшее объявление — константа.
+    pub fn assign(&mut self, name: &str, val: Object) -> Result<(), String> {
+        if let Some(binding) = self.store.get_mut(name) {
+            if !binding.mutable {
+                return Err(format!("cannot assign to constant {}", name));
+            }
+            binding.value = val;
+            return Ok(());
+        }
+        match &self.outer {
+            Some(outer) => outer.borrow_mut().assign(name, val),
+            None => Err(format!("identifier not found: {}", name)),
+        }
     }
 }
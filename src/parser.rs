@@ -1,11 +1,13 @@
-use crate::ast::{self, AccessModifier, Program};
+use crate::ast::{self, AccessModifier, InterfaceMember, Program};
 use crate::lexer::Lexer;
 use crate::token::{Token, TokenType};
+use std::num::IntErrorKind;
 
 // Определение приоритетов операторов
 #[derive(PartialEq, PartialOrd)]
 enum Precedence {
     Lowest,
+    Assign,      // = (правоассоциативное)
     Arrow,       // => (самый низкий приоритет для разделения паттерна и выражения)
     Or,          // ||
     And,         // &&
@@ -17,6 +19,7 @@ enum Precedence {
     Range,       // .. , ..= (выше Sum/Product, ниже Prefix)
     Prefix,      // -X или !X
     Call,        // myFunction(X)
+    Index,       // array[index]
     Dot,         // object.member
 }
 
@@ -27,21 +30,30 @@ pub enum ParserError {
 }
 
 // Парсер
-pub struct Parser {
-    lexer: Lexer,
+pub struct Parser<'a> {
+    lexer: Lexer<'a>,
     current_token: Token,
     next_token: Token,
     errors: Vec<ParserError>,
+    // Количество токенов, считанных из лексера (позиция текущего токена)
+    token_index: usize,
+    // Последний назначенный идентификатор узла AST
+    last_node_id: u32,
+    // Глубина вложенности циклов for в текущей функции (для break/continue)
+    loop_depth: usize,
 }
 
-impl Parser {
+impl<'a> Parser<'a> {
     // Создает новый парсер
-    pub fn new(lexer: Lexer) -> Self {
+    pub fn new(lexer: Lexer<'a>) -> Self {
         let mut parser = Parser {
             lexer,
             current_token: Token::new(TokenType::Illegal, "".to_string()),
             next_token: Token::new(TokenType::Illegal, "".to_string()),
             errors: Vec::new(),
+            token_index: 0,
+            last_node_id: 0,
+            loop_depth: 0,
         };
 
         // Инициализация current_token и next_token
@@ -51,20 +63,43 @@ impl Parser {
         parser
     }
 
+    // Назначает следующий идентификатор узла (в порядке начала разбора)
+    fn next_node_id(&mut self) -> ast::NodeId {
+        self.last_node_id += 1;
+        ast::NodeId(self.last_node_id)
+    }
+
     // Сдвигает токены
     fn next_token(&mut self) {
         self.current_token = self.next_token.clone();
         self.next_token = self.lexer.next_token();
+        self.token_index += 1;
     }
 
     // Основной метод парсинга программы
     pub fn parse_program(&mut self) -> Result<Program, Vec<ParserError>> {
         let mut program = Program::new();
+        let mut last_position = None;
 
         while self.current_token.token_type != TokenType::Eof {
+            // Защита от зацикливания: если парсер не сдвинулся с прошлой итерации,
+            // сообщаем об ошибке и принудительно пропускаем текущий токен
+            if let Err(e) = self.ensure_progress(&mut last_position, "program") {
+                self.errors.push(e);
+                self.next_token();
+                continue;
+            }
+
             match self.parse_statement() {
                 Ok(statement) => program.statements.push(statement),
-                Err(e) => self.errors.push(e),
+                Err(e) => {
+                    self.errors.push(e);
+                    // `;` завершает ошибочный оператор и не должен разбираться
+                    // как начало следующего
+                    if self.peek_token_is(TokenType::Semicolon) {
+                        self.next_token();
+                    }
+                }
             }
             self.next_token();
         }
@@ -76,27 +111,60 @@ impl Parser {
         }
     }
 
+    /// Разбирает ровно одно выражение — для встраивания: калькуляторы,
+    /// вычисление значений конфигурации. Допускается одна завершающая `;`;
+    /// любые другие токены после выражения — ошибка.
+    pub fn parse_expression_only(&mut self) -> Result<ast::Expression, Vec<ParserError>> {
+        match self.parse_expression(Precedence::Lowest) {
+            Ok(expression) => {
+                if self.peek_token_is(TokenType::Semicolon) {
+                    self.next_token();
+                }
+                if !self.peek_token_is(TokenType::Eof) {
+                    self.errors.push(ParserError::UnexpectedToken(format!(
+                        "expected end of input after expression, got {:?}",
+                        self.next_token.token_type
+                    )));
+                }
+                if self.errors.is_empty() {
+                    return Ok(expression);
+                }
+            }
+            Err(e) => self.errors.push(e),
+        }
+        Err(std::mem::take(&mut self.errors))
+    }
+
     // Парсинг оператора
     fn parse_statement(&mut self) -> Result<ast::Statement, ParserError> {
         match self.current_token.token_type {
-            TokenType::Let => self.parse_let_statement(),
+            TokenType::Let | TokenType::Const => self.parse_let_statement(),
             TokenType::Return => self.parse_return_statement(),
+            TokenType::Break | TokenType::Continue => self.parse_loop_control_statement(),
             TokenType::Class => self.parse_class_declaration(),
             TokenType::Struct => self.parse_struct_declaration(),
             TokenType::Interface => self.parse_interface_declaration(),
+            TokenType::Function if self.peek_token_is(TokenType::Ident) => {
+                self.parse_function_declaration()
+            }
             TokenType::Match => self.parse_expression_statement(), // Match - это выражение, поэтому парсим как expression statement
             _ => self.parse_expression_statement(),
         }
     }
 
-    // Парсинг оператора let
+    // Парсинг оператора let и const; у const деструктуризации нет
     fn parse_let_statement(&mut self) -> Result<ast::Statement, ParserError> {
         let let_token = self.current_token.clone();
 
+        if let_token.token_type == TokenType::Let && self.peek_token_is(TokenType::LBracket) {
+            return self.parse_let_destructure_statement(let_token);
+        }
+        let id = self.next_node_id();
+
         if !self.expect_peek(TokenType::Ident) {
             return Err(ParserError::UnexpectedToken(format!(
-                "expected next token to be IDENT, got {:?} instead",
-                self.next_token.token_type
+                "expected identifier after '{}', got {:?}",
+                let_token.literal, self.next_token.token_type
             )));
         }
 
@@ -104,10 +172,11 @@ impl Parser {
             token: self.current_token.clone(),
             value: self.current_token.literal.clone(),
         };
+        let type_annotation = self.parse_optional_type_annotation().map(Box::new);
 
         if !self.expect_peek(TokenType::Assign) {
             return Err(ParserError::UnexpectedToken(format!(
-                "expected next token to be =, got {:?} instead",
+                "expected '=' after variable name, got {:?}",
                 self.next_token.token_type
             )));
         }
@@ -121,35 +190,172 @@ impl Parser {
         }
 
         Ok(ast::Statement::Let(ast::LetStatement {
+            id,
             token: let_token,
             name,
+            type_annotation,
             value,
         }))
     }
 
+    // Парсинг деструктурирующего let: let [a, [b, c], ..rest] = value;
+    fn parse_let_destructure_statement(
+        &mut self,
+        let_token: Token,
+    ) -> Result<ast::Statement, ParserError> {
+        let id = self.next_node_id();
+        self.next_token(); // '['
+        let pattern = self.parse_pattern()?;
+        Self::validate_binding_pattern(&pattern)?;
+
+        if !self.expect_peek(TokenType::Assign) {
+            return Err(ParserError::UnexpectedToken(format!(
+                "expected '=' after pattern, got {:?}",
+                self.next_token.token_type
+            )));
+        }
+
+        self.next_token();
+        let value = self.parse_expression(Precedence::Lowest)?;
+
+        if self.peek_token_is(TokenType::Semicolon) {
+            self.next_token();
+        }
+
+        Ok(ast::Statement::LetDestructure(
+            ast::LetDestructureStatement {
+                id,
+                token: let_token,
+                pattern: Box::new(pattern),
+                value,
+            },
+        ))
+    }
+
+    /// В `let` допустимы только паттерны, которые не проверяют значения:
+    /// идентификаторы, `_`, кортежи и остаток `..имя`.
+    fn validate_binding_pattern(pattern: &ast::Pattern) -> Result<(), ParserError> {
+        match pattern {
+            ast::Pattern::Identifier(_) | ast::Pattern::Wildcard | ast::Pattern::Rest(_) => Ok(()),
+            ast::Pattern::Tuple(patterns) => {
+                patterns.iter().try_for_each(Self::validate_binding_pattern)
+            }
+            other => Err(ParserError::UnexpectedToken(format!(
+                "pattern not allowed in let: {}",
+                other
+            ))),
+        }
+    }
+
+    // Парсинг именованного объявления функции: fn name(params) { body }
+    // Сводится к let name = fn(params) { body };
+    fn parse_function_declaration(&mut self) -> Result<ast::Statement, ParserError> {
+        let id = self.next_node_id();
+        let fn_token = self.current_token.clone();
+        self.next_token();
+
+        let name = ast::Identifier {
+            token: self.current_token.clone(),
+            value: self.current_token.literal.clone(),
+        };
+
+        if !self.expect_peek(TokenType::LParen) {
+            return Err(ParserError::UnexpectedToken(format!(
+                "expected '(' after function name {}",
+                name.value
+            )));
+        }
+
+        let (parameters, rest, types) = self.parse_function_parameters()?;
+
+        if !self.expect_peek(TokenType::LBrace) {
+            return Err(ParserError::UnexpectedToken(
+                "expected '{' after parameters".to_string(),
+            ));
+        }
+
+        let body = self.parse_function_body()?;
+
+        if self.peek_token_is(TokenType::Semicolon) {
+            self.next_token();
+        }
+
+        Ok(ast::Statement::Let(ast::LetStatement {
+            id,
+            token: Token::new(TokenType::Let, "let".to_string()),
+            name,
+            type_annotation: None,
+            value: ast::Expression::FunctionLiteral(ast::FunctionLiteral {
+                token: fn_token,
+                parameters,
+                rest,
+                types,
+                body,
+            }),
+        }))
+    }
+
     // Парсинг оператора return
     fn parse_return_statement(&mut self) -> Result<ast::Statement, ParserError> {
+        let id = self.next_node_id();
         let return_token = self.current_token.clone();
-        self.next_token();
 
-        let return_value = self.parse_expression(Precedence::Lowest)?;
+        // Голый `return;` возвращает null
+        let return_value = if self.peek_token_is(TokenType::Semicolon)
+            || self.peek_token_is(TokenType::RBrace)
+            || self.peek_token_is(TokenType::Eof)
+        {
+            ast::Expression::Null(ast::NullLiteral {
+                token: Token::new(TokenType::Null, "null".to_string()),
+            })
+        } else {
+            self.next_token();
+            self.parse_expression(Precedence::Lowest)?
+        };
 
         if self.peek_token_is(TokenType::Semicolon) {
             self.next_token();
         }
 
         Ok(ast::Statement::Return(ast::ReturnStatement {
+            id,
             token: return_token,
             return_value,
         }))
     }
 
+    // Парсинг break/continue: допустимы только внутри цикла текущей функции
+    fn parse_loop_control_statement(&mut self) -> Result<ast::Statement, ParserError> {
+        let id = self.next_node_id();
+        let token = self.current_token.clone();
+
+        if self.loop_depth == 0 {
+            return Err(ParserError::UnexpectedToken(format!(
+                "'{}' outside of a loop",
+                token.literal
+            )));
+        }
+
+        if self.peek_token_is(TokenType::Semicolon) {
+            self.next_token();
+        }
+
+        Ok(if token.token_type == TokenType::Break {
+            ast::Statement::Break(ast::BreakStatement { id, token })
+        } else {
+            ast::Statement::Continue(ast::ContinueStatement { id, token })
+        })
+    }
+
     // Парсинг оператора-выражения
     fn parse_expression_statement(&mut self) -> Result<ast::Statement, ParserError> {
+        let id = self.next_node_id();
+        let token = self.current_token.clone();
         let expression = self.parse_expression(Precedence::Lowest)?;
 
         let stmt = ast::ExpressionStatement {
-            token: self.current_token.clone(),
+            id,
+            token,
             expression,
         };
 
@@ -162,10 +368,6 @@ impl Parser {
 
     // Парсинг выражения (Pratt parser)
     fn parse_expression(&mut self, precedence: Precedence) -> Result<ast::Expression, ParserError> {
-        eprintln!(
-            "DEBUG: parse_expression: current_token={:?}, next_token={:?}",
-            self.current_token, self.next_token
-        );
         let mut left_exp = self.parse_prefix()?;
 
         while !self.peek_token_is(TokenType::Semicolon) && precedence < self.peek_precedence() {
@@ -176,6 +378,7 @@ impl Parser {
                 | TokenType::Asterisk
                 | TokenType::Eq
                 | TokenType::NotEq
+                | TokenType::Is
                 | TokenType::Lt
                 | TokenType::Gt
                 | TokenType::Power
@@ -183,10 +386,19 @@ impl Parser {
                 | TokenType::Or
                 | TokenType::Modulo
                 | TokenType::LParen
+                | TokenType::LBracket
+                | TokenType::Range
+                | TokenType::Assign
                 | TokenType::Dot => {
                     self.next_token();
-                    if self.current_token.token_type == TokenType::LParen {
+                    if self.current_token.token_type == TokenType::Assign {
+                        left_exp = self.parse_assign_expression(left_exp)?;
+                    } else if self.current_token.token_type == TokenType::LParen {
                         left_exp = self.parse_call_expression(left_exp)?;
+                    } else if self.current_token.token_type == TokenType::LBracket {
+                        left_exp = self.parse_index_expression(left_exp)?;
+                    } else if self.current_token.token_type == TokenType::Range {
+                        left_exp = self.parse_range_expression(left_exp)?;
                     } else {
                         left_exp = self.parse_infix(left_exp)?;
                     }
@@ -208,15 +420,34 @@ impl Parser {
             TokenType::Int => self.parse_integer_literal(),
             TokenType::String => self.parse_string_literal(),
             TokenType::Bang | TokenType::Minus => self.parse_prefix_expression(),
+            TokenType::Typeof => self.parse_typeof_expression(),
             TokenType::True | TokenType::False => self.parse_boolean(),
+            TokenType::Null => Ok(ast::Expression::Null(ast::NullLiteral {
+                token: self.current_token.clone(),
+            })),
             TokenType::LParen => self.parse_grouped_expression(),
             TokenType::LBracket => self.parse_array_literal(),
+            TokenType::LBrace => self.parse_hash_literal(),
             TokenType::If => self.parse_if_expression(),
+            TokenType::For => self.parse_for_expression(),
             TokenType::Function => self.parse_function_literal(),
             TokenType::New => self.parse_new_expression(),
             TokenType::This => self.parse_this_expression(),
             TokenType::Super => self.parse_super_expression(),
             TokenType::Match => self.parse_match_expression(),
+            TokenType::Illegal => Err(ParserError::UnexpectedToken(
+                match self.current_token.literal.chars().next() {
+                    Some('"' | '\'' | '`') => format!(
+                        "unterminated string literal: {}",
+                        self.current_token.literal
+                    ),
+                    Some('\\') => format!(
+                        "invalid escape sequence '{}' in string literal",
+                        self.current_token.literal
+                    ),
+                    _ => format!("illegal character '{}'", self.current_token.literal),
+                },
+            )),
             _ => Err(ParserError::UnexpectedToken(format!(
                 "no prefix parse function for {:?} found",
                 self.current_token.token_type
@@ -233,13 +464,13 @@ impl Parser {
             | TokenType::Asterisk
             | TokenType::Eq
             | TokenType::NotEq
+            | TokenType::Is
             | TokenType::Lt
             | TokenType::Gt
             | TokenType::Power
             | TokenType::And
             | TokenType::Or
-            | TokenType::Modulo
-            | TokenType::Assign => self.parse_infix_expression(left),
+            | TokenType::Modulo => self.parse_infix_expression(left),
             TokenType::LParen => self.parse_call_expression(left),
             TokenType::Dot => self.parse_property_access_expression(left),
             _ => Err(ParserError::UnexpectedToken(format!(
@@ -251,12 +482,24 @@ impl Parser {
 
     // Парсинг целочисленного литерала
     fn parse_integer_literal(&mut self) -> Result<ast::Expression, ParserError> {
-        let value = self.current_token.literal.parse::<i64>().map_err(|_| {
-            ParserError::UnexpectedToken(format!(
-                "could not parse {} as integer",
-                self.current_token.literal
-            ))
-        })?;
+        // Знак — отдельный префиксный оператор, поэтому сам литерал не
+        // может быть больше i64::MAX
+        let value =
+            self.current_token
+                .literal
+                .parse::<i64>()
+                .map_err(|error| match error.kind() {
+                    IntErrorKind::PosOverflow | IntErrorKind::NegOverflow => {
+                        ParserError::UnexpectedToken(format!(
+                            "integer literal too large for i64: {}",
+                            self.current_token.literal
+                        ))
+                    }
+                    _ => ParserError::UnexpectedToken(format!(
+                        "could not parse {} as integer",
+                        self.current_token.literal
+                    )),
+                })?;
 
         Ok(ast::Expression::IntegerLiteral(ast::IntegerLiteral {
             token: self.current_token.clone(),
@@ -287,6 +530,17 @@ impl Parser {
         }))
     }
 
+    // Парсинг `typeof значение` с приоритетом префиксного оператора
+    fn parse_typeof_expression(&mut self) -> Result<ast::Expression, ParserError> {
+        let token = self.current_token.clone();
+        self.next_token();
+        let operand = self.parse_expression(Precedence::Prefix)?;
+        Ok(ast::Expression::Typeof(ast::TypeofExpression {
+            token,
+            operand: Box::new(operand),
+        }))
+    }
+
     fn parse_infix_expression(
         &mut self,
         left: ast::Expression,
@@ -317,7 +571,7 @@ impl Parser {
         let exp = self.parse_expression(Precedence::Lowest)?;
         if !self.expect_peek(TokenType::RParen) {
             return Err(ParserError::UnexpectedToken(format!(
-                "expected next token to be ), got {:?} instead",
+                "expected ')' after expression, got {:?}",
                 self.next_token.token_type
             )));
         }
@@ -326,7 +580,7 @@ impl Parser {
 
     fn parse_array_literal(&mut self) -> Result<ast::Expression, ParserError> {
         let token = self.current_token.clone();
-        let elements = self.parse_expression_list(TokenType::RBracket)?;
+        let elements = self.parse_expression_list(TokenType::RBracket, "array literal")?;
         // После parse_expression_list(), current_token указывает на RBracket
         // Нам нужно оставить current_token на последнем элементе или на скобке?
         // Посмотрим на то, как это работает в parse_expression()
@@ -337,12 +591,61 @@ impl Parser {
         }))
     }
 
+    /// Литерал хэша: `{}` или пары `ключ: значение` через запятую; после
+    /// последней пары допускается запятая.
+    fn parse_hash_literal(&mut self) -> Result<ast::Expression, ParserError> {
+        let token = self.current_token.clone();
+        let mut pairs = Vec::new();
+
+        loop {
+            if self.peek_token_is(TokenType::RBrace) {
+                self.next_token();
+                return Ok(ast::Expression::HashLiteral(ast::HashLiteral {
+                    token,
+                    pairs,
+                }));
+            }
+            if self.peek_token_is(TokenType::Comma) {
+                return Err(Self::stray_comma_error("hash literal"));
+            }
+            self.next_token();
+            let key = self.parse_expression(Precedence::Lowest)?;
+            if !self.expect_peek(TokenType::Colon) {
+                return Err(ParserError::UnexpectedToken(format!(
+                    "expected ':' after hash key, got {:?}",
+                    self.next_token.token_type
+                )));
+            }
+            self.next_token();
+            let value = self.parse_expression(Precedence::Lowest)?;
+            pairs.push((key, value));
+            if !self.peek_token_is(TokenType::Comma) {
+                break;
+            }
+            self.next_token();
+        }
+
+        if !self.expect_peek(TokenType::RBrace) {
+            return Err(ParserError::UnexpectedToken(format!(
+                "expected ',' or '}}' in hash literal, got {:?}",
+                self.next_token.token_type
+            )));
+        }
+
+        Ok(ast::Expression::HashLiteral(ast::HashLiteral {
+            token,
+            pairs,
+        }))
+    }
+
     fn parse_block_statement(&mut self) -> Result<ast::BlockStatement, ParserError> {
         let token = self.current_token.clone();
         let mut statements = Vec::new();
         self.next_token();
 
+        let mut last_position = None;
         while !self.current_token_is(TokenType::RBrace) && !self.current_token_is(TokenType::Eof) {
+            self.ensure_progress(&mut last_position, "block")?;
             let stmt = self.parse_statement()?;
             statements.push(stmt);
             self.next_token();
@@ -354,6 +657,10 @@ impl Parser {
     fn parse_if_expression(&mut self) -> Result<ast::Expression, ParserError> {
         let token = self.current_token.clone();
 
+        if self.peek_token_is(TokenType::Let) {
+            return self.parse_if_let_expression(token);
+        }
+
         if !self.expect_peek(TokenType::LParen) {
             return Err(ParserError::UnexpectedToken(
                 "expected '(' after 'if'".to_string(),
@@ -371,7 +678,7 @@ impl Parser {
 
         if !self.expect_peek(TokenType::LBrace) {
             return Err(ParserError::UnexpectedToken(
-                "expected '{{' after ')'".to_string(),
+                "expected '{' after ')'".to_string(),
             ));
         }
 
@@ -381,7 +688,7 @@ impl Parser {
             self.next_token();
             if !self.expect_peek(TokenType::LBrace) {
                 return Err(ParserError::UnexpectedToken(
-                    "expected '{{' after 'else'".to_string(),
+                    "expected '{' after 'else'".to_string(),
                 ));
             }
             Some(self.parse_block_statement()?)
@@ -397,6 +704,135 @@ impl Parser {
         }))
     }
 
+    // Парсинг условного сопоставления: if let паттерн = значение { ... } else { ... }
+    fn parse_if_let_expression(&mut self, token: Token) -> Result<ast::Expression, ParserError> {
+        self.next_token(); // 'let'
+        self.next_token(); // начало паттерна
+        let pattern = self.parse_pattern()?;
+
+        if !self.expect_peek(TokenType::Assign) {
+            return Err(ParserError::UnexpectedToken(format!(
+                "expected '=' after pattern in 'if let', got {:?}",
+                self.next_token.token_type
+            )));
+        }
+
+        self.next_token();
+        let value = self.parse_expression(Precedence::Lowest)?;
+
+        if !self.expect_peek(TokenType::LBrace) {
+            return Err(ParserError::UnexpectedToken(
+                "expected '{' after 'if let' value".to_string(),
+            ));
+        }
+
+        let consequence = self.parse_block_statement()?;
+
+        let alternative = if self.peek_token_is(TokenType::Else) {
+            self.next_token();
+            if !self.expect_peek(TokenType::LBrace) {
+                return Err(ParserError::UnexpectedToken(
+                    "expected '{' after 'else'".to_string(),
+                ));
+            }
+            Some(self.parse_block_statement()?)
+        } else {
+            None
+        };
+
+        Ok(ast::Expression::IfLet(ast::IfLetExpression {
+            token,
+            pattern: Box::new(pattern),
+            value: Box::new(value),
+            consequence,
+            alternative,
+        }))
+    }
+
+    // Парсинг цикла: for (x in iterable) { ... }
+    fn parse_for_expression(&mut self) -> Result<ast::Expression, ParserError> {
+        let token = self.current_token.clone();
+
+        if !self.expect_peek(TokenType::LParen) {
+            return Err(ParserError::UnexpectedToken(
+                "expected '(' after 'for'".to_string(),
+            ));
+        }
+
+        if !self.expect_peek(TokenType::Ident) {
+            return Err(ParserError::UnexpectedToken(format!(
+                "expected loop variable after 'for (', got {:?}",
+                self.next_token.token_type
+            )));
+        }
+        let variable = ast::Identifier {
+            token: self.current_token.clone(),
+            value: self.current_token.literal.clone(),
+        };
+
+        if !self.expect_peek(TokenType::In) {
+            return Err(ParserError::UnexpectedToken(format!(
+                "expected 'in' after loop variable, got {:?}",
+                self.next_token.token_type
+            )));
+        }
+
+        self.next_token();
+        let iterable = self.parse_expression(Precedence::Lowest)?;
+
+        if !self.expect_peek(TokenType::RParen) {
+            return Err(ParserError::UnexpectedToken(
+                "expected ')' after iterable".to_string(),
+            ));
+        }
+
+        if !self.expect_peek(TokenType::LBrace) {
+            return Err(ParserError::UnexpectedToken(
+                "expected '{' after ')'".to_string(),
+            ));
+        }
+
+        self.loop_depth += 1;
+        let body = self.parse_block_statement();
+        self.loop_depth -= 1;
+        let body = body?;
+
+        Ok(ast::Expression::For(ast::ForExpression {
+            token,
+            variable,
+            iterable: Box::new(iterable),
+            body,
+        }))
+    }
+
+    // Парсинг присваивания: target = value (правоассоциативно)
+    fn parse_assign_expression(
+        &mut self,
+        target: ast::Expression,
+    ) -> Result<ast::Expression, ParserError> {
+        if !matches!(
+            target,
+            ast::Expression::Identifier(_)
+                | ast::Expression::PropertyAccess(_)
+                | ast::Expression::Index(_)
+        ) {
+            return Err(ParserError::UnexpectedToken(format!(
+                "invalid assignment target: {}",
+                target
+            )));
+        }
+
+        let token = self.current_token.clone();
+        self.next_token();
+        let value = self.parse_expression(Precedence::Lowest)?;
+
+        Ok(ast::Expression::Assign(ast::AssignExpression {
+            token,
+            target: Box::new(target),
+            value: Box::new(value),
+        }))
+    }
+
     fn parse_function_literal(&mut self) -> Result<ast::Expression, ParserError> {
         let token = self.current_token.clone();
 
@@ -406,47 +842,98 @@ impl Parser {
             ));
         }
 
-        let parameters = self.parse_function_parameters()?;
+        let (parameters, rest, types) = self.parse_function_parameters()?;
 
         if !self.expect_peek(TokenType::LBrace) {
             return Err(ParserError::UnexpectedToken(
-                "expected '{{' after parameters".to_string(),
+                "expected '{' after parameters".to_string(),
             ));
         }
 
-        let body = self.parse_block_statement()?;
+        let body = self.parse_function_body()?;
 
         Ok(ast::Expression::FunctionLiteral(ast::FunctionLiteral {
             token,
             parameters,
+            rest,
+            types,
             body,
         }))
     }
 
-    fn parse_function_parameters(&mut self) -> Result<Vec<ast::Identifier>, ParserError> {
-        let mut identifiers = Vec::new();
-
-        if self.peek_token_is(TokenType::RParen) {
-            self.next_token();
-            return Ok(identifiers);
-        }
-
-        self.next_token();
+    /// Тело функции или метода. `break` и `continue` в нём не относятся
+    /// к циклу, внутри которого функция объявлена.
+    fn parse_function_body(&mut self) -> Result<ast::BlockStatement, ParserError> {
+        let outer_loops = std::mem::take(&mut self.loop_depth);
+        let body = self.parse_block_statement();
+        self.loop_depth = outer_loops;
+        body
+    }
 
-        let ident = ast::Identifier {
-            token: self.current_token.clone(),
-            value: self.current_token.literal.clone(),
-        };
-        identifiers.push(ident);
+    /// Параметры функции: обычные и необязательный rest-параметр `...имя`,
+    /// который может быть только последним. Допускается запятая после
+    /// последнего параметра. У каждого параметра и у списка целиком
+    /// (тип результата) может быть аннотация `: Тип`.
+    fn parse_function_parameters(
+        &mut self,
+    ) -> Result<
+        (
+            Vec<ast::Identifier>,
+            Option<ast::Identifier>,
+            ast::SignatureTypes,
+        ),
+        ParserError,
+    > {
+        let mut identifiers = Vec::new();
+        let mut rest = None;
+        let mut types = ast::SignatureTypes::default();
 
-        while self.peek_token_is(TokenType::Comma) {
-            self.next_token();
+        loop {
+            if self.peek_token_is(TokenType::RParen) {
+                self.next_token();
+                types.return_type = self.parse_optional_type_annotation().map(Box::new);
+                return Ok((identifiers, rest, types));
+            }
+            if self.peek_token_is(TokenType::Comma) {
+                return Err(Self::stray_comma_error("parameter list"));
+            }
             self.next_token();
-            let ident = ast::Identifier {
+            if self.current_token_is(TokenType::Ellipsis) {
+                if !self.expect_peek(TokenType::Ident) {
+                    return Err(ParserError::UnexpectedToken(
+                        "expected parameter name after '...'".to_string(),
+                    ));
+                }
+                rest = Some(ast::Identifier {
+                    token: self.current_token.clone(),
+                    value: self.current_token.literal.clone(),
+                });
+                types.rest = self.parse_optional_type_annotation().map(Box::new);
+                if self.peek_token_is(TokenType::Comma) {
+                    self.next_token();
+                    if !self.peek_token_is(TokenType::RParen) {
+                        return Err(ParserError::UnexpectedToken(
+                            "rest parameter must be the last parameter".to_string(),
+                        ));
+                    }
+                }
+                break;
+            }
+            if !self.current_token_is(TokenType::Ident) {
+                return Err(ParserError::UnexpectedToken(format!(
+                    "expected parameter name, got {:?}",
+                    self.current_token.token_type
+                )));
+            }
+            identifiers.push(ast::Identifier {
                 token: self.current_token.clone(),
                 value: self.current_token.literal.clone(),
-            };
-            identifiers.push(ident);
+            });
+            types.parameters.push(self.parse_optional_type_annotation());
+            if !self.peek_token_is(TokenType::Comma) {
+                break;
+            }
+            self.next_token();
         }
 
         if !self.expect_peek(TokenType::RParen) {
@@ -454,8 +941,91 @@ impl Parser {
                 "expected ')' after parameters".to_string(),
             ));
         }
+        types.return_type = self.parse_optional_type_annotation().map(Box::new);
+
+        Ok((identifiers, rest, types))
+    }
+
+    /// Параметры метода или сигнатуры интерфейса: rest-параметр в них
+    /// не поддерживается.
+    fn parse_method_parameters(
+        &mut self,
+    ) -> Result<(Vec<ast::Identifier>, ast::SignatureTypes), ParserError> {
+        let (parameters, rest, types) = self.parse_function_parameters()?;
+        if rest.is_some() {
+            return Err(ParserError::UnexpectedToken(
+                "rest parameters are only supported in functions".to_string(),
+            ));
+        }
+        Ok((parameters, types))
+    }
 
-        Ok(identifiers)
+    /// Необязательная аннотация `: Тип` после имени или списка параметров.
+    /// Ошибка в аннотации не прерывает разбор: она записывается, токены
+    /// пропускаются до `=`, `,`, `)`, `]`, `{` или `;`, и разбор
+    /// продолжается так, будто аннотации не было.
+    fn parse_optional_type_annotation(&mut self) -> Option<ast::TypeAnnotation> {
+        if !self.peek_token_is(TokenType::Colon) {
+            return None;
+        }
+        self.next_token(); // ':'
+        self.parse_type_annotation()
+    }
+
+    /// Имя типа и необязательные аргументы в квадратных скобках: `Array[Int]`.
+    /// Имена не проверяются — этим занимается режим проверки.
+    fn parse_type_annotation(&mut self) -> Option<ast::TypeAnnotation> {
+        if !self.peek_token_is(TokenType::Ident) {
+            self.errors.push(ParserError::UnexpectedToken(format!(
+                "expected type name, got {:?}",
+                self.next_token.token_type
+            )));
+            self.skip_invalid_type_annotation();
+            return None;
+        }
+        self.next_token();
+        let token = self.current_token.clone();
+
+        let mut arguments = Vec::new();
+        if self.peek_token_is(TokenType::LBracket) {
+            self.next_token(); // '['
+            loop {
+                arguments.extend(self.parse_type_annotation());
+                if !self.peek_token_is(TokenType::Comma) {
+                    break;
+                }
+                self.next_token();
+            }
+            if !self.expect_peek(TokenType::RBracket) {
+                self.errors.push(ParserError::UnexpectedToken(format!(
+                    "expected ']' after type arguments, got {:?}",
+                    self.next_token.token_type
+                )));
+                self.skip_invalid_type_annotation();
+            }
+        }
+
+        Some(ast::TypeAnnotation {
+            name: token.literal.clone(),
+            token,
+            arguments,
+        })
+    }
+
+    fn skip_invalid_type_annotation(&mut self) {
+        while ![
+            TokenType::Assign,
+            TokenType::Comma,
+            TokenType::RParen,
+            TokenType::RBracket,
+            TokenType::LBrace,
+            TokenType::Semicolon,
+            TokenType::Eof,
+        ]
+        .contains(&self.next_token.token_type)
+        {
+            self.next_token();
+        }
     }
 
     fn parse_call_expression(
@@ -463,7 +1033,7 @@ impl Parser {
         function: ast::Expression,
     ) -> Result<ast::Expression, ParserError> {
         let token = self.current_token.clone();
-        let arguments = self.parse_expression_list(TokenType::RParen)?;
+        let arguments = self.parse_expression_list(TokenType::RParen, "call arguments")?;
         Ok(ast::Expression::Call(ast::CallExpression {
             token,
             function: Box::new(function),
@@ -471,36 +1041,91 @@ impl Parser {
         }))
     }
 
-    fn parse_expression_list(
+    // Парсинг индексного выражения: left[index]
+    fn parse_index_expression(
         &mut self,
-        end: TokenType,
-    ) -> Result<Vec<ast::Expression>, ParserError> {
-        let mut list = Vec::new();
+        left: ast::Expression,
+    ) -> Result<ast::Expression, ParserError> {
+        let token = self.current_token.clone();
+        self.next_token();
 
-        if self.peek_token_is(end) {
-            self.next_token();
-            return Ok(list);
+        let index = self.parse_expression(Precedence::Lowest)?;
+
+        if !self.expect_peek(TokenType::RBracket) {
+            return Err(ParserError::UnexpectedToken(format!(
+                "expected ']' after index, got {:?}",
+                self.next_token.token_type
+            )));
         }
 
+        Ok(ast::Expression::Index(ast::IndexExpression {
+            token,
+            left: Box::new(left),
+            index: Box::new(index),
+        }))
+    }
+
+    // Парсинг диапазона: start..end
+    fn parse_range_expression(
+        &mut self,
+        start: ast::Expression,
+    ) -> Result<ast::Expression, ParserError> {
+        let token = self.current_token.clone();
         self.next_token();
-        list.push(self.parse_expression(Precedence::Lowest)?);
 
-        while self.peek_token_is(TokenType::Comma) {
-            self.next_token();
+        let end = self.parse_expression(Precedence::Range)?;
+
+        Ok(ast::Expression::Range(ast::RangeExpression {
+            token,
+            start: Box::new(start),
+            end: Box::new(end),
+        }))
+    }
+
+    /// Элементы через запятую до `end`; допускается запятая после последнего
+    /// элемента. `construct` называет список в сообщениях об ошибках.
+    fn parse_expression_list(
+        &mut self,
+        end: TokenType,
+        construct: &str,
+    ) -> Result<Vec<ast::Expression>, ParserError> {
+        let mut list = Vec::new();
+
+        loop {
+            if self.peek_token_is(end) {
+                self.next_token();
+                return Ok(list);
+            }
+            if self.peek_token_is(TokenType::Comma) {
+                return Err(Self::stray_comma_error(construct));
+            }
             self.next_token();
             list.push(self.parse_expression(Precedence::Lowest)?);
+            if !self.peek_token_is(TokenType::Comma) {
+                break;
+            }
+            self.next_token();
         }
 
         if !self.expect_peek(end) {
+            let closing = if end == TokenType::RParen { ')' } else { ']' };
             return Err(ParserError::UnexpectedToken(format!(
-                "expected next token to be {:?}, got {:?} instead",
-                end, self.next_token.token_type
+                "expected '{}' after {}, got {:?}",
+                closing, construct, self.next_token.token_type
             )));
         }
 
         Ok(list)
     }
 
+    /// Запятая без элемента перед ней: `[1,, 2]`, `f(,)`.
+    fn stray_comma_error(construct: &str) -> ParserError {
+        ParserError::UnexpectedToken(format!(
+            "unexpected ',' in {}: expected an element before it",
+            construct
+        ))
+    }
+
     fn parse_property_access_expression(
         &mut self,
         left: ast::Expression,
@@ -550,7 +1175,7 @@ impl Parser {
             )));
         }
 
-        let arguments = self.parse_expression_list(TokenType::RParen)?;
+        let arguments = self.parse_expression_list(TokenType::RParen, "constructor arguments")?;
 
         Ok(ast::Expression::New(ast::NewExpression {
             token,
@@ -590,15 +1215,23 @@ impl Parser {
         let mut arms = Vec::new();
         self.next_token(); // Пропускаем '{'
 
-        // Парсим ветви match
+        // Парсим ветви match; запятая после последней ветви необязательна
+        let mut last_position = None;
         while !self.current_token_is(TokenType::RBrace) && !self.current_token_is(TokenType::Eof) {
-            arms.push(self.parse_match_arm()?);
+            self.ensure_progress(&mut last_position, "match arms")?;
+            if self.current_token_is(TokenType::Comma) {
+                return Err(Self::stray_comma_error("match arms"));
+            }
+            let arm = self.parse_match_arm()?;
+            let block_body = arm.has_block_body();
+            arms.push(arm);
 
-            // После parse_match_arm(), current_token указывает на запятую или '}'
+            // После parse_match_arm(), current_token указывает на запятую или '}';
+            // после тела-блока запятая необязательна, как в Rust
             if self.current_token_is(TokenType::Comma) {
                 // Пропускаем запятую, переходим к следующей ветви
                 self.next_token();
-            } else if !self.current_token_is(TokenType::RBrace) {
+            } else if !block_body && !self.current_token_is(TokenType::RBrace) {
                 // Если это не запятая и не '}', ошибка
                 return Err(ParserError::UnexpectedToken(format!(
                     "expected ',' or '}}' after match arm, got {:?}",
@@ -607,6 +1240,13 @@ impl Parser {
             }
         }
 
+        // Пустой match не может сопоставить ни одно значение
+        if arms.is_empty() {
+            return Err(ParserError::UnexpectedToken(
+                "match expression must have at least one arm".to_string(),
+            ));
+        }
+
         Ok(ast::Expression::Match(ast::MatchExpression {
             token,
             value: Box::new(value),
@@ -615,6 +1255,7 @@ impl Parser {
     }
 
     fn parse_class_declaration(&mut self) -> Result<ast::Statement, ParserError> {
+        let id = self.next_node_id();
         let token = self.current_token.clone();
 
         if !self.expect_peek(TokenType::Ident) {
@@ -645,6 +1286,27 @@ impl Parser {
             None
         };
 
+        let mut interfaces = Vec::new();
+        if self.peek_token_is(TokenType::Implements) {
+            self.next_token(); // consume 'implements'
+            loop {
+                if !self.expect_peek(TokenType::Ident) {
+                    return Err(ParserError::UnexpectedToken(format!(
+                        "expected interface name after 'implements', got {:?}",
+                        self.next_token.token_type
+                    )));
+                }
+                interfaces.push(ast::Identifier {
+                    token: self.current_token.clone(),
+                    value: self.current_token.literal.clone(),
+                });
+                if !self.peek_token_is(TokenType::Comma) {
+                    break;
+                }
+                self.next_token(); // consume ','
+            }
+        }
+
         if !self.expect_peek(TokenType::LBrace) {
             return Err(ParserError::UnexpectedToken(format!(
                 "expected '{{' after class name, got {:?}",
@@ -657,7 +1319,9 @@ impl Parser {
 
         self.next_token(); // Пропускаем LBrace
 
+        let mut last_position = None;
         while !self.current_token_is(TokenType::RBrace) && !self.current_token_is(TokenType::Eof) {
+            self.ensure_progress(&mut last_position, "class body")?;
             let (access_modifier, is_static) = self.parse_access_modifier_and_static();
 
             if self.current_token_is(TokenType::Let) {
@@ -708,16 +1372,18 @@ impl Parser {
         }
 
         Ok(ast::Statement::ClassDeclaration(ast::ClassDeclaration {
+            id,
             token,
             name,
             super_class,
-            interfaces: Vec::new(), // TODO: Реализовать парсинг `implements`
+            interfaces,
             properties,
             methods,
         }))
     }
 
     fn parse_struct_declaration(&mut self) -> Result<ast::Statement, ParserError> {
+        let id = self.next_node_id();
         let token = self.current_token.clone();
 
         if !self.expect_peek(TokenType::Ident) {
@@ -742,7 +1408,9 @@ impl Parser {
         let mut properties = Vec::new();
         self.next_token(); // Пропускаем LBrace
 
+        let mut last_position = None;
         while !self.current_token_is(TokenType::RBrace) && !self.current_token_is(TokenType::Eof) {
+            self.ensure_progress(&mut last_position, "struct body")?;
             let (access_modifier, is_static) = self.parse_access_modifier_and_static();
             if self.current_token_is(TokenType::Let) {
                 let prop = self.parse_property_declaration(access_modifier, is_static)?;
@@ -768,6 +1436,7 @@ impl Parser {
         }
 
         Ok(ast::Statement::StructDeclaration(ast::StructDeclaration {
+            id,
             token,
             name,
             properties,
@@ -775,6 +1444,7 @@ impl Parser {
     }
 
     fn parse_interface_declaration(&mut self) -> Result<ast::Statement, ParserError> {
+        let id = self.next_node_id();
         let token = self.current_token.clone();
 
         if !self.expect_peek(TokenType::Ident) {
@@ -796,12 +1466,13 @@ impl Parser {
             )));
         }
 
-        let mut method_signatures = Vec::new();
+        let mut members = Vec::new();
         self.next_token(); // Пропускаем LBrace
 
+        let mut last_position = None;
         while !self.current_token_is(TokenType::RBrace) && !self.current_token_is(TokenType::Eof) {
-            let signature = self.parse_method_signature_declaration()?;
-            method_signatures.push(signature);
+            self.ensure_progress(&mut last_position, "interface body")?;
+            members.push(self.parse_method_signature_declaration()?);
         }
 
         if !self.current_token_is(TokenType::RBrace) {
@@ -818,9 +1489,10 @@ impl Parser {
 
         Ok(ast::Statement::InterfaceDeclaration(
             ast::InterfaceDeclaration {
+                id,
                 token,
                 name,
-                method_signatures,
+                members,
             },
         ))
     }
@@ -920,7 +1592,7 @@ impl Parser {
             )));
         }
 
-        let parameters = self.parse_function_parameters()?;
+        let (parameters, types) = self.parse_method_parameters()?;
 
         if !self.expect_peek(TokenType::LBrace) {
             return Err(ParserError::UnexpectedToken(format!(
@@ -929,13 +1601,14 @@ impl Parser {
             )));
         }
 
-        let body = self.parse_block_statement()?;
+        let body = self.parse_function_body()?;
         self.next_token(); // Пропускаем '}'
 
         Ok(ast::MethodDeclaration {
             token,
             name,
             parameters,
+            types,
             body,
             access_modifier,
             is_static,
@@ -962,7 +1635,7 @@ impl Parser {
             )));
         }
 
-        let parameters = self.parse_function_parameters()?;
+        let (parameters, types) = self.parse_method_parameters()?;
 
         if !self.expect_peek(TokenType::LBrace) {
             return Err(ParserError::UnexpectedToken(format!(
@@ -971,13 +1644,14 @@ impl Parser {
             )));
         }
 
-        let body = self.parse_block_statement()?;
+        let body = self.parse_function_body()?;
         self.next_token(); // Пропускаем '}'
 
         Ok(ast::MethodDeclaration {
             token,
             name,
             parameters,
+            types,
             body,
             access_modifier,
             is_static,
@@ -1022,10 +1696,9 @@ impl Parser {
         })
     }
 
-    // Парсит сигнатуру метода в интерфейсе.
-    fn parse_method_signature_declaration(
-        &mut self,
-    ) -> Result<ast::MethodSignatureDeclaration, ParserError> {
+    // Парсит метод интерфейса: после списка параметров `;` завершает
+    // сигнатуру, а `{` начинает реализацию по умолчанию.
+    fn parse_method_signature_declaration(&mut self) -> Result<InterfaceMember, ParserError> {
         if !self.current_token_is(TokenType::Function) {
             return Err(ParserError::UnexpectedToken(format!(
                 "expected 'fn' for method signature, got {:?}",
@@ -1053,24 +1726,63 @@ impl Parser {
             )));
         }
 
-        let parameters = self.parse_function_parameters()?;
+        let (parameters, types) = self.parse_method_parameters()?;
+
+        if self.peek_token_is(TokenType::LBrace) {
+            self.next_token();
+            let body = self.parse_function_body()?;
+            self.next_token(); // Пропускаем '}'
+            return Ok(InterfaceMember::Default(ast::MethodDeclaration {
+                token,
+                name,
+                parameters,
+                types,
+                body,
+                access_modifier: AccessModifier::Public,
+                is_static: false,
+            }));
+        }
 
         if !self.expect_peek(TokenType::Semicolon) {
             return Err(ParserError::UnexpectedToken(format!(
-                "expected ';' after method signature, got {:?}",
+                "expected ';' or '{{' after method signature, got {:?}",
                 self.next_token.token_type
             )));
         }
         self.next_token(); // Пропускаем ';'
 
-        Ok(ast::MethodSignatureDeclaration {
-            token,
-            name,
-            parameters,
-        })
+        Ok(InterfaceMember::Signature(
+            ast::MethodSignatureDeclaration {
+                token,
+                name,
+                parameters,
+                types,
+            },
+        ))
     }
 
     // Вспомогательные функции
+
+    // Проверяет, что с прошлой итерации цикла парсер продвинулся хотя бы на один токен.
+    // Возвращает ошибку, если позиция не изменилась (цикл мог бы крутиться вечно).
+    // Вызывается в начале каждой итерации циклов по телу конструкции (блок,
+    // ветви match, тела классов, структур и интерфейсов, паттерны): ошибка
+    // прерывает разбор конструкции, а parse_program продолжает со следующего токена.
+    fn ensure_progress(
+        &self,
+        last_position: &mut Option<usize>,
+        context: &str,
+    ) -> Result<(), ParserError> {
+        if *last_position == Some(self.token_index) {
+            return Err(ParserError::UnexpectedToken(format!(
+                "parser made no progress at token {:?} ({:?}) inside {}",
+                self.current_token.token_type, self.current_token.literal, context
+            )));
+        }
+        *last_position = Some(self.token_index);
+        Ok(())
+    }
+
     fn peek_token_is(&self, t: TokenType) -> bool {
         self.next_token.token_type == t
     }
@@ -1079,35 +1791,30 @@ impl Parser {
         self.current_token.token_type == t
     }
 
+    /// Сдвигается на следующий токен, если он типа `t`. Ошибку не
+    /// записывает: о несовпадении сообщает вызывающий, возвращая свою
+    /// ошибку с контекстом, — иначе одна опечатка давала бы две записи.
     fn expect_peek(&mut self, t: TokenType) -> bool {
-        if self.peek_token_is(t.clone()) {
+        if self.peek_token_is(t) {
             self.next_token();
             true
         } else {
-            self.peek_error(t);
             false
         }
     }
 
-    fn peek_error(&mut self, t: TokenType) {
-        let msg = format!(
-            "expected next token to be {:?}, got {:?} instead",
-            t, self.next_token.token_type
-        );
-        self.errors.push(ParserError::UnexpectedToken(msg));
-    }
-
     fn get_precedence(token_type: &TokenType) -> Precedence {
         match token_type {
-            TokenType::Eq | TokenType::NotEq => Precedence::Equals,
+            TokenType::Eq | TokenType::NotEq | TokenType::Is => Precedence::Equals,
             TokenType::Lt | TokenType::Gt => Precedence::LessGreater,
             TokenType::Plus | TokenType::Minus => Precedence::Sum,
             TokenType::Slash | TokenType::Asterisk | TokenType::Modulo => Precedence::Product,
             TokenType::Power => Precedence::Power,
             TokenType::And => Precedence::And,
             TokenType::Or => Precedence::Or,
-            TokenType::Assign => Precedence::Lowest,
+            TokenType::Assign => Precedence::Assign,
             TokenType::LParen => Precedence::Call,
+            TokenType::LBracket => Precedence::Index,
             TokenType::Dot => Precedence::Dot,
             TokenType::Range => Precedence::Range,
             TokenType::Arrow => Precedence::Arrow,
@@ -1126,13 +1833,19 @@ impl Parser {
     // Парсит паттерн для match выражения.
     fn parse_pattern(&mut self) -> Result<ast::Pattern, ParserError> {
         match self.current_token.token_type {
-            TokenType::Int | TokenType::String | TokenType::True | TokenType::False => {
+            TokenType::Int
+            | TokenType::String
+            | TokenType::True
+            | TokenType::False
+            | TokenType::Null => {
                 // Литеральные паттерны
                 let expr = self.parse_prefix()?;
                 // После parse_prefix(), current_token указывает на последний токен выражения
                 // Проверяем, является ли это диапазонным паттерном
-                if self.peek_token_is(TokenType::Range) {
-                    self.next_token(); // Переместиться на '..'
+                if self.peek_token_is(TokenType::Range)
+                    || self.peek_token_is(TokenType::RangeInclusive)
+                {
+                    self.next_token(); // Переместиться на '..' или '..='
                     return self.parse_range_pattern(expr);
                 }
                 Ok(ast::Pattern::Literal(expr))
@@ -1148,6 +1861,11 @@ impl Parser {
                         token: self.current_token.clone(),
                         value: ident_value,
                     };
+                    // `Имя { поля }` — паттерн структуры или класса
+                    if self.peek_token_is(TokenType::LBrace) {
+                        self.next_token();
+                        return self.parse_struct_pattern(ident);
+                    }
                     Ok(ast::Pattern::Identifier(ident))
                 }
             }
@@ -1162,10 +1880,31 @@ impl Parser {
                 let mut patterns = vec![];
                 self.next_token(); // Переместиться внутрь скобок
 
+                let mut last_position = None;
                 while !self.current_token_is(closing_bracket)
                     && !self.current_token_is(TokenType::Eof)
                 {
-                    patterns.push(self.parse_pattern()?);
+                    self.ensure_progress(&mut last_position, "tuple pattern")?;
+                    if matches!(patterns.last(), Some(ast::Pattern::Rest(_))) {
+                        return Err(ParserError::UnexpectedToken(
+                            "rest pattern must be the last element".to_string(),
+                        ));
+                    }
+                    if self.current_token_is(TokenType::Range) {
+                        // Остаток кортежа: ..имя
+                        if !self.expect_peek(TokenType::Ident) {
+                            return Err(ParserError::UnexpectedToken(format!(
+                                "expected identifier after '..' in tuple pattern, got {:?}",
+                                self.next_token.token_type
+                            )));
+                        }
+                        patterns.push(ast::Pattern::Rest(ast::Identifier {
+                            token: self.current_token.clone(),
+                            value: self.current_token.literal.clone(),
+                        }));
+                    } else {
+                        patterns.push(self.parse_pattern()?);
+                    }
                     // После parse_pattern(), current_token указывает на последний токен паттерна
 
                     self.next_token(); // Переместиться на запятую или закрывающую скобку
@@ -1211,17 +1950,19 @@ impl Parser {
         &mut self,
         start_expr: ast::Expression,
     ) -> Result<ast::Pattern, ParserError> {
-        // current_token указывает на '..'
-        let is_inclusive = if self.current_token_is(TokenType::Range) {
-            false
-        } else {
-            return Err(ParserError::UnexpectedToken(format!(
-                "expected '..' in range pattern, got {:?}",
-                self.current_token.token_type
-            )));
+        // current_token указывает на '..' или '..='
+        let is_inclusive = match self.current_token.token_type {
+            TokenType::Range => false,
+            TokenType::RangeInclusive => true,
+            _ => {
+                return Err(ParserError::UnexpectedToken(format!(
+                    "expected '..' or '..=' in range pattern, got {:?}",
+                    self.current_token.token_type
+                )))
+            }
         };
 
-        self.next_token(); // Пропускаем '..'
+        self.next_token(); // Пропускаем '..' или '..='
         let end_expr = self.parse_prefix()?;
         // После parse_prefix(), current_token указывает на последний токен выражения
         // Не вызываем next_token здесь - это сделает вызывающая функция
@@ -1233,20 +1974,16 @@ impl Parser {
         }))
     }
 
-    #[allow(dead_code)]
+    /// Парсит паттерн `Имя { поле, поле: паттерн, ... }` для структур и классов.
+    /// При входе current_token — '{', при выходе — закрывающая '}'.
     fn parse_struct_pattern(&mut self, name: ast::Identifier) -> Result<ast::Pattern, ParserError> {
-        // current_token уже должен быть LBrace
-        if !self.current_token_is(TokenType::LBrace) {
-            return Err(ParserError::UnexpectedToken(format!(
-                "expected '{{' after struct name in pattern, got {:?}",
-                self.current_token.token_type
-            )));
-        }
         self.next_token(); // Пропускаем '{'
 
         let mut fields = Vec::new();
 
-        while !self.current_token_is(TokenType::RBrace) && !self.current_token_is(TokenType::Eof) {
+        let mut last_position = None;
+        while !self.current_token_is(TokenType::RBrace) {
+            self.ensure_progress(&mut last_position, "struct pattern")?;
             if !self.current_token_is(TokenType::Ident) {
                 return Err(ParserError::UnexpectedToken(format!(
                     "expected identifier for struct field, got {:?}",
@@ -1257,10 +1994,9 @@ impl Parser {
                 token: self.current_token.clone(),
                 value: self.current_token.literal.clone(),
             };
-            self.next_token();
 
-            let field_pattern = if self.current_token_is(TokenType::Colon) {
-                self.next_token(); // Пропускаем ':'
+            let field_pattern = if self.peek_token_is(TokenType::Colon) {
+                self.next_token(); // Переходим на ':'
                 self.next_token(); // Переходим к паттерну значения
                 Some(self.parse_pattern()?)
             } else {
@@ -1268,28 +2004,36 @@ impl Parser {
             };
             fields.push((field_name, field_pattern));
 
-            if self.peek_token_is(TokenType::Comma) {
-                self.next_token(); // Пропускаем ','
-                self.next_token(); // Переходим к следующему полю
-            } else if !self.peek_token_is(TokenType::RBrace) {
+            // current_token — последний токен поля
+            self.next_token();
+            if self.current_token_is(TokenType::Comma) {
+                self.next_token(); // Следующее поле или '}'
+            } else if !self.current_token_is(TokenType::RBrace) {
                 return Err(ParserError::UnexpectedToken(format!(
                     "expected ',' or '}}' after struct field, got {:?}",
-                    self.next_token.token_type
+                    self.current_token.token_type
                 )));
             }
         }
 
-        if !self.expect_peek(TokenType::RBrace) {
-            return Err(ParserError::UnexpectedToken(format!(
-                "expected '}}' to close struct pattern, got {:?}",
-                self.next_token.token_type
-            )));
-        }
-
         Ok(ast::Pattern::Struct(ast::StructPattern { name, fields }))
     }
 
     // Парсит одну ветвь match выражения.
+    /// Начинает ли текущая `{` литерал хэша, а не блок: за ней сразу `}`
+    /// или ключ-литерал и `:`.
+    fn hash_literal_ahead(&mut self) -> bool {
+        match self.next_token.token_type {
+            TokenType::RBrace => true,
+            TokenType::String
+            | TokenType::Int
+            | TokenType::Ident
+            | TokenType::True
+            | TokenType::False => self.lexer.peek_token().token_type == TokenType::Colon,
+            _ => false,
+        }
+    }
+
     fn parse_match_arm(&mut self) -> Result<ast::MatchArm, ParserError> {
         let pattern = self.parse_pattern()?;
         // После parse_pattern(), current_token указывает на последний токен паттерна
@@ -1316,20 +2060,25 @@ impl Parser {
         }
         self.next_token(); // Пропускаем '=>'
 
-        // Тело ветви: парсим выражение до запятой или '}'
-        let body_expr = self.parse_expression(Precedence::Lowest)?;
-
-        // После parse_expression(), current_token указывает на последний токен выражения
-        self.next_token(); // Перемещаемся на следующий токен (запятую или '}')
-
-        // Создаем BlockStatement с одним ExpressionStatement
-        let consequence = ast::BlockStatement {
-            token: Token::new(TokenType::LBrace, "{".to_string()),
-            statements: vec![ast::Statement::Expression(ast::ExpressionStatement {
-                token: Token::new(TokenType::Ident, "expr".to_string()), // Временный токен
-                expression: body_expr,
-            })],
+        // Тело ветви: блок `{ ... }` или одно выражение до запятой или '}'
+        let consequence = if self.current_token_is(TokenType::LBrace) && !self.hash_literal_ahead()
+        {
+            self.parse_block_statement()?
+        } else {
+            let id = self.next_node_id();
+            let token = self.current_token.clone();
+            let expression = self.parse_expression(Precedence::Lowest)?;
+            ast::BlockStatement {
+                token: token.clone(),
+                statements: vec![ast::Statement::Expression(ast::ExpressionStatement {
+                    id,
+                    token,
+                    expression,
+                })],
+            }
         };
+        // current_token — последний токен тела; переходим к запятой или '}'
+        self.next_token();
 
         Ok(ast::MatchArm {
             pattern,
@@ -1341,9 +2090,28 @@ impl Parser {
 
 #[cfg(test)]
 mod tests {
-    use crate::ast::{AccessModifier, Expression, Statement};
+    use crate::ast::{AccessModifier, Expression, ExpressionStatement, Pattern, Statement};
     use crate::lexer::Lexer;
-    use crate::parser::Parser;
+    use crate::parser::{Parser, ParserError};
+    use crate::token::{Token, TokenType};
+
+    /// Разбор каждого входа должен завершиться ошибкой, среди сообщений
+    /// которой есть ожидаемое.
+    fn assert_parse_errors(tests: &[(&str, &str)]) {
+        for (input, expected) in tests {
+            let errors = Parser::new(Lexer::new(input.to_string()))
+                .parse_program()
+                .unwrap_err();
+            assert!(
+                errors
+                    .iter()
+                    .any(|ParserError::UnexpectedToken(msg)| msg == expected),
+                "{}: {:?}",
+                input,
+                errors
+            );
+        }
+    }
 
     #[test]
     fn test_let_statements() {
@@ -1359,7 +2127,7 @@ mod tests {
 
         assert_eq!(program.statements.len(), 3);
 
-        let tests = vec!["x", "y", "foobar"];
+        let tests = ["x", "y", "foobar"];
 
         for (i, tt) in tests.iter().enumerate() {
             let stmt = &program.statements[i];
@@ -1372,6 +2140,28 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_const_statements() {
+        let program = Parser::new(Lexer::new("const x: Int = 5; let y = 1;".to_string()))
+            .parse_program()
+            .unwrap();
+        let Statement::Let(constant) = &program.statements[0] else {
+            panic!("expected const statement");
+        };
+        assert!(constant.is_constant());
+        assert_eq!(constant.to_string(), "const x: Int = 5;");
+        let Statement::Let(variable) = &program.statements[1] else {
+            panic!("expected let statement");
+        };
+        assert!(!variable.is_constant());
+
+        assert!(
+            Parser::new(Lexer::new("const [a, b] = [1, 2];".to_string()))
+                .parse_program()
+                .is_err()
+        );
+    }
+
     #[test]
     fn test_return_statements() {
         let input = "
@@ -1395,6 +2185,29 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_bare_return_statement() {
+        let input = "return; fn() { return }; return";
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        assert_eq!(program.statements.len(), 3);
+        let Statement::Return(ret) = &program.statements[0] else {
+            panic!("stmt not a return statement");
+        };
+        assert!(matches!(ret.return_value, Expression::Null(_)));
+        assert_eq!(
+            program.to_string(),
+            "return null;fn() return null;return null;"
+        );
+
+        // Напечатанный голый return разбирается в тот же оператор
+        let reparsed = Parser::new(Lexer::new(program.statements[0].to_string()))
+            .parse_program()
+            .unwrap();
+        assert_eq!(reparsed.statements[0].to_string(), "return null;");
+    }
+
     #[test]
     fn test_identifier_expression() {
         let input = "foobar;";
@@ -1448,7 +2261,7 @@ mod tests {
 
         if let Statement::Expression(exp_stmt) = &program.statements[0] {
             if let Expression::Boolean(boolean) = &exp_stmt.expression {
-                assert_eq!(boolean.value, true);
+                assert!(boolean.value);
                 assert_eq!(boolean.token.literal, "true");
             } else {
                 panic!("not a boolean");
@@ -1459,7 +2272,7 @@ mod tests {
 
         if let Statement::Expression(exp_stmt) = &program.statements[1] {
             if let Expression::Boolean(boolean) = &exp_stmt.expression {
-                assert_eq!(boolean.value, false);
+                assert!(!boolean.value);
                 assert_eq!(boolean.token.literal, "false");
             } else {
                 panic!("not a boolean");
@@ -1469,6 +2282,27 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_null_literal_expression() {
+        let input = "null; let x = null; x == null;";
+        let lexer = Lexer::new(input.to_string());
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_program().unwrap();
+
+        assert_eq!(program.statements.len(), 3);
+        if let Statement::Expression(exp_stmt) = &program.statements[0] {
+            if let Expression::Null(null) = &exp_stmt.expression {
+                assert_eq!(null.token.literal, "null");
+            } else {
+                panic!("not a null literal");
+            }
+        } else {
+            panic!("not an expression statement");
+        }
+        assert_eq!(program.statements[1].to_string(), "let x = null;");
+        assert_eq!(program.statements[2].to_string(), "(x == null)");
+    }
+
     #[test]
     fn test_string_literal_expression() {
         let input = "\"hello world\";";
@@ -1595,6 +2429,26 @@ mod tests {
                 "add(a + b + c * d / f + g)",
                 "add((((a + b) + ((c * d) / f)) + g))",
             ),
+            (
+                "a * [1, 2, 3, 4][b * c] * d",
+                "((a * ([1, 2, 3, 4][(b * c)])) * d)",
+            ),
+            (
+                "add(a * b[2], b[1], 2 * [1, 2][1])",
+                "add((a * (b[2])), (b[1]), (2 * ([1, 2][1])))",
+            ),
+            ("-a[0]", "(-(a[0]))"),
+            ("a[1..3]", "(a[(1..3)])"),
+            ("1..n * 2", "((1..n) * 2)"),
+            ("a is B == true", "((a is B) == true)"),
+            ("a.b() is C && d", "(((a.b)() is C) && d)"),
+            ("a.b.c", "((a.b).c)"),
+            ("a.b().c[0]", "(((a.b)().c)[0])"),
+            ("a[0].b.c(1)[2]", "((((a[0]).b).c)(1)[2])"),
+            ("-a.b.c", "(-((a.b).c))"),
+            ("a = b = 1 + 2", "(a = (b = (1 + 2)))"),
+            ("this.n = this.n + 1", "((this.n) = ((this.n) + 1))"),
+            ("x = a == b", "(x = (a == b))"),
         ];
 
         for tt in tests {
@@ -1679,6 +2533,20 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_function_declaration_parsing() {
+        let input = "fn add(x, y) { x + y; }";
+        let lexer = Lexer::new(input.to_string());
+        let mut parser = Parser::new(lexer);
+        let program = parser.parse_program().unwrap();
+
+        assert_eq!(program.statements.len(), 1);
+        assert_eq!(
+            program.statements[0].to_string(),
+            "let add = fn(x, y) (x + y);"
+        );
+    }
+
     #[test]
     fn test_function_literal_parsing() {
         let input = "fn(x, y) { x + y; }";
@@ -1713,6 +2581,73 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_rest_parameter_parsing() {
+        let program = Parser::new(Lexer::new("fn sum(a, ...xs) { xs };".to_string()))
+            .parse_program()
+            .unwrap();
+        if let Statement::Let(let_stmt) = &program.statements[0] {
+            if let Expression::FunctionLiteral(func) = &let_stmt.value {
+                assert_eq!(func.parameters.len(), 1);
+                assert_eq!(func.rest.as_ref().unwrap().value, "xs");
+                assert_eq!(func.to_string(), "fn(a, ...xs) xs");
+            } else {
+                panic!("not a function literal");
+            }
+        } else {
+            panic!("not a let statement");
+        }
+
+        let tests = [
+            (
+                "fn(...xs, a) { a }",
+                "rest parameter must be the last parameter",
+            ),
+            ("fn(...) { 1 }", "expected parameter name after '...'"),
+            (
+                "class A { m(...xs) { xs } }",
+                "rest parameters are only supported in functions",
+            ),
+        ];
+        for (input, expected) in tests {
+            let err = Parser::new(Lexer::new(input.to_string()))
+                .parse_program()
+                .unwrap_err();
+            assert!(
+                format!("{:?}", err).contains(expected),
+                "{}: {:?}",
+                input,
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn test_empty_program() {
+        for input in ["", "   ", "\n\t \r\n", "// комментарий", "  // a\n// b\n\n"] {
+            let program = Parser::new(Lexer::new(input.to_string()))
+                .parse_program()
+                .unwrap();
+            assert!(program.statements.is_empty(), "{:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_typeof_parsing() {
+        let tests = [
+            ("typeof x", "(typeof x)"),
+            (r#"typeof x == "INTEGER""#, "((typeof x) == INTEGER)"),
+            ("typeof -a[0]", "(typeof (-(a[0])))"),
+            ("typeof typeof 1", "(typeof (typeof 1))"),
+        ];
+        for (input, expected) in tests {
+            let program = Parser::new(Lexer::new(input.to_string()))
+                .parse_program()
+                .unwrap();
+            assert_eq!(program.to_string(), expected, "{}", input);
+        }
+    }
+
     #[test]
     fn test_call_expression_parsing() {
         let input = "add(1, 2 * 3, 4 + 5);";
@@ -1847,6 +2782,311 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_chained_access_nesting() {
+        let input = "a.b().c[0];";
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        let expression = match &program.statements[0] {
+            Statement::Expression(exp_stmt) => &exp_stmt.expression,
+            other => panic!("not an expression statement: {:?}", other),
+        };
+
+        // Index(PropertyAccess(Call(PropertyAccess(a, b)), c), 0)
+        let Expression::Index(index) = expression else {
+            panic!("not an index expression: {}", expression);
+        };
+        assert_eq!(index.index.to_string(), "0");
+        let Expression::PropertyAccess(outer) = &*index.left else {
+            panic!("not a property access: {}", index.left);
+        };
+        assert_eq!(outer.property.value, "c");
+        let Expression::Call(call) = &*outer.left else {
+            panic!("not a call: {}", outer.left);
+        };
+        assert!(call.arguments.is_empty());
+        let Expression::PropertyAccess(inner) = &*call.function else {
+            panic!("not a property access: {}", call.function);
+        };
+        assert_eq!(inner.left.to_string(), "a");
+        assert_eq!(inner.property.value, "b");
+    }
+
+    #[test]
+    fn test_for_expression() {
+        let input = "for (x in [1, 2]) { x; }";
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        match &program.statements[0] {
+            Statement::Expression(ExpressionStatement {
+                expression: Expression::For(fe),
+                ..
+            }) => {
+                assert_eq!(fe.variable.value, "x");
+                assert_eq!(fe.iterable.to_string(), "[1, 2]");
+                assert_eq!(fe.body.statements.len(), 1);
+            }
+            other => panic!("not a for expression: {:?}", other),
+        }
+
+        for input in [
+            "for x in xs {}",
+            "for (1 in xs) {}",
+            "for (x xs) {}",
+            "1 = 2",
+        ] {
+            assert!(
+                Parser::new(Lexer::new(input.to_string()))
+                    .parse_program()
+                    .is_err(),
+                "input: {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_struct_pattern_parsing() {
+        let input =
+            "match p { Point { x, y: 0 } => x, Empty {} => 1, Pair { a: [b, c], } => b, _ => 0 }";
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        match &program.statements[0] {
+            Statement::Expression(ExpressionStatement {
+                expression: Expression::Match(me),
+                ..
+            }) => {
+                let patterns: Vec<String> =
+                    me.arms.iter().map(|arm| arm.pattern.to_string()).collect();
+                assert_eq!(
+                    patterns,
+                    vec!["Point { x, y: 0 }", "Empty {  }", "Pair { a: (b, c) }", "_"]
+                );
+            }
+            other => panic!("not a match expression: {:?}", other),
+        }
+
+        for input in [
+            "match p { Point { 1 } => 0 }",
+            "match p { Point { x y } => 0 }",
+        ] {
+            assert!(
+                Parser::new(Lexer::new(input.to_string()))
+                    .parse_program()
+                    .is_err(),
+                "input: {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_struct_pattern_field_subpatterns() {
+        let input = "match p { Point { x: 0, y } => y, Point { x: 1..3, y: [a, _] } => a, _ => 0 }";
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        let Statement::Expression(ExpressionStatement {
+            expression: Expression::Match(me),
+            ..
+        }) = &program.statements[0]
+        else {
+            panic!("not a match expression");
+        };
+        let Pattern::Struct(point) = &me.arms[0].pattern else {
+            panic!("expected struct pattern");
+        };
+        let fields: Vec<(String, Option<String>)> = point
+            .fields
+            .iter()
+            .map(|(name, pattern)| (name.value.clone(), pattern.as_ref().map(|p| p.to_string())))
+            .collect();
+        assert_eq!(
+            fields,
+            vec![
+                ("x".to_string(), Some("0".to_string())),
+                ("y".to_string(), None)
+            ]
+        );
+        assert_eq!(
+            me.arms[1].pattern.to_string(),
+            "Point { x: 1..3, y: (a, _) }"
+        );
+    }
+
+    #[test]
+    fn test_hash_literal_parsing() {
+        let parse = |input: &str| Parser::new(Lexer::new(input.to_string())).parse_program();
+        let hash = |input: &str| match parse(input).unwrap().statements.remove(0) {
+            Statement::Expression(ExpressionStatement {
+                expression: Expression::HashLiteral(h),
+                ..
+            }) => h,
+            other => panic!("expected hash literal, got {}", other),
+        };
+
+        assert!(hash("{}").pairs.is_empty());
+        let pairs = hash(r#"{"one": 1, 2: "two", true: 1 + 2,}"#).pairs;
+        let text: Vec<String> = pairs
+            .iter()
+            .map(|(k, v)| format!("{} => {}", k, v))
+            .collect();
+        assert_eq!(text, vec!["one => 1", "2 => two", "true => (1 + 2)"]);
+        // Ключи и значения — любые выражения; литералы вкладываются
+        assert_eq!(
+            parse(r#"{"a" + "b": [1], k: {"x": f(1)}}"#)
+                .unwrap()
+                .to_string(),
+            "{(a + b): [1], k: {x: f(1)}}"
+        );
+        assert_eq!(
+            parse(r#"let h = {"a": 1}; h["a"]"#).unwrap().to_string(),
+            "let h = {a: 1};(h[a])"
+        );
+
+        let cases = [
+            (r#"{"a" 1}"#, "expected ':' after hash key, got Int"),
+            (
+                r#"{"a": 1 "b": 2}"#,
+                "expected ',' or '}' in hash literal, got String",
+            ),
+            (
+                "{, }",
+                "unexpected ',' in hash literal: expected an element before it",
+            ),
+        ];
+        for (input, message) in cases {
+            let errors = parse(input).unwrap_err();
+            assert_eq!(
+                format!("{:?}", errors[0]),
+                format!("UnexpectedToken({:?})", message),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_if_let_expression() {
+        let tests = vec![
+            (
+                "if let [a, b] = pair { a } else { 0 }",
+                "if let (a, b) = pair a else 0",
+            ),
+            ("if let x = f(1) { x }", "if let x = f(1) x"),
+            ("if let Point { x } = p { x }", "if let Point { x } = p x"),
+        ];
+        for (input, expected) in tests {
+            let program = Parser::new(Lexer::new(input.to_string()))
+                .parse_program()
+                .unwrap();
+            assert_eq!(program.to_string(), expected, "input: {}", input);
+        }
+
+        for input in [
+            "if let [a] pair { a }",
+            "if let [a] = pair a",
+            "if let = x { 1 }",
+        ] {
+            assert!(
+                Parser::new(Lexer::new(input.to_string()))
+                    .parse_program()
+                    .is_err(),
+                "input: {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_missing_block_brace_errors() {
+        let tests = vec![
+            ("if (x) 1", "expected '{' after ')'"),
+            ("if (x) { 1 } else 2", "expected '{' after 'else'"),
+            ("if let a = b { a } else 2", "expected '{' after 'else'"),
+            ("fn(x) x", "expected '{' after parameters"),
+        ];
+        for (input, expected) in tests {
+            let errors = Parser::new(Lexer::new(input.to_string()))
+                .parse_program()
+                .unwrap_err();
+            assert!(
+                format!("{:?}", errors).contains(expected),
+                "{}: {:?}",
+                input,
+                errors
+            );
+        }
+    }
+
+    #[test]
+    fn test_let_destructure_statement() {
+        let tests = vec![
+            "let [a, b] = pair;",
+            "let [a, [b, c], ..rest] = f(x);",
+            "let [_, .._] = xs;",
+        ];
+        for input in tests {
+            let program = Parser::new(Lexer::new(input.to_string()))
+                .parse_program()
+                .unwrap();
+            assert!(
+                matches!(program.statements[0], Statement::LetDestructure(_)),
+                "input: {}",
+                input
+            );
+            // Display даёт исходный текст, который снова разбирается в то же дерево
+            assert_eq!(program.to_string(), input);
+            let reparsed = Parser::new(Lexer::new(program.to_string()))
+                .parse_program()
+                .unwrap();
+            assert_eq!(reparsed, program);
+        }
+
+        for input in [
+            "let [a, 1] = xs;",
+            "let [a, ..rest, b] = xs;",
+            "let [..1] = xs;",
+            "let [a, b] xs;",
+        ] {
+            assert!(
+                Parser::new(Lexer::new(input.to_string()))
+                    .parse_program()
+                    .is_err(),
+                "input: {}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_class_declaration_with_implements() {
+        let input = "class Dog extends Animal implements Speaker, Named {}";
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+
+        if let Statement::ClassDeclaration(class_decl) = &program.statements[0] {
+            assert_eq!(class_decl.super_class.as_ref().unwrap().value, "Animal");
+            let interfaces: Vec<&str> = class_decl
+                .interfaces
+                .iter()
+                .map(|i| i.value.as_str())
+                .collect();
+            assert_eq!(interfaces, vec!["Speaker", "Named"]);
+        } else {
+            panic!("statement not a ClassDeclaration");
+        }
+
+        let input = "class Dog implements {}";
+        assert!(Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .is_err());
+    }
+
     #[test]
     fn test_class_declaration() {
         let input = "class MyClass {}";
@@ -1996,17 +3236,515 @@ mod tests {
 
         if let Statement::InterfaceDeclaration(iface_decl) = &program.statements[0] {
             assert_eq!(iface_decl.name.value, "MyInterface");
-            assert_eq!(iface_decl.method_signatures.len(), 2);
+            let signatures: Vec<_> = iface_decl.method_signatures().collect();
+            assert_eq!(signatures.len(), 2);
 
-            let sig1 = &iface_decl.method_signatures[0];
+            let sig1 = signatures[0];
             assert_eq!(sig1.name.value, "doSomething");
             assert_eq!(sig1.parameters.len(), 2);
 
-            let sig2 = &iface_decl.method_signatures[1];
+            let sig2 = signatures[1];
             assert_eq!(sig2.name.value, "doAnotherThing");
             assert_eq!(sig2.parameters.len(), 0);
         } else {
             panic!("statement not an InterfaceDeclaration");
         }
     }
+
+    #[test]
+    fn test_interface_default_methods() {
+        let input = r#"
+        interface Greeter {
+            fn greet() { return "hi"; }
+            fn name();
+            fn describe(x) { x }
+        }
+        "#;
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+
+        let Statement::InterfaceDeclaration(iface_decl) = &program.statements[0] else {
+            panic!("statement not an InterfaceDeclaration");
+        };
+        let signatures: Vec<&str> = iface_decl
+            .method_signatures()
+            .map(|s| s.name.value.as_str())
+            .collect();
+        assert_eq!(signatures, vec!["name"]);
+        let defaults: Vec<&str> = iface_decl
+            .default_methods()
+            .map(|m| m.name.value.as_str())
+            .collect();
+        assert_eq!(defaults, vec!["greet", "describe"]);
+        assert_eq!(
+            iface_decl
+                .default_methods()
+                .nth(1)
+                .unwrap()
+                .parameters
+                .len(),
+            1
+        );
+        assert_eq!(
+            iface_decl.to_string(),
+            "interface Greeter {\n    fn greet() return hi;\n    name();\n    fn describe(x) x\n}"
+        );
+
+        let errors = Parser::new(Lexer::new("interface I { fn f() 1 }".to_string()))
+            .parse_program()
+            .unwrap_err();
+        assert!(
+            format!("{:?}", errors).contains("expected ';' or '{' after method signature"),
+            "{:?}",
+            errors
+        );
+    }
+
+    #[test]
+    fn test_integer_literal_bounds() {
+        let parse = |input: &str| Parser::new(Lexer::new(input.to_string())).parse_program();
+        let program = parse("9223372036854775807").unwrap();
+        assert_eq!(program.to_string(), "9223372036854775807");
+
+        for input in ["99999999999999999999", "-9223372036854775808"] {
+            let ParserError::UnexpectedToken(msg) = &parse(input).unwrap_err()[0];
+            assert_eq!(
+                *msg,
+                format!(
+                    "integer literal too large for i64: {}",
+                    input.trim_start_matches('-')
+                )
+            );
+        }
+
+        // Лексер выдаёт Int только из цифр — испорченный токен подставляем вручную
+        let mut parser = Parser::new(Lexer::new(String::new()));
+        parser.current_token = Token::new(TokenType::Int, "12a".to_string());
+        let ParserError::UnexpectedToken(msg) = parser.parse_integer_literal().unwrap_err();
+        assert_eq!(msg, "could not parse 12a as integer");
+    }
+
+    #[test]
+    fn test_illegal_token_errors() {
+        let tests = [
+            ("let s = 'abc;", "unterminated string literal: 'abc;"),
+            ("\"a' + 1", "unterminated string literal: \"a' + 1"),
+            ("1 & 2", "illegal character '&'"),
+            (
+                r#"let s = "\u{110000}";"#,
+                r"invalid escape sequence '\u{110000}' in string literal",
+            ),
+            ("let x = @;", "illegal character '@'"),
+        ];
+        assert_parse_errors(&tests);
+    }
+
+    #[test]
+    fn test_loop_control_statements() {
+        let program = Parser::new(Lexer::new(
+            "for (x in xs) { if (x) { continue; } break }".to_string(),
+        ))
+        .parse_program()
+        .unwrap();
+        let Statement::Expression(stmt) = &program.statements[0] else {
+            panic!("expected expression statement");
+        };
+        let Expression::For(for_expr) = &stmt.expression else {
+            panic!("expected for expression");
+        };
+        assert!(matches!(for_expr.body.statements[1], Statement::Break(_)));
+        assert_eq!(for_expr.body.to_string(), "if x continue;break;");
+
+        // Тело функции не видит циклов, внутри которых она объявлена
+        let tests = [
+            ("break;", "'break' outside of a loop"),
+            ("if (true) { continue; }", "'continue' outside of a loop"),
+            (
+                "for (x in xs) { let f = fn() { break; }; }",
+                "'break' outside of a loop",
+            ),
+        ];
+        assert_parse_errors(&tests);
+    }
+
+    #[test]
+    fn test_match_arm_bodies() {
+        let parse = |input: &str| Parser::new(Lexer::new(input.to_string())).parse_program();
+        let arms = |input: &str| match parse(input).unwrap().statements.remove(0) {
+            Statement::Expression(ExpressionStatement {
+                expression: Expression::Match(m),
+                ..
+            }) => m.arms,
+            other => panic!("expected match, got {}", other),
+        };
+
+        // Тело-выражение: блок из одного оператора с токеном самого выражения
+        let sugar = arms("match x { 1 => y + 1, _ => 0 }");
+        assert!(!sugar[0].has_block_body());
+        assert_eq!(sugar[0].consequence.token.literal, "y");
+        assert_eq!(sugar[0].consequence.statements.len(), 1);
+
+        // Тело-блок: настоящий блок с токеном '{' и несколькими операторами
+        let block = arms("match x { 1 => { let t = f(); t * 2 }, _ => 0 }");
+        assert!(block[0].has_block_body());
+        assert_eq!(block[0].consequence.token.token_type, TokenType::LBrace);
+        assert_eq!(block[0].consequence.statements.len(), 2);
+        assert_eq!(block[0].to_string(), "    1 => { let t = f();(t * 2) }");
+        assert_eq!(block[1].to_string(), "    _ => 0");
+
+        // Обе формы в одном match; после блока запятая необязательна
+        let mixed = arms("match x { 1 => { a } 2 => b, n if n > 2 => { c }, _ => { d } }");
+        let kinds: Vec<bool> = mixed.iter().map(|arm| arm.has_block_body()).collect();
+        assert_eq!(kinds, vec![true, false, true, true]);
+        assert_eq!(
+            parse("match x { 1 => { a } _ => b }").unwrap().to_string(),
+            parse("match x { 1 => { a }, _ => b }").unwrap().to_string()
+        );
+
+        // Диапазоны в паттернах: `..` исключает конец, `..=` включает
+        let ranges = arms("match x { 1..5 => a, 5..=9 => b, _ => c }");
+        let inclusive: Vec<bool> = ranges[..2]
+            .iter()
+            .map(|arm| match &arm.pattern {
+                Pattern::Range(range) => range.inclusive,
+                other => panic!("expected range pattern, got {}", other),
+            })
+            .collect();
+        assert_eq!(inclusive, vec![false, true]);
+        assert_eq!(ranges[1].pattern.to_string(), "5..=9");
+
+        // `{}` и `{` с ключом и `:` — тело-хэш, а не блок
+        let hashes = arms("match x { 1 => {\"a\": 1}, 2 => {}, 3 => {y: 2}, _ => { {} } }");
+        let kinds: Vec<bool> = hashes.iter().map(|arm| arm.has_block_body()).collect();
+        assert_eq!(kinds, vec![false, false, false, true]);
+        assert_eq!(hashes[0].to_string(), "    1 => {a: 1}");
+        assert_eq!(hashes[1].to_string(), "    2 => {}");
+        assert_eq!(hashes[3].to_string(), "    _ => { {} }");
+
+        // После тела-выражения запятая по-прежнему обязательна
+        let errors = parse("match x { 1 => a 2 => b }").unwrap_err();
+        assert_eq!(
+            format!("{:?}", errors[0]),
+            "UnexpectedToken(\"expected ',' or '}' after match arm, got Int\")"
+        );
+    }
+
+    #[test]
+    fn test_parse_expression_only() {
+        let parse =
+            |input: &str| Parser::new(Lexer::new(input.to_string())).parse_expression_only();
+
+        let expression = parse("1 + 2 * 3").unwrap();
+        assert_eq!(expression.to_string(), "(1 + (2 * 3))");
+        assert_eq!(parse("[1, 2][0];").unwrap().to_string(), "([1, 2][0])");
+
+        let errors = parse("1 + 2; 3").unwrap_err();
+        assert_eq!(
+            format!("{:?}", errors),
+            "[UnexpectedToken(\"expected end of input after expression, got Int\")]"
+        );
+        assert_eq!(
+            format!("{:?}", parse("").unwrap_err()),
+            "[UnexpectedToken(\"no prefix parse function for Eof found\")]"
+        );
+        // Оператор — не выражение
+        assert!(parse("let x = 1;").is_err());
+    }
+
+    #[test]
+    fn test_missing_token_reported_once() {
+        let errors = |input: &str| {
+            Parser::new(Lexer::new(input.to_string()))
+                .parse_program()
+                .unwrap_err()
+        };
+        let cases = [
+            ("(1 + 2;", "expected ')' after expression, got Semicolon"),
+            (
+                "f(1, 2;",
+                "expected ')' after call arguments, got Semicolon",
+            ),
+            ("[1, 2;", "expected ']' after array literal, got Semicolon"),
+            ("let x 5;", "expected '=' after variable name, got Int"),
+            ("let [a] 5;", "expected '=' after pattern, got Int"),
+            ("const 5;", "expected identifier after 'const', got Int"),
+        ];
+        for (input, message) in cases {
+            let errors = errors(input);
+            assert_eq!(errors.len(), 1, "{}: {:?}", input, errors);
+            assert_eq!(
+                format!("{:?}", errors[0]),
+                format!("UnexpectedToken({:?})", message),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_comma_separated_lists() {
+        let parse = |input: &str| Parser::new(Lexer::new(input.to_string())).parse_program();
+
+        // (пустой, один элемент, запятая в конце) — последние два разбираются одинаково
+        let lists = [
+            ("[]", "[1]", "[1,]"),
+            ("f()", "f(1)", "f(1, )"),
+            ("new P()", "new P(1)", "new P(1,)"),
+            ("fn() {}", "fn(a) { a }", "fn(a,) { a }"),
+            ("fn(...xs) {}", "fn(a, ...xs) { a }", "fn(a, ...xs,) { a }"),
+            (
+                "class A { public f() {} }",
+                "class A { public f(a) { a } }",
+                "class A { public f(a,) { a } }",
+            ),
+            (
+                "match x { _ => 0 }",
+                "match x { 1 => 2, _ => 0 }",
+                "match x { 1 => 2, _ => 0, }",
+            ),
+        ];
+        for (empty, one, trailing) in lists {
+            assert!(parse(empty).is_ok(), "{}", empty);
+            assert_eq!(
+                parse(trailing).unwrap().to_string(),
+                parse(one).unwrap().to_string(),
+                "{}",
+                trailing
+            );
+        }
+
+        let errors = [
+            (
+                "[1,, 2]",
+                "unexpected ',' in array literal: expected an element before it",
+            ),
+            (
+                "[,]",
+                "unexpected ',' in array literal: expected an element before it",
+            ),
+            (
+                "f(1,,)",
+                "unexpected ',' in call arguments: expected an element before it",
+            ),
+            (
+                "new P(,1)",
+                "unexpected ',' in constructor arguments: expected an element before it",
+            ),
+            (
+                "fn(a,, b) {}",
+                "unexpected ',' in parameter list: expected an element before it",
+            ),
+            (
+                "match x { 1 => 2,, _ => 0 }",
+                "unexpected ',' in match arms: expected an element before it",
+            ),
+            ("fn(1) {}", "expected parameter name, got Int"),
+            (
+                "fn(...xs, a) {}",
+                "rest parameter must be the last parameter",
+            ),
+            ("match x {}", "match expression must have at least one arm"),
+        ];
+        assert_parse_errors(&errors);
+    }
+
+    #[test]
+    fn test_parser_progress_guard() {
+        // Повторная проверка без сдвига токенов должна вернуть ошибку, а не зациклиться
+        let lexer = Lexer::new(";".to_string());
+        let parser = Parser::new(lexer);
+        let mut last_position = None;
+        assert!(parser
+            .ensure_progress(&mut last_position, "program")
+            .is_ok());
+        let err = parser
+            .ensure_progress(&mut last_position, "program")
+            .unwrap_err();
+        let ParserError::UnexpectedToken(msg) = err;
+        assert!(msg.contains("no progress"));
+        assert!(msg.contains("Semicolon"));
+    }
+
+    /// Разбирает вход в отдельном потоке и возвращает ошибки; зависание
+    /// дольше нескольких секунд — провал теста.
+    fn parse_errors_within_bounds(input: &str) -> Vec<ParserError> {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let source = input.to_string();
+        std::thread::spawn(move || {
+            let result = Parser::new(Lexer::new(source)).parse_program();
+            let _ = sender.send(result);
+        });
+        match receiver.recv_timeout(std::time::Duration::from_secs(5)) {
+            Ok(result) => result.err().unwrap_or_default(),
+            Err(_) => panic!("parser did not terminate on {:?}", input),
+        }
+    }
+
+    #[test]
+    fn test_parser_terminates_on_malformed_constructs() {
+        let inputs = [
+            "class A { ;; }",
+            "class A { public ;; fn m() { 1 } }",
+            "class A { public",
+            "match x { => 1 }",
+            "match x { 1 => 2, => 3 }",
+            "match x { [a, => 1 }",
+            "match x { P { a b } => 1 }",
+            "interface I { fn m();",
+            "interface I { ; }",
+            "struct S { let a; ;; }",
+            "fn f() { let = ; ) }",
+        ];
+        for input in inputs {
+            let errors = parse_errors_within_bounds(input);
+            assert!(!errors.is_empty(), "expected errors for {:?}", input);
+        }
+    }
+
+    #[test]
+    fn test_parse_program_terminates_on_pathological_input() {
+        // Токены, для которых нет prefix-функции, не потребляются parse_statement
+        let input = ") ] } => , ) ] }";
+        let lexer = Lexer::new(input.to_string());
+        let mut parser = Parser::new(lexer);
+        let errors = parser.parse_program().unwrap_err();
+        assert!(!errors.is_empty());
+    }
+
+    #[test]
+    fn test_type_annotations() {
+        let parse = |input: &str| Parser::new(Lexer::new(input.to_string())).parse_program();
+
+        // let
+        let program = parse("let x: Int = 5; let y = 1;").unwrap();
+        let Statement::Let(annotated) = &program.statements[0] else {
+            panic!("expected let");
+        };
+        let annotation = annotated.type_annotation.as_ref().unwrap();
+        assert_eq!(annotation.name, "Int");
+        assert_eq!(annotation.token.token_type, TokenType::Ident);
+        assert!(annotation.arguments.is_empty());
+        let Statement::Let(plain) = &program.statements[1] else {
+            panic!("expected let");
+        };
+        assert!(plain.type_annotation.is_none());
+        assert_eq!(program.to_string(), "let x: Int = 5;let y = 1;");
+
+        // Параметры функции, rest-параметр и тип результата
+        let program = parse("let f = fn(a: Int, b, ...rest: Array[Int]): Int { a };").unwrap();
+        let Statement::Let(statement) = &program.statements[0] else {
+            panic!("expected let");
+        };
+        let Expression::FunctionLiteral(function) = &statement.value else {
+            panic!("expected function literal");
+        };
+        let names: Vec<Option<String>> = function
+            .types
+            .parameters
+            .iter()
+            .map(|t| t.as_ref().map(|t| t.to_string()))
+            .collect();
+        assert_eq!(names, vec![Some("Int".to_string()), None]);
+        assert_eq!(
+            function.types.rest.as_ref().unwrap().to_string(),
+            "Array[Int]"
+        );
+        assert_eq!(function.types.return_type.as_ref().unwrap().name, "Int");
+        assert_eq!(
+            program.to_string(),
+            "let f = fn(a: Int, b, ...rest: Array[Int]): Int a;"
+        );
+
+        // Именованная функция и тип `Null` — обычное имя, не литерал `null`
+        assert_eq!(
+            parse("fn add(a: Int, b: Int): Null { a + b }")
+                .unwrap()
+                .to_string(),
+            "let add = fn(a: Int, b: Int): Null (a + b);"
+        );
+
+        // Вложенные аргументы типов
+        let program = parse("let m: Map[String, Array[Array[Int]]] = 1;").unwrap();
+        let Statement::Let(statement) = &program.statements[0] else {
+            panic!("expected let");
+        };
+        let annotation = statement.type_annotation.as_ref().unwrap();
+        assert_eq!(annotation.arguments.len(), 2);
+        assert_eq!(
+            annotation.arguments[1].arguments[0].arguments[0].name,
+            "Int"
+        );
+        let walked: Vec<&str> = annotation.walk().iter().map(|a| a.name.as_str()).collect();
+        assert_eq!(walked, vec!["Map", "String", "Array", "Array", "Int"]);
+        assert_eq!(
+            program.to_string(),
+            "let m: Map[String, Array[Array[Int]]] = 1;"
+        );
+
+        // Методы класса (с `fn` и без) и интерфейса
+        let program = parse(
+            "class P { fn get(a: Int): Int { a } public set(v: String) { v } }
+             interface Shape { fn area(): Int; fn scale(k: Int): Shape { this } }",
+        )
+        .unwrap();
+        let Statement::ClassDeclaration(class) = &program.statements[0] else {
+            panic!("expected class");
+        };
+        assert_eq!(class.methods[0].to_string(), "private get(a: Int): Int a");
+        assert_eq!(class.methods[1].to_string(), "public set(v: String) v");
+        let Statement::InterfaceDeclaration(interface) = &program.statements[1] else {
+            panic!("expected interface");
+        };
+        assert_eq!(
+            interface.method_signatures().next().unwrap().to_string(),
+            "area(): Int"
+        );
+        assert_eq!(
+            interface
+                .default_methods()
+                .next()
+                .unwrap()
+                .types
+                .return_type
+                .as_ref()
+                .unwrap()
+                .name,
+            "Shape"
+        );
+        assert!(interface
+            .to_string()
+            .contains("fn scale(k: Int): Shape this"));
+    }
+
+    #[test]
+    fn test_type_annotation_errors() {
+        let errors = |input: &str| -> Vec<String> {
+            Parser::new(Lexer::new(input.to_string()))
+                .parse_program()
+                .unwrap_err()
+                .iter()
+                .map(|e| format!("{:?}", e))
+                .collect()
+        };
+        let expected = |got: &str| {
+            vec![format!(
+                "UnexpectedToken(\"expected type name, got {}\")",
+                got
+            )]
+        };
+
+        // Ошибка в аннотации записывается один раз, а разбор продолжается
+        // со следующего за ней токена, поэтому ошибки не множатся
+        assert_eq!(errors("let x: 5 = 1; let y = 2;"), expected("Int"));
+        assert_eq!(errors("let x: = 1;"), expected("Assign"));
+        assert_eq!(errors("fn f(a: 1 + 2, b) { a }"), expected("Int"));
+        assert_eq!(errors("fn f(a): { a }"), expected("LBrace"));
+        assert_eq!(errors("let x: Array[5] = 1;"), expected("Int"));
+        assert_eq!(errors("let x: null = 1;"), expected("Null"));
+        assert_eq!(
+            errors("let x: Array[Int = 1;"),
+            vec!["UnexpectedToken(\"expected ']' after type arguments, got Assign\")".to_string()]
+        );
+    }
 }
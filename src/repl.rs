@@ -0,0 +1,570 @@
+use crate::ast::{Node, Program};
+use crate::bytecode::disassembler::disassemble_with_program;
+use crate::bytecode::instructions::Instructions;
+use crate::compiler::Compiler;
+use crate::evaluator::{eval, stringify};
+use crate::lexer::Lexer;
+use crate::object::{Environment, Object};
+use crate::parser::Parser;
+use crate::vm::VM;
+use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::env;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+use std::rc::Rc;
+
+/// Переменная окружения, переопределяющая путь к файлу истории.
+pub const HISTORY_ENV: &str = "SOFIA_HISTORY";
+/// Имя файла истории в домашнем каталоге.
+const HISTORY_FILE: &str = ".sofia_history";
+/// Сколько последних вводов хранит история.
+pub const HISTORY_CAPACITY: usize = 1000;
+
+const PROMPT: &str = ">> ";
+const GOODBYE: &str = "Goodbye!";
+
+/// Путь к файлу истории: `$SOFIA_HISTORY` или `~/.sofia_history`.
+pub fn default_history_path() -> Option<PathBuf> {
+    if let Some(path) = env::var_os(HISTORY_ENV) {
+        return Some(PathBuf::from(path));
+    }
+    env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE))
+}
+
+/// История ввода: кольцо из последних `capacity` строк, при наличии пути
+/// сохраняемое в файл (по строке на ввод).
+#[derive(Debug)]
+pub struct History {
+    entries: VecDeque<String>,
+    capacity: usize,
+    path: Option<PathBuf>,
+}
+
+impl History {
+    /// Пустая история, не связанная с файлом.
+    pub fn new(capacity: usize) -> Self {
+        History {
+            entries: VecDeque::new(),
+            capacity,
+            path: None,
+        }
+    }
+
+    /// Загружает историю из файла; отсутствующий файл — пустая история.
+    /// В файл же история будет сохранена методом `save`.
+    pub fn load(path: PathBuf, capacity: usize) -> io::Result<Self> {
+        let mut history = History::new(capacity);
+        match fs::read_to_string(&path) {
+            Ok(text) => text.lines().for_each(|line| history.push(line)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        history.path = Some(path);
+        Ok(history)
+    }
+
+    /// Добавляет ввод, вытесняя самый старый при переполнении.
+    /// Пустые строки не запоминаются.
+    pub fn push(&mut self, line: &str) {
+        if line.trim().is_empty() || self.capacity == 0 {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(line.to_string());
+    }
+
+    /// Ввод с номером `n` (нумерация с единицы, как в `:history`).
+    pub fn get(&self, n: usize) -> Option<&str> {
+        n.checked_sub(1)
+            .and_then(|i| self.entries.get(i))
+            .map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(String::as_str)
+    }
+
+    /// Сохраняет историю в файл, если он задан.
+    pub fn save(&self) -> io::Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let mut text = String::new();
+        for entry in &self.entries {
+            text.push_str(entry);
+            text.push('\n');
+        }
+        fs::write(path, text)
+    }
+}
+
+/// Аргумент команды `name`: текст после имени, отделённый пробелом.
+/// `None`, если строка — не эта команда.
+fn command_argument<'a>(command: &'a str, name: &str) -> Option<&'a str> {
+    let rest = command.strip_prefix(name)?;
+    if rest.is_empty() || rest.starts_with(char::is_whitespace) {
+        Some(rest.trim())
+    } else {
+        None
+    }
+}
+
+/// Компилирует исходный код и возвращает его дизассемблированный листинг
+/// (команда `:dis`); ошибки разбора и компиляции возвращаются текстом.
+pub fn disassemble_source(source: &str) -> Result<String, String> {
+    let (program, instructions) = compile_source(source)?;
+    Ok(disassemble_with_program(&instructions, &program))
+}
+
+/// Компилирует исходный код и возвращает листинг его пула констант
+/// (команда `:bytecode`).
+pub fn dump_source_constants(source: &str) -> Result<String, String> {
+    let (_, instructions) = compile_source(source)?;
+    if instructions.constants.is_empty() {
+        return Ok("no constants\n".to_string());
+    }
+    Ok(instructions.dump_constants())
+}
+
+fn compile_source(source: &str) -> Result<(Program, Instructions), String> {
+    let program = Parser::new(Lexer::new(source.to_string()))
+        .parse_program()
+        .map_err(|errors| {
+            let messages: Vec<String> = errors.iter().map(|e| format!("{:?}", e)).collect();
+            messages.join(", ")
+        })?;
+    let instructions = Compiler::new().compile(&program)?;
+    Ok((program, instructions))
+}
+
+/// Что делать REPL после обработки строки.
+#[derive(Debug, PartialEq)]
+enum Flow {
+    Continue,
+    Quit,
+}
+
+/// Цикл Read-Eval-Print над произвольными вводом и выводом.
+///
+/// Команды: `:quit` и `exit` — выход, `:history` — список предыдущих
+/// вводов, `:! N` — повторить ввод номер `N`, `:dis [код]` — показать
+/// байткод кода (по умолчанию — последнего ввода) вместо выполнения,
+/// `:bytecode [код]` — только его пул констант. Конец ввода (Ctrl-D)
+/// тоже завершает сессию; при выходе история сохраняется в файл,
+/// ошибка сохранения лишь выводится.
+pub struct Repl<R: BufRead, W: Write> {
+    input: R,
+    output: W,
+    use_vm: bool,
+    env: Rc<RefCell<Environment>>,
+    /// Компилятор сессии VM: каждый ввод дописывается к прежнему байткоду
+    /// и видит объявленные раньше глобальные переменные.
+    compiler: Compiler,
+    /// Глобальные переменные VM, общие для всех вводов сессии.
+    globals: Rc<RefCell<HashMap<String, Object>>>,
+    /// Длина уже выполненного байткода: с неё начинается следующий ввод.
+    compiled_len: usize,
+    history: History,
+}
+
+impl<R: BufRead, W: Write> Repl<R, W> {
+    pub fn new(input: R, output: W, use_vm: bool, history: History) -> Self {
+        let mut compiler = Compiler::new();
+        compiler.allow_redefinition();
+        Repl {
+            input,
+            output,
+            use_vm,
+            env: Rc::new(RefCell::new(Environment::allowing_redefinition())),
+            compiler,
+            globals: Rc::new(RefCell::new(HashMap::new())),
+            compiled_len: 0,
+            history,
+        }
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// Читает и выполняет строки до выхода или конца ввода.
+    pub fn run(&mut self) -> io::Result<()> {
+        loop {
+            write!(self.output, "{}", PROMPT)?;
+            self.output.flush()?;
+
+            let mut line = String::new();
+            if self.input.read_line(&mut line)? == 0 {
+                // Ctrl-D: переводим строку после приглашения
+                writeln!(self.output)?;
+                break;
+            }
+            if self.dispatch(line.trim_end_matches(['\n', '\r']))? == Flow::Quit {
+                break;
+            }
+        }
+        writeln!(self.output, "{}", GOODBYE)?;
+        if let Err(e) = self.history.save() {
+            writeln!(self.output, "warning: could not save history: {}", e)?;
+        }
+        Ok(())
+    }
+
+    /// Обрабатывает одну строку ввода: команду REPL или код SOFIA.
+    fn dispatch(&mut self, line: &str) -> io::Result<Flow> {
+        let command = line.trim();
+        match command {
+            "" => return Ok(Flow::Continue),
+            ":quit" | "exit" => return Ok(Flow::Quit),
+            ":history" => {
+                for (i, entry) in self.history.iter().enumerate() {
+                    writeln!(self.output, "{:>4}  {}", i + 1, entry)?;
+                }
+                return Ok(Flow::Continue);
+            }
+            _ => {}
+        }
+
+        if let Some(arg) = command.strip_prefix(":!") {
+            let entry = arg
+                .trim()
+                .parse::<usize>()
+                .ok()
+                .and_then(|n| self.history.get(n))
+                .map(str::to_string);
+            match entry {
+                Some(entry) => {
+                    writeln!(self.output, "{}", entry)?;
+                    self.history.push(&entry);
+                    self.execute(&entry)?;
+                }
+                None => writeln!(self.output, "no history entry: {}", arg.trim())?,
+            }
+        } else if let Some(arg) = command_argument(command, ":dis") {
+            self.show_compiled(arg, disassemble_source)?;
+        } else if let Some(arg) = command_argument(command, ":bytecode") {
+            self.show_compiled(arg, dump_source_constants)?;
+        } else if command.starts_with(':') {
+            writeln!(self.output, "unknown command: {}", command)?;
+        } else {
+            self.history.push(line);
+            self.execute(line)?;
+        }
+        Ok(Flow::Continue)
+    }
+
+    /// Печатает листинг `render` для кода из аргумента команды или, если
+    /// аргумента нет, для последнего введённого кода.
+    fn show_compiled(
+        &mut self,
+        arg: &str,
+        render: fn(&str) -> Result<String, String>,
+    ) -> io::Result<()> {
+        let source = if arg.is_empty() {
+            self.history.iter().last().map(str::to_string)
+        } else {
+            Some(arg.to_string())
+        };
+        match source.as_deref().map(render) {
+            Some(Ok(listing)) => write!(self.output, "{}", listing),
+            Some(Err(e)) => writeln!(self.output, "ERROR: {}", e),
+            None => writeln!(self.output, "nothing to disassemble"),
+        }
+    }
+
+    /// Разбирает и выполняет исходный код, печатая результат или ошибки.
+    fn execute(&mut self, source: &str) -> io::Result<()> {
+        let mut parser = Parser::new(Lexer::new(source.to_string()));
+        let program = match parser.parse_program() {
+            Ok(program) => program,
+            Err(errors) => {
+                for error in errors {
+                    writeln!(self.output, "\t{:?}", error)?;
+                }
+                return Ok(());
+            }
+        };
+        // Только пробелы и комментарии: ничего не выполняем и не печатаем
+        if program.statements.is_empty() {
+            return Ok(());
+        }
+
+        if self.use_vm {
+            self.run_with_vm(&program)
+        } else {
+            let evaluated = eval(Node::Program(program), Rc::clone(&self.env));
+            match stringify(&evaluated) {
+                Ok(text) => writeln!(self.output, "{}", text),
+                Err(error) => writeln!(self.output, "{}", error),
+            }
+        }
+    }
+
+    /// Компилирует программу компилятором сессии и запускает на VM с начала
+    /// нового фрагмента, печатая его предупреждения перед результатом.
+    /// Глобальные переменные переживают ввод: следующая строка их видит.
+    fn run_with_vm(&mut self, program: &Program) -> io::Result<()> {
+        let seen_warnings = self.compiler.warnings().len();
+        let compiled = self.compiler.compile(program);
+        for warning in &self.compiler.warnings()[seen_warnings..] {
+            writeln!(self.output, "{}", warning)?;
+        }
+        let start = self.compiled_len;
+        let result = compiled.map_err(String::from).and_then(|instructions| {
+            self.compiled_len = instructions.bytes.len();
+            let mut vm = VM::new(instructions);
+            vm.set_globals(Rc::clone(&self.globals));
+            vm.run_from(start)
+        });
+        match result {
+            Ok(result) => writeln!(self.output, "{}", result),
+            Err(e) => writeln!(self.output, "ERROR: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+    use std::path::Path;
+
+    /// Прогоняет сессию по сценарию и возвращает вывод и историю.
+    fn run_session(script: &str, use_vm: bool, history: History) -> (String, History) {
+        let mut output = Vec::new();
+        let mut repl = Repl::new(Cursor::new(script.as_bytes()), &mut output, use_vm, history);
+        repl.run().unwrap();
+        let history = repl.history;
+        (String::from_utf8(output).unwrap(), history)
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("sofia_repl_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_repl_eof_exits_cleanly() {
+        let (output, _) = run_session("", true, History::new(10));
+        assert_eq!(output, ">> \nGoodbye!\n");
+
+        // Последняя строка без перевода строки выполняется до выхода
+        let (output, _) = run_session("1 + 2", true, History::new(10));
+        assert_eq!(output, ">> 3\n>> \nGoodbye!\n");
+    }
+
+    #[test]
+    fn test_repl_blank_input_is_noop() {
+        for use_vm in [true, false] {
+            let script = "\n   \n\t\n// комментарий\n  // a // b\n1;\n";
+            let (output, _) = run_session(script, use_vm, History::new(10));
+            assert_eq!(
+                output, ">> >> >> >> >> >> 1\n>> \nGoodbye!\n",
+                "vm: {}",
+                use_vm
+            );
+        }
+    }
+
+    #[test]
+    fn test_repl_quit_commands() {
+        for quit in [":quit", "exit", "  exit  "] {
+            let script = format!("1;\n{}\n2;\n", quit);
+            let (output, _) = run_session(&script, true, History::new(10));
+            assert_eq!(output, ">> 1\n>> Goodbye!\n", "{}", quit);
+        }
+    }
+
+    #[test]
+    fn test_repl_history_commands() {
+        let script = "let x = 4;\nx * 2;\n\n:history\n:! 2\n:!9\n:nope\n";
+        let (output, history) = run_session(script, false, History::new(10));
+        let expected = [
+            ">> null",
+            ">> 8",
+            ">> >>    1  let x = 4;",
+            "   2  x * 2;",
+            ">> x * 2;",
+            "8",
+            ">> no history entry: 9",
+            ">> unknown command: :nope",
+            ">> ",
+            "Goodbye!",
+            "",
+        ]
+        .join("\n");
+        assert_eq!(output, expected);
+        // Повторённый ввод тоже попадает в историю, команды — нет
+        let entries: Vec<&str> = history.iter().collect();
+        assert_eq!(entries, vec!["let x = 4;", "x * 2;", "x * 2;"]);
+    }
+
+    #[test]
+    fn test_repl_allows_type_redefinition() {
+        let script =
+            "class A { public v = 1; }\nclass A { public v = 2; }\nnew A().v;\nlet A = 5;\nA;\n";
+        let (output, _) = run_session(script, false, History::new(10));
+        assert_eq!(
+            output,
+            ">> null\n>> null\n>> 2\n>> null\n>> 5\n>> \nGoodbye!\n"
+        );
+    }
+
+    #[test]
+    fn test_repl_vm_keeps_globals_between_lines() {
+        let script = "let a = 5;\na;\nlet inc = fn(n) { n + a };\na = a + 1;\ninc(1);\n";
+        let (output, _) = run_session(script, true, History::new(10));
+        assert_eq!(
+            output,
+            ">> 5\n>> 5\n>> closure (1 params, 1 locals, 0 free)\n>> 6\n>> 7\n>> \nGoodbye!\n"
+        );
+
+        // Ошибка компиляции не мешает следующим строкам
+        let (output, _) = run_session("let b = 1;\nc = 2;\nb + 1;\n", true, History::new(10));
+        assert!(output.ends_with(">> 2\n>> \nGoodbye!\n"), "{}", output);
+    }
+
+    #[test]
+    fn test_repl_prints_compiler_warnings() {
+        let (output, _) = run_session("match 1 { 1 => 1, 1 => 2, _ => 3 }", true, History::new(10));
+        assert_eq!(
+            output,
+            ">> warning: match arm 2 `1` is unreachable: duplicates arm 1\n1\n>> \nGoodbye!\n"
+        );
+    }
+
+    #[test]
+    fn test_disassemble_source() {
+        let listing = disassemble_source("1 + 2").unwrap();
+        assert!(listing.contains("ADD"), "{}", listing);
+        assert!(listing.contains("MAP_TO_AST 1 ; (1 + 2)"), "{}", listing);
+        assert_eq!(
+            disassemble_source("fn() { let x = 0; fn() { x = 1 } };"),
+            Err("присваивания захваченным переменным пока не поддерживаются в режиме VM — используйте --ast".to_string())
+        );
+    }
+
+    #[test]
+    fn test_repl_bytecode_command() {
+        let (output, history) = run_session(
+            ":bytecode\n:bytecode let f = fn(a) { a + 1 }; \"s\"\ntrue;\n:bytecode\n",
+            true,
+            History::new(10),
+        );
+        assert_eq!(
+            output,
+            ">> nothing to disassemble\n\
+             >> [0] INTEGER 1\n\
+             [1] COMPILED_FUNCTION fn/1 @0x0006 (1 locals)\n\
+             [2] STRING \"f\"\n\
+             [3] STRING \"s\"\n\
+             >> true\n\
+             >> no constants\n\
+             >> \nGoodbye!\n"
+        );
+        assert_eq!(history.iter().collect::<Vec<_>>(), vec!["true;"]);
+    }
+
+    #[test]
+    fn test_repl_dis_command() {
+        let (output, history) = run_session(
+            ":dis\n:dis 1 + 2\n3 * 4;\n:dis\n:display\n",
+            true,
+            History::new(10),
+        );
+        assert!(
+            output.starts_with(">> nothing to disassemble\n>> === BYTECODE"),
+            "{}",
+            output
+        );
+        let listings: Vec<&str> = output.split("=== INSTRUCTIONS ===").collect();
+        assert_eq!(listings.len(), 3, "{}", output);
+        assert!(
+            listings[1].contains("ADD") && !listings[1].contains("MUL"),
+            "{}",
+            output
+        );
+        assert!(listings[2].contains("MUL"), "{}", output);
+        assert!(
+            output.contains(">> unknown command: :display\n"),
+            "{}",
+            output
+        );
+        // :dis не выполняет код и не попадает в историю
+        assert_eq!(history.iter().collect::<Vec<_>>(), vec!["3 * 4;"]);
+    }
+
+    #[test]
+    fn test_history_ring_drops_oldest() {
+        let mut history = History::new(2);
+        for line in ["a", "b", "c"] {
+            history.push(line);
+        }
+        assert_eq!(history.len(), 2);
+        assert_eq!(history.get(1), Some("b"));
+        assert_eq!(history.get(2), Some("c"));
+        assert_eq!(history.get(0), None);
+        assert_eq!(history.get(3), None);
+    }
+
+    #[test]
+    fn test_history_persists_between_sessions() {
+        let dir = temp_dir("persist");
+        let path = dir.join("history");
+
+        let history = History::load(path.clone(), 3).unwrap();
+        assert!(history.is_empty());
+        run_session("1;\n2;\n:quit\n", true, history);
+        assert_eq!(fs::read_to_string(&path).unwrap(), "1;\n2;\n");
+
+        let history = History::load(path.clone(), 3).unwrap();
+        let (output, _) = run_session(":! 2\n3;\n4;\n", true, history);
+        assert!(output.starts_with(">> 2;\n2\n"), "{}", output);
+        // В файле остаются только последние три ввода
+        assert_eq!(fs::read_to_string(&path).unwrap(), "2;\n3;\n4;\n");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_history_save_error_is_not_fatal() {
+        let dir = temp_dir("save_error");
+        // Каталог вместо файла: прочитать и записать историю нельзя
+        assert!(History::load(dir.clone(), 10).is_err());
+
+        let mut history = History::new(10);
+        history.path = Some(dir.clone());
+        let (output, _) = run_session("1;\n", true, history);
+        assert!(output.starts_with(">> 1\n>> \nGoodbye!\n"), "{}", output);
+        assert!(
+            output.contains("warning: could not save history"),
+            "{}",
+            output
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_default_history_path() {
+        let path = default_history_path();
+        match env::var_os(HISTORY_ENV) {
+            Some(custom) => assert_eq!(path.as_deref(), Some(Path::new(&custom))),
+            None => assert!(path.is_none_or(|p| p.ends_with(HISTORY_FILE))),
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use crate::ast::Node;
+use crate::compiler::Compiler;
+use crate::evaluator::eval;
+use crate::lexer::Lexer;
+use crate::object::{Environment, Object};
+use crate::parser::Parser;
+use crate::vm::VM;
+use std::cell::RefCell;
+use std::fmt;
+use std::rc::Rc;
+
+/// Исполнитель программы.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Engine {
+    /// AST-интерпретатор (`evaluator`).
+    Ast,
+    /// Компилятор байткода и VM.
+    Vm,
+}
+
+impl fmt::Display for Engine {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Engine::Ast => write!(f, "ast"),
+            Engine::Vm => write!(f, "vm"),
+        }
+    }
+}
+
+/// Откуда пришёл исходный текст. От режима зависит, можно ли повторно
+/// объявить класс, структуру или интерфейс в той же области видимости.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Программа из файла: повторное объявление — ошибка.
+    Program,
+    /// Ввод REPL: повторное объявление заменяет прежнее.
+    Repl,
+}
+
+/// Разбирает и выполняет программу целиком в чистом окружении и возвращает
+/// её результат. Ошибки разбора (через перевод строки), компиляции и
+/// выполнения, в том числе `Object::Error` интерпретатора, возвращаются
+/// текстом.
+pub fn run_source(source: &str, engine: Engine) -> Result<Object, String> {
+    run_source_with_mode(source, engine, Mode::Program)
+}
+
+/// Как `run_source`, но в заданном режиме: интерпретатор получает
+/// окружение, а компилятор — настройку этого режима.
+pub fn run_source_with_mode(source: &str, engine: Engine, mode: Mode) -> Result<Object, String> {
+    let program = Parser::new(Lexer::new(source.to_string()))
+        .parse_program()
+        .map_err(|errors| {
+            let messages: Vec<String> = errors.iter().map(|e| format!("{:?}", e)).collect();
+            messages.join("\n")
+        })?;
+    match engine {
+        Engine::Ast => {
+            let env = match mode {
+                Mode::Program => Environment::new(),
+                Mode::Repl => Environment::allowing_redefinition(),
+            };
+            match eval(Node::Program(program), Rc::new(RefCell::new(env))) {
+                Object::Error(error) => Err(error.to_string()),
+                result => Ok(result),
+            }
+        }
+        Engine::Vm => {
+            let mut compiler = Compiler::new();
+            if mode == Mode::Repl {
+                compiler.allow_redefinition();
+            }
+            let instructions = compiler.compile(&program)?;
+            VM::new(instructions).run()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_source_on_both_engines() {
+        for engine in [Engine::Ast, Engine::Vm] {
+            assert_eq!(
+                run_source("let x = 2; x * 21", engine),
+                Ok(Object::Integer(42)),
+                "{}",
+                engine
+            );
+            assert!(run_source("1 / 0", engine).is_err(), "{}", engine);
+            assert!(run_source("let = 1;", engine)
+                .unwrap_err()
+                .contains("UnexpectedToken"));
+        }
+    }
+
+    #[test]
+    fn test_run_source_mode_controls_redefinition() {
+        let source = "class A {} class A {} 1";
+        assert_eq!(
+            run_source(source, Engine::Ast),
+            Err("class 'A' is already defined".to_string())
+        );
+        assert_eq!(
+            run_source_with_mode(source, Engine::Ast, Mode::Repl),
+            Ok(Object::Integer(1))
+        );
+    }
+
+    #[test]
+    fn test_run_source_mode_controls_struct_redefinition_on_vm() {
+        for source in [
+            "struct S { let x = 1; } struct S { let y = 2; } 1",
+            "let S = 5; struct S { let y = 2; } 1",
+            "struct S { let x = 1; } let S = 5; 1",
+        ] {
+            for engine in [Engine::Ast, Engine::Vm] {
+                assert_eq!(
+                    run_source(source, engine),
+                    Err("struct 'S' is already defined".to_string()),
+                    "{} ({:?})",
+                    source,
+                    engine
+                );
+                assert_eq!(
+                    run_source_with_mode(source, engine, Mode::Repl),
+                    Ok(Object::Integer(1)),
+                    "{} ({:?})",
+                    source,
+                    engine
+                );
+            }
+        }
+    }
+}
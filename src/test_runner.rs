@@ -0,0 +1,187 @@
+use crate::ast::{
+    CallExpression, Expression, ExpressionStatement, Identifier, Node, NodeId, Program, Statement,
+};
+use crate::evaluator::eval;
+use crate::lexer::Lexer;
+use crate::object::{Environment, Object};
+use crate::parser::Parser;
+use crate::token::{Token, TokenType};
+use std::cell::RefCell;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// Суффикс файлов с тестами.
+const TEST_FILE_SUFFIX: &str = "_test.sf";
+/// Префикс имени тестовой функции.
+const TEST_FN_PREFIX: &str = "test_";
+
+/// Итог прогона тестов.
+#[derive(Debug, Default)]
+pub struct TestReport {
+    pub passed: usize,
+    pub failed: usize,
+    /// Построчный отчёт о каждом тесте и итоговая сводка.
+    pub output: String,
+}
+
+impl TestReport {
+    pub fn success(&self) -> bool {
+        self.failed == 0
+    }
+
+    fn pass(&mut self, name: &str) {
+        self.passed += 1;
+        self.output.push_str(&format!("PASS {}\n", name));
+    }
+
+    fn fail(&mut self, name: &str, message: &str) {
+        self.failed += 1;
+        self.output.push_str(&format!("FAIL {}\n", name));
+        for line in message.lines() {
+            self.output.push_str(&format!("    {}\n", line));
+        }
+    }
+}
+
+/// Находит все файлы `*_test.sf` в каталоге (рекурсивно) и запускает
+/// в них функции `test_*`.
+///
+/// Каждый тест исполняется в новом окружении: файл вычисляется заново,
+/// затем вызывается тестовая функция без аргументов. Тест считается
+/// проваленным, если вычисление вернуло ошибку.
+pub fn run_tests(dir: &Path) -> io::Result<TestReport> {
+    let mut files = Vec::new();
+    collect_test_files(dir, &mut files)?;
+    files.sort();
+
+    let mut report = TestReport::default();
+    for file in &files {
+        let source = fs::read_to_string(file)?;
+        let file_name = file.strip_prefix(dir).unwrap_or(file).display().to_string();
+        run_test_file(&file_name, &source, &mut report);
+    }
+
+    report.output.push_str(&format!(
+        "\ntest result: {}. {} passed; {} failed\n",
+        if report.success() { "ok" } else { "FAILED" },
+        report.passed,
+        report.failed
+    ));
+    Ok(report)
+}
+
+fn collect_test_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_test_files(&path, files)?;
+        } else if path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.ends_with(TEST_FILE_SUFFIX))
+        {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+fn run_test_file(file_name: &str, source: &str, report: &mut TestReport) {
+    let mut parser = Parser::new(Lexer::new(source.to_string()));
+    let program = match parser.parse_program() {
+        Ok(program) => program,
+        Err(errors) => {
+            let message = errors
+                .iter()
+                .map(|e| format!("{:?}", e))
+                .collect::<Vec<_>>()
+                .join("\n");
+            report.fail(file_name, &message);
+            return;
+        }
+    };
+
+    for test_name in test_function_names(&program) {
+        let name = format!("{}::{}", file_name, test_name);
+        match run_test_function(&program, &test_name) {
+            Ok(()) => report.pass(&name),
+            Err(message) => report.fail(&name, &message),
+        }
+    }
+}
+
+/// Имена функций верхнего уровня вида `fn test_xxx() { ... }` в порядке объявления.
+fn test_function_names(program: &Program) -> Vec<String> {
+    program
+        .statements
+        .iter()
+        .filter_map(|statement| match statement {
+            Statement::Let(ls)
+                if ls.name.value.starts_with(TEST_FN_PREFIX)
+                    && matches!(ls.value, Expression::FunctionLiteral(_)) =>
+            {
+                Some(ls.name.value.clone())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+fn run_test_function(program: &Program, test_name: &str) -> Result<(), String> {
+    let env = Rc::new(RefCell::new(Environment::new()));
+
+    if let Object::Error(error) = eval(Node::Program(program.clone()), Rc::clone(&env)) {
+        return Err(error.to_string());
+    }
+
+    let call = Program {
+        statements: vec![Statement::Expression(ExpressionStatement {
+            id: NodeId::default(),
+            token: Token::new(TokenType::Ident, test_name.to_string()),
+            expression: Expression::Call(CallExpression {
+                token: Token::new(TokenType::LParen, "(".to_string()),
+                function: Box::new(Expression::Identifier(Identifier {
+                    token: Token::new(TokenType::Ident, test_name.to_string()),
+                    value: test_name.to_string(),
+                })),
+                arguments: Vec::new(),
+            }),
+        })],
+    };
+
+    match eval(Node::Program(call), env) {
+        Object::Error(error) => Err(error.to_string()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_test_function_names() {
+        let input = "fn helper() { 1 } fn test_a() { 1 } let test_b = 2; fn test_c() { 2 }";
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        assert_eq!(test_function_names(&program), vec!["test_a", "test_c"]);
+    }
+
+    #[test]
+    fn test_run_test_file() {
+        let input = r#"
+            fn test_ok() { assert_eq(1 + 1, 2) }
+            fn test_bad() { assert(1 > 2) }
+        "#;
+        let mut report = TestReport::default();
+        run_test_file("math_test.sf", input, &mut report);
+        assert_eq!(report.passed, 1);
+        assert_eq!(report.failed, 1);
+        assert!(report.output.contains("PASS math_test.sf::test_ok\n"));
+        assert!(report.output.contains("FAIL math_test.sf::test_bad\n"));
+        assert!(report.output.contains("    assertion failed: false\n"));
+    }
+}
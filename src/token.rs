@@ -1,3 +1,5 @@
+use std::hash::{Hash, Hasher};
+
 // Типы токенов, которые распознает лексер
 #[derive(Debug, PartialEq, Clone, Copy, Eq, Hash)]
 pub enum TokenType {
@@ -43,18 +45,29 @@ pub enum TokenType {
     // Ключевые слова
     Function,
     Let,
+    Const,
     True,
     False,
+    Null,
     If,
     Else,
     Return,
+    For,
+    Break,
+    Continue,
+    In,
+    Typeof,
 
     // Ключевые слова для сопоставления с образцом
     Match,
 
     // Операторы для сопоставления с образцом
-    Arrow, // =>
-    Range, // ..
+    Arrow,          // =>
+    Range,          // ..
+    RangeInclusive, // ..= (только в паттернах)
+
+    // Rest-параметр функции
+    Ellipsis, // ...
 
     // ООП ключевые слова
     Class,
@@ -65,25 +78,81 @@ pub enum TokenType {
     New,
     Extends,
     Implements,
+    Is,
     Public,
     Private,
     Static,
 }
 
-// Структура, представляющая лексическую единицу (токен)
-#[derive(Debug, PartialEq, Clone, Eq, Hash)]
+// Байтовый диапазон токена в исходном коде: `start..end`,
+// а также строка и столбец его начала (с единицы; 0 — позиция неизвестна)
+#[derive(Debug, PartialEq, Clone, Copy, Eq, Hash, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    // Создает диапазон `start..end` без строки и столбца
+    pub fn new(start: usize, end: usize) -> Self {
+        Span {
+            start,
+            end,
+            line: 0,
+            column: 0,
+        }
+    }
+
+    // Известна ли позиция в исходном коде
+    pub fn has_position(&self) -> bool {
+        self.line > 0
+    }
+}
+
+// Структура, представляющая лексическую единицу (токен).
+// Позиция (`span`) — служебная информация: токены сравниваются
+// и хешируются только по типу и литералу.
+#[derive(Debug, Clone)]
 pub struct Token {
     pub token_type: TokenType,
     pub literal: String,
+    pub span: Span,
 }
 
 // Реализация методов структуры Token
 impl Token {
-    // Создает новый токен
+    // Создает новый токен без позиции
     pub fn new(token_type: TokenType, literal: String) -> Self {
         Token {
             token_type,
             literal,
+            span: Span::default(),
         }
     }
+
+    // Создает новый токен с байтовым диапазоном в исходном коде
+    pub fn with_span(token_type: TokenType, literal: String, span: Span) -> Self {
+        Token {
+            token_type,
+            literal,
+            span,
+        }
+    }
+}
+
+impl PartialEq for Token {
+    fn eq(&self, other: &Self) -> bool {
+        self.token_type == other.token_type && self.literal == other.literal
+    }
+}
+
+impl Eq for Token {}
+
+impl Hash for Token {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.token_type.hash(state);
+        self.literal.hash(state);
+    }
 }
@@ -1,29 +1,81 @@
+use crate::ast::NodeId;
+use crate::builtins;
+use crate::bytecode::constant::Constant;
 use crate::bytecode::instructions::Instructions;
 use crate::bytecode::opcode::Opcode;
-use crate::object::Object;
+use crate::object::{
+    arithmetic, compare_values, in_range_pattern, index_value, instance_fields,
+    non_exhaustive_match, range_pattern_bounds, set_index, values_equal, Closure, CompiledFunction,
+    HashKey, Object, OrderedMap, Struct, StructInstance,
+};
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::rc::Rc;
 
+pub mod profile;
+
+use profile::{ProfileReport, Profiler};
+
 /// Размер стека виртуальной машины (в элементах Object).
 const STACK_SIZE: usize = 2048;
 
 /// Количество регистров общего назначения.
 const NUM_REGISTERS: usize = 16;
 
+/// Сколько верхних элементов стека передаётся в `DebugEvent`.
+const DEBUG_STACK_DEPTH: usize = 4;
+
+/// Состояние VM перед выполнением очередной инструкции, передаваемое
+/// отладочному хуку.
+#[derive(Debug)]
+pub struct DebugEvent<'a> {
+    /// Смещение инструкции в байткоде.
+    pub ip: usize,
+    /// Декодированный опкод.
+    pub opcode: Opcode,
+    /// Декодированные операнды инструкции.
+    pub operands: Vec<u16>,
+    /// Указатель стека.
+    pub sp: usize,
+    /// Верхние элементы стека (не больше `DEBUG_STACK_DEPTH`), вершина — последняя.
+    pub stack_top: &'a [Object],
+    /// Число активных фреймов вызова; 0 — код верхнего уровня.
+    pub frame_depth: usize,
+}
+
+/// Решение отладочного хука о дальнейшем выполнении.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DebugAction {
+    /// Выполнять дальше; хук вызовется на следующей точке останова
+    /// (или на каждой инструкции, если точек останова нет).
+    Continue,
+    /// Выполнить эту инструкцию и вызвать хук перед следующей.
+    Step,
+    /// Остановить выполнение перед этой инструкцией. `run` возвращает ошибку,
+    /// повторный `run` продолжает с того же места, не вызывая хук повторно.
+    Pause,
+}
+
+/// Отладочный хук VM.
+pub type DebugHook = Box<dyn FnMut(&DebugEvent) -> DebugAction>;
+
 /// Виртуальная машина (VM) для выполнения байткода.
 /// Использует стек для хранения значений и поддерживает глобальные переменные.
 pub struct VM {
     /// Инструкции байткода, которые должна выполнить VM.
     instructions: Instructions,
 
+    /// Пул констант, превращённый в объекты времени выполнения.
+    constants: Vec<Object>,
+
     /// Стек значений для выполнения операций.
     stack: Vec<Object>,
 
     /// Указатель стека (индекс следующей свободной позиции).
     sp: usize,
 
-    /// Регистры общего назначения.
+    /// Регистры общего назначения для регистровой арифметики
+    /// (`LoadReg`, `AddReg`, ...).
     registers: Vec<Object>,
 
     /// Указатель инструкции, текущая позиция в байткоде.
@@ -33,6 +85,7 @@ pub struct VM {
     frames: Vec<CallFrame>,
 
     /// Индекс текущего фрейма вызова.
+    #[allow(dead_code)]
     current_frame_index: usize,
 
     /// Глобальные переменные.
@@ -40,6 +93,28 @@ pub struct VM {
 
     /// Флаг режима отладки.
     debug_mode: bool,
+
+    /// Отладочный хук, вызываемый перед инструкциями.
+    debug_hook: Option<DebugHook>,
+
+    /// Смещения инструкций, на которых вызывается хук.
+    breakpoints: HashSet<usize>,
+
+    /// Пошаговый режим: хук вызывается перед каждой инструкцией.
+    stepping: bool,
+
+    /// Выполнение было приостановлено хуком; при продолжении хук
+    /// для текущей инструкции не вызывается.
+    resuming: bool,
+
+    /// Последний снятый со стека элемент (результат последнего оператора-выражения).
+    last_popped: Object,
+
+    /// Оператор AST, который сейчас выполняется (последний `MapToAst`).
+    current_node: Option<NodeId>,
+
+    /// Счётчики профилирования; `None`, пока профилирование не включено.
+    profiler: Option<Profiler>,
 }
 
 /// Информация о фрейме вызова функции.
@@ -53,13 +128,25 @@ pub struct CallFrame {
 
     /// Количество локальных переменных.
     pub num_locals: usize,
+
+    /// Оператор вызывающего кода, восстанавливаемый при возврате.
+    pub caller_node: Option<NodeId>,
 }
 
 impl VM {
     /// Создает новый экземпляр виртуальной машины с заданными инструкциями.
+    ///
+    /// Константы превращаются в объекты здесь, в потоке, который будет
+    /// выполнять программу: сами инструкции от потока не зависят.
     pub fn new(instructions: Instructions) -> Self {
+        let constants = instructions
+            .constants
+            .iter()
+            .map(Constant::to_object)
+            .collect();
         VM {
             instructions,
+            constants,
             stack: vec![Object::Null; STACK_SIZE],
             sp: 0,
             registers: vec![Object::Null; NUM_REGISTERS],
@@ -68,9 +155,51 @@ impl VM {
             current_frame_index: 0,
             globals: Rc::new(RefCell::new(HashMap::new())),
             debug_mode: false,
+            debug_hook: None,
+            breakpoints: HashSet::new(),
+            stepping: false,
+            resuming: false,
+            last_popped: Object::Null,
+            current_node: None,
+            profiler: None,
         }
     }
 
+    /// Оператор AST, выполнявшийся последним; по нему ошибку можно
+    /// сопоставить с исходником через `Program::find_node`.
+    pub fn current_node(&self) -> Option<NodeId> {
+        self.current_node
+    }
+
+    /// Последнее снятое со стека значение. После программы из операторов-
+    /// выражений это значение последнего из них (каждый завершается `Pop`).
+    pub fn last_popped(&self) -> &Object {
+        &self.last_popped
+    }
+
+    /// Копия занятой части стека, от дна к вершине.
+    pub fn stack_snapshot(&self) -> Vec<Object> {
+        self.stack[..self.sp].to_vec()
+    }
+
+    /// Копия текущих глобальных переменных.
+    pub fn globals_snapshot(&self) -> HashMap<String, Object> {
+        self.globals.borrow().clone()
+    }
+
+    /// Таблица глобальных переменных. Её можно передать следующей VM через
+    /// `set_globals`, чтобы переменные пережили запуск следующего фрагмента
+    /// программы (см. `run_from`).
+    pub fn globals(&self) -> Rc<RefCell<HashMap<String, Object>>> {
+        Rc::clone(&self.globals)
+    }
+
+    /// Заменяет таблицу глобальных переменных, например, таблицей
+    /// предыдущей VM; изменения видны всем её владельцам.
+    pub fn set_globals(&mut self, globals: Rc<RefCell<HashMap<String, Object>>>) {
+        self.globals = globals;
+    }
+
     /// Включить режим отладки.
     pub fn enable_debug_mode(&mut self) {
         self.debug_mode = true;
@@ -81,10 +210,133 @@ impl VM {
         self.debug_mode = false;
     }
 
+    /// Устанавливает отладочный хук. Без точек останова хук вызывается перед
+    /// каждой инструкцией; с ними — только на точках останова и при пошаговом
+    /// выполнении (`DebugAction::Step`).
+    pub fn set_debug_hook(&mut self, hook: DebugHook) {
+        self.debug_hook = Some(hook);
+    }
+
+    /// Снимает отладочный хук.
+    pub fn clear_debug_hook(&mut self) {
+        self.debug_hook = None;
+    }
+
+    /// Добавляет точку останова на инструкции со смещением `ip`.
+    pub fn add_breakpoint(&mut self, ip: usize) {
+        self.breakpoints.insert(ip);
+    }
+
+    /// Удаляет точку останова; возвращает `false`, если её не было.
+    pub fn remove_breakpoint(&mut self, ip: usize) -> bool {
+        self.breakpoints.remove(&ip)
+    }
+
+    /// Включает профилирование: число выполнений и время каждого опкода,
+    /// число вызовов каждой скомпилированной функции. Накопленное не
+    /// сбрасывается между запусками `run`.
+    pub fn enable_profiling(&mut self) {
+        self.profiler.get_or_insert_with(Profiler::default);
+    }
+
+    /// Отчёт профилировщика; пустой, если профилирование не включено.
+    pub fn profile_report(&self) -> ProfileReport {
+        self.profiler
+            .as_ref()
+            .map(Profiler::report)
+            .unwrap_or_default()
+    }
+
+    /// Вызывает отладочный хук перед инструкцией `opcode`, если на текущем
+    /// смещении он должен сработать, и применяет его решение.
+    fn call_debug_hook(&mut self, opcode: Opcode) -> DebugAction {
+        if self.resuming {
+            self.resuming = false;
+            return DebugAction::Continue;
+        }
+        let ip = self.ip;
+        if !(self.stepping || self.breakpoints.is_empty() || self.breakpoints.contains(&ip)) {
+            return DebugAction::Continue;
+        }
+        let Some(hook) = self.debug_hook.as_mut() else {
+            return DebugAction::Continue;
+        };
+        let (operands, _) = Instructions::read_operands(opcode, &self.instructions.bytes, ip + 1);
+        let event = DebugEvent {
+            ip,
+            opcode,
+            operands,
+            sp: self.sp,
+            stack_top: &self.stack[self.sp.saturating_sub(DEBUG_STACK_DEPTH)..self.sp],
+            frame_depth: self.frames.len(),
+        };
+        let action = hook(&event);
+        self.stepping = action == DebugAction::Step;
+        self.resuming = action == DebugAction::Pause;
+        action
+    }
+
     /// Запускает выполнение байткода.
-    /// Возвращает результат исполнения (верхний элемент стека) или ошибку.
+    /// Возвращает результат исполнения или ошибку: верхний элемент стека,
+    /// а если стек пуст — значение последнего выражения-инструкции
+    /// (`1; 2; 3` даёт `3`, как и в интерпретаторе).
     pub fn run(&mut self) -> Result<Object, String> {
+        self.execute(None)
+    }
+
+    /// Как `run`, но начинает со смещения `offset`. Переиспользуемый
+    /// компилятор дописывает каждый фрагмент программы к прежнему байткоду,
+    /// а функции прежних фрагментов, сохранённые в глобальных переменных,
+    /// помнят абсолютные смещения своих тел. Поэтому следующей VM передают
+    /// весь байткод и запускают её с начала нового фрагмента.
+    pub fn run_from(&mut self, offset: usize) -> Result<Object, String> {
+        if offset > self.instructions.bytes.len() {
+            return Err(format!(
+                "Смещение {} за пределами байткода ({} байт)",
+                offset,
+                self.instructions.bytes.len()
+            ));
+        }
+        self.ip = offset;
+        self.execute(None)
+    }
+
+    /// Как `run`, но выполняет не более `max_steps` опкодов; при превышении
+    /// возвращает ошибку. Нужен для встраивания, где зацикливание недопустимо.
+    pub fn run_with_limit(&mut self, max_steps: usize) -> Result<Object, String> {
+        self.execute(Some(max_steps))
+    }
+
+    /// Выполняет байткод; к ошибке времени выполнения добавляет
+    /// ` at line N, column M` — позицию оператора, на котором она произошла,
+    /// если компилятор её записал. Приостановка хуком ошибкой не считается и
+    /// не дополняется.
+    fn execute(&mut self, max_steps: Option<usize>) -> Result<Object, String> {
+        let result = self.execute_instructions(max_steps);
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.finish();
+        }
+        let position = self
+            .current_node
+            .and_then(|node| self.instructions.positions.get(&node));
+        result.map_err(|e| match position {
+            Some((line, column)) if !self.resuming => {
+                format!("{} at line {}, column {}", e, line, column)
+            }
+            _ => e,
+        })
+    }
+
+    fn execute_instructions(&mut self, max_steps: Option<usize>) -> Result<Object, String> {
+        let mut steps: usize = 0;
         while self.ip < self.instructions.bytes.len() {
+            if let Some(max_steps) = max_steps {
+                if steps >= max_steps {
+                    return Err("execution step limit exceeded".to_string());
+                }
+                steps += 1;
+            }
+
             if self.debug_mode {
                 eprintln!("IP: {}, SP: {}", self.ip, self.sp);
             }
@@ -93,18 +345,28 @@ impl VM {
                 format!("Неизвестный опкод: {}", self.instructions.bytes[self.ip])
             })?;
 
+            if self.debug_hook.is_some() {
+                if let DebugAction::Pause = self.call_debug_hook(opcode) {
+                    return Err(format!("Выполнение приостановлено на ip {}", self.ip));
+                }
+            }
+
             if self.debug_mode {
                 eprintln!("Executing: {}", opcode.mnemonic());
             }
 
+            if let Some(profiler) = self.profiler.as_mut() {
+                profiler.start(opcode);
+            }
+
             self.ip += 1;
 
             match opcode {
                 Opcode::Constant => {
                     let const_index = self.read_u16() as usize;
                     let constant = self
-                        .instructions
-                        .get_constant(const_index)
+                        .constants
+                        .get(const_index)
                         .ok_or_else(|| format!("Константа с индексом {} не найдена", const_index))?
                         .clone();
                     self.push(constant)?;
@@ -129,42 +391,71 @@ impl VM {
                 Opcode::Add => {
                     let b = self.pop()?;
                     let a = self.pop()?;
-                    let result = self.apply_operation(&a, &b, "+")?;
+                    let result = arithmetic("+", &a, &b)?;
                     self.push(result)?;
                 }
 
                 Opcode::Sub => {
                     let b = self.pop()?;
                     let a = self.pop()?;
-                    let result = self.apply_operation(&a, &b, "-")?;
+                    let result = arithmetic("-", &a, &b)?;
                     self.push(result)?;
                 }
 
                 Opcode::Mul => {
                     let b = self.pop()?;
                     let a = self.pop()?;
-                    let result = self.apply_operation(&a, &b, "*")?;
+                    let result = arithmetic("*", &a, &b)?;
                     self.push(result)?;
                 }
 
                 Opcode::Div => {
                     let b = self.pop()?;
                     let a = self.pop()?;
-                    let result = self.apply_operation(&a, &b, "/")?;
+                    let result = arithmetic("/", &a, &b)?;
                     self.push(result)?;
                 }
 
                 Opcode::Mod => {
                     let b = self.pop()?;
                     let a = self.pop()?;
-                    let result = self.apply_operation(&a, &b, "%")?;
+                    let result = arithmetic("%", &a, &b)?;
                     self.push(result)?;
                 }
 
+                Opcode::LoadReg => {
+                    let register = self.read_register()?;
+                    self.registers[register] = self.pop()?;
+                }
+
+                Opcode::PushReg => {
+                    let register = self.read_register()?;
+                    self.push(self.registers[register].clone())?;
+                }
+
+                Opcode::AddReg
+                | Opcode::SubReg
+                | Opcode::MulReg
+                | Opcode::DivReg
+                | Opcode::ModReg => {
+                    let target = self.read_register()?;
+                    let left = self.read_register()?;
+                    let right = self.read_register()?;
+                    let op = match opcode {
+                        Opcode::AddReg => "+",
+                        Opcode::SubReg => "-",
+                        Opcode::MulReg => "*",
+                        Opcode::DivReg => "/",
+                        _ => "%",
+                    };
+                    self.registers[target] =
+                        arithmetic(op, &self.registers[left], &self.registers[right])?;
+                }
+
                 Opcode::Pow => {
                     let exp = self.pop()?;
                     let base = self.pop()?;
-                    let result = self.apply_operation(&base, &exp, "**")?;
+                    let result = arithmetic("**", &base, &exp)?;
                     self.push(result)?;
                 }
 
@@ -191,6 +482,36 @@ impl VM {
                     self.push(result)?;
                 }
 
+                Opcode::Typeof => {
+                    let a = self.pop()?;
+                    self.push(Object::String(a.type_str().to_string()))?;
+                }
+
+                Opcode::Is => {
+                    let type_object = self.pop()?;
+                    let value = self.pop()?;
+                    let matches = value.is_instance_of(&type_object).ok_or_else(|| {
+                        format!(
+                            "right operand of 'is' must be a type, got {}",
+                            type_object.type_str()
+                        )
+                    })?;
+                    self.push(Object::Boolean(matches))?;
+                }
+
+                Opcode::IsType => {
+                    let name_idx = self.read_u16() as usize;
+                    let type_name = match self.constants.get(name_idx) {
+                        Some(Object::String(type_name)) => type_name.clone(),
+                        _ => return Err(format!("Ожидалось имя типа, получено {}", name_idx)),
+                    };
+                    let value = self.pop()?;
+                    let matches = value
+                        .is_primitive_type(&type_name)
+                        .ok_or_else(|| format!("Неизвестный встроенный тип {}", type_name))?;
+                    self.push(Object::Boolean(matches))?;
+                }
+
                 Opcode::And => {
                     let b = self.pop()?;
                     let a = self.pop()?;
@@ -208,64 +529,109 @@ impl VM {
                 Opcode::Equal => {
                     let b = self.pop()?;
                     let a = self.pop()?;
-                    self.push(Object::Boolean(a == b))?;
+                    let result = values_equal("==", &a, &b)?;
+                    self.push(Object::Boolean(result))?;
                 }
 
                 Opcode::NotEqual => {
                     let b = self.pop()?;
                     let a = self.pop()?;
-                    self.push(Object::Boolean(a != b))?;
+                    let result = values_equal("!=", &a, &b)?;
+                    self.push(Object::Boolean(result))?;
+                }
+
+                Opcode::MatchLiteral => {
+                    let pattern = self.pop()?;
+                    let value = self.pop()?;
+                    self.push(Object::Boolean(value == pattern))?;
+                }
+
+                Opcode::MatchRange => {
+                    let inclusive = self.read_u8() != 0;
+                    let end = self.pop()?;
+                    let start = self.pop()?;
+                    let value = self.pop()?;
+                    let (start, end) = range_pattern_bounds(&start, &end, inclusive)?;
+                    let matches = in_range_pattern(&value, start, end, inclusive);
+                    self.push(Object::Boolean(matches))?;
+                }
+
+                Opcode::MatchFail => {
+                    let value = self.pop()?;
+                    return Err(non_exhaustive_match(&value));
                 }
 
                 Opcode::GreaterThan => {
                     let b = self.pop()?;
                     let a = self.pop()?;
-                    let result = self.compare_objects(&a, &b)?;
-                    self.push(Object::Boolean(result > 0))?;
+                    self.push(Object::Boolean(compare_values(">", &a, &b)?))?;
                 }
 
                 Opcode::LessThan => {
                     let b = self.pop()?;
                     let a = self.pop()?;
-                    let result = self.compare_objects(&a, &b)?;
-                    self.push(Object::Boolean(result < 0))?;
+                    self.push(Object::Boolean(compare_values("<", &a, &b)?))?;
                 }
 
                 Opcode::GreaterThanOrEqual => {
                     let b = self.pop()?;
                     let a = self.pop()?;
-                    let result = self.compare_objects(&a, &b)?;
-                    self.push(Object::Boolean(result >= 0))?;
+                    self.push(Object::Boolean(compare_values(">=", &a, &b)?))?;
                 }
 
                 Opcode::LessThanOrEqual => {
                     let b = self.pop()?;
                     let a = self.pop()?;
-                    let result = self.compare_objects(&a, &b)?;
-                    self.push(Object::Boolean(result <= 0))?;
+                    self.push(Object::Boolean(compare_values("<=", &a, &b)?))?;
                 }
 
                 Opcode::Jump => {
-                    let pos = self.read_u16() as usize;
-                    self.ip = pos;
+                    let offset = self.read_u16() as i16;
+                    self.jump_relative(offset as isize)?;
+                }
+
+                Opcode::StackDepth => {
+                    self.push(Object::Integer(self.sp as i64))?;
+                }
+
+                Opcode::Unwind => {
+                    let depth = match self.pop()? {
+                        Object::Integer(depth) if depth >= 0 && depth as usize <= self.sp => {
+                            depth as usize
+                        }
+                        other => {
+                            return Err(format!(
+                                "Unwind ожидает глубину стека не больше {}, получено {}",
+                                self.sp, other
+                            ))
+                        }
+                    };
+                    self.sp = depth;
                 }
 
                 Opcode::JumpIfFalse => {
-                    let pos = self.read_u16() as usize;
+                    let offset = self.read_u16() as i16;
                     let condition = self.pop()?;
                     if !self.is_truthy(&condition) {
-                        self.ip = pos;
+                        self.jump_relative(offset as isize)?;
                     }
                 }
 
                 Opcode::JumpIfTrue => {
-                    let pos = self.read_u16() as usize;
+                    let offset = self.read_u16() as i16;
                     let condition = self.pop()?;
                     if self.is_truthy(&condition) {
-                        self.ip = pos;
+                        self.jump_relative(offset as isize)?;
                     }
                 }
 
+                Opcode::JumpTable => {
+                    let table_idx = self.read_u16() as usize;
+                    let value = self.pop()?;
+                    let offset = self.jump_table_offset(table_idx, &value)?;
+                    self.jump_relative(offset)?;
+                }
+
                 Opcode::Return => {
                     if self.frames.is_empty() {
                         return Ok(Object::Null);
@@ -273,6 +639,7 @@ impl VM {
                     if let Some(frame) = self.frames.pop() {
                         self.sp = frame.base_pointer - 1;
                         self.ip = frame.return_addr;
+                        self.current_node = frame.caller_node;
                         self.push(Object::Null)?;
                     }
                 }
@@ -285,6 +652,7 @@ impl VM {
                     if let Some(frame) = self.frames.pop() {
                         self.sp = frame.base_pointer - 1;
                         self.ip = frame.return_addr;
+                        self.current_node = frame.caller_node;
                         self.push(result)?;
                     }
                 }
@@ -292,8 +660,8 @@ impl VM {
                 Opcode::GetGlobal => {
                     let name_idx = self.read_u16() as usize;
                     let name = self
-                        .instructions
-                        .get_constant(name_idx)
+                        .constants
+                        .get(name_idx)
                         .ok_or_else(|| format!("Константа {} не найдена", name_idx))?;
                     if let Object::String(var_name) = name {
                         // Используем блок scope для освобождения borrow перед push()
@@ -311,8 +679,8 @@ impl VM {
                     let name_idx = self.read_u16() as usize;
                     // Клонируем name перед вызовом pop() чтобы избежать borrow конфликта
                     let name = {
-                        self.instructions
-                            .get_constant(name_idx)
+                        self.constants
+                            .get(name_idx)
                             .ok_or_else(|| format!("Константа {} не найдена", name_idx))?
                             .clone()
                     };
@@ -349,41 +717,33 @@ impl VM {
                         elements.push(self.pop()?);
                     }
                     elements.reverse();
-                    self.push(Object::Array(elements))?;
+                    self.push(Object::Array(Rc::new(elements)))?;
                 }
 
                 Opcode::Hash => {
                     let num_pairs = self.read_u16() as usize;
-                    let mut hash = HashMap::new();
+                    let mut pairs = Vec::with_capacity(num_pairs);
                     for _ in 0..num_pairs {
                         let value = self.pop()?;
-                        let key = self.pop()?;
-                        if let Object::String(k) = key {
-                            hash.insert(k, value);
-                        } else {
-                            return Err(format!(
-                                "Ключ хэша должен быть строкой, получено {}",
-                                key.type_str()
-                            ));
-                        }
+                        let key = HashKey::from_object(&self.pop()?)?;
+                        pairs.push((key, value));
                     }
-                    // TODO: Реализовать правильный объект Hash с Object::Hash
-                    self.push(Object::Null)?; // Временное решение
+                    // Пары сняты со стека в обратном порядке; при повторе ключа побеждает последняя
+                    let hash: OrderedMap<Object, HashKey> = pairs.into_iter().rev().collect();
+                    self.push(Object::Hash(Rc::new(hash)))?;
                 }
 
                 Opcode::Index => {
                     let index = self.pop()?;
-                    let array = self.pop()?;
-                    match (array, index) {
-                        (Object::Array(arr), Object::Integer(idx)) => {
-                            if idx < 0 || idx as usize >= arr.len() {
-                                self.push(Object::Null)?;
-                            } else {
-                                self.push(arr[idx as usize].clone())?;
-                            }
-                        }
-                        _ => return Err("Неподдерживаемая операция индексирования".to_string()),
-                    }
+                    let left = self.pop()?;
+                    self.push(index_value(&left, &index)?)?;
+                }
+
+                Opcode::SetIndex => {
+                    let value = self.pop()?;
+                    let index = self.pop()?;
+                    let container = self.pop()?;
+                    self.push(set_index(container, &index, value)?)?;
                 }
 
                 Opcode::Call => {
@@ -393,55 +753,175 @@ impl VM {
 
                     match func_obj {
                         Object::CompiledFunction(cf) => {
-                            if num_args != cf.num_params {
-                                return Err(format!(
-                                    "wrong number of arguments: expected {}, got {}",
-                                    cf.num_params, num_args
-                                ));
+                            self.call_compiled(&cf, fn_idx, num_args)?
+                        }
+                        Object::Closure(closure) => {
+                            self.call_compiled(&closure.function, fn_idx, num_args)?
+                        }
+                        Object::BuiltinFunction {
+                            name,
+                            num_params,
+                            handler,
+                        } => {
+                            let args = self.stack[fn_idx + 1..self.sp].to_vec();
+                            let result = builtins::call(&name, num_params, handler, args);
+                            self.sp = fn_idx;
+                            match result {
+                                Object::Error(error) => return Err(error.to_string()),
+                                value => self.push(value)?,
                             }
+                        }
+                        _ => return Err(format!("not a function: {}", func_obj.type_str())),
+                    }
+                }
 
-                            self.frames.push(CallFrame {
-                                return_addr: self.ip,
-                                base_pointer: fn_idx + 1,
-                                num_locals: cf.num_locals,
-                            });
+                Opcode::GetBuiltin => {
+                    let index = self.read_u8() as usize;
+                    let builtin = builtins::get(index)
+                        .ok_or_else(|| format!("Неизвестная встроенная функция {}", index))?;
+                    self.push(builtin)?;
+                }
 
-                            for _ in num_args..cf.num_locals {
-                                self.push(Object::Null)?;
-                            }
+                Opcode::Struct => {
+                    let layout_idx = self.read_u16() as usize;
+                    let layout = match self.constants.get(layout_idx) {
+                        Some(Object::Array(layout)) if !layout.is_empty() => Rc::clone(layout),
+                        _ => return Err(format!("Некорректное описание структуры {}", layout_idx)),
+                    };
+                    let mut defaults = Vec::with_capacity(layout.len() - 1);
+                    for _ in 1..layout.len() {
+                        defaults.push(self.pop()?);
+                    }
+                    defaults.reverse();
 
-                            self.ip = cf.instructions_offset;
-                        }
-                        _ => return Err(format!("not a function: {}", func_obj.type_str())),
+                    let mut properties = OrderedMap::new();
+                    for (field, value) in layout[1..].iter().zip(defaults) {
+                        properties.insert(field.to_string(), value);
                     }
+                    self.push(Object::Struct(Rc::new(RefCell::new(Struct {
+                        name: layout[0].to_string(),
+                        properties,
+                    }))))?;
+                }
+
+                Opcode::New => {
+                    let num_args = self.read_u8() as usize;
+                    let mut args = Vec::with_capacity(num_args);
+                    for _ in 0..num_args {
+                        args.push(self.pop()?);
+                    }
+                    args.reverse();
+                    let struct_def = match self.pop()? {
+                        Object::Struct(struct_def) => struct_def,
+                        Object::Class(_) => {
+                            return Err("Классы в VM пока не поддерживаются".to_string())
+                        }
+                        other => {
+                            return Err(format!("not a class or struct: {}", other.type_str()))
+                        }
+                    };
+                    let (name, defaults) = {
+                        let def = struct_def.borrow();
+                        (def.name.clone(), def.properties.clone())
+                    };
+                    let fields = instance_fields(&name, defaults, args)?;
+                    self.push(Object::StructInstance(Rc::new(RefCell::new(
+                        StructInstance { struct_def, fields },
+                    ))))?;
+                }
+
+                Opcode::GetProperty => {
+                    let name = self.read_property_name()?;
+                    let value = match self.pop()? {
+                        Object::StructInstance(instance) => {
+                            let instance = instance.borrow();
+                            instance.fields.get(&name).cloned().ok_or_else(|| {
+                                format!(
+                                    "property '{}' not found on struct '{}'",
+                                    name,
+                                    instance.struct_def.borrow().name
+                                )
+                            })?
+                        }
+                        other => {
+                            return Err(format!(
+                                "property access not supported for type '{}'",
+                                other.type_str()
+                            ))
+                        }
+                    };
+                    self.push(value)?;
+                }
+
+                Opcode::SetProperty => {
+                    let name = self.read_property_name()?;
+                    let value = self.pop()?;
+                    // Экземпляр структуры не меняется на месте: на стек
+                    // кладётся копия с новым полем, её записывает обратно
+                    // компилятор (см. `StructInstance`)
+                    let updated = match self.pop()? {
+                        Object::StructInstance(instance) => {
+                            let instance = instance.borrow();
+                            let Some(updated) = instance.with_field(&name, value) else {
+                                return Err(format!(
+                                    "property '{}' not found on struct '{}'",
+                                    name,
+                                    instance.struct_def.borrow().name
+                                ));
+                            };
+                            updated
+                        }
+                        other => {
+                            return Err(format!(
+                                "property access not supported for type '{}'",
+                                other.type_str()
+                            ))
+                        }
+                    };
+                    self.push(Object::StructInstance(Rc::new(RefCell::new(updated))))?;
                 }
 
-                Opcode::New
-                | Opcode::Class
-                | Opcode::GetProperty
-                | Opcode::SetProperty
+                Opcode::Class
                 | Opcode::This
                 | Opcode::Super
-                | Opcode::MapToAst
                 | Opcode::GetFree
                 | Opcode::SetFree
-                | Opcode::GetCurrentClosure
-                | Opcode::Closure
-                | Opcode::GetBuiltin => {
+                | Opcode::GetCurrentClosure => {
                     return Err(format!("Опкод {} пока не реализован", opcode.mnemonic()));
                 }
 
+                Opcode::Closure => {
+                    let const_index = self.read_u16() as usize;
+                    let num_free = self.read_u8() as usize;
+                    let Some(Object::CompiledFunction(function)) = self.constants.get(const_index)
+                    else {
+                        return Err(format!("Константа {} не является функцией", const_index));
+                    };
+                    let function = function.clone();
+                    if num_free > self.sp {
+                        return Err("Underflow стека".to_string());
+                    }
+                    let free = self.stack[self.sp - num_free..self.sp].to_vec();
+                    self.sp -= num_free;
+                    self.push(Object::Closure(Rc::new(Closure { function, free })))?;
+                }
+
+                Opcode::MapToAst => {
+                    self.current_node = Some(NodeId(u32::from(self.read_u16())));
+                }
+
                 Opcode::NoOp => {
                     // Ничего не делаем
                 }
             }
         }
 
-        // Возвращаем верхний элемент стека как результат
+        // Возвращаем верхний элемент стека как результат, а если стек пуст —
+        // значение последнего оператора-выражения (снятое через Pop)
         if self.sp > 0 {
             Ok(self.stack[self.sp - 1].clone())
         } else {
-            Ok(Object::Null)
+            Ok(self.last_popped.clone())
         }
     }
 
@@ -456,12 +936,46 @@ impl VM {
     }
 
     /// Взять значение со стека.
+    /// Входит в скомпилированную функцию: кадр начинается сразу за ней на
+    /// стеке, аргументы становятся первыми локальными переменными.
+    fn call_compiled(
+        &mut self,
+        cf: &CompiledFunction,
+        fn_idx: usize,
+        num_args: usize,
+    ) -> Result<(), String> {
+        if num_args != cf.num_params {
+            return Err(format!(
+                "wrong number of arguments: expected {}, got {}",
+                cf.num_params, num_args
+            ));
+        }
+        if let Some(profiler) = self.profiler.as_mut() {
+            profiler.record_call(cf);
+        }
+
+        self.frames.push(CallFrame {
+            return_addr: self.ip,
+            base_pointer: fn_idx + 1,
+            num_locals: cf.num_locals,
+            caller_node: self.current_node,
+        });
+
+        for _ in num_args..cf.num_locals {
+            self.push(Object::Null)?;
+        }
+
+        self.ip = cf.instructions_offset;
+        Ok(())
+    }
+
     fn pop(&mut self) -> Result<Object, String> {
         if self.sp == 0 {
             return Err("Underflow стека".to_string());
         }
         self.sp -= 1;
-        Ok(self.stack[self.sp].clone())
+        self.last_popped = self.stack[self.sp].clone();
+        Ok(self.last_popped.clone())
     }
 
     /// Прочитать двухбайтовый операнд и увеличить IP.
@@ -472,6 +986,58 @@ impl VM {
         (high << 8) | low
     }
 
+    /// Сместить IP на знаковое смещение относительно конца текущей инструкции.
+    fn jump_relative(&mut self, offset: isize) -> Result<(), String> {
+        let target = self.ip as isize + offset;
+        if target < 0 || target as usize > self.instructions.bytes.len() {
+            return Err(format!("Недопустимый адрес перехода: {}", target));
+        }
+        self.ip = target as usize;
+        Ok(())
+    }
+
+    /// Найти смещение перехода для значения в таблице `JumpTable`.
+    /// Значения вне таблицы и нецелые значения ведут на смещение по умолчанию.
+    fn jump_table_offset(&self, table_idx: usize, value: &Object) -> Result<isize, String> {
+        let table = match self.constants.get(table_idx) {
+            Some(Object::Array(table)) if table.len() >= 2 => table,
+            _ => return Err(format!("Некорректная таблица переходов {}", table_idx)),
+        };
+        let (base, default) = match (&table[0], &table[1]) {
+            (Object::Integer(base), Object::Integer(default)) => (*base, *default),
+            _ => return Err(format!("Некорректная таблица переходов {}", table_idx)),
+        };
+
+        let offset = match value {
+            Object::Integer(n) if *n >= base && *n - base < (table.len() - 2) as i64 => {
+                match &table[(*n - base) as usize + 2] {
+                    Object::Integer(offset) => *offset,
+                    _ => return Err(format!("Некорректная таблица переходов {}", table_idx)),
+                }
+            }
+            _ => default,
+        };
+        Ok(offset as isize)
+    }
+
+    /// Прочитать операнд `GetProperty`/`SetProperty` — имя свойства из пула констант.
+    fn read_property_name(&mut self) -> Result<String, String> {
+        let name_idx = self.read_u16() as usize;
+        match self.constants.get(name_idx) {
+            Some(Object::String(name)) => Ok(name.clone()),
+            _ => Err(format!("Некорректное имя свойства {}", name_idx)),
+        }
+    }
+
+    /// Прочитать однобайтовый операнд — номер регистра.
+    fn read_register(&mut self) -> Result<usize, String> {
+        let register = self.read_u8() as usize;
+        if register >= NUM_REGISTERS {
+            return Err(format!("Некорректный номер регистра {}", register));
+        }
+        Ok(register)
+    }
+
     /// Прочитать однобайтовый операнд и увеличить IP.
     fn read_u8(&mut self) -> u8 {
         let byte = self.instructions.bytes[self.ip];
@@ -487,74 +1053,6 @@ impl VM {
             _ => true,
         }
     }
-
-    /// Сравнить два объекта. Возвращает: < 0 если a < b, 0 если a == b, > 0 если a > b.
-    fn compare_objects(&self, a: &Object, b: &Object) -> Result<i32, String> {
-        match (a, b) {
-            (Object::Integer(x), Object::Integer(y)) => Ok(if x < y {
-                -1
-            } else if x > y {
-                1
-            } else {
-                0
-            }),
-            (Object::String(x), Object::String(y)) => Ok(if x < y {
-                -1
-            } else if x > y {
-                1
-            } else {
-                0
-            }),
-            _ => Err(format!(
-                "Невозможно сравнить {} и {}",
-                a.type_str(),
-                b.type_str()
-            )),
-        }
-    }
-
-    /// Применить бинарную операцию к двум объектам.
-    fn apply_operation(&self, a: &Object, b: &Object, op: &str) -> Result<Object, String> {
-        match (a, b) {
-            (Object::Integer(x), Object::Integer(y)) => match op {
-                "+" => Ok(Object::Integer(x + y)),
-                "-" => Ok(Object::Integer(x - y)),
-                "*" => Ok(Object::Integer(x * y)),
-                "/" => {
-                    if *y == 0 {
-                        Err("Деление на ноль".to_string())
-                    } else {
-                        Ok(Object::Integer(x / y))
-                    }
-                }
-                "%" => {
-                    if *y == 0 {
-                        Err("Деление на ноль в операции модуля".to_string())
-                    } else {
-                        Ok(Object::Integer(x % y))
-                    }
-                }
-                "**" => {
-                    if *y < 0 {
-                        Err("Отрицательные степени не поддерживаются для целых чисел".to_string())
-                    } else {
-                        Ok(Object::Integer(x.pow(*y as u32)))
-                    }
-                }
-                _ => Err(format!("Неизвестная операция: {}", op)),
-            },
-            (Object::String(x), Object::String(y)) => match op {
-                "+" => Ok(Object::String(format!("{}{}", x, y))),
-                _ => Err(format!("Неподдерживаемая операция для строк: {}", op)),
-            },
-            _ => Err(format!(
-                "Операция {} не поддерживается для {} и {}",
-                op,
-                a.type_str(),
-                b.type_str()
-            )),
-        }
-    }
 }
 
 #[cfg(test)]
@@ -567,7 +1065,7 @@ mod tests {
     fn test_vm_constant() {
         // Тестируем: Constant(10)
         let mut instr = Instructions::new();
-        instr.constants.push(Object::Integer(10));
+        instr.constants.push(Constant::Integer(10));
         instr.bytes = vec![Opcode::Constant as u8, 0, 0]; // Opcode + 2-byte operand 0
 
         let mut vm = VM::new(instr);
@@ -580,8 +1078,8 @@ mod tests {
     fn test_vm_add() {
         // Тестируем: Constant(5), Constant(10), Add
         let mut instr = Instructions::new();
-        instr.constants.push(Object::Integer(5));
-        instr.constants.push(Object::Integer(10));
+        instr.constants.push(Constant::Integer(5));
+        instr.constants.push(Constant::Integer(10));
         instr.bytes = vec![
             Opcode::Constant as u8,
             0,
@@ -602,8 +1100,8 @@ mod tests {
     fn test_vm_sub() {
         // Тестируем: Constant(20), Constant(7), Sub → 20 - 7 = 13
         let mut instr = Instructions::new();
-        instr.constants.push(Object::Integer(20));
-        instr.constants.push(Object::Integer(7));
+        instr.constants.push(Constant::Integer(20));
+        instr.constants.push(Constant::Integer(7));
         instr.bytes = vec![
             Opcode::Constant as u8,
             0,
@@ -624,8 +1122,8 @@ mod tests {
     fn test_vm_mul() {
         // Тестируем: Constant(4), Constant(5), Mul → 4 * 5 = 20
         let mut instr = Instructions::new();
-        instr.constants.push(Object::Integer(4));
-        instr.constants.push(Object::Integer(5));
+        instr.constants.push(Constant::Integer(4));
+        instr.constants.push(Constant::Integer(5));
         instr.bytes = vec![
             Opcode::Constant as u8,
             0,
@@ -646,8 +1144,8 @@ mod tests {
     fn test_vm_div() {
         // Тестируем: Constant(20), Constant(4), Div → 20 / 4 = 5
         let mut instr = Instructions::new();
-        instr.constants.push(Object::Integer(20));
-        instr.constants.push(Object::Integer(4));
+        instr.constants.push(Constant::Integer(20));
+        instr.constants.push(Constant::Integer(4));
         instr.bytes = vec![
             Opcode::Constant as u8,
             0,
@@ -668,8 +1166,8 @@ mod tests {
     fn test_vm_div_by_zero() {
         // Тестируем ошибку: деление на ноль
         let mut instr = Instructions::new();
-        instr.constants.push(Object::Integer(10));
-        instr.constants.push(Object::Integer(0));
+        instr.constants.push(Constant::Integer(10));
+        instr.constants.push(Constant::Integer(0));
         instr.bytes = vec![
             Opcode::Constant as u8,
             0,
@@ -683,15 +1181,15 @@ mod tests {
         let mut vm = VM::new(instr);
         let result = vm.run();
         assert!(result.is_err());
-        assert!(result.unwrap_err().contains("ноль"));
+        assert!(result.unwrap_err().contains("division by zero"));
     }
 
     #[test]
     fn test_vm_mod() {
         // Тестируем: Constant(17), Constant(5), Mod → 17 % 5 = 2
         let mut instr = Instructions::new();
-        instr.constants.push(Object::Integer(17));
-        instr.constants.push(Object::Integer(5));
+        instr.constants.push(Constant::Integer(17));
+        instr.constants.push(Constant::Integer(5));
         instr.bytes = vec![
             Opcode::Constant as u8,
             0,
@@ -712,8 +1210,8 @@ mod tests {
     fn test_vm_pow() {
         // Тестируем: Constant(2), Constant(8), Pow → 2 ^ 8 = 256
         let mut instr = Instructions::new();
-        instr.constants.push(Object::Integer(2));
-        instr.constants.push(Object::Integer(8));
+        instr.constants.push(Constant::Integer(2));
+        instr.constants.push(Constant::Integer(8));
         instr.bytes = vec![
             Opcode::Constant as u8,
             0,
@@ -734,7 +1232,7 @@ mod tests {
     fn test_vm_neg() {
         // Тестируем: Constant(42), Neg → -42
         let mut instr = Instructions::new();
-        instr.constants.push(Object::Integer(42));
+        instr.constants.push(Constant::Integer(42));
         instr.bytes = vec![
             Opcode::Constant as u8,
             0,
@@ -846,8 +1344,8 @@ mod tests {
     fn test_vm_equal_integers() {
         // Тестируем: Constant(5), Constant(5), Equal → True
         let mut instr = Instructions::new();
-        instr.constants.push(Object::Integer(5));
-        instr.constants.push(Object::Integer(5));
+        instr.constants.push(Constant::Integer(5));
+        instr.constants.push(Constant::Integer(5));
         instr.bytes = vec![
             Opcode::Constant as u8,
             0,
@@ -868,8 +1366,8 @@ mod tests {
     fn test_vm_not_equal_integers() {
         // Тестируем: Constant(5), Constant(7), NotEqual → True
         let mut instr = Instructions::new();
-        instr.constants.push(Object::Integer(5));
-        instr.constants.push(Object::Integer(7));
+        instr.constants.push(Constant::Integer(5));
+        instr.constants.push(Constant::Integer(7));
         instr.bytes = vec![
             Opcode::Constant as u8,
             0,
@@ -890,8 +1388,8 @@ mod tests {
     fn test_vm_greater_than() {
         // Тестируем: Constant(10), Constant(5), GreaterThan → True (10 > 5)
         let mut instr = Instructions::new();
-        instr.constants.push(Object::Integer(10));
-        instr.constants.push(Object::Integer(5));
+        instr.constants.push(Constant::Integer(10));
+        instr.constants.push(Constant::Integer(5));
         instr.bytes = vec![
             Opcode::Constant as u8,
             0,
@@ -912,8 +1410,8 @@ mod tests {
     fn test_vm_less_than() {
         // Тестируем: Constant(5), Constant(10), LessThan → True (5 < 10)
         let mut instr = Instructions::new();
-        instr.constants.push(Object::Integer(5));
-        instr.constants.push(Object::Integer(10));
+        instr.constants.push(Constant::Integer(5));
+        instr.constants.push(Constant::Integer(10));
         instr.bytes = vec![
             Opcode::Constant as u8,
             0,
@@ -934,8 +1432,8 @@ mod tests {
     fn test_vm_greater_than_or_equal() {
         // Тестируем: Constant(10), Constant(10), GreaterThanOrEqual → True
         let mut instr = Instructions::new();
-        instr.constants.push(Object::Integer(10));
-        instr.constants.push(Object::Integer(10));
+        instr.constants.push(Constant::Integer(10));
+        instr.constants.push(Constant::Integer(10));
         instr.bytes = vec![
             Opcode::Constant as u8,
             0,
@@ -956,8 +1454,8 @@ mod tests {
     fn test_vm_less_than_or_equal() {
         // Тестируем: Constant(5), Constant(10), LessThanOrEqual → True
         let mut instr = Instructions::new();
-        instr.constants.push(Object::Integer(5));
-        instr.constants.push(Object::Integer(10));
+        instr.constants.push(Constant::Integer(5));
+        instr.constants.push(Constant::Integer(10));
         instr.bytes = vec![
             Opcode::Constant as u8,
             0,
@@ -976,9 +1474,9 @@ mod tests {
 
     #[test]
     fn test_vm_pop() {
-        // Тестируем: Constant(10), Pop → Null (стек пуст)
+        // Тестируем: Constant(10), Pop → 10 (стек пуст, результат — снятое значение)
         let mut instr = Instructions::new();
-        instr.constants.push(Object::Integer(10));
+        instr.constants.push(Constant::Integer(10));
         instr.bytes = vec![
             Opcode::Constant as u8,
             0,
@@ -989,16 +1487,18 @@ mod tests {
         let mut vm = VM::new(instr);
         let result = vm.run();
         assert!(result.is_ok());
-        assert_eq!(result.unwrap(), Object::Null);
+        assert_eq!(result.unwrap(), Object::Integer(10));
+        assert_eq!(vm.last_popped(), &Object::Integer(10));
+        assert!(vm.stack_snapshot().is_empty());
     }
 
     #[test]
     fn test_vm_multiple_operations() {
         // Тестируем: Constant(5), Constant(10), Add, Constant(3), Mul → (5 + 10) * 3 = 45
         let mut instr = Instructions::new();
-        instr.constants.push(Object::Integer(5));
-        instr.constants.push(Object::Integer(10));
-        instr.constants.push(Object::Integer(3));
+        instr.constants.push(Constant::Integer(5));
+        instr.constants.push(Constant::Integer(10));
+        instr.constants.push(Constant::Integer(3));
         instr.bytes = vec![
             Opcode::Constant as u8,
             0,
@@ -1059,7 +1559,7 @@ mod tests {
     fn test_vm_stack_overflow() {
         // Пытаемся переполнить стек
         let mut instr = Instructions::new();
-        instr.constants.push(Object::Integer(1));
+        instr.constants.push(Constant::Integer(1));
         let mut bytes = vec![];
         // Добавляем больше операций, чем вмещает стек
         for _ in 0..(STACK_SIZE + 10) {
@@ -1091,8 +1591,8 @@ mod tests {
     fn test_vm_string_concatenation() {
         // Тестируем: Constant("Hello"), Constant(" World"), Add → "Hello World"
         let mut instr = Instructions::new();
-        instr.constants.push(Object::String("Hello".to_string()));
-        instr.constants.push(Object::String(" World".to_string()));
+        instr.constants.push(Constant::String("Hello".to_string()));
+        instr.constants.push(Constant::String(" World".to_string()));
         instr.bytes = vec![
             Opcode::Constant as u8,
             0,
@@ -1111,20 +1611,20 @@ mod tests {
 
     #[test]
     fn test_vm_jump() {
-        // Тестируем: Jump(3) - пропускаем одну инструкцию
-        // Constant(5), Jump(4), Constant(10), Constant(20)
+        // Тестируем: Jump(+3) - пропускаем одну инструкцию
+        // Constant(5), Jump(+3), Constant(10), Constant(20)
         // Результат должен быть 20 (пропускаем Constant(10))
         let mut instr = Instructions::new();
-        instr.constants.push(Object::Integer(5));
-        instr.constants.push(Object::Integer(10));
-        instr.constants.push(Object::Integer(20));
+        instr.constants.push(Constant::Integer(5));
+        instr.constants.push(Constant::Integer(10));
+        instr.constants.push(Constant::Integer(20));
         instr.bytes = vec![
             Opcode::Constant as u8,
             0,
             0, // Constant(5)
             Opcode::Jump as u8,
             0,
-            6, // Jump to 6 (пропускаем следующие 3 байта)
+            3, // Jump +3 (пропускаем следующие 3 байта)
             Opcode::Constant as u8,
             0,
             1, // Constant(10) - пропускается
@@ -1143,8 +1643,8 @@ mod tests {
     fn test_vm_get_global() {
         // Тестируем: SetGlobal("x", 10), GetGlobal("x") → 10
         let mut instr = Instructions::new();
-        instr.constants.push(Object::Integer(10));
-        instr.constants.push(Object::String("x".to_string()));
+        instr.constants.push(Constant::Integer(10));
+        instr.constants.push(Constant::String("x".to_string()));
         instr.bytes = vec![
             Opcode::Constant as u8,
             0,
@@ -1162,4 +1662,357 @@ mod tests {
         assert!(result.is_ok());
         assert_eq!(result.unwrap(), Object::Integer(10));
     }
+
+    #[test]
+    fn test_vm_run_with_limit() {
+        // Jump(-3) прыгает сам на себя — бесконечный цикл
+        let mut instr = Instructions::new();
+        instr.bytes = vec![Opcode::Jump as u8, 0xFF, 0xFD];
+
+        let mut vm = VM::new(instr);
+        assert_eq!(
+            vm.run_with_limit(1000),
+            Err("execution step limit exceeded".to_string())
+        );
+    }
+
+    #[test]
+    fn test_vm_run_with_limit_allows_short_programs() {
+        // Constant(5), Constant(10), Add — ровно три шага
+        let mut instr = Instructions::new();
+        instr.constants.push(Constant::Integer(5));
+        instr.constants.push(Constant::Integer(10));
+        instr.bytes = vec![
+            Opcode::Constant as u8,
+            0,
+            0,
+            Opcode::Constant as u8,
+            0,
+            1,
+            Opcode::Add as u8,
+        ];
+
+        assert_eq!(
+            VM::new(instr.clone()).run_with_limit(3),
+            Ok(Object::Integer(15))
+        );
+        assert_eq!(
+            VM::new(instr).run_with_limit(2),
+            Err("execution step limit exceeded".to_string())
+        );
+    }
+
+    /// Constant(5), Constant(10), Add — смещения 0, 3, 6.
+    fn add_program() -> Instructions {
+        let mut instr = Instructions::new();
+        instr.constants.push(Constant::Integer(5));
+        instr.constants.push(Constant::Integer(10));
+        instr.bytes = vec![
+            Opcode::Constant as u8,
+            0,
+            0,
+            Opcode::Constant as u8,
+            0,
+            1,
+            Opcode::Add as u8,
+        ];
+        instr
+    }
+
+    #[test]
+    fn test_vm_profiling_counts_opcodes() {
+        let mut vm = VM::new(add_program());
+        vm.enable_profiling();
+        assert_eq!(vm.run(), Ok(Object::Integer(15)));
+
+        let report = vm.profile_report();
+        assert_eq!(report.count(Opcode::Constant), 2);
+        assert_eq!(report.count(Opcode::Add), 1);
+        assert_eq!(report.opcodes.len(), 2);
+        assert_eq!(report.total_count(), 3);
+        assert!(report.functions.is_empty());
+        let text = report.to_string();
+        assert!(text.contains("\nTOTAL                     3 "), "{}", text);
+    }
+
+    #[test]
+    fn test_vm_profiling_disabled_records_nothing() {
+        let mut vm = VM::new(add_program());
+        assert_eq!(vm.run(), Ok(Object::Integer(15)));
+        let report = vm.profile_report();
+        assert!(report.is_empty());
+        assert_eq!(report.total_count(), 0);
+    }
+
+    #[test]
+    fn test_vm_debug_hook_traces_every_instruction() {
+        let trace = Rc::new(RefCell::new(Vec::new()));
+        let recorded = Rc::clone(&trace);
+        let mut vm = VM::new(add_program());
+        vm.set_debug_hook(Box::new(move |event| {
+            recorded.borrow_mut().push((
+                event.ip,
+                event.opcode,
+                event.operands.clone(),
+                event.stack_top.to_vec(),
+                event.frame_depth,
+            ));
+            DebugAction::Continue
+        }));
+
+        assert_eq!(vm.run(), Ok(Object::Integer(15)));
+        assert_eq!(
+            *trace.borrow(),
+            vec![
+                (0, Opcode::Constant, vec![0], vec![], 0),
+                (3, Opcode::Constant, vec![1], vec![Object::Integer(5)], 0),
+                (
+                    6,
+                    Opcode::Add,
+                    vec![],
+                    vec![Object::Integer(5), Object::Integer(10)],
+                    0
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_vm_breakpoint_fires_once() {
+        let hits = Rc::new(RefCell::new(Vec::new()));
+        let recorded = Rc::clone(&hits);
+        let mut vm = VM::new(add_program());
+        vm.add_breakpoint(3);
+        vm.set_debug_hook(Box::new(move |event| {
+            recorded.borrow_mut().push(event.ip);
+            DebugAction::Continue
+        }));
+
+        assert_eq!(vm.run(), Ok(Object::Integer(15)));
+        assert_eq!(*hits.borrow(), vec![3]);
+    }
+
+    #[test]
+    fn test_vm_debug_step_and_pause() {
+        let hits = Rc::new(RefCell::new(Vec::new()));
+        let recorded = Rc::clone(&hits);
+        let mut vm = VM::new(add_program());
+        vm.add_breakpoint(0);
+        vm.set_debug_hook(Box::new(move |event| {
+            recorded.borrow_mut().push(event.ip);
+            // С точки останова — шаг, на следующей инструкции — пауза
+            match event.ip {
+                0 => DebugAction::Step,
+                _ => DebugAction::Pause,
+            }
+        }));
+
+        assert_eq!(
+            vm.run(),
+            Err("Выполнение приостановлено на ip 3".to_string())
+        );
+        // Продолжение не вызывает хук повторно для той же инструкции
+        assert_eq!(vm.run(), Ok(Object::Integer(15)));
+        assert_eq!(*hits.borrow(), vec![0, 3]);
+    }
+
+    #[test]
+    fn test_vm_stack_snapshot() {
+        let mut instr = Instructions::new();
+        let one = instr.add_constant(Constant::Integer(1));
+        let two = instr.add_constant(Constant::Integer(2));
+        instr.emit(Opcode::Constant, &[one]).unwrap();
+        instr.emit(Opcode::Constant, &[two]).unwrap();
+        instr.emit(Opcode::True, &[]).unwrap();
+        instr.emit(Opcode::Pop, &[]).unwrap();
+
+        let mut vm = VM::new(instr);
+        assert!(vm.stack_snapshot().is_empty());
+        assert_eq!(vm.last_popped(), &Object::Null);
+        assert_eq!(vm.run(), Ok(Object::Integer(2)));
+        assert_eq!(
+            vm.stack_snapshot(),
+            vec![Object::Integer(1), Object::Integer(2)]
+        );
+        assert_eq!(vm.last_popped(), &Object::Boolean(true));
+    }
+
+    #[test]
+    fn test_vm_globals_snapshot_mid_run() {
+        // x = 10; y = 20 — пауза перед вторым SetGlobal
+        let mut instr = Instructions::new();
+        for (name, value) in [("x", 10), ("y", 20)] {
+            let value_idx = instr.add_constant(Constant::Integer(value));
+            let name_idx = instr.add_constant(string(name));
+            instr.emit(Opcode::Constant, &[value_idx]).unwrap();
+            instr.emit(Opcode::SetGlobal, &[name_idx]).unwrap();
+        }
+
+        let mut vm = VM::new(instr);
+        vm.add_breakpoint(9);
+        vm.set_debug_hook(Box::new(|_| DebugAction::Pause));
+        assert!(vm.run().is_err());
+        assert_eq!(
+            vm.globals_snapshot(),
+            HashMap::from([("x".to_string(), Object::Integer(10))])
+        );
+        assert_eq!(vm.stack_snapshot(), vec![Object::Integer(20)]);
+
+        vm.run().unwrap();
+        let globals = vm.globals_snapshot();
+        assert_eq!(globals.len(), 2);
+        assert_eq!(globals["y"], Object::Integer(20));
+        assert!(vm.stack_snapshot().is_empty());
+    }
+
+    /// Собирает хэш опкодом Hash из пар (ключ, значение) в порядке записи.
+    fn run_hash(pairs: &[(Constant, Constant)]) -> Result<Object, String> {
+        let mut instr = Instructions::new();
+        for (key, value) in pairs {
+            for obj in [key, value] {
+                let idx = instr.add_constant(obj.clone());
+                instr.emit(Opcode::Constant, &[idx]).unwrap();
+            }
+        }
+        instr.emit(Opcode::Hash, &[pairs.len()]).unwrap();
+        VM::new(instr).run()
+    }
+
+    fn string(s: &str) -> Constant {
+        Constant::String(s.to_string())
+    }
+
+    #[test]
+    fn test_vm_hash_equality_and_display() {
+        let ba = run_hash(&[
+            (string("b"), Constant::Integer(2)),
+            (string("a"), Constant::Integer(1)),
+        ])
+        .unwrap();
+        let ab = run_hash(&[
+            (string("a"), Constant::Integer(1)),
+            (string("b"), Constant::Integer(2)),
+        ])
+        .unwrap();
+
+        // Равенство не зависит от порядка, вывод идёт в порядке вставки
+        assert_eq!(ba, ab);
+        assert_eq!(ba.to_string(), r#"{"b": 2, "a": 1}"#);
+        assert_eq!(ab.to_string(), r#"{"a": 1, "b": 2}"#);
+        assert_eq!(ba.type_str(), "HASH");
+        assert_ne!(
+            ba,
+            run_hash(&[(string("a"), Constant::Integer(1))]).unwrap()
+        );
+
+        // При повторе ключа побеждает последнее значение
+        let dup = run_hash(&[
+            (string("k"), Constant::Integer(1)),
+            (string("k"), Constant::Integer(2)),
+        ])
+        .unwrap();
+        assert_eq!(dup.to_string(), r#"{"k": 2}"#);
+        assert_eq!(run_hash(&[]).unwrap().to_string(), "{}");
+    }
+
+    #[test]
+    fn test_vm_hash_integer_and_boolean_keys() {
+        let hash = run_hash(&[
+            (Constant::Integer(2), string("two")),
+            (string("1"), string("string one")),
+            (Constant::Integer(1), string("one")),
+            (Constant::Boolean(true), string("yes")),
+        ])
+        .unwrap();
+        // Ключ 1 и ключ "1" различны; вывод — в порядке вставки
+        assert_eq!(
+            hash.to_string(),
+            r#"{2: two, "1": string one, 1: one, true: yes}"#
+        );
+        assert_eq!(
+            index_value(&hash, &Object::Integer(2)),
+            Ok(Object::String("two".to_string()))
+        );
+        assert_eq!(
+            index_value(&hash, &Object::Boolean(false)),
+            Ok(Object::Null)
+        );
+    }
+
+    #[test]
+    fn test_vm_hash_rejects_unhashable_keys() {
+        // То же сообщение, что у индексации хэша в интерпретаторе
+        assert_eq!(
+            run_hash(&[(
+                Constant::Array(vec![Constant::Integer(1)]),
+                Constant::Integer(2)
+            )]),
+            Err("unusable as hash key: ARRAY".to_string())
+        );
+        assert_eq!(
+            run_hash(&[(Constant::Null, Constant::Integer(2))]),
+            Err("unusable as hash key: NULL".to_string())
+        );
+    }
+
+    #[test]
+    fn test_vm_register_add() {
+        // Тестируем: 2 + 3 целиком через регистры
+        let mut instr = Instructions::new();
+        instr.constants.push(Constant::Integer(2));
+        instr.constants.push(Constant::Integer(3));
+        instr.bytes = vec![
+            Opcode::Constant as u8,
+            0,
+            0, // Constant(2)
+            Opcode::LoadReg as u8,
+            0, // r0 = 2
+            Opcode::Constant as u8,
+            0,
+            1, // Constant(3)
+            Opcode::LoadReg as u8,
+            1, // r1 = 3
+            Opcode::AddReg as u8,
+            2,
+            0,
+            1, // r2 = r0 + r1
+            Opcode::PushReg as u8,
+            2,
+        ];
+
+        let mut vm = VM::new(instr);
+        assert_eq!(vm.run(), Ok(Object::Integer(5)));
+    }
+
+    #[test]
+    fn test_vm_register_errors() {
+        let mut instr = Instructions::new();
+        instr.bytes = vec![Opcode::PushReg as u8, NUM_REGISTERS as u8];
+        assert_eq!(
+            VM::new(instr).run(),
+            Err(format!("Некорректный номер регистра {}", NUM_REGISTERS))
+        );
+
+        // Ошибки операций совпадают со стековыми опкодами
+        let mut instr = Instructions::new();
+        instr.constants.push(Constant::Integer(1));
+        instr.constants.push(Constant::Integer(0));
+        instr.bytes = vec![
+            Opcode::Constant as u8,
+            0,
+            0,
+            Opcode::LoadReg as u8,
+            0,
+            Opcode::Constant as u8,
+            0,
+            1,
+            Opcode::LoadReg as u8,
+            1,
+            Opcode::DivReg as u8,
+            0,
+            0,
+            1,
+        ];
+        assert_eq!(VM::new(instr).run(), Err("division by zero".to_string()));
+    }
 }
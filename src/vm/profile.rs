@@ -0,0 +1,271 @@
+//! Профилирование VM: сколько раз выполнен каждый опкод, сколько времени
+//! на него ушло и сколько раз вызвана каждая скомпилированная функция.
+//!
+//! Профилировщик включается `VM::enable_profiling`. Время меряется грубо:
+//! перед каждой инструкцией берётся один отсчёт `Instant`, и промежуток
+//! от предыдущего отсчёта записывается на счёт предыдущего опкода. Без
+//! профилировщика цикл VM проверяет только `Option`.
+
+use crate::bytecode::opcode::Opcode;
+use crate::object::CompiledFunction;
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+/// Накопленная статистика одного опкода.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OpcodeProfile {
+    pub opcode: Opcode,
+    /// Сколько раз опкод выполнен.
+    pub count: u64,
+    /// Суммарное время выполнения.
+    pub time: Duration,
+}
+
+/// Число вызовов скомпилированной функции.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionProfile {
+    pub function: CompiledFunction,
+    pub calls: u64,
+}
+
+/// Отчёт профилировщика. Опкоды упорядочены по убыванию времени, затем
+/// числа выполнений, затем по мнемонике; функции — по убыванию числа
+/// вызовов, затем по смещению тела.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ProfileReport {
+    pub opcodes: Vec<OpcodeProfile>,
+    pub functions: Vec<FunctionProfile>,
+}
+
+impl ProfileReport {
+    /// Суммарное время всех опкодов — время выполнения под профилировщиком.
+    pub fn total_time(&self) -> Duration {
+        self.opcodes.iter().map(|profile| profile.time).sum()
+    }
+
+    /// Общее число выполненных инструкций.
+    pub fn total_count(&self) -> u64 {
+        self.opcodes.iter().map(|profile| profile.count).sum()
+    }
+
+    /// Число выполнений опкода; 0, если он не встречался.
+    pub fn count(&self, opcode: Opcode) -> u64 {
+        self.opcodes
+            .iter()
+            .find(|profile| profile.opcode == opcode)
+            .map_or(0, |profile| profile.count)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.opcodes.is_empty() && self.functions.is_empty()
+    }
+
+    fn sort(&mut self) {
+        self.opcodes.sort_by(|a, b| {
+            b.time
+                .cmp(&a.time)
+                .then(b.count.cmp(&a.count))
+                .then(a.opcode.mnemonic().cmp(b.opcode.mnemonic()))
+        });
+        self.functions.sort_by(|a, b| {
+            b.calls.cmp(&a.calls).then(
+                a.function
+                    .instructions_offset
+                    .cmp(&b.function.instructions_offset),
+            )
+        });
+    }
+}
+
+fn milliseconds(time: Duration) -> String {
+    format!("{:.3}ms", time.as_secs_f64() * 1000.0)
+}
+
+/// Таблица `опкод / число / время / % от выполнения`, строка `TOTAL` и,
+/// если были вызовы, таблица функций. Строки выводятся в порядке
+/// `ProfileReport::opcodes`.
+impl fmt::Display for ProfileReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let total = self.total_time();
+        writeln!(
+            f,
+            "{:<16} {:>10} {:>12} {:>7}",
+            "OPCODE", "COUNT", "TIME", "%"
+        )?;
+        for profile in &self.opcodes {
+            let share = if total.is_zero() {
+                0.0
+            } else {
+                profile.time.as_secs_f64() / total.as_secs_f64() * 100.0
+            };
+            writeln!(
+                f,
+                "{:<16} {:>10} {:>12} {:>6.1}%",
+                profile.opcode.mnemonic(),
+                profile.count,
+                milliseconds(profile.time),
+                share
+            )?;
+        }
+        writeln!(
+            f,
+            "{:<16} {:>10} {:>12} {:>6.1}%",
+            "TOTAL",
+            self.total_count(),
+            milliseconds(total),
+            if self.opcodes.is_empty() { 0.0 } else { 100.0 }
+        )?;
+        if !self.functions.is_empty() {
+            writeln!(f)?;
+            writeln!(f, "{:<32} {:>10}", "FUNCTION", "CALLS")?;
+            for profile in &self.functions {
+                writeln!(
+                    f,
+                    "{:<32} {:>10}",
+                    profile.function.summary(),
+                    profile.calls
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Счётчики, которые VM пополняет во время выполнения.
+#[derive(Debug, Default)]
+pub(crate) struct Profiler {
+    opcodes: HashMap<Opcode, (u64, Duration)>,
+    /// Вызовы по смещению тела функции.
+    functions: HashMap<usize, (CompiledFunction, u64)>,
+    /// Опкод, который выполняется сейчас, и момент его начала.
+    current: Option<(Opcode, Instant)>,
+}
+
+impl Profiler {
+    /// Отмечает начало инструкции `opcode` и закрывает предыдущую.
+    pub(crate) fn start(&mut self, opcode: Opcode) {
+        let now = Instant::now();
+        self.close(now);
+        self.opcodes.entry(opcode).or_default().0 += 1;
+        self.current = Some((opcode, now));
+    }
+
+    /// Закрывает последнюю инструкцию: выполнение завершилось или
+    /// прервалось.
+    pub(crate) fn finish(&mut self) {
+        self.close(Instant::now());
+    }
+
+    fn close(&mut self, now: Instant) {
+        if let Some((opcode, started)) = self.current.take() {
+            self.opcodes.entry(opcode).or_default().1 += now - started;
+        }
+    }
+
+    pub(crate) fn record_call(&mut self, function: &CompiledFunction) {
+        self.functions
+            .entry(function.instructions_offset)
+            .or_insert_with(|| (function.clone(), 0))
+            .1 += 1;
+    }
+
+    pub(crate) fn report(&self) -> ProfileReport {
+        let mut report = ProfileReport {
+            opcodes: self
+                .opcodes
+                .iter()
+                .map(|(&opcode, &(count, time))| OpcodeProfile {
+                    opcode,
+                    count,
+                    time,
+                })
+                .collect(),
+            functions: self
+                .functions
+                .values()
+                .map(|(function, calls)| FunctionProfile {
+                    function: function.clone(),
+                    calls: *calls,
+                })
+                .collect(),
+        };
+        report.sort();
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn opcode(opcode: Opcode, count: u64, micros: u64) -> OpcodeProfile {
+        OpcodeProfile {
+            opcode,
+            count,
+            time: Duration::from_micros(micros),
+        }
+    }
+
+    #[test]
+    fn test_report_display_is_sorted_by_time() {
+        let mut report = ProfileReport {
+            opcodes: vec![
+                opcode(Opcode::Pop, 4, 250),
+                opcode(Opcode::Add, 2, 500),
+                opcode(Opcode::Constant, 4, 250),
+            ],
+            functions: vec![
+                FunctionProfile {
+                    function: CompiledFunction {
+                        instructions_offset: 0x20,
+                        num_locals: 1,
+                        num_params: 1,
+                    },
+                    calls: 1,
+                },
+                FunctionProfile {
+                    function: CompiledFunction {
+                        instructions_offset: 0x40,
+                        num_locals: 2,
+                        num_params: 2,
+                    },
+                    calls: 3,
+                },
+            ],
+        };
+        report.sort();
+        let expected = [
+            "OPCODE                COUNT         TIME       %",
+            "ADD                       2      0.500ms   50.0%",
+            "CONSTANT                  4      0.250ms   25.0%",
+            "POP                       4      0.250ms   25.0%",
+            "TOTAL                    10      1.000ms  100.0%",
+            "",
+            "FUNCTION                              CALLS",
+            "fn/2 @0x0040 (2 locals)                   3",
+            "fn/1 @0x0020 (1 locals)                   1",
+            "",
+        ]
+        .join("\n");
+        assert_eq!(report.to_string(), expected);
+    }
+
+    #[test]
+    fn test_profiler_attributes_time_to_previous_opcode() {
+        let mut profiler = Profiler::default();
+        profiler.start(Opcode::Constant);
+        profiler.start(Opcode::Constant);
+        profiler.start(Opcode::Add);
+        profiler.finish();
+        // Повторный finish ничего не добавляет
+        profiler.finish();
+
+        let report = profiler.report();
+        assert_eq!(report.count(Opcode::Constant), 2);
+        assert_eq!(report.count(Opcode::Add), 1);
+        assert_eq!(report.count(Opcode::Pop), 0);
+        assert_eq!(report.total_count(), 3);
+        assert!(report.functions.is_empty());
+    }
+}
@@ -0,0 +1,204 @@
+//! Общий корпус программ для AST-интерпретатора и VM.
+//!
+//! Каждая программа `tests/conformance/<имя>.sf` сопровождается файлом
+//! `<имя>.expected` с ожидаемым результатом: строковым представлением
+//! значения или `ERROR: <сообщение>`. Строки `#` в начале программы —
+//! заголовок; `# vm: yes` дополнительно запускает программу на VM.
+//! Позиция ошибки ` at line N, column M` у движков разная (интерпретатор
+//! указывает на выражение, VM — на начало оператора) и при сравнении
+//! отбрасывается.
+
+use project_sofia_lib::ast::Node;
+use project_sofia_lib::compiler::Compiler;
+use project_sofia_lib::evaluator::{eval, stringify};
+use project_sofia_lib::lexer::Lexer;
+use project_sofia_lib::object::Environment;
+use project_sofia_lib::parser::Parser;
+use project_sofia_lib::vm::VM;
+use std::cell::RefCell;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+const CORPUS_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/conformance");
+
+struct Program {
+    source: String,
+    vm: bool,
+}
+
+/// Отделяет заголовок (начальные строки `#`) от исходного кода.
+fn read_program(path: &Path) -> Program {
+    let text = fs::read_to_string(path).unwrap();
+    let mut vm = false;
+    let mut source = String::new();
+    let mut in_header = true;
+    for line in text.lines() {
+        if in_header && line.starts_with('#') {
+            if line.trim_start_matches('#').trim() == "vm: yes" {
+                vm = true;
+            }
+            continue;
+        }
+        in_header = false;
+        source.push_str(line);
+        source.push('\n');
+    }
+    Program { source, vm }
+}
+
+fn parse(source: &str) -> Result<project_sofia_lib::ast::Program, String> {
+    Parser::new(Lexer::new(source.to_string()))
+        .parse_program()
+        .map_err(|errors| {
+            let messages: Vec<String> = errors.iter().map(|e| format!("{:?}", e)).collect();
+            format!("PARSE ERROR: {}", messages.join(", "))
+        })
+}
+
+fn run_evaluator(source: &str) -> String {
+    match parse(source) {
+        Ok(program) => {
+            let env = Rc::new(RefCell::new(Environment::new()));
+            let text = match stringify(&eval(Node::Program(program), env)) {
+                Ok(text) => text,
+                Err(error) => error.to_string(),
+            };
+            strip_positions(&text)
+        }
+        Err(e) => e,
+    }
+}
+
+/// Убирает из сообщения об ошибке позиции вида ` at line N, column M`.
+fn strip_positions(output: &str) -> String {
+    let mut result = String::new();
+    let mut rest = output;
+    while let Some(at) = rest.find(" at line ") {
+        result.push_str(&rest[..at]);
+        let tail = &rest[at + " at line ".len()..];
+        let line_end = tail
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or(tail.len());
+        let after_line = &tail[line_end..];
+        match after_line.strip_prefix(", column ") {
+            Some(column) if line_end > 0 => {
+                let column_end = column
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(column.len());
+                rest = &column[column_end..];
+            }
+            _ => {
+                result.push_str(" at line ");
+                rest = tail;
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+fn run_vm(source: &str) -> String {
+    let program = match parse(source) {
+        Ok(program) => program,
+        Err(e) => return e,
+    };
+    let instructions = match Compiler::new().compile(&program) {
+        Ok(instructions) => instructions,
+        Err(e) => return format!("ERROR: {}", String::from(e)),
+    };
+    match VM::new(instructions).run() {
+        Ok(result) => result.to_string(),
+        Err(e) => strip_positions(&format!("ERROR: {}", e)),
+    }
+}
+
+fn corpus_files() -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(CORPUS_DIR)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "sf"))
+        .collect();
+    files.sort();
+    files
+}
+
+#[test]
+fn test_conformance_corpus() {
+    let files = corpus_files();
+    assert!(!files.is_empty(), "conformance corpus is empty");
+
+    let mut failures = Vec::new();
+    for path in &files {
+        let program = read_program(path);
+        let expected = fs::read_to_string(path.with_extension("expected"))
+            .unwrap_or_else(|_| panic!("missing expected file for {}", path.display()));
+        let expected = expected.trim_end();
+
+        let ast = run_evaluator(&program.source);
+        let vm = program.vm.then(|| run_vm(&program.source));
+
+        let ast_ok = ast == expected;
+        let vm_ok = vm.as_deref().is_none_or(|out| out == expected);
+        if !ast_ok || !vm_ok {
+            failures.push(format!(
+                "{}\n  expected: {}\n  ast:      {}\n  vm:       {}",
+                path.display(),
+                expected,
+                ast,
+                vm.as_deref().unwrap_or("(not run)")
+            ));
+        }
+    }
+
+    assert!(
+        failures.is_empty(),
+        "{} of {} conformance programs failed:\n\n{}",
+        failures.len(),
+        files.len(),
+        failures.join("\n\n")
+    );
+}
+
+#[test]
+fn test_comparisons_agree_across_engines() {
+    let cases = [
+        (r#""a" < "b""#, "true"),
+        (r#""b" < "a""#, "false"),
+        (r#""abc" > "abd""#, "false"),
+        (r#""apple" < "apples""#, "true"),
+        (r#""" < "a""#, "true"),
+        (r#""Z" < "a""#, "true"),
+        (r#""z" < "é""#, "true"),
+        (r#""a" == "a""#, "true"),
+        (r#""a" != "a""#, "false"),
+        (r#""a" == "b""#, "false"),
+        (r#""a" < 1"#, "ERROR: type mismatch: STRING < INTEGER"),
+        (r#"1 > "a""#, "ERROR: type mismatch: INTEGER > STRING"),
+        (r#""a" == 1"#, "ERROR: type mismatch: STRING == INTEGER"),
+        (r#"1 != "a""#, "ERROR: type mismatch: INTEGER != STRING"),
+        ("true < false", "ERROR: unknown operator: BOOLEAN < BOOLEAN"),
+        ("null == null", "true"),
+        ("1 != null", "true"),
+        ("null < 1", "ERROR: type mismatch: NULL < INTEGER"),
+        ("1 == true", "ERROR: type mismatch: INTEGER == BOOLEAN"),
+        ("false != 0", "ERROR: type mismatch: BOOLEAN != INTEGER"),
+        ("true == true", "true"),
+        ("[1] == [1]", "ERROR: unknown operator: ARRAY == ARRAY"),
+        ("1 < 2", "true"),
+        ("2 == 2", "true"),
+    ];
+
+    let mut failures = Vec::new();
+    for (source, expected) in cases {
+        let ast = run_evaluator(source);
+        let vm = run_vm(source);
+        if ast != expected || vm != expected {
+            failures.push(format!(
+                "{}\n  expected: {}\n  ast:      {}\n  vm:       {}",
+                source, expected, ast, vm
+            ));
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+}
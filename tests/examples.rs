@@ -0,0 +1,116 @@
+//! Программы из `examples/` как дымовые тесты.
+//!
+//! Каждый файл `examples/*.sf` выполняется через `runner::run_source` на
+//! AST-интерпретаторе и на VM. Строка `// engine: ast` в начальных
+//! комментариях оставляет только интерпретатор. Программа должна
+//! завершиться без ошибки; если последняя непустая строка файла —
+//! `// expect: <значение>`, строковое представление результата должно с
+//! ним совпасть. Файлы `tests/examples_fixtures/*.sf` намеренно сломаны:
+//! харнесс обязан их отвергнуть.
+
+use project_sofia_lib::runner::{run_source, Engine};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const EXAMPLES_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/examples");
+const FIXTURES_DIR: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/examples_fixtures");
+
+struct Example {
+    source: String,
+    engines: Vec<Engine>,
+    expect: Option<String>,
+}
+
+fn read_example(path: &Path) -> Example {
+    let source = fs::read_to_string(path).unwrap();
+    let header = source
+        .lines()
+        .take_while(|line| line.trim_start().starts_with("//"));
+    let ast_only = header
+        .map(|line| line.trim_start().trim_start_matches('/').trim())
+        .any(|comment| comment == "engine: ast");
+    let expect = source
+        .lines()
+        .rev()
+        .find(|line| !line.trim().is_empty())
+        .and_then(|line| line.trim().strip_prefix("// expect:"))
+        .map(|value| value.trim().to_string());
+    let engines = if ast_only {
+        vec![Engine::Ast]
+    } else {
+        vec![Engine::Ast, Engine::Vm]
+    };
+    Example {
+        source,
+        engines,
+        expect,
+    }
+}
+
+/// Выполняет пример на всех его движках; ошибки собираются по строке на
+/// движок.
+fn check_example(path: &Path) -> Result<(), String> {
+    let example = read_example(path);
+    let mut failures = Vec::new();
+    for &engine in &example.engines {
+        match run_source(&example.source, engine) {
+            Err(error) => failures.push(format!("[{}] error: {}", engine, error)),
+            Ok(result) => {
+                if let Some(expect) = &example.expect {
+                    let actual = result.to_string();
+                    if &actual != expect {
+                        failures.push(format!(
+                            "[{}] expected `{}`, got `{}`",
+                            engine, expect, actual
+                        ));
+                    }
+                }
+            }
+        }
+    }
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("{}:\n  {}", path.display(), failures.join("\n  ")))
+    }
+}
+
+fn sf_files(dir: &str) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "sf"))
+        .collect();
+    files.sort();
+    files
+}
+
+#[test]
+fn test_examples_run() {
+    let files = sf_files(EXAMPLES_DIR);
+    assert!(files.len() >= 5, "expected at least five examples");
+
+    let failures: Vec<String> = files
+        .iter()
+        .filter_map(|path| check_example(path).err())
+        .collect();
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
+
+#[test]
+fn test_harness_rejects_broken_fixtures() {
+    let files = sf_files(FIXTURES_DIR);
+    assert!(!files.is_empty(), "no broken fixtures");
+    for path in &files {
+        assert!(
+            check_example(path).is_err(),
+            "{} passed, but it must fail",
+            path.display()
+        );
+    }
+
+    // Пример, который ломается только на VM, отвергается именно из-за неё
+    let failure = check_example(&Path::new(FIXTURES_DIR).join("vm_only_failure.sf")).unwrap_err();
+    assert!(failure.contains("[vm]"), "{}", failure);
+    assert!(!failure.contains("[ast]"), "{}", failure);
+}
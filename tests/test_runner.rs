@@ -0,0 +1,36 @@
+use std::fs;
+use std::process::Command;
+
+#[test]
+fn test_runner_reports_pass_and_fail() {
+    let dir = std::env::temp_dir().join(format!("sofia_test_runner_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join("math_test.sf"),
+        "fn test_add() { assert_eq(1 + 2, 3) }",
+    )
+    .unwrap();
+    fs::write(
+        dir.join("broken_test.sf"),
+        "fn test_sub() { assert_eq(5 - 2, 4) }",
+    )
+    .unwrap();
+    // Файлы без суффикса _test.sf не запускаются
+    fs::write(dir.join("helper.sf"), "fn test_ignored() { assert(false) }").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_project-sofia"))
+        .arg("--test")
+        .arg(&dir)
+        .output()
+        .unwrap();
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    fs::remove_dir_all(&dir).unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    assert!(stdout.contains("PASS math_test.sf::test_add\n"));
+    assert!(stdout.contains("FAIL broken_test.sf::test_sub\n"));
+    assert!(stdout.contains("    assertion failed: 3 != 4\n"));
+    assert!(!stdout.contains("test_ignored"));
+    assert!(stdout.contains("test result: FAILED. 1 passed; 1 failed\n"));
+}
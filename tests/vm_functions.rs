@@ -1,8 +1,13 @@
+use project_sofia_lib::ast::NodeId;
+use project_sofia_lib::bytecode::disassembler::{disassemble, disassemble_with_program};
+use project_sofia_lib::bytecode::instructions::Instructions;
+use project_sofia_lib::bytecode::opcode::Opcode;
 use project_sofia_lib::compiler::Compiler;
 use project_sofia_lib::lexer::Lexer;
 use project_sofia_lib::object::Object;
 use project_sofia_lib::parser::Parser;
 use project_sofia_lib::vm::VM;
+use std::rc::Rc;
 
 fn eval_with_vm(input: &str) -> Object {
     let lexer = Lexer::new(input.to_string());
@@ -13,7 +18,15 @@ fn eval_with_vm(input: &str) -> Object {
     let instructions = compiler.compile(&program).unwrap();
 
     let mut vm = VM::new(instructions);
-    vm.run().unwrap()
+    vm.run().unwrap();
+    // Каждый оператор верхнего уровня снимает свой результат со стека
+    assert_eq!(
+        vm.stack_snapshot(),
+        vec![],
+        "stack not empty after {}",
+        input
+    );
+    vm.last_popped().clone()
 }
 
 #[test]
@@ -33,3 +46,546 @@ fn test_function_with_locals() {
     let result = eval_with_vm("let compute = fn(a, b) { let sum = a + b; let product = a * b; return sum + product; }; compute(2, 3);");
     assert_eq!(result, Object::Integer(11));
 }
+
+#[test]
+fn test_function_past_64kb_boundary() {
+    // Каждое выражение `1;` даёт Constant + Pop (4 байта): 20000 выражений
+    // сдвигают функцию за границу 65535 байт
+    let mut input = "1;".repeat(20000);
+    input.push_str("let inc = fn(x) { x + 1; }; inc(41);");
+    let result = eval_with_vm(&input);
+    assert_eq!(result, Object::Integer(42));
+}
+
+#[test]
+fn test_array_negative_index() {
+    assert_eq!(eval_with_vm("[10, 20, 30][-1];"), Object::Integer(30));
+    assert_eq!(eval_with_vm("[10, 20, 30][-3];"), Object::Integer(10));
+    assert_eq!(eval_with_vm("[10, 20, 30][1];"), Object::Integer(20));
+    assert_eq!(eval_with_vm("[1][-5];"), Object::Null);
+    assert_eq!(eval_with_vm("[1][1];"), Object::Null);
+}
+
+#[test]
+fn test_string_index() {
+    let string = |s: &str| Object::String(s.to_string());
+    assert_eq!(eval_with_vm("\"abc\"[0];"), string("a"));
+    assert_eq!(eval_with_vm("\"abc\"[-1];"), string("c"));
+    // Индекс считается в символах, а не в байтах
+    assert_eq!(eval_with_vm("\"héy\"[1];"), string("é"));
+    assert_eq!(eval_with_vm("\"abc\"[3];"), Object::Null);
+    assert_eq!(eval_with_vm("\"abc\"[-4];"), Object::Null);
+    assert_eq!(eval_with_vm("\"\"[0];"), Object::Null);
+
+    let error = VM::new(compile("\"abc\"[true];")).run().unwrap_err();
+    assert!(
+        error.starts_with("index operator not supported: STRING[BOOLEAN]"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn test_builtin_calls() {
+    assert_eq!(eval_with_vm("len([1, 2, 3]);"), Object::Integer(3));
+    assert_eq!(
+        eval_with_vm("let f = fn(s) { int(s) * 2 }; f(\"21\");"),
+        Object::Integer(42)
+    );
+    assert_eq!(
+        eval_with_vm("format(\"{}-{}\", 1, [2]);"),
+        Object::String("1-[2]".to_string())
+    );
+    // Вызов встроенной функции внутри выражения не оставляет аргументов на стеке
+    assert_eq!(eval_with_vm("1 + len(\"ab\") * 10;"), Object::Integer(21));
+
+    let error = VM::new(compile("trim(1, 2);")).run().unwrap_err();
+    assert!(
+        error.starts_with("wrong number of arguments to trim: got=2, want=1"),
+        "{}",
+        error
+    );
+}
+
+fn compile(input: &str) -> Instructions {
+    let lexer = Lexer::new(input.to_string());
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program().unwrap();
+    Compiler::new().compile(&program).unwrap()
+}
+
+fn dense_match(subject: &str) -> String {
+    format!(
+        "match {} {{ 0 => 100, 1 => 101, 2 => 102, 3 => 103, 4 => 104, 5 => 105, 6 => 106, 7 => 107, _ => 99 }};",
+        subject
+    )
+}
+
+#[test]
+fn test_dense_match_compiles_to_jump_table() {
+    let listing = disassemble(&compile(&dense_match("x")));
+    assert!(listing.contains("JUMP_TABLE"), "{}", listing);
+    // Таблица заменяет цепочку сравнений
+    assert!(!listing.contains("EQUAL"), "{}", listing);
+
+    for value in 0..8 {
+        let result = eval_with_vm(&format!("let x = {}; {}", value, dense_match("x")));
+        assert_eq!(result, Object::Integer(100 + value));
+    }
+    assert_eq!(eval_with_vm(&dense_match("8")), Object::Integer(99));
+    assert_eq!(eval_with_vm(&dense_match("1000")), Object::Integer(99));
+    assert_eq!(eval_with_vm(&dense_match("\"abc\"")), Object::Integer(99));
+}
+
+#[test]
+fn test_sparse_match_uses_comparisons() {
+    let listing = disassemble(&compile(
+        "match 5 { 1 => 1, 100 => 2, 1000 => 3, 10000 => 4 };",
+    ));
+    assert!(!listing.contains("JUMP_TABLE"), "{}", listing);
+}
+
+#[test]
+fn test_match_span_overflow_uses_comparisons() {
+    let input = "match 1 { 0 => 1, 1 => 2, 2 => 3, 9223372036854775807 => 4, _ => 5 };";
+    let listing = disassemble(&compile(input));
+    assert!(!listing.contains("JUMP_TABLE"), "{}", listing);
+    assert_eq!(eval_with_vm(input), Object::Integer(2));
+}
+
+#[test]
+fn test_match_without_matching_arm_is_error() {
+    // Линейная цепочка и таблица переходов
+    for input in [
+        "match 5 { 1 => 2 };",
+        "match 9 { 0 => 0, 1 => 1, 2 => 2, 3 => 3 };",
+    ] {
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        let instructions = Compiler::new().compile(&program).unwrap();
+        let error = VM::new(instructions).run().unwrap_err();
+        assert!(
+            error.starts_with("non-exhaustive match pattern for value: "),
+            "{}: {}",
+            input,
+            error
+        );
+    }
+}
+
+#[test]
+fn test_match_linear_arms() {
+    let input = r#"
+        let classify = fn(x) {
+            match x {
+                0 => "zero",
+                1..10 => "digit",
+                n if n < 0 => "negative",
+                _ => "large",
+            }
+        };
+        [classify(0), classify(7), classify(-3), classify(42)];
+    "#;
+    assert_eq!(
+        eval_with_vm(input),
+        Object::Array(Rc::new(vec![
+            Object::String("zero".to_string()),
+            Object::String("digit".to_string()),
+            Object::String("negative".to_string()),
+            Object::String("large".to_string()),
+        ]))
+    );
+}
+
+#[test]
+fn test_runtime_error_reports_statement_node() {
+    let input = "let f = fn(x) { x + true }; let a = 1; f(a);";
+    let program = Parser::new(Lexer::new(input.to_string()))
+        .parse_program()
+        .unwrap();
+    let instructions = Compiler::new().compile(&program).unwrap();
+    let listing = disassemble_with_program(&instructions, &program);
+    assert!(listing.contains("MAP_TO_AST 3 ; let a = 1;"), "{}", listing);
+
+    let mut vm = VM::new(instructions);
+    let error = vm.run().unwrap_err();
+    // Ошибка возникла в теле функции — операторе #2, с позиции `x`
+    assert!(error.ends_with(" at line 1, column 17"), "{}", error);
+    assert_eq!(vm.current_node(), Some(NodeId(2)));
+    let statement = program.find_node(NodeId(2)).unwrap();
+    assert_eq!(statement.to_string(), "(x + true)");
+}
+
+#[test]
+fn test_runtime_error_after_return_reports_caller_node() {
+    let input = "let f = fn() { 1 }; f() + true;";
+    let program = Parser::new(Lexer::new(input.to_string()))
+        .parse_program()
+        .unwrap();
+    let instructions = Compiler::new().compile(&program).unwrap();
+    let error = VM::new(instructions).run().unwrap_err();
+    assert!(error.ends_with(" at line 1, column 21"), "{}", error);
+}
+
+#[test]
+fn test_if_expression_stack_hygiene() {
+    // eval_with_vm проверяет, что стек пуст после каждой программы
+    assert_eq!(eval_with_vm("if (true) { 1 }; 2"), Object::Integer(2));
+    assert_eq!(eval_with_vm("if (false) { 1 }"), Object::Null);
+    assert_eq!(
+        eval_with_vm("let f = fn(x) { if (x > 0) { x } else { -x } }; f(-3); f(4)"),
+        Object::Integer(4)
+    );
+    assert_eq!(
+        eval_with_vm("let x = if (true) { 5 }; x"),
+        Object::Integer(5)
+    );
+}
+
+#[test]
+fn test_if_branches_with_statements() {
+    let globals_after = |input: &str| {
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        let mut vm = VM::new(Compiler::new().compile(&program).unwrap());
+        let result = vm.run().unwrap();
+        assert!(vm.stack_snapshot().is_empty(), "{}", input);
+        let mut names: Vec<String> = vm.globals_snapshot().into_keys().collect();
+        names.sort();
+        (result, names)
+    };
+
+    // Невыполненная ветвь не объявляет свои глобальные переменные
+    let branches = "if (c) { let x = 1; let t = x + 1; } else { let y = 2; let e = y * 3; }";
+    assert_eq!(
+        globals_after(&format!("let c = true; {} t", branches)),
+        (
+            Object::Integer(2),
+            vec!["c".to_string(), "t".to_string(), "x".to_string()]
+        )
+    );
+    assert_eq!(
+        globals_after(&format!("let c = false; {} e", branches)),
+        (
+            Object::Integer(6),
+            vec!["c".to_string(), "e".to_string(), "y".to_string()]
+        )
+    );
+
+    // Операторы в ветвях внутри функции: только выполненная ветвь меняет счётчик
+    let input = "let n = 0;
+        let f = fn(c) { if (c) { let d = 10; n = n + d; } else { let d = 100; n = n + d; } };
+        f(true); f(false); f(true); n";
+    assert_eq!(eval_with_vm(input), Object::Integer(120));
+    assert_eq!(
+        eval_with_vm("let r = 0; if (false) { r = 1; } else { if (true) { let z = 2; r = z; } } r"),
+        Object::Integer(2)
+    );
+}
+
+#[test]
+fn test_assignment_global_and_local() {
+    assert_eq!(eval_with_vm("let x = 0; x = x + 5; x"), Object::Integer(5));
+    assert_eq!(eval_with_vm("let x = 1; x = 7"), Object::Integer(7));
+    assert_eq!(
+        eval_with_vm("let f = fn(n) { let acc = 1; acc = acc * n; acc = acc + n; acc }; f(4);"),
+        Object::Integer(8)
+    );
+}
+
+#[test]
+fn test_compiled_program_runs_on_another_thread() {
+    let compile = |input: &str| -> Instructions {
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        Compiler::new().compile(&program).unwrap()
+    };
+    let compiled = std::thread::spawn(move || {
+        compile(r#"let add = fn(a, b) { a + b }; let s = "n="; match add(2, 3) { 5 => s + "five", _ => s }"#)
+    })
+    .join()
+    .unwrap();
+
+    // Объекты VM не Send, поэтому наружу возвращается строка
+    let result = std::thread::spawn(move || VM::new(compiled).run().map(|r| r.to_string()))
+        .join()
+        .unwrap();
+    assert_eq!(result, Ok("n=five".to_string()));
+}
+
+#[test]
+fn test_struct_field_get_and_set() {
+    let input = "struct Point { let x = 1; let y; }; let p = new Point(5); p.x";
+    assert_eq!(eval_with_vm(input), Object::Integer(5));
+    assert_eq!(
+        eval_with_vm("struct Point { let x = 1; let y; }; let p = new Point(); [p.x, p.y]"),
+        eval_with_vm("[1, null]")
+    );
+    assert_eq!(
+        eval_with_vm("struct C { let n = 0; }; let c = new C(); c.n = c.n + 2; c.n = c.n * 10"),
+        Object::Integer(20)
+    );
+    // Экземпляры не разделяют поля друг с другом
+    assert_eq!(
+        eval_with_vm(
+            "struct C { let n = 0; }; let f = fn() { let a = new C(); let b = new C(); a.n = 1; b.n }; f()"
+        ),
+        Object::Integer(0)
+    );
+    // Экземпляры — значения: присваивание полю не видно через копию
+    assert_eq!(
+        eval_with_vm(
+            "struct C { let n = 0; }; let f = fn() { let a = new C(); let b = a; b.n = 1; [a.n, b.n] }; f()"
+        ),
+        eval_with_vm("[0, 1]")
+    );
+}
+
+#[test]
+fn test_struct_errors() {
+    let run = |input: &str| {
+        let program = Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap();
+        VM::new(Compiler::new().compile(&program).unwrap())
+            .run()
+            .unwrap_err()
+    };
+    let error = run("struct P { let x; }; let p = new P(); p.z");
+    assert!(
+        error.starts_with("property 'z' not found on struct 'P'"),
+        "{}",
+        error
+    );
+    let error = run("struct P { let x; }; new P(1, 2)");
+    assert!(
+        error.starts_with("too many arguments to new P: got=2, want<=1"),
+        "{}",
+        error
+    );
+    let error = run("let n = 1; n.x = 2");
+    assert!(
+        error.starts_with("property access not supported for type 'INTEGER'"),
+        "{}",
+        error
+    );
+}
+
+#[test]
+fn test_index_assignment() {
+    assert_eq!(
+        eval_with_vm("let a = [1, 2, 3]; a[1] = 5; a"),
+        eval_with_vm("[1, 5, 3]")
+    );
+    assert_eq!(
+        eval_with_vm("let f = fn() { let m = [[1], [2]]; m[-1][0] = 9; m }; f()"),
+        eval_with_vm("[[1], [9]]")
+    );
+    let listing = disassemble(&compile("let a = [1]; a[0] = 2;"));
+    assert!(listing.contains("SET_INDEX"), "{}", listing);
+}
+
+#[test]
+fn test_run_returns_last_expression_statement() {
+    let program = Parser::new(Lexer::new("1; 2; 3".to_string()))
+        .parse_program()
+        .unwrap();
+    let mut vm = VM::new(Compiler::new().compile(&program).unwrap());
+    assert_eq!(vm.run(), Ok(Object::Integer(3)));
+    assert_eq!(vm.last_popped(), &Object::Integer(3));
+    assert_eq!(eval_with_vm("5 + 5; 10"), Object::Integer(10));
+}
+
+#[test]
+fn test_range_loop_break_and_continue() {
+    assert_eq!(
+        eval_with_vm("let s = 0; for (i in 0..10) { if (i == 5) { break; } s = s + i; } s"),
+        Object::Integer(10)
+    );
+    assert_eq!(
+        eval_with_vm("let s = 0; for (i in 0..6) { if (i % 2 == 0) { continue; } s = s + i; } s"),
+        Object::Integer(9)
+    );
+    // break выходит только из внутреннего цикла
+    assert_eq!(
+        eval_with_vm(
+            "let n = 0; for (i in 0..3) { for (j in 0..10) { if (j == i) { break; } n = n + 1; } } n"
+        ),
+        Object::Integer(3)
+    );
+    // Цикл внутри функции: локальные переменные и return из тела
+    assert_eq!(
+        eval_with_vm(
+            "let find = fn(limit) { for (i in 1..limit) { if (i * i > 20) { return i; } } 0 }; \
+             [find(10), find(3)]"
+        ),
+        Object::Array(Rc::new(vec![Object::Integer(5), Object::Integer(0)]))
+    );
+    assert_eq!(eval_with_vm("for (i in 3..1) { i }"), Object::Null);
+}
+
+#[test]
+fn test_loop_control_in_expression_unwinds_stack() {
+    // Без сброса стека каждая итерация оставляла бы на нём элементы 1 и 2,
+    // и к концу цикла стек переполнился бы
+    assert_eq!(
+        eval_with_vm(
+            "let n = 0; for (i in 0..100000) { n = n + 1; let a = [1, 2, if (true) { continue; } else { 3 }]; } n"
+        ),
+        Object::Integer(100000)
+    );
+    assert_eq!(
+        eval_with_vm(
+            "let s = 0; for (i in 0..10) { s = s + 1 + if (i == 3) { break; } else { 0 }; } s"
+        ),
+        Object::Integer(3)
+    );
+}
+
+#[test]
+fn test_globals_shared_between_vms() {
+    let parse = |input: &str| {
+        Parser::new(Lexer::new(input.to_string()))
+            .parse_program()
+            .unwrap()
+    };
+    let mut compiler = Compiler::new();
+    let first = compiler
+        .compile(&parse("let x = 41; let f = fn(n) { n + 100 };"))
+        .unwrap();
+    let mut vm = VM::new(first.clone());
+    vm.run().unwrap();
+
+    // Вторая VM получает весь байткод, но выполняет только новый фрагмент;
+    // функция из первого фрагмента вызывается по своему смещению
+    let second = compiler.compile(&parse("x = x + 1; f(1)")).unwrap();
+    let mut next = VM::new(second);
+    next.set_globals(vm.globals());
+    assert_eq!(next.run_from(first.bytes.len()), Ok(Object::Integer(101)));
+    assert_eq!(vm.globals_snapshot()["x"], Object::Integer(42));
+
+    assert_eq!(
+        next.run_from(first.bytes.len() + 1_000),
+        Err(format!(
+            "Смещение {} за пределами байткода ({} байт)",
+            first.bytes.len() + 1_000,
+            compiler.compile(&parse("")).unwrap().bytes.len()
+        ))
+    );
+}
+
+fn run_with_registers(input: &str) -> (String, Result<Object, String>) {
+    let lexer = Lexer::new(input.to_string());
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program().unwrap();
+
+    let mut compiler = Compiler::new();
+    compiler.enable_register_arithmetic();
+    let instructions = compiler.compile(&program).unwrap();
+    let listing = disassemble(&instructions);
+
+    let mut vm = VM::new(instructions);
+    let result = vm.run().map(|_| vm.last_popped().clone());
+    (listing, result)
+}
+
+#[test]
+fn test_register_arithmetic_matches_stack() {
+    for input in [
+        "2 + 3",
+        "let a = 7; let b = 2; (a - b) * (a % b) + a / b",
+        "\"ab\" + \"cd\"",
+        "let f = fn(n) { n * n + 1 }; f(4) - f(2)",
+        "2 ** 3 + 1",
+    ] {
+        let (listing, result) = run_with_registers(input);
+        assert!(listing.contains("LOAD_REG"), "{}", listing);
+        assert_eq!(result, Ok(eval_with_vm(input)), "{}", input);
+    }
+
+    // Ошибки те же, что и при стековой арифметике
+    let (_, result) = run_with_registers("1 / 0");
+    assert_eq!(
+        result,
+        Err("division by zero at line 1, column 1".to_string())
+    );
+}
+
+fn run_with_peephole(input: &str) -> (usize, Result<Object, String>) {
+    let lexer = Lexer::new(input.to_string());
+    let mut parser = Parser::new(lexer);
+    let program = parser.parse_program().unwrap();
+
+    let mut compiler = Compiler::new();
+    compiler.enable_peephole();
+    let instructions = compiler.compile(&program).unwrap();
+    let size = instructions.bytes.len();
+
+    let mut vm = VM::new(instructions);
+    let result = vm.run().map(|_| vm.last_popped().clone());
+    (size, result)
+}
+
+#[test]
+fn test_peephole_preserves_results() {
+    let input = "
+        1; \"unused\"; !true;
+        let a = !false;
+        let b = if (!a) { 10 } else { 20 };
+        let total = 0;
+        for (i in 1..6) {
+            if (i == 3) { continue; }
+            100;
+            total = total + i;
+        }
+        let f = fn(n) { 7; if (n > 0) { n * 2 } else { !true } };
+        [a, b, total, f(4), f(0)]
+    ";
+    let lexer = Lexer::new(input.to_string());
+    let program = Parser::new(lexer).parse_program().unwrap();
+    let plain = Compiler::new().compile(&program).unwrap();
+
+    let (size, result) = run_with_peephole(input);
+    assert!(
+        size < plain.bytes.len(),
+        "{} >= {}",
+        size,
+        plain.bytes.len()
+    );
+    assert_eq!(result, Ok(eval_with_vm(input)));
+
+    // Значение последнего оператора-константы остаётся результатом
+    let (_, result) = run_with_peephole("1; 2; 3");
+    assert_eq!(result, Ok(Object::Integer(3)));
+}
+
+#[test]
+fn test_profiling_counts_function_calls() {
+    let input =
+        "let double = fn(x) { x * 2 }; let inc = fn(x) { x + 1 }; double(inc(1)) + inc(2) + inc(3)";
+    let program = Parser::new(Lexer::new(input.to_string()))
+        .parse_program()
+        .unwrap();
+    let mut vm = VM::new(Compiler::new().compile(&program).unwrap());
+    vm.enable_profiling();
+    vm.run().unwrap();
+    assert_eq!(vm.last_popped(), &Object::Integer(11));
+
+    let report = vm.profile_report();
+    let calls: Vec<(usize, u64)> = report
+        .functions
+        .iter()
+        .map(|profile| (profile.function.num_params, profile.calls))
+        .collect();
+    // Сначала чаще вызываемая inc
+    assert_eq!(calls, vec![(1, 3), (1, 1)]);
+    assert!(
+        report.functions[0].function.instructions_offset
+            > report.functions[1].function.instructions_offset
+    );
+    assert_eq!(report.count(Opcode::Call), 4);
+    assert_eq!(report.count(Opcode::ReturnValue), 4);
+}